@@ -0,0 +1,442 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::models::{PipelineOptions, ProcessingRequest, ProcessingState, ToolPaths};
+use crate::probe::{frame_rates_match, MediaReport, ProbeTools, DEFAULT_FPS_TOLERANCE};
+use crate::processing::{
+    detect_letterbox_bars, dv_delay_plan, dv_height_on_hdr, frame_fit, hdr10plus_frame_count, is_json_file, DelayPlan, FrameFit,
+};
+use crate::utils::{absolute_path, emit_event, emit_log, find_matching_dv_file, resolve_path, scan_folder_pairs};
+
+/// Duration difference, in seconds, above which a pair is flagged.
+const DURATION_TOLERANCE_SECS: f64 = 1.0;
+
+/// What the probe found about one input of a pair.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamSummary {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    pub bit_depth: Option<u32>,
+    pub duration_secs: Option<f64>,
+    pub frame_count: Option<u64>,
+    pub format: Option<String>,
+    pub hdr_format: Option<String>,
+    pub dv_profile: Option<u32>,
+    pub hdr10plus: bool,
+}
+
+/// Suggested `dv_delay_ms` and where it came from: `"container"` (the video
+/// tracks' start offsets) or `"frameCount"` (assumes the extra frames are at
+/// the start).
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DelayEstimate {
+    pub delay_ms: f64,
+    pub frames: i64,
+    pub source: String,
+}
+
+/// Verdict on whether one HDR/DV pair will hybrid cleanly. `issues` would
+/// fail the run or break the output; `warnings` are worth a look.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PairAnalysis {
+    pub hdr_path: String,
+    pub dv_path: String,
+    pub hdr10plus_path: Option<String>,
+    pub hdr: Option<StreamSummary>,
+    pub dv: Option<StreamSummary>,
+    pub compatible: bool,
+    pub issues: Vec<String>,
+    pub warnings: Vec<String>,
+    pub delay: Option<DelayEstimate>,
+    /// What the RPU edit would do with the requested `dv_delay_ms`.
+    pub delay_plan: Option<DelayPlan>,
+    pub frame_fit: Option<FrameFit>,
+    /// Baked-in bars found by cropdetect, as (top, bottom), when
+    /// `detect_letterbox` is on and the frames match.
+    pub letterbox: Option<(u32, u32)>,
+    pub hdr10plus_present: Option<bool>,
+    /// Whether the run would inject HDR10+ metadata.
+    pub hdr10plus_inject: bool,
+    /// Set when the pair could not be probed at all.
+    pub error: Option<String>,
+}
+
+/// Sent as `processing:analysis` for each pair, queue item or folder analyzed.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisReport {
+    pub queue_id: Option<String>,
+    pub pairs: Vec<PairAnalysis>,
+    /// HDR files no DV file could be paired with.
+    pub unpaired: Vec<String>,
+    pub compatible: usize,
+}
+
+fn summarize(path: &Path, report: &MediaReport) -> StreamSummary {
+    StreamSummary {
+        path: path.to_string_lossy().to_string(),
+        width: report.width,
+        height: report.height,
+        fps: report.fps,
+        bit_depth: report.bit_depth,
+        duration_secs: report.duration_secs,
+        frame_count: report.frame_count,
+        format: report.format.clone(),
+        hdr_format: report.hdr_format.clone(),
+        dv_profile: report.dolby_vision_profile(),
+        hdr10plus: report.has_hdr10plus(),
+    }
+}
+
+/// Delay that lines the DV stream up with the HDR one: positive duplicates
+/// DV frames at the start, negative drops them.
+fn estimate_delay(hdr: &MediaReport, dv: &MediaReport) -> Option<DelayEstimate> {
+    let frame_ms = 1000.0 / hdr.fps;
+    let from_frames = |frames: i64, source: &str| DelayEstimate {
+        delay_ms: (frames as f64 * frame_ms).round(),
+        frames,
+        source: source.to_string(),
+    };
+    let offset_ms = (dv.delay_secs.unwrap_or(0.0) - hdr.delay_secs.unwrap_or(0.0)) * 1000.0;
+    if offset_ms.abs() >= frame_ms / 2.0 {
+        return Some(from_frames((offset_ms / frame_ms).round() as i64, "container"));
+    }
+    let (hdr_frames, dv_frames) = (hdr.frame_count?, dv.frame_count?);
+    (hdr_frames != dv_frames).then(|| from_frames(hdr_frames as i64 - dv_frames as i64, "frameCount"))
+}
+
+fn check_hdr10plus(
+    state: &ProcessingState,
+    tools: &ProbeTools,
+    donor: &Path,
+) -> Result<bool, String> {
+    if is_json_file(donor) {
+        Ok(hdr10plus_frame_count(donor).map(|frames| frames > 0).unwrap_or(false))
+    } else {
+        Ok(state.probe.probe(tools, donor)?.has_hdr10plus())
+    }
+}
+
+/// The inputs of one analysis: a pair (or two folders), the HDR10+ donor
+/// and the DV delay the run would use.
+pub struct PairInput<'a> {
+    pub hdr: &'a Path,
+    pub dv: &'a Path,
+    pub hdr10plus: Option<&'a Path>,
+    pub dv_delay_ms: f64,
+}
+
+/// Probe one pair and check it against what the pipeline needs, without
+/// extracting anything. The crop and delay decisions are the pipeline's own.
+pub fn analyze_pair(
+    app: &AppHandle,
+    state: &ProcessingState,
+    tool_paths: &ToolPaths,
+    input: &PairInput,
+    detect_letterbox: bool,
+    fps_tolerance: f64,
+    scale_dv_frame: bool,
+) -> PairAnalysis {
+    let hdr_path = absolute_path(input.hdr);
+    let dv_path = absolute_path(input.dv);
+    let hdr10plus_path = input
+        .hdr10plus
+        .filter(|path| !path.as_os_str().is_empty())
+        .map(absolute_path);
+    let mut analysis = PairAnalysis {
+        hdr_path: hdr_path.to_string_lossy().to_string(),
+        dv_path: dv_path.to_string_lossy().to_string(),
+        hdr10plus_path: hdr10plus_path.as_ref().map(|path| path.to_string_lossy().to_string()),
+        hdr: None,
+        dv: None,
+        compatible: false,
+        issues: Vec::new(),
+        warnings: Vec::new(),
+        delay: None,
+        delay_plan: None,
+        frame_fit: None,
+        letterbox: None,
+        hdr10plus_present: None,
+        hdr10plus_inject: false,
+        error: None,
+    };
+
+    let work_dir = hdr_path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let tools = ProbeTools::new(
+        &resolve_path(app, &tool_paths.mediainfo),
+        &resolve_path(app, &tool_paths.mkvmerge),
+        &work_dir,
+    );
+    let probed = state
+        .probe
+        .probe(&tools, &hdr_path)
+        .and_then(|hdr| Ok((hdr, state.probe.probe(&tools, &dv_path)?)));
+    let (hdr, dv) = match probed {
+        Ok(reports) => reports,
+        Err(err) => {
+            analysis.issues.push(format!("Probe failed: {}", err));
+            analysis.error = Some(err);
+            return analysis;
+        }
+    };
+    analysis.hdr = Some(summarize(&hdr_path, &hdr));
+    analysis.dv = Some(summarize(&dv_path, &dv));
+
+    if !frame_rates_match(&hdr, &dv, fps_tolerance) {
+        analysis.issues.push(format!(
+            "Frame rate mismatch - DV: {} | HDR: {}",
+            dv.fps_label(),
+            hdr.fps_label()
+        ));
+    }
+    if !dv.has_dolby_vision() {
+        analysis.issues.push(format!(
+            "DV input carries no Dolby Vision (HDR format: {})",
+            dv.hdr_format.as_deref().unwrap_or("none")
+        ));
+    }
+    match dv.dolby_vision_profile() {
+        Some(5) => analysis.warnings.push(
+            "DV input is profile 5 (IPT); the converted RPU may shift colors".to_string(),
+        ),
+        Some(7) => analysis.warnings.push(format!(
+            "DV input is profile 7 ({}); FEL/MEL is checked against the FEL policy during the run",
+            dv.dolby_vision_label().unwrap_or_default()
+        )),
+        _ => {}
+    }
+    if let Some(depth) = hdr.bit_depth.filter(|depth| *depth < 10) {
+        analysis
+            .issues
+            .push(format!("HDR base is {}-bit; Dolby Vision needs a 10-bit stream", depth));
+    }
+    if let (Some(hdr_depth), Some(dv_depth)) = (hdr.bit_depth, dv.bit_depth) {
+        if hdr_depth != dv_depth {
+            analysis
+                .warnings
+                .push(format!("Bit depth differs - DV: {} | HDR: {}", dv_depth, hdr_depth));
+        }
+    }
+    if hdr.width != dv.width {
+        analysis
+            .warnings
+            .push(format!("Width differs - DV: {} | HDR: {}", dv.width, hdr.width));
+    }
+    if hdr.has_dolby_vision() {
+        analysis
+            .warnings
+            .push("HDR input already carries Dolby Vision; its RPU will be replaced".to_string());
+    }
+    if let (Some(hdr_secs), Some(dv_secs)) = (hdr.duration_secs, dv.duration_secs) {
+        if (hdr_secs - dv_secs).abs() > DURATION_TOLERANCE_SECS {
+            analysis.warnings.push(format!(
+                "Duration differs by {:.2}s - DV: {:.2}s | HDR: {:.2}s",
+                (hdr_secs - dv_secs).abs(),
+                dv_secs,
+                hdr_secs
+            ));
+        }
+    }
+
+    analysis.delay = estimate_delay(&hdr, &dv);
+    if let Some(delay) = &analysis.delay {
+        analysis.warnings.push(format!(
+            "Streams look offset by {} frame(s); try a DV delay of {} ms (from {})",
+            delay.frames, delay.delay_ms, delay.source
+        ));
+    }
+
+    analysis.delay_plan = Some(dv_delay_plan(input.dv_delay_ms, hdr.fps));
+
+    let fit = match dv_height_on_hdr(&hdr, &dv, scale_dv_frame) {
+        Ok(dv_height) => Some(frame_fit(hdr.height, dv_height)),
+        Err(err) => {
+            analysis.issues.push(err);
+            None
+        }
+    };
+    analysis.frame_fit = fit;
+    if detect_letterbox && fit == Some(FrameFit::Same) {
+        let ffmpeg = resolve_path(app, &tool_paths.ffmpeg);
+        match detect_letterbox_bars(app, &ffmpeg, &hdr_path, &hdr, &work_dir) {
+            Ok(bars) => analysis.letterbox = bars,
+            Err(err) => analysis.warnings.push(format!("Letterbox detection failed: {}", err)),
+        }
+    }
+
+    if let Some(donor) = &hdr10plus_path {
+        match check_hdr10plus(state, &tools, donor) {
+            Ok(present) => {
+                analysis.hdr10plus_present = Some(present);
+                analysis.hdr10plus_inject = present;
+                if !present {
+                    analysis.warnings.push(format!(
+                        "HDR10+ donor {} has no dynamic metadata; the run would be Dolby Vision only",
+                        donor.display()
+                    ));
+                }
+            }
+            Err(err) => analysis.warnings.push(format!("Could not probe the HDR10+ donor: {}", err)),
+        }
+    }
+
+    analysis.compatible = analysis.issues.is_empty();
+    analysis
+}
+
+fn log_verdict(app: &AppHandle, analysis: &PairAnalysis) {
+    let name = Path::new(&analysis.hdr_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| analysis.hdr_path.clone());
+    if analysis.compatible {
+        emit_log(
+            app,
+            "success",
+            format!("{}: compatible ({} warning(s))", name, analysis.warnings.len()),
+        );
+    } else {
+        emit_log(
+            app,
+            "error",
+            format!("{}: {}", name, analysis.issues.join("; ")),
+        );
+    }
+    for warning in &analysis.warnings {
+        emit_log(app, "warning", format!("{}: {}", name, warning));
+    }
+}
+
+/// Analyze a single pair or every pair of a folder.
+fn analyze_item(
+    app: &AppHandle,
+    state: &ProcessingState,
+    tool_paths: &ToolPaths,
+    options: &PipelineOptions,
+    input: &PairInput,
+) -> Result<AnalysisReport, String> {
+    let (hdr_path, dv_path, hdr10plus_path) = (input.hdr, input.dv, input.hdr10plus);
+    let mut pairs = Vec::new();
+    let mut unpaired = Vec::new();
+    if hdr_path.is_dir() && dv_path.is_dir() {
+        let scan = scan_folder_pairs(hdr_path, dv_path, options.output_structure.recursive())?;
+        let donor_files: Vec<String> = match hdr10plus_path.filter(|path| path.is_dir()) {
+            Some(dir) => fs::read_dir(dir)
+                .map_err(|e| e.to_string())?
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .collect(),
+            None => Vec::new(),
+        };
+        for pair in &scan.pairs {
+            let donor: Option<PathBuf> = match hdr10plus_path {
+                Some(dir) if dir.is_dir() => {
+                    find_matching_dv_file(&donor_files, &pair.base).map(|name| dir.join(name))
+                }
+                other => other.map(Path::to_path_buf),
+            };
+            let hdr_file = hdr_path.join(&pair.hdr_file);
+            let dv_file = dv_path.join(&pair.dv_file);
+            let pair_input = PairInput {
+                hdr: &hdr_file,
+                dv: &dv_file,
+                hdr10plus: donor.as_deref(),
+                dv_delay_ms: input.dv_delay_ms,
+            };
+            let analysis = analyze_pair(app, state, tool_paths, &pair_input, options.detect_letterbox, options.fps_tolerance, options.scale_dv_frame);
+            log_verdict(app, &analysis);
+            pairs.push(analysis);
+        }
+        for file in &scan.unmatched {
+            emit_log(app, "warning", format!("{}: no matching DV file", file));
+        }
+        for file in &scan.unmatched_dv {
+            emit_log(app, "warning", format!("{}: no matching HDR file", file));
+        }
+        unpaired = scan.unmatched;
+    } else {
+        let analysis = analyze_pair(app, state, tool_paths, input, options.detect_letterbox, options.fps_tolerance, options.scale_dv_frame);
+        log_verdict(app, &analysis);
+        pairs.push(analysis);
+    }
+
+    let compatible = pairs.iter().filter(|pair| pair.compatible).count();
+    emit_log(
+        app,
+        "info",
+        format!("Analysis: {} of {} pair(s) compatible", compatible, pairs.len()),
+    );
+    Ok(AnalysisReport {
+        queue_id: None,
+        pairs,
+        unpaired,
+        compatible,
+    })
+}
+
+/// `mode: "analyze"`: report on every pair of the request instead of
+/// processing it.
+pub fn run_analysis(
+    app: &AppHandle,
+    state: &ProcessingState,
+    options: &PipelineOptions,
+    request: &ProcessingRequest,
+) -> Result<(), String> {
+    let hdr10plus_path = Some(PathBuf::from(&request.hdr10plus_path))
+        .filter(|path| !path.as_os_str().is_empty());
+    if request.queue.is_empty() {
+        let input = PairInput {
+            hdr: Path::new(&request.hdr_path),
+            dv: Path::new(&request.dv_path),
+            hdr10plus: hdr10plus_path.as_deref(),
+            dv_delay_ms: request.dv_delay_ms.unwrap_or(0.0),
+        };
+        let report = analyze_item(app, state, &request.tool_paths, options, &input)?;
+        emit_event(app, "processing:analysis", report);
+        return Ok(());
+    }
+    for item in &request.queue {
+        let donor = item.resolve_hdr10plus_path(hdr10plus_path.as_deref());
+        let input = PairInput {
+            hdr: Path::new(&item.hdr_path),
+            dv: Path::new(&item.dv_path),
+            hdr10plus: donor.as_deref(),
+            dv_delay_ms: request.dv_delay_ms.unwrap_or(0.0),
+        };
+        let report = analyze_item(app, state, &request.tool_paths, options, &input)?;
+        emit_event(
+            app,
+            "processing:analysis",
+            AnalysisReport {
+                queue_id: Some(item.id.clone()),
+                ..report
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Analyze one pair on its own, outside a run.
+pub fn analyze_single_pair(
+    app: &AppHandle,
+    state: &ProcessingState,
+    tool_paths: &ToolPaths,
+    input: &PairInput,
+) -> AnalysisReport {
+    let analysis = analyze_pair(app, state, tool_paths, input, false, DEFAULT_FPS_TOLERANCE, false);
+    log_verdict(app, &analysis);
+    AnalysisReport {
+        queue_id: None,
+        compatible: usize::from(analysis.compatible),
+        pairs: vec![analysis],
+        unpaired: Vec::new(),
+    }
+}
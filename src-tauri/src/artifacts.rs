@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::utils::{emit_event, emit_log};
+
+/// One intermediate file left on disk by `keep_temp_files`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Artifact {
+    pub path: String,
+    pub size: u64,
+    /// `"rpu"`, `"hevc"`, `"mka"`, `"json"` or `"other"`.
+    pub kind: String,
+}
+
+/// Sent as `processing:artifacts` when a file finishes with its temp files kept.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactsPayload {
+    pub output: String,
+    pub artifacts: Vec<Artifact>,
+    pub total_size: u64,
+}
+
+fn artifact_kind(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("bin") => "rpu",
+        Some("hevc") | Some("h265") => "hevc",
+        Some("mka") => "mka",
+        Some("json") => "json",
+        _ => "other",
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1 << 30 {
+        format!("{:.2} GB", bytes as f64 / (1u64 << 30) as f64)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1u64 << 20) as f64)
+    }
+}
+
+/// List the temp files of `output` that still exist and report them, so
+/// the UI can show what was kept and offer to delete it.
+pub fn report_kept_artifacts(app: &AppHandle, output: &Path, temp_files: &[PathBuf]) {
+    let mut artifacts: Vec<Artifact> = Vec::new();
+    for file in temp_files {
+        let Ok(metadata) = fs::metadata(file) else {
+            continue;
+        };
+        let path = file.to_string_lossy().to_string();
+        if !metadata.is_file() || artifacts.iter().any(|artifact| artifact.path == path) {
+            continue;
+        }
+        artifacts.push(Artifact {
+            path,
+            size: metadata.len(),
+            kind: artifact_kind(file).to_string(),
+        });
+    }
+    let total_size = artifacts.iter().map(|artifact| artifact.size).sum();
+    emit_log(
+        app,
+        "info",
+        format!(
+            "Kept {} temporary file(s), {} in total",
+            artifacts.len(),
+            format_size(total_size)
+        ),
+    );
+    emit_event(
+        app,
+        "processing:artifacts",
+        ArtifactsPayload {
+            output: output.to_string_lossy().to_string(),
+            artifacts,
+            total_size,
+        },
+    );
+}
+
+/// Delete kept artifacts, returning the bytes freed. Only regular files are
+/// removed; every path is attempted and the failures reported together.
+pub fn delete_artifacts(app: &AppHandle, paths: &[String]) -> Result<u64, String> {
+    let mut freed = 0u64;
+    let mut failures = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        let result = match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_file() => fs::remove_file(path)
+                .map(|_| metadata.len())
+                .map_err(|e| e.to_string()),
+            Ok(_) => Err("not a regular file".to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        match result {
+            Ok(size) => freed += size,
+            Err(err) => failures.push(format!("{}: {}", path.display(), err)),
+        }
+    }
+    emit_log(
+        app,
+        if failures.is_empty() { "success" } else { "warning" },
+        format!(
+            "Deleted {} artifact(s), freed {}",
+            paths.len() - failures.len(),
+            format_size(freed)
+        ),
+    );
+    if failures.is_empty() {
+        Ok(freed)
+    } else {
+        Err(format!("Could not delete {} artifact(s):\n  {}", failures.len(), failures.join("\n  ")))
+    }
+}
@@ -0,0 +1,224 @@
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+
+const CHAPTERS_HEADER: &str = "<?xml version=\"1.0\"?>\n<!DOCTYPE Chapters SYSTEM \"matroskachapters.dtd\">\n";
+
+/// Byte ranges of the `<tag>` elements directly inside `text`, skipping
+/// ones nested in an element of the same name.
+fn top_level_spans(text: &str, tag: &str) -> Vec<(usize, usize)> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut spans = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0usize;
+    let mut pos = 0usize;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        if rest.starts_with(&open) {
+            if depth == 0 {
+                start = pos;
+            }
+            depth += 1;
+            pos += open.len();
+        } else if rest.starts_with(&close) && depth > 0 {
+            depth -= 1;
+            pos += close.len();
+            if depth == 0 {
+                spans.push((start, pos));
+            }
+        } else {
+            pos += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+        }
+    }
+    spans
+}
+
+/// Check that `xml` is a well-formed Matroska chapters file: balanced tags
+/// under a `<Chapters>` root, and at least one chapter whose start time reads
+/// `HH:MM:SS(.nnn)`. Returns the number of chapters.
+pub fn check_chapters_xml(xml: &str) -> Result<usize, String> {
+    let tag_re = Regex::new(r"(?s)<!--.*?-->|<[?!][^>]*>|<(/?)([A-Za-z_][\w.-]*)[^>]*?(/?)>").map_err(|e| e.to_string())?;
+    let mut stack: Vec<String> = Vec::new();
+    let mut root = None;
+    for caps in tag_re.captures_iter(xml) {
+        let Some(name) = caps.get(2).map(|m| m.as_str()) else {
+            continue;
+        };
+        let closing = !caps[1].is_empty();
+        let empty = !caps[3].is_empty();
+        if closing {
+            match stack.pop() {
+                Some(open) if open == name => {}
+                Some(open) => return Err(format!("</{}> closes <{}>", name, open)),
+                None => return Err(format!("</{}> has no opening tag", name)),
+            }
+        } else {
+            if stack.is_empty() && root.replace(name.to_string()).is_some() {
+                return Err("More than one root element".to_string());
+            }
+            if !empty {
+                stack.push(name.to_string());
+            }
+        }
+    }
+    if let Some(open) = stack.last() {
+        return Err(format!("<{}> is never closed", open));
+    }
+    if root.as_deref() != Some("Chapters") {
+        return Err("Not a Matroska chapters file: the root element must be <Chapters>".to_string());
+    }
+
+    let start_re = Regex::new(r"<ChapterTimeStart>\s*([^<]*?)\s*</ChapterTimeStart>").map_err(|e| e.to_string())?;
+    let time_re = Regex::new(r"^\d+:\d{2}:\d{2}(\.\d+)?$").map_err(|e| e.to_string())?;
+    let mut count = 0;
+    for caps in start_re.captures_iter(xml) {
+        if !time_re.is_match(&caps[1]) {
+            return Err(format!("Chapter start {} is not HH:MM:SS.nnn", &caps[1]));
+        }
+        count += 1;
+    }
+    if count == 0 {
+        return Err("The file has no chapters with a start time".to_string());
+    }
+    Ok(count)
+}
+
+/// Read and check a user-supplied chapters XML file.
+pub fn read_chapters_file(path: &Path) -> Result<usize, String> {
+    let xml = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    check_chapters_xml(&xml).map_err(|e| format!("Chapters file {} is not valid: {}", path.display(), e))
+}
+
+/// Flatten extracted chapters to one plain edition for players that break
+/// on ordered chapters: the default edition (else the first) is kept, its
+/// ordered flag removed, and chapters linking to other files dropped.
+/// Returns the new XML and a summary, or `None` when there is nothing to
+/// drop.
+pub fn drop_editions(xml: &str) -> Result<Option<(String, String)>, String> {
+    let editions = top_level_spans(xml, "EditionEntry");
+    if editions.is_empty() {
+        return Ok(None);
+    }
+    let ordered_re = Regex::new(r"\s*<EditionFlagOrdered>\s*1\s*</EditionFlagOrdered>").map_err(|e| e.to_string())?;
+    let flag_re = Regex::new(r"\s*<EditionFlagOrdered>[^<]*</EditionFlagOrdered>").map_err(|e| e.to_string())?;
+    let default_re = Regex::new(r"<EditionFlagDefault>\s*1\s*</EditionFlagDefault>").map_err(|e| e.to_string())?;
+
+    let kept = editions
+        .iter()
+        .position(|(start, end)| default_re.is_match(&xml[*start..*end]))
+        .unwrap_or(0);
+    let (start, end) = editions[kept];
+    let edition = &xml[start..end];
+    let ordered = ordered_re.is_match(edition);
+    let linked: Vec<(usize, usize)> = top_level_spans(edition, "ChapterAtom")
+        .into_iter()
+        .filter(|(atom_start, atom_end)| edition[*atom_start..*atom_end].contains("<ChapterSegmentUID"))
+        .collect();
+    if editions.len() == 1 && !ordered && linked.is_empty() {
+        return Ok(None);
+    }
+
+    let mut flattened = String::with_capacity(edition.len());
+    let mut pos = 0;
+    for (atom_start, atom_end) in &linked {
+        flattened.push_str(&edition[pos..*atom_start]);
+        pos = *atom_end;
+    }
+    flattened.push_str(&edition[pos..]);
+    let flattened = flag_re.replace_all(&flattened, "").to_string();
+    let remaining = top_level_spans(&flattened, "ChapterAtom").len();
+    if remaining == 0 {
+        return Err("every chapter of the edition links to another file".to_string());
+    }
+
+    let mut summary = format!("kept edition {} of {}", kept + 1, editions.len());
+    if ordered {
+        summary.push_str(", ordered flag removed");
+    }
+    if !linked.is_empty() {
+        summary.push_str(&format!(", {} linked chapter(s) dropped", linked.len()));
+    }
+    let xml = format!("{}<Chapters>\n  {}\n</Chapters>\n", CHAPTERS_HEADER, flattened);
+    check_chapters_xml(&xml)?;
+    Ok(Some((xml, summary)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(start: &str, extra: &str) -> String {
+        format!(
+            "<ChapterAtom><ChapterTimeStart>{}</ChapterTimeStart>{}<ChapterDisplay><ChapterString>Chapter</ChapterString></ChapterDisplay></ChapterAtom>",
+            start, extra
+        )
+    }
+
+    fn chapters(editions: &[String]) -> String {
+        let editions: String = editions
+            .iter()
+            .map(|edition| format!("<EditionEntry>{}</EditionEntry>", edition))
+            .collect();
+        format!("{}<Chapters>{}</Chapters>\n", CHAPTERS_HEADER, editions)
+    }
+
+    #[test]
+    fn valid_chapters_are_counted() {
+        let xml = chapters(&[format!("{}{}", atom("00:00:00.000", ""), atom("00:12:34.567", ""))]);
+        assert_eq!(check_chapters_xml(&xml), Ok(2));
+    }
+
+    #[test]
+    fn malformed_chapters_are_rejected() {
+        let unclosed = format!("{}<Chapters><EditionEntry>{}</Chapters>", CHAPTERS_HEADER, atom("00:00:00.000", ""));
+        assert_eq!(
+            check_chapters_xml(&unclosed),
+            Err("</Chapters> closes <EditionEntry>".to_string())
+        );
+        let wrong_root = "<Tags><Tag></Tag></Tags>";
+        assert!(check_chapters_xml(wrong_root).unwrap_err().contains("<Chapters>"));
+        let bad_time = chapters(&[atom("12:34", "")]);
+        assert_eq!(
+            check_chapters_xml(&bad_time),
+            Err("Chapter start 12:34 is not HH:MM:SS.nnn".to_string())
+        );
+        let empty = chapters(&[String::new()]);
+        assert!(check_chapters_xml(&empty).is_err());
+    }
+
+    #[test]
+    fn a_single_plain_edition_is_left_alone() {
+        let xml = chapters(&[atom("00:00:00.000", "")]);
+        assert_eq!(drop_editions(&xml), Ok(None));
+    }
+
+    #[test]
+    fn the_default_edition_is_kept_and_flattened() {
+        let first = atom("00:00:00.000", "");
+        let second = format!(
+            "<EditionFlagDefault>1</EditionFlagDefault><EditionFlagOrdered>1</EditionFlagOrdered>{}{}",
+            atom("00:00:00.000", ""),
+            atom("00:05:00.000", "<ChapterSegmentUID format=\"hex\">0A1B</ChapterSegmentUID>")
+        );
+        let (xml, summary) = drop_editions(&chapters(&[first, second])).unwrap().unwrap();
+        assert_eq!(
+            summary,
+            "kept edition 2 of 2, ordered flag removed, 1 linked chapter(s) dropped"
+        );
+        assert_eq!(top_level_spans(&xml, "EditionEntry").len(), 1);
+        assert!(!xml.contains("EditionFlagOrdered"));
+        assert!(!xml.contains("ChapterSegmentUID"));
+        assert_eq!(check_chapters_xml(&xml), Ok(1));
+    }
+
+    #[test]
+    fn an_edition_of_only_linked_chapters_is_an_error() {
+        let linked = format!(
+            "<EditionFlagOrdered>1</EditionFlagOrdered>{}",
+            atom("00:00:00.000", "<ChapterSegmentUID>0A1B</ChapterSegmentUID>")
+        );
+        assert!(drop_editions(&chapters(&[linked])).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+
+use crate::models::ProcessingState;
+use crate::utils::{emit_log, emit_step};
+
+pub const HASH_STEP_ID: usize = 9;
+pub const HASH_STEP_NAME: &str = "Hash Output";
+
+const HASH_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+const HASH_REPORT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Integrity record written next to a finished output as `<output>.json`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputSidecar {
+    pub file: String,
+    pub size: u64,
+    /// Only when `hash_output` is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+fn sidecar_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_os_string();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+fn write_sidecar(output: &Path, size: u64, sha256: Option<String>) -> Result<OutputSidecar, String> {
+    let sidecar = OutputSidecar {
+        file: output
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        size,
+        sha256,
+    };
+    let json = serde_json::to_vec_pretty(&sidecar).map_err(|e| e.to_string())?;
+    let path = sidecar_path(output);
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(sidecar)
+}
+
+/// Write a sidecar with the size of every file in `outputs`; with `hash`,
+/// SHA-256 them first as one step and add the digests to the run's metrics.
+/// Runs after the mux, so it only ever reads finished files.
+pub fn write_sidecars(
+    app: &AppHandle,
+    state: &ProcessingState,
+    outputs: &[PathBuf],
+    hash: bool,
+) -> Result<Vec<OutputSidecar>, String> {
+    if !hash {
+        emit_step(app, HASH_STEP_ID, HASH_STEP_NAME, "completed", 100);
+        return outputs
+            .iter()
+            .map(|output| {
+                let size = fs::metadata(output)
+                    .map_err(|e| format!("Failed to read {}: {}", output.display(), e))?
+                    .len();
+                write_sidecar(output, size, None)
+            })
+            .collect();
+    }
+    emit_step(app, HASH_STEP_ID, HASH_STEP_NAME, "active", 0);
+
+    let total: u64 = outputs
+        .iter()
+        .map(|output| fs::metadata(output).map(|m| m.len()).unwrap_or(0))
+        .sum();
+    let mut hashed = 0u64;
+    let started = Instant::now();
+    let mut sidecars = Vec::with_capacity(outputs.len());
+
+    for output in outputs {
+        let result = hash_file(app, state, output, &mut hashed, total, started)
+            .and_then(|(size, sha256)| write_sidecar(output, size, Some(sha256)));
+        match result {
+            Ok(sidecar) => {
+                emit_log(
+                    app,
+                    "success",
+                    format!("SHA-256 {}: {}", output.display(), sidecar.sha256.as_deref().unwrap_or_default()),
+                );
+                state.metrics.record_hash(sidecar.clone());
+                sidecars.push(sidecar);
+            }
+            Err(err) => {
+                let status = if err == "Processing cancelled" { "pending" } else { "error" };
+                emit_step(app, HASH_STEP_ID, HASH_STEP_NAME, status, 0);
+                return Err(err);
+            }
+        }
+    }
+
+    emit_step(app, HASH_STEP_ID, HASH_STEP_NAME, "completed", 100);
+    Ok(sidecars)
+}
+
+/// Stream `path` through SHA-256, returning its size and lowercase hex digest.
+fn hash_file(
+    app: &AppHandle,
+    state: &ProcessingState,
+    path: &Path,
+    hashed: &mut u64,
+    total: u64,
+    started: Instant,
+) -> Result<(u64, String), String> {
+    let mut reader = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_CHUNK_BYTES];
+    let mut size = 0u64;
+    let mut last_report = Instant::now();
+    loop {
+        if *state.cancel_flag.lock().map_err(|_| "State lock failed")? {
+            return Err("Processing cancelled".to_string());
+        }
+        let read = reader.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+        *hashed += read as u64;
+
+        if last_report.elapsed() >= HASH_REPORT_INTERVAL {
+            last_report = Instant::now();
+            let percent = ((*hashed as f64 / total.max(1) as f64) * 100.0).min(99.0) as u8;
+            let speed = *hashed as f64 / 1_048_576.0 / started.elapsed().as_secs_f64().max(0.001);
+            emit_step(
+                app,
+                HASH_STEP_ID,
+                &format!("{} ({:.1} MB/s)", HASH_STEP_NAME, speed),
+                "active",
+                percent,
+            );
+        }
+    }
+    let digest = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Ok((size, digest))
+}
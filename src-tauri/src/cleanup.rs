@@ -0,0 +1,168 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use tauri::AppHandle;
+
+use crate::utils::emit_log;
+
+/// Tries per temp file; a file still locked after the last one is leaked.
+const REMOVE_ATTEMPTS: u32 = 3;
+const REMOVE_RETRY_DELAY: Duration = Duration::from_millis(500);
+/// Temp files that could not be removed, swept again on the next startup.
+const LEAK_LEDGER: &str = "leaked_temp.json";
+
+/// Serializes ledger updates from parallel workers.
+static LEDGER_LOCK: Mutex<()> = Mutex::new(());
+
+/// Remove `path`, retrying while another process (an antivirus scan, a
+/// killed tool that has not let go yet) still holds it. Returns the bytes
+/// freed; a file that is already gone frees nothing.
+fn remove_with_retry(app: &AppHandle, path: &Path) -> Result<u64, String> {
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.to_string()),
+    };
+    let mut attempt = 1;
+    loop {
+        match fs::remove_file(path) {
+            Ok(()) => return Ok(size),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(err) if attempt < REMOVE_ATTEMPTS => {
+                emit_log(
+                    app,
+                    "debug",
+                    format!(
+                        "Could not remove {} (attempt {}/{}): {}",
+                        path.display(),
+                        attempt,
+                        REMOVE_ATTEMPTS,
+                        err
+                    ),
+                );
+                thread::sleep(REMOVE_RETRY_DELAY);
+                attempt += 1;
+            }
+            Err(err) => return Err(err.to_string()),
+        }
+    }
+}
+
+fn ledger_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path_resolver().app_data_dir().map(|dir| dir.join(LEAK_LEDGER))
+}
+
+fn read_ledger(path: &Path) -> Vec<PathBuf> {
+    fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn write_ledger(path: &Path, files: &[PathBuf]) -> Result<(), String> {
+    if files.is_empty() {
+        return match fs::remove_file(path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.to_string()),
+            _ => Ok(()),
+        };
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(files).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+fn record_leaks(app: &AppHandle, leaked: &[PathBuf]) {
+    let Some(path) = ledger_path(app) else {
+        return;
+    };
+    let _guard = LEDGER_LOCK.lock();
+    let mut files = read_ledger(&path);
+    for file in leaked {
+        if !files.contains(file) {
+            files.push(file.clone());
+        }
+    }
+    if let Err(err) = write_ledger(&path, &files) {
+        emit_log(app, "debug", format!("Could not record leaked temp files: {}", err));
+    }
+}
+
+/// Delete a finished file's temp files, returning the bytes freed. Files
+/// that stay locked are logged with the reason and remembered, so
+/// `cleanup_temp` can remove them on the next start.
+pub fn remove_temp_files(app: &AppHandle, files: &[PathBuf]) -> u64 {
+    let mut freed = 0u64;
+    let mut leaked = Vec::new();
+    for file in files {
+        if leaked.contains(file) {
+            continue;
+        }
+        match remove_with_retry(app, file) {
+            Ok(size) => freed += size,
+            Err(err) => {
+                emit_log(
+                    app,
+                    "warning",
+                    format!("Could not remove temp file {}: {}", file.display(), err),
+                );
+                leaked.push(file.clone());
+            }
+        }
+    }
+    if !leaked.is_empty() {
+        emit_log(
+            app,
+            "warning",
+            format!("{} temp file(s) left behind; they will be removed on the next start", leaked.len()),
+        );
+        record_leaks(app, &leaked);
+    }
+    freed
+}
+
+/// Remove the temp files earlier runs could not, at startup. Ones that are
+/// still locked stay in the ledger for the next start.
+pub fn cleanup_temp(app: &AppHandle) {
+    let Some(path) = ledger_path(app) else {
+        return;
+    };
+    let _guard = LEDGER_LOCK.lock();
+    let files = read_ledger(&path);
+    if files.is_empty() {
+        return;
+    }
+    let mut remaining = Vec::new();
+    let mut removed = 0usize;
+    for file in files {
+        match fs::remove_file(&file) {
+            Ok(()) => removed += 1,
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                emit_log(
+                    app,
+                    "debug",
+                    format!("Leaked temp file {} is still locked: {}", file.display(), err),
+                );
+                remaining.push(file);
+            }
+        }
+    }
+    emit_log(
+        app,
+        "info",
+        format!(
+            "Removed {} temp file(s) left by earlier runs; {} still locked",
+            removed,
+            remaining.len()
+        ),
+    );
+    if let Err(err) = write_ledger(&path, &remaining) {
+        emit_log(app, "debug", format!("Could not update the leaked temp ledger: {}", err));
+    }
+}
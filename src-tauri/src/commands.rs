@@ -1,101 +1,145 @@
-use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::io::Write;
+use std::time::{Duration, Instant};
 use tauri::AppHandle;
-use regex::Regex;
 
-use crate::models::{ProcessingState, ProcessingRequest};
-use crate::processing::{process_queue_item, run_pipeline};
+use crate::artifacts;
+use crate::estimate::{self, EstimateReport};
+use crate::event_socket::{EventSocket, EventSocketInfo};
+use crate::job::{self, ImportedJob};
+use crate::models::{ProcessingState, ProcessingRequest, RemuxStrategy, StopReason, ToolPaths};
+use crate::selftest::{run_self_test, SelfTestReport};
+use crate::schedule::{parse_start_at, preflight, wait_until};
+use crate::support;
+use crate::title_memo::{self, TitleMemoEntry};
+use crate::watch::{WatchFolder, WatchStatus};
+use crate::web_status::{status_snapshot, StatusSnapshot};
+use crate::analyze::{analyze_single_pair, run_analysis, AnalysisReport, PairInput};
+use crate::download::download_tool;
+use crate::processing::{
+    check_add_dv_inputs, check_mkvmerge_version, check_unmatched, claim_batch_output, claim_output, claim_queue_outputs, emit_scan_report, failure_report, partial_end_step,
+    process_queue_item, run_pipeline,
+};
 use crate::utils::{
-    emit_log, emit_status, compute_output_for_batch, compute_output_for_single,
-    find_matching_dv_file
+    emit_event, emit_log, emit_processing_status, emit_status, emit_status_with_reason,
+    effective_parallel_tasks, compute_output_for_single, input_bytes, resolve_path, scan_folder_pairs, warn_output_in_inputs,
 };
 
-#[tauri::command]
-pub async fn download_file(url: String, filename: String, app: AppHandle) -> Result<String, String> {
-    emit_log(&app, "info", format!("Downloading {}...", filename));
-    
-    // Use AppData directory to avoid permission issues (OS Error 5 in Program Files)
-    let bin_path = app.path_resolver()
-        .app_data_dir()
-        .ok_or("Could not resolve app data directory".to_string())?
-        .join("bin");
-
-    if !bin_path.exists() {
-        fs::create_dir_all(&bin_path).map_err(|e| e.to_string())?;
-    }
+/// How long `reset_state` waits for a cancelled run to wind down.
+const RESET_WAIT: Duration = Duration::from_secs(30);
 
-    let target_path = bin_path.join(&filename);
-    let mut last_error = String::from("Unknown error");
-    let max_retries = 3;
-
-    for attempt in 1..=max_retries {
-        if attempt > 1 {
-            emit_log(&app, "info", format!("Retrying download (attempt {}/{})...", attempt, max_retries));
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-        }
-
-        let download_result = async {
-            let response = reqwest::get(&url)
-                .await
-                .map_err(|e| format!("Failed to connect: {}", e))?;
-            
-            if !response.status().is_success() {
-                return Err(format!("Download failed with status: {}", response.status()));
-            }
+#[tauri::command]
+pub async fn download_file(
+    url: String,
+    filename: String,
+    app: AppHandle,
+    state: tauri::State<'_, ProcessingState>,
+) -> Result<String, String> {
+    let path = download_tool(&app, Arc::clone(&state.download_cancel), &url, &filename).await?;
+    Ok(path.to_string_lossy().to_string())
+}
 
-            let content = response.bytes()
-                .await
-                .map_err(|e| format!("Failed to read bytes: {}", e))?;
-
-            // Write to a temporary file first to avoid corruption? 
-            // For now, simplicity: write to target directly but truncate.
-            let mut file = fs::File::create(&target_path)
-                .map_err(|e| format!("Failed to create file: {}", e))?;
-            
-            file.write_all(&content)
-                .map_err(|e| format!("Failed to write to file: {}", e))?;
-                
-            Ok(())
-        }.await;
-
-        match download_result {
-            Ok(_) => {
-                emit_log(&app, "success", format!("Downloaded {} to {}", filename, target_path.display()));
-                return Ok(target_path.to_string_lossy().to_string());
-            },
-            Err(e) => {
-                emit_log(&app, "warning", format!("Download attempt {} failed: {}", attempt, e));
-                last_error = e;
-            }
-        }
+#[tauri::command]
+pub fn cancel_download(state: tauri::State<'_, ProcessingState>) {
+    if let Ok(mut flag) = state.download_cancel.lock() {
+        *flag = true;
     }
-
-    Err(format!("Failed after {} attempts. Last error: {}", max_retries, last_error))
 }
 
 #[tauri::command]
 pub async fn start_processing(
     app: AppHandle,
     state: tauri::State<'_, ProcessingState>,
+    watch: tauri::State<'_, WatchFolder>,
     request: ProcessingRequest,
 ) -> Result<(), String> {
-    {
-        let mut guard = state.cancel_flag.lock().map_err(|_| "State lock failed")?;
-        *guard = false;
+    if watch.status().running {
+        return Err("Watch mode is running; stop it before starting a run".to_string());
+    }
+    // Resetting would wipe the live run's flags, claims and emitters.
+    if state.is_running() {
+        return Err("A run is already in progress".to_string());
+    }
+    state.reset_run();
+    if let Some(window) = &request.allowed_window {
+        window.bounds()?;
+        if let Ok(mut current) = state.window.lock() {
+            *current = Some(window.clone());
+        }
+    }
+    state
+        .probe
+        .configure(&app, request.probe_concurrency, request.probe_retries);
+    let run_id = state.history.begin(&app, &request);
+    state.metrics.start();
+    state
+        .progress
+        .start(&app, request.progress_interval_ms.map(Duration::from_millis));
+    state.logs.start(&app);
+    if let Some(settings) = request.web_status.as_ref().filter(|settings| settings.enabled) {
+        match state.web.start(&app, settings) {
+            Ok(url) => emit_log(&app, "info", format!("Status page: {}", url)),
+            Err(err) => emit_log(&app, "warning", format!("Status page not started: {}", err)),
+        }
+    }
+    if let Ok(mut level) = state.log_level.lock() {
+        *level = request.log_level;
     }
 
-    emit_status(&app, "processing");
+    let mut options = request.pipeline_options();
+    let (parallel_tasks, parallel_reason) = effective_parallel_tasks(request.parallel_tasks);
+    options.parallel_tasks = parallel_tasks;
+    if request.mode == "add-dv" {
+        // Only the video is rebuilt; the MKV's other tracks go in as they are.
+        options.remux_strategy = RemuxStrategy::ReplaceVideo;
+    }
+
+    let scheduled = request.start_at.clone().filter(|start_at| !start_at.trim().is_empty());
+    if scheduled.is_none() {
+        emit_processing_status(&app, parallel_tasks);
+    }
     emit_log(&app, "info", "Starting Hybrid DV HDR processing...");
+    if let Some(run_id) = run_id {
+        emit_log(&app, "info", format!("Run {} (logged for support bundles)", run_id));
+    }
+    emit_log(
+        &app,
+        "info",
+        format!("Parallel tasks: {} ({})", parallel_tasks, parallel_reason),
+    );
+    check_mkvmerge_version(&app, &resolve_path(&app, &request.tool_paths.mkvmerge));
 
-    let tool_paths = request.tool_paths;
+    let partial_end = partial_end_step(&options);
+    let tool_paths = request.tool_paths.clone();
     let app_handle = app.clone();
     let state_inner = state.inner().clone();
 
     let result = tauri::async_runtime::spawn_blocking(move || {
-        if request.mode == "batch" {
+        // A scheduled run is checked now so problems surface before the
+        // wait, then holds until its start time without touching any file.
+        if let Some(start_at) = &scheduled {
+            let start = parse_start_at(start_at)?;
+            preflight(&app_handle, &request, &options)?;
+            wait_until(&app_handle, &state_inner, start)?;
+            emit_processing_status(&app_handle, options.parallel_tasks);
+        }
+        let multi_file = request.mode == "batch" || Path::new(&request.hdr_path).is_dir();
+        if options.base_hevc_path.is_some() && multi_file && request.mode != "analyze" {
+            return Err("A prepared HDR10 base layer belongs to one file; clear it for folder and queue runs".to_string());
+        }
+        if request.mode == "add-dv" {
+            check_add_dv_inputs(Path::new(&request.hdr_path), Path::new(&request.dv_path))?;
+            emit_log(
+                &app_handle,
+                "info",
+                "Add DV mode: injecting the RPU into the MKV's video and keeping all of its other tracks",
+            );
+        }
+        if request.mode == "analyze" {
+            emit_log(&app_handle, "info", "Analyze mode: probing inputs only, nothing is extracted");
+            run_analysis(&app_handle, &state_inner, &options, &request)?;
+        } else if request.mode == "batch" {
             if request.queue.is_empty() {
                 return Err("Queue is empty".to_string());
             }
@@ -105,8 +149,15 @@ pub async fn start_processing(
                 format!("Batch mode: {} items", request.queue.len()),
             );
 
+            if let Ok(mut job) = state_inner.job_progress.lock() {
+                for item in &request.queue {
+                    let bytes = input_bytes(&[Path::new(&item.hdr_path), Path::new(&item.dv_path)]);
+                    job.set_expected(&item.id, 1, bytes);
+                }
+            }
+
             let mut handles = Vec::new();
-            let error_state = Arc::new(Mutex::new(None::<String>));
+            let error_state = Arc::new(Mutex::new(Vec::<(String, String)>::new()));
             let hdr10plus_path = if request.hdr10plus_path.is_empty() {
                 None
             } else {
@@ -114,22 +165,29 @@ pub async fn start_processing(
             };
             let dv_delay_ms = request.dv_delay_ms;
             let hdr10plus_delay_ms = request.hdr10plus_delay_ms;
+            claim_queue_outputs(&app_handle, &state_inner, &options, &request.queue)?;
 
             for item in request.queue.iter().cloned() {
                 let app_handle = app_handle.clone();
                 let state = state_inner.clone();
                 let tool_paths = tool_paths.clone();
+                let options = options.clone();
                 let error_state = Arc::clone(&error_state);
                 let keep_temp = request.keep_temp_files;
-                let hdr10plus_path = hdr10plus_path.clone();
-                let dv_delay_ms = dv_delay_ms;
-                let hdr10plus_delay_ms = hdr10plus_delay_ms;
+                let hdr10plus_path = item.resolve_hdr10plus_path(hdr10plus_path.as_deref());
+                let hdr10plus_delay_ms = item.hdr10plus_delay_ms.unwrap_or(hdr10plus_delay_ms);
+
+                let name = Path::new(&item.hdr_path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| item.id.clone());
 
                 let handle = thread::spawn(move || {
                     let result = process_queue_item(
                         app_handle,
                         state,
                         tool_paths,
+                        options,
                         item,
                         hdr10plus_path,
                         dv_delay_ms,
@@ -138,11 +196,9 @@ pub async fn start_processing(
                     );
 
                     if let Err(err) = result {
-                        let _ = error_state.lock().map(|mut e| {
-                            if e.is_none() {
-                                *e = Some(err);
-                            }
-                        });
+                        if let Ok(mut errors) = error_state.lock() {
+                            errors.push((name, err));
+                        }
                     }
                 });
                 handles.push(handle);
@@ -152,57 +208,79 @@ pub async fn start_processing(
                 let _ = handle.join();
             }
 
-            if let Ok(mut guard) = error_state.lock() {
-                if let Some(err) = guard.take() {
-                    return Err(err);
-                }
-            };
+            let errors = std::mem::take(&mut *error_state.lock().map_err(|_| "State lock failed")?);
+            failure_report(errors)?;
         } else if Path::new(&request.hdr_path).is_dir() {
             let hdr10plus_path = if request.hdr10plus_path.is_empty() {
                 None
             } else {
                 Some(PathBuf::from(&request.hdr10plus_path))
             };
-            let mut hdr_files = fs::read_dir(&request.hdr_path)
-                .map_err(|e| e.to_string())?
-                .filter_map(|entry| entry.ok())
-                .filter_map(|entry| entry.file_name().into_string().ok())
-                .collect::<Vec<String>>();
-
-            let mut dv_files = fs::read_dir(&request.dv_path)
-                .map_err(|e| e.to_string())?
-                .filter_map(|entry| entry.ok())
-                .filter_map(|entry| entry.file_name().into_string().ok())
-                .collect::<Vec<String>>();
-
-            hdr_files.sort();
-            dv_files.sort();
+            let mut scan = scan_folder_pairs(
+                Path::new(&request.hdr_path),
+                Path::new(&request.dv_path),
+                options.output_structure.recursive(),
+            )?;
+            scan.order_pairs(Path::new(&request.hdr_path), Path::new(&request.dv_path), options.ordering);
+            for pair in &scan.pairs {
+                emit_log(
+                    &app_handle,
+                    "info",
+                    format!("Paired {} with {} (by {})", pair.hdr_file, pair.dv_file, pair.matched_by),
+                );
+            }
+            let mut report = scan.report(None, Path::new(&request.hdr_path));
+            check_unmatched(&app_handle, &options, &scan)?;
+            let pairs = scan.pairs;
+
+            let bytes = pairs
+                .iter()
+                .map(|pair| {
+                    input_bytes(&[
+                        &Path::new(&request.hdr_path).join(&pair.hdr_file),
+                        &Path::new(&request.dv_path).join(&pair.dv_file),
+                    ])
+                })
+                .sum();
+            if let Ok(mut job) = state_inner.job_progress.lock() {
+                job.set_expected("job", pairs.len(), bytes);
+            }
             let output_base = if request.output_path.is_empty() {
-                tool_paths.default_output.clone()
+                options.default_output_batch.clone()
             } else {
                 request.output_path.clone()
             };
+            warn_output_in_inputs(
+                &app_handle,
+                Path::new(&output_base),
+                &[Path::new(&request.hdr_path), Path::new(&request.dv_path)],
+            );
 
-            for (index, hdr_file) in hdr_files.iter().enumerate() {
-                let base_regex = Regex::new(r"(.*)\.(HDR)+.*")
-                    .map_err(|e| e.to_string())?;
-                let base = base_regex
-                    .captures(hdr_file)
-                    .and_then(|c| c.get(1).map(|m| m.as_str()))
-                    .unwrap_or_else(|| hdr_file.split('.').next().unwrap_or(hdr_file));
-
-                let dv_file = find_matching_dv_file(&dv_files, base)
-                    .or_else(|| dv_files.get(index).cloned())
-                    .ok_or_else(|| format!("No DV file available for {}", hdr_file))?;
+            // Claim every output first so the scan report lists the whole batch.
+            let mut tasks = Vec::with_capacity(pairs.len());
+            for pair in pairs {
+                let hdr_path = PathBuf::from(&request.hdr_path).join(&pair.hdr_file);
+                let dv_path = PathBuf::from(&request.dv_path).join(&pair.dv_file);
+                let output_path = claim_batch_output(
+                    &app_handle,
+                    &state_inner,
+                    &options,
+                    &output_base,
+                    &pair.hdr_file,
+                    &hdr_path,
+                )?;
+                report.add_pair(&pair.hdr_file, &pair.dv_file, &pair.matched_by, &output_path);
+                tasks.push((hdr_path, dv_path, output_path));
+            }
+            emit_scan_report(&app_handle, &options, report);
 
-                let hdr_path = PathBuf::from(&request.hdr_path).join(hdr_file);
-                let dv_path = PathBuf::from(&request.dv_path).join(dv_file);
-                let output_path = compute_output_for_batch(&output_base, hdr_file);
+            for (hdr_path, dv_path, output_path) in tasks {
 
                 run_pipeline(
                     &app_handle,
                     &state_inner,
                     &tool_paths,
+                    &options,
                     &hdr_path,
                     &dv_path,
                     hdr10plus_path.as_deref(),
@@ -225,18 +303,37 @@ pub async fn start_processing(
             } else {
                 Some(PathBuf::from(&request.hdr10plus_path))
             };
-            let hdr_path = PathBuf::from(&request.hdr_path);
+            if let Ok(mut job) = state_inner.job_progress.lock() {
+                job.set_expected(
+                    "job",
+                    1,
+                    input_bytes(&[Path::new(&request.hdr_path), Path::new(&request.dv_path)]),
+                );
+            }
             let dv_path = PathBuf::from(&request.dv_path);
+            let hdr_path = if options.dv_only {
+                emit_log(
+                    &app_handle,
+                    "info",
+                    "No HDR input: remuxing the DV file with its own base layer, audio and subtitles",
+                );
+                dv_path.clone()
+            } else {
+                PathBuf::from(&request.hdr_path)
+            };
             let output_path = compute_output_for_single(
-                &tool_paths.default_output,
+                &options.default_output,
                 &request.output_path,
                 &hdr_path,
             );
+            let output_path =
+                claim_output(&app_handle, &state_inner, &options, &output_path, &hdr_path)?;
 
             run_pipeline(
                 &app_handle,
                 &state_inner,
                 &tool_paths,
+                &options,
                 &hdr_path,
                 &dv_path,
                 hdr10plus_path.as_deref(),
@@ -257,22 +354,41 @@ pub async fn start_processing(
         Ok(())
     })
     .await
-    .map_err(|e| e.to_string())?;
+    .map_err(|e| e.to_string());
+    // Flush coalesced progress and log lines before the terminal status goes out.
+    state.progress.stop();
+    state.logs.stop();
+    state.web.stop();
+    let result = result?;
+    let metrics = state.metrics.summary();
+    emit_event(&app, "processing:metrics", metrics.clone());
 
     match result {
         Ok(_) => {
-            emit_log(&app, "success", "Processing completed successfully!");
-            emit_status(&app, "completed");
+            emit_log(
+                &app,
+                "success",
+                format!("Processing completed successfully! ({})", metrics.describe()),
+            );
+            state.history.finish("completed", None, &metrics);
+            match partial_end {
+                Some(step) => emit_status_with_reason(&app, "completed", Some(StopReason::EndStep(step.to_string()))),
+                None => emit_status(&app, "completed"),
+            }
             Ok(())
         }
         Err(err) => {
+            let reason = state.stop_reason();
             if err == "Processing cancelled" {
-                emit_log(&app, "warning", err.clone());
-                emit_status(&app, "idle");
+                let reason = reason.unwrap_or(StopReason::UserCancelled);
+                emit_log(&app, "warning", format!("Processing {}", reason.describe()));
+                state.history.finish("cancelled", None, &metrics);
+                emit_status_with_reason(&app, "idle", Some(reason));
                 Ok(())
             } else {
                 emit_log(&app, "error", err.clone());
-                emit_status(&app, "error");
+                state.history.finish("error", Some(&err), &metrics);
+                emit_status_with_reason(&app, "error", reason);
                 Err(err)
             }
         }
@@ -281,8 +397,197 @@ pub async fn start_processing(
 
 #[tauri::command]
 pub fn cancel_processing(state: tauri::State<'_, ProcessingState>, app: AppHandle) {
-    if let Ok(mut guard) = state.cancel_flag.lock() {
-        *guard = true;
-    }
+    state.request_stop(StopReason::UserCancelled);
     let _ = app;
 }
+
+/// Cancel any active run, wait for it to wind down, then clear all per-run
+/// state so the next `start_processing` begins from a clean baseline.
+#[tauri::command]
+pub async fn reset_state(app: AppHandle, state: tauri::State<'_, ProcessingState>) -> Result<(), String> {
+    let state_inner = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if state_inner.is_running() {
+            state_inner.request_stop(StopReason::UserCancelled);
+            let deadline = Instant::now() + RESET_WAIT;
+            while state_inner.is_running() {
+                if Instant::now() >= deadline {
+                    return Err(format!(
+                        "The run did not stop within {}s; reset again once it has",
+                        RESET_WAIT.as_secs()
+                    ));
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+        state_inner.reset_run();
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    emit_status(&app, "idle");
+    emit_log(&app, "info", "Processing state reset");
+    Ok(())
+}
+
+#[tauri::command]
+pub fn start_event_socket(
+    port: Option<u16>,
+    app: AppHandle,
+    socket: tauri::State<'_, EventSocket>,
+) -> Result<EventSocketInfo, String> {
+    let info = socket.start(&app, port.unwrap_or(0))?;
+    emit_log(
+        &app,
+        "info",
+        format!("Event socket listening on 127.0.0.1:{}", info.port),
+    );
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn stop_event_socket(socket: tauri::State<'_, EventSocket>) {
+    socket.stop();
+}
+
+/// Watch the request's HDR and DV folders and process every new pair as it
+/// lands, until `stop_watch`.
+#[tauri::command]
+pub fn start_watch(
+    app: AppHandle,
+    state: tauri::State<'_, ProcessingState>,
+    watch: tauri::State<'_, WatchFolder>,
+    request: ProcessingRequest,
+    settle_secs: Option<u64>,
+    max_attempts: Option<u32>,
+) -> Result<WatchStatus, String> {
+    watch.start(&app, state.inner(), request, settle_secs, max_attempts)
+}
+
+#[tauri::command]
+pub fn stop_watch(state: tauri::State<'_, ProcessingState>, watch: tauri::State<'_, WatchFolder>) {
+    watch.stop(state.inner());
+}
+
+#[tauri::command]
+pub fn watch_status(watch: tauri::State<'_, WatchFolder>) -> WatchStatus {
+    watch.status()
+}
+
+/// Rough duration of `request` from its input sizes. With `benchmark`, the
+/// throughput comes from a short read of the first HDR file instead of the
+/// average measured over earlier runs.
+#[tauri::command]
+pub async fn estimate_queue(
+    app: AppHandle,
+    request: ProcessingRequest,
+    benchmark: Option<bool>,
+) -> Result<EstimateReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        estimate::estimate_queue(&app, &request, benchmark.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Run a few seconds of synthetic material through every tool to confirm
+/// the configured setup works end to end.
+#[tauri::command]
+pub async fn self_test(
+    app: AppHandle,
+    state: tauri::State<'_, ProcessingState>,
+    tool_paths: ToolPaths,
+) -> Result<SelfTestReport, String> {
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || run_self_test(&app, &state, &tool_paths))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Probe a single HDR/DV pair and return the crop, delay and HDR10+ plan a
+/// run would use, without extracting or writing anything.
+#[tauri::command]
+pub async fn analyze_pair(
+    app: AppHandle,
+    state: tauri::State<'_, ProcessingState>,
+    hdr_path: String,
+    dv_path: String,
+    hdr10plus_path: Option<String>,
+    dv_delay_ms: Option<f64>,
+    tool_paths: ToolPaths,
+) -> Result<AnalysisReport, String> {
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let hdr10plus_path = hdr10plus_path.filter(|path| !path.trim().is_empty()).map(PathBuf::from);
+        let input = PairInput {
+            hdr: Path::new(&hdr_path),
+            dv: Path::new(&dv_path),
+            hdr10plus: hdr10plus_path.as_deref(),
+            dv_delay_ms: dv_delay_ms.unwrap_or(0.0),
+        };
+        analyze_single_pair(&app, &state, &tool_paths, &input)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Status, overall progress and latest queue/file progress of the current
+/// run; the status page serves the same snapshot.
+#[tauri::command]
+pub fn get_processing_status(state: tauri::State<'_, ProcessingState>) -> StatusSnapshot {
+    status_snapshot(&state)
+}
+
+/// Save a request, queue included, as a job file to run elsewhere.
+#[tauri::command]
+pub async fn export_job(app: AppHandle, request: ProcessingRequest, path: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || job::export_job(&app, &request, Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Load a job file with this machine's tool paths, listing any inputs
+/// that are not here.
+#[tauri::command]
+pub async fn import_job(app: AppHandle, path: String, tool_paths: ToolPaths) -> Result<ImportedJob, String> {
+    tauri::async_runtime::spawn_blocking(move || job::import_job(&app, Path::new(&path), &tool_paths))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Zip the log, request, tool versions and input MediaInfo of a recorded run
+/// (the latest failed one by default) for attaching to a bug report.
+#[tauri::command]
+pub async fn export_support_bundle(
+    app: AppHandle,
+    run_id: Option<String>,
+    destination: String,
+    anonymize: Option<bool>,
+) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        support::export_support_bundle(&app, run_id, Path::new(&destination), anonymize.unwrap_or(false))
+            .map(|path| path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Delete intermediates listed by a `processing:artifacts` event, returning
+/// the bytes freed.
+#[tauri::command]
+pub async fn delete_artifacts(app: AppHandle, paths: Vec<String>) -> Result<u64, String> {
+    tauri::async_runtime::spawn_blocking(move || artifacts::delete_artifacts(&app, &paths))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Settings remembered per DV input, most recently updated first.
+#[tauri::command]
+pub fn list_title_memos(app: AppHandle) -> Result<Vec<TitleMemoEntry>, String> {
+    title_memo::list_title_memos(&app)
+}
+
+#[tauri::command]
+pub fn delete_title_memo(app: AppHandle, key: String) -> Result<(), String> {
+    title_memo::delete_title_memo(&app, &key)
+}
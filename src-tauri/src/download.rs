@@ -0,0 +1,391 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tauri::AppHandle;
+use zip::ZipArchive;
+
+use crate::models::DownloadProgressPayload;
+use crate::processing::hide_console_window;
+use crate::utils::{emit_event, emit_log};
+
+const DOWNLOAD_RETRIES: u32 = 3;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const CANCELLED: &str = "Download cancelled";
+
+/// Flag that makes each downloadable tool print its version, by file stem.
+const VERSION_ARGS: &[(&str, &str)] = &[
+    ("dovi_tool", "--version"),
+    ("mkvmerge", "--version"),
+    ("mkvextract", "--version"),
+    ("ffmpeg", "-version"),
+    ("mediainfo", "--Version"),
+    ("mp4box", "-version"),
+    ("hdr10plus_tool", "--version"),
+];
+
+fn cancelled(flag: &Arc<Mutex<bool>>) -> bool {
+    flag.lock().map(|flag| *flag).unwrap_or(false)
+}
+
+/// Throttled `download:progress` events for one file.
+struct ProgressReporter<'a> {
+    app: &'a AppHandle,
+    filename: &'a str,
+    phase: &'static str,
+    total: Option<u64>,
+    last: Instant,
+}
+
+impl<'a> ProgressReporter<'a> {
+    fn new(app: &'a AppHandle, filename: &'a str, phase: &'static str, total: Option<u64>) -> Self {
+        ProgressReporter {
+            app,
+            filename,
+            phase,
+            total,
+            // Backdated so the first chunk is reported right away.
+            last: Instant::now() - PROGRESS_INTERVAL,
+        }
+    }
+
+    fn report(&mut self, done: u64, force: bool) {
+        if !force && self.last.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        self.last = Instant::now();
+        let progress = self
+            .total
+            .filter(|total| *total > 0)
+            .map(|total| ((done as f64 / total as f64) * 100.0).min(100.0) as u8);
+        emit_event(
+            self.app,
+            "download:progress",
+            DownloadProgressPayload {
+                filename: self.filename.to_string(),
+                phase: self.phase.to_string(),
+                done,
+                total: self.total,
+                progress,
+            },
+        );
+    }
+}
+
+/// Stream `url` into `target` through a `.part` file, which is removed if the
+/// download fails or is cancelled.
+async fn fetch(
+    app: &AppHandle,
+    cancel: &Arc<Mutex<bool>>,
+    url: &str,
+    filename: &str,
+    target: &Path,
+) -> Result<(), String> {
+    let mut response = reqwest::get(url)
+        .await
+        .map_err(|e| format!("Failed to connect: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Download failed with status: {}", response.status()));
+    }
+
+    let part = PathBuf::from(format!("{}.part", target.to_string_lossy()));
+    let mut file = File::create(&part).map_err(|e| format!("Failed to create file: {}", e))?;
+    let mut reporter = ProgressReporter::new(app, filename, "download", response.content_length());
+    let mut received = 0u64;
+    let result = loop {
+        if cancelled(cancel) {
+            break Err(CANCELLED.to_string());
+        }
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                if let Err(err) = file.write_all(&chunk) {
+                    break Err(format!("Failed to write to file: {}", err));
+                }
+                received += chunk.len() as u64;
+                reporter.report(received, false);
+            }
+            Ok(None) => break file.flush().map_err(|e| format!("Failed to write to file: {}", e)),
+            Err(err) => break Err(format!("Failed to read bytes: {}", err)),
+        }
+    };
+    drop(file);
+    if let Err(err) = result {
+        let _ = fs::remove_file(&part);
+        return Err(err);
+    }
+    reporter.report(received, true);
+    fs::rename(&part, target).map_err(|e| format!("Failed to move {} into place: {}", part.display(), e))
+}
+
+/// Unpack `archive` into a folder named after it and check every tool
+/// binary inside, returning the one named after the archive (`MP4Box.zip`
+/// gives `MP4Box.exe`). On any failure the folder is removed, so a partial
+/// extraction never looks like an installed tool.
+fn extract_zip(
+    app: &AppHandle,
+    cancel: &Arc<Mutex<bool>>,
+    archive: &Path,
+    filename: &str,
+) -> Result<PathBuf, String> {
+    let dest = archive.with_extension("");
+    let result = unpack_zip(app, cancel, archive, filename, &dest).and_then(|binaries| verify_binaries(archive, &binaries));
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&dest);
+    }
+    result
+}
+
+/// Whether an extracted file is a tool binary: a `.exe`, or on Unix a file
+/// named after one of the tools.
+fn is_tool_binary(path: &Path) -> bool {
+    let is_exe = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("exe"))
+        .unwrap_or(false);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    is_exe || (cfg!(unix) && path.extension().is_none() && VERSION_ARGS.iter().any(|(name, _)| *name == stem))
+}
+
+/// Write every entry of `archive` under `dest`, returning the tool binaries.
+fn unpack_zip(
+    app: &AppHandle,
+    cancel: &Arc<Mutex<bool>>,
+    archive: &Path,
+    filename: &str,
+    dest: &Path,
+) -> Result<Vec<PathBuf>, String> {
+    let file = File::open(archive).map_err(|e| format!("Failed to open {}: {}", archive.display(), e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("Not a valid zip archive: {}", e))?;
+    let total: u64 = (0..zip.len())
+        .filter_map(|index| zip.by_index(index).ok().map(|entry| entry.size()))
+        .sum();
+    let mut reporter = ProgressReporter::new(app, filename, "extract", Some(total));
+    let mut written = 0u64;
+    let mut binaries = Vec::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+
+    fs::create_dir_all(dest).map_err(|e| e.to_string())?;
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index).map_err(|e| e.to_string())?;
+        // Entries that would land outside `dest` are skipped.
+        let Some(relative) = entry.enclosed_name().map(Path::to_path_buf) else {
+            continue;
+        };
+        let out_path = dest.join(&relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path).map_err(|e| e.to_string())?;
+            continue;
+        }
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut out = File::create(&out_path).map_err(|e| format!("Failed to create {}: {}", out_path.display(), e))?;
+        loop {
+            if cancelled(cancel) {
+                return Err(CANCELLED.to_string());
+            }
+            let read = entry.read(&mut buffer).map_err(|e| e.to_string())?;
+            if read == 0 {
+                break;
+            }
+            out.write_all(&buffer[..read]).map_err(|e| e.to_string())?;
+            written += read as u64;
+            reporter.report(written, false);
+        }
+        if is_tool_binary(&out_path) {
+            binaries.push(out_path);
+        }
+    }
+    reporter.report(written, true);
+    Ok(binaries)
+}
+
+/// Run [`verify_tool`] on every extracted binary and return the one named
+/// after the archive, failing when it is missing.
+fn verify_binaries(archive: &Path, binaries: &[PathBuf]) -> Result<PathBuf, String> {
+    let stem = |path: &Path| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default()
+    };
+    let expected = stem(archive);
+    let main = binaries
+        .iter()
+        .find(|binary| stem(binary) == expected)
+        .cloned()
+        .ok_or_else(|| format!("{} does not contain the {} executable", archive.display(), expected))?;
+    for binary in binaries {
+        verify_tool(binary).map_err(|e| format!("{} is unusable: {}", binary.display(), e))?;
+    }
+    Ok(main)
+}
+
+/// Check that a downloaded or extracted tool can actually be used: not
+/// empty, executable on Unix, and answering its version flag where this
+/// platform can run it.
+fn verify_tool(path: &Path) -> Result<(), String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("{} is missing: {}", path.display(), e))?;
+    if metadata.len() == 0 {
+        return Err(format!("{} is empty; the download or extraction was incomplete", path.display()));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = metadata.permissions();
+        if permissions.mode() & 0o111 == 0 {
+            permissions.set_mode(permissions.mode() | 0o755);
+            fs::set_permissions(path, permissions)
+                .map_err(|e| format!("Cannot make {} executable: {}", path.display(), e))?;
+        }
+    }
+
+    let is_exe = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("exe"))
+        .unwrap_or(false);
+    if is_exe && !cfg!(target_os = "windows") {
+        return Ok(());
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let Some((_, version_arg)) = VERSION_ARGS.iter().find(|(name, _)| *name == stem) else {
+        return Ok(());
+    };
+    let mut command = std::process::Command::new(path);
+    command.arg(version_arg);
+    hide_console_window(&mut command);
+    let output = command
+        .output()
+        .map_err(|e| format!("{} does not run: {}", path.display(), e))?;
+    if output.stdout.is_empty() && output.stderr.is_empty() {
+        return Err(format!("{} ran but printed no version", path.display()));
+    }
+    Ok(())
+}
+
+/// Download a tool into `app_data_dir/bin`, retrying failed attempts and
+/// reporting progress as `download:progress`. Zip archives are unpacked
+/// and the executable named after the archive is returned instead. Raising
+/// `cancel` stops between chunks and removes whatever was partially written.
+pub async fn download_tool(
+    app: &AppHandle,
+    cancel: Arc<Mutex<bool>>,
+    url: &str,
+    filename: &str,
+) -> Result<PathBuf, String> {
+    if let Ok(mut flag) = cancel.lock() {
+        *flag = false;
+    }
+    emit_log(app, "info", format!("Downloading {}...", filename));
+
+    // Use AppData directory to avoid permission issues (OS Error 5 in Program Files)
+    let bin_path = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or("Could not resolve app data directory".to_string())?
+        .join("bin");
+    fs::create_dir_all(&bin_path).map_err(|e| e.to_string())?;
+    let target_path = bin_path.join(filename);
+
+    let mut last_error = String::from("Unknown error");
+    let mut downloaded = false;
+    for attempt in 1..=DOWNLOAD_RETRIES {
+        if attempt > 1 {
+            emit_log(
+                app,
+                "info",
+                format!("Retrying download (attempt {}/{})...", attempt, DOWNLOAD_RETRIES),
+            );
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+        match fetch(app, &cancel, url, filename, &target_path).await {
+            Ok(()) => {
+                downloaded = true;
+                break;
+            }
+            Err(err) if err == CANCELLED => {
+                emit_log(app, "warning", format!("Download of {} cancelled", filename));
+                return Err(err);
+            }
+            Err(err) => {
+                emit_log(app, "warning", format!("Download attempt {} failed: {}", attempt, err));
+                last_error = err;
+            }
+        }
+    }
+    if !downloaded {
+        return Err(format!(
+            "Failed after {} attempts. Last error: {}",
+            DOWNLOAD_RETRIES, last_error
+        ));
+    }
+    emit_log(
+        app,
+        "success",
+        format!("Downloaded {} to {}", filename, target_path.display()),
+    );
+
+    let is_zip = target_path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false);
+    if !is_zip {
+        if let Err(err) = verify_tool(&target_path) {
+            emit_log(app, "error", format!("{} is unusable: {}", filename, err));
+            let _ = fs::remove_file(&target_path);
+            return Err(err);
+        }
+        return Ok(target_path);
+    }
+    let extract_app = app.clone();
+    let extract_name = filename.to_string();
+    let archive = target_path.clone();
+    let extracted = tauri::async_runtime::spawn_blocking(move || {
+        extract_zip(&extract_app, &cancel, &archive, &extract_name)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let _ = fs::remove_file(&target_path);
+    match extracted {
+        Ok(path) => {
+            emit_log(app, "success", format!("Extracted {} to {}", filename, path.display()));
+            Ok(path)
+        }
+        Err(err) => {
+            emit_log(app, "error", format!("Extracting {} failed: {}", filename, err));
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_extracted_binary_is_verified() {
+        let dir = std::env::temp_dir().join(format!("hybrid-dv-extract-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (main, helper) = (dir.join("MP4Box.exe"), dir.join("gpac_helper.exe"));
+        fs::write(&main, b"MZ").unwrap();
+        fs::write(&helper, b"").unwrap();
+        let archive = dir.join("MP4Box.zip");
+
+        let with_empty_helper = verify_binaries(&archive, &[main.clone(), helper.clone()]);
+        let without_main = verify_binaries(&archive, std::slice::from_ref(&helper));
+        fs::write(&helper, b"MZ").unwrap();
+        let complete = verify_binaries(&archive, &[main.clone(), helper]);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(with_empty_helper.unwrap_err().contains("gpac_helper.exe"));
+        assert!(without_main.unwrap_err().contains("does not contain the mp4box executable"));
+        assert_eq!(complete.unwrap(), main);
+    }
+}
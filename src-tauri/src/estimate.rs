@@ -0,0 +1,203 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::models::ProcessingRequest;
+use crate::utils::scan_folder_pairs;
+
+/// Throughput assumed before any run has been measured.
+const DEFAULT_BYTES_PER_SEC: f64 = 40.0 * 1024.0 * 1024.0;
+/// How much of the first HDR file the benchmark reads.
+const BENCHMARK_BYTES: u64 = 256 * 1024 * 1024;
+/// Full passes the pipeline makes over its input (demux, extract, inject,
+/// mux); the benchmark's raw read rate is divided by this.
+const PIPELINE_PASSES: f64 = 4.0;
+/// Weight of a new measurement in the persisted running average.
+const MEASUREMENT_WEIGHT: f64 = 0.3;
+/// Runs shorter than this (e.g. resumed, already-finished files) say
+/// nothing about throughput and are not recorded.
+const MIN_SAMPLE: Duration = Duration::from_secs(10);
+
+static THROUGHPUT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+struct ThroughputRecord {
+    bytes_per_sec: f64,
+    samples: u32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimateItem {
+    pub id: String,
+    pub hdr_path: String,
+    pub dv_path: String,
+    pub input_bytes: u64,
+    pub estimated_secs: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimateReport {
+    pub items: Vec<EstimateItem>,
+    /// HDR files that have no DV partner and would be skipped or rejected.
+    pub unmatched: Vec<String>,
+    pub total_bytes: u64,
+    pub bytes_per_sec: f64,
+    /// `"default"`, `"measured"` or `"benchmark"`.
+    pub throughput_source: String,
+    /// Sequential estimate; parallel workers usually finish sooner.
+    pub estimated_secs: u64,
+}
+
+fn throughput_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path_resolver()
+        .app_data_dir()
+        .map(|dir| dir.join("throughput.json"))
+}
+
+fn load_throughput(app: &AppHandle) -> Option<ThroughputRecord> {
+    let content = fs::read_to_string(throughput_path(app)?).ok()?;
+    serde_json::from_str::<ThroughputRecord>(&content)
+        .ok()
+        .filter(|record| record.bytes_per_sec > 0.0)
+}
+
+/// Fold a finished pipeline run into the persisted throughput average.
+pub fn record_throughput(app: &AppHandle, input_bytes: u64, elapsed: Duration) {
+    if elapsed < MIN_SAMPLE || input_bytes == 0 {
+        return;
+    }
+    let Some(path) = throughput_path(app) else {
+        return;
+    };
+    let Ok(_guard) = THROUGHPUT_LOCK.lock() else {
+        return;
+    };
+
+    let measured = input_bytes as f64 / elapsed.as_secs_f64();
+    let record = match load_throughput(app) {
+        Some(previous) => ThroughputRecord {
+            bytes_per_sec: previous.bytes_per_sec * (1.0 - MEASUREMENT_WEIGHT)
+                + measured * MEASUREMENT_WEIGHT,
+            samples: previous.samples.saturating_add(1),
+        },
+        None => ThroughputRecord {
+            bytes_per_sec: measured,
+            samples: 1,
+        },
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_vec_pretty(&record) {
+        let _ = fs::write(path, json);
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Time a sequential read of the start of `path` and scale it down to the
+/// pipeline's expected throughput.
+fn benchmark_throughput(path: &Path) -> Result<f64, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut buffer = vec![0u8; 8 * 1024 * 1024];
+    let mut read_total = 0u64;
+    let started = Instant::now();
+    while read_total < BENCHMARK_BYTES {
+        let read = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        read_total += read as u64;
+    }
+    if read_total == 0 {
+        return Err(format!("{} is empty", path.display()));
+    }
+    let elapsed = started.elapsed().as_secs_f64().max(0.001);
+    Ok(read_total as f64 / elapsed / PIPELINE_PASSES)
+}
+
+/// An item id with its HDR and DV inputs.
+pub(crate) type EstimatePair = (String, PathBuf, PathBuf);
+
+/// Every pair the request would process, plus the HDR files left unpaired.
+pub(crate) fn collect_pairs(request: &ProcessingRequest) -> Result<(Vec<EstimatePair>, Vec<String>), String> {
+    let sources: Vec<(String, String, String)> = if request.mode == "batch" {
+        request
+            .queue
+            .iter()
+            .map(|item| (item.id.clone(), item.hdr_path.clone(), item.dv_path.clone()))
+            .collect()
+    } else {
+        // A DV-only run reads the DV file in place of the HDR input.
+        let hdr = if request.dv_only() { &request.dv_path } else { &request.hdr_path };
+        vec![("job".to_string(), hdr.clone(), request.dv_path.clone())]
+    };
+
+    let mut pairs = Vec::new();
+    let mut unmatched = Vec::new();
+    for (id, hdr, dv) in sources {
+        let hdr = PathBuf::from(hdr);
+        let dv = PathBuf::from(dv);
+        if hdr.is_dir() {
+            let scan = scan_folder_pairs(&hdr, &dv, request.output_structure.recursive())?;
+            for (index, pair) in scan.pairs.into_iter().enumerate() {
+                pairs.push((
+                    format!("{}:{}", id, index),
+                    hdr.join(&pair.hdr_file),
+                    dv.join(&pair.dv_file),
+                ));
+            }
+            unmatched.extend(scan.unmatched);
+        } else {
+            pairs.push((id, hdr, dv));
+        }
+    }
+    Ok((pairs, unmatched))
+}
+
+/// Estimate how long `request` will take from its input sizes.
+pub fn estimate_queue(app: &AppHandle, request: &ProcessingRequest, benchmark: bool) -> Result<EstimateReport, String> {
+    let (pairs, unmatched) = collect_pairs(request)?;
+
+    let (bytes_per_sec, throughput_source) = match pairs.first() {
+        Some((_, hdr, _)) if benchmark => (benchmark_throughput(hdr)?, "benchmark"),
+        _ => match load_throughput(app) {
+            Some(record) => (record.bytes_per_sec, "measured"),
+            None => (DEFAULT_BYTES_PER_SEC, "default"),
+        },
+    };
+
+    let items: Vec<EstimateItem> = pairs
+        .into_iter()
+        .map(|(id, hdr, dv)| {
+            let input_bytes = file_size(&hdr) + file_size(&dv);
+            EstimateItem {
+                id,
+                hdr_path: hdr.to_string_lossy().to_string(),
+                dv_path: dv.to_string_lossy().to_string(),
+                input_bytes,
+                estimated_secs: (input_bytes as f64 / bytes_per_sec).ceil() as u64,
+            }
+        })
+        .collect();
+    let total_bytes = items.iter().map(|item| item.input_bytes).sum();
+
+    Ok(EstimateReport {
+        estimated_secs: items.iter().map(|item| item.estimated_secs).sum(),
+        items,
+        unmatched,
+        total_bytes,
+        bytes_per_sec,
+        throughput_source: throughput_source.to_string(),
+    })
+}
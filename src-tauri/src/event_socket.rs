@@ -0,0 +1,213 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::models::{ProcessingState, StopReason};
+
+/// How long a new client has to send its token before it is dropped.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Clients = Arc<Mutex<Vec<Arc<TcpStream>>>>;
+
+/// Local JSON-lines mirror of every `processing:*` event for external
+/// consumers. Clients connect to `127.0.0.1:<port>`, send the token as their
+/// first line, and may then send `{"command": "status" | "cancel" | "pause" | "resume"}`.
+#[derive(Clone, Default)]
+pub struct EventSocket {
+    server: Arc<Mutex<Option<SocketServer>>>,
+}
+
+struct SocketServer {
+    port: u16,
+    token: String,
+    clients: Clients,
+    stop: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSocketInfo {
+    pub port: u16,
+    pub token: String,
+}
+
+/// 128 bits from the OS random source, as hex.
+pub(crate) fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate a token: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+impl EventSocket {
+    pub fn start(&self, app: &AppHandle, port: u16) -> Result<EventSocketInfo, String> {
+        let mut server = self.server.lock().map_err(|_| "Socket lock failed")?;
+        if let Some(existing) = server.as_ref() {
+            return Ok(EventSocketInfo {
+                port: existing.port,
+                token: existing.token.clone(),
+            });
+        }
+
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .map_err(|e| format!("Failed to bind event socket on port {}: {}", port, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+
+        let token = generate_token()?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        {
+            let app = app.clone();
+            let token = token.clone();
+            let clients = Arc::clone(&clients);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let app = app.clone();
+                            let token = token.clone();
+                            let clients = Arc::clone(&clients);
+                            thread::spawn(move || serve_client(app, stream, token, clients));
+                        }
+                        Err(_) => thread::sleep(Duration::from_millis(200)),
+                    }
+                }
+            });
+        }
+
+        *server = Some(SocketServer {
+            port,
+            token: token.clone(),
+            clients,
+            stop,
+        });
+        Ok(EventSocketInfo { port, token })
+    }
+
+    pub fn stop(&self) {
+        if let Ok(mut server) = self.server.lock() {
+            if let Some(server) = server.take() {
+                server.stop.store(true, Ordering::Relaxed);
+                if let Ok(mut clients) = server.clients.lock() {
+                    for client in clients.drain(..) {
+                        let _ = client.shutdown(std::net::Shutdown::Both);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send one event to every authenticated client, dropping any that went away.
+    /// The writes happen on a snapshot of the clients, so a slow client never
+    /// holds the locks other emitters and new connections wait on.
+    pub fn broadcast<S: Serialize>(&self, event: &str, payload: &S) {
+        let Some(clients) = self
+            .server
+            .lock()
+            .ok()
+            .and_then(|server| server.as_ref().map(|server| Arc::clone(&server.clients)))
+        else {
+            return;
+        };
+        let snapshot = match clients.lock() {
+            Ok(clients) if !clients.is_empty() => clients.clone(),
+            _ => return,
+        };
+        let line = json!({ "event": event, "payload": payload }).to_string() + "\n";
+        let gone: Vec<Arc<TcpStream>> = snapshot
+            .into_iter()
+            .filter(|client| client.as_ref().write_all(line.as_bytes()).is_err())
+            .collect();
+        if gone.is_empty() {
+            return;
+        }
+        let Ok(mut clients) = clients.lock() else {
+            return;
+        };
+        clients.retain(|client| !gone.iter().any(|gone| Arc::ptr_eq(gone, client)));
+    }
+}
+
+fn serve_client(
+    app: AppHandle,
+    stream: TcpStream,
+    token: String,
+    clients: Clients,
+) {
+    let _ = stream.set_nonblocking(false);
+    // Only the token has a deadline; an authenticated client may stay idle.
+    let _ = stream.set_read_timeout(Some(AUTH_TIMEOUT));
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut lines = BufReader::new(stream).lines();
+
+    match lines.next() {
+        Some(Ok(line)) if line.trim() == token => {}
+        _ => {
+            let _ = writer.write_all(b"{\"ok\":false,\"error\":\"unauthorized\"}\n");
+            return;
+        }
+    }
+    let _ = writer.set_read_timeout(None);
+    let _ = writer.write_all(b"{\"ok\":true}\n");
+    if let (Ok(broadcast_handle), Ok(mut clients)) = (writer.try_clone(), clients.lock()) {
+        // A stalled client must not hold up the pipeline's event emission.
+        let _ = broadcast_handle.set_write_timeout(Some(Duration::from_millis(250)));
+        clients.push(Arc::new(broadcast_handle));
+    }
+
+    for line in lines.map_while(Result::ok) {
+        let command = serde_json::from_str::<Value>(&line)
+            .ok()
+            .and_then(|v| v.get("command").and_then(Value::as_str).map(str::to_string))
+            .unwrap_or_default();
+        let response = handle_command(&app, &command);
+        if writer
+            .write_all((response.to_string() + "\n").as_bytes())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+fn handle_command(app: &AppHandle, command: &str) -> Value {
+    let state = app.state::<ProcessingState>();
+    let set_flag = |flag: &Arc<Mutex<bool>>, value: bool| {
+        if let Ok(mut guard) = flag.lock() {
+            *guard = value;
+        }
+    };
+
+    match command {
+        "status" => {
+            let status = state.status.lock().map(|s| s.clone()).unwrap_or_default();
+            let overall = state.job_progress.lock().ok().map(|job| job.payload());
+            let paused = state.pause_flag.lock().map(|p| *p).unwrap_or(false);
+            json!({ "ok": true, "status": status, "paused": paused, "overall": overall })
+        }
+        "cancel" => {
+            state.request_stop(StopReason::UserCancelled);
+            json!({ "ok": true })
+        }
+        "pause" => {
+            set_flag(&state.pause_flag, true);
+            json!({ "ok": true })
+        }
+        "resume" => {
+            set_flag(&state.pause_flag, false);
+            json!({ "ok": true })
+        }
+        other => json!({ "ok": false, "error": format!("unknown command: {}", other) }),
+    }
+}
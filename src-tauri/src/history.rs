@@ -0,0 +1,166 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::metrics::MetricsSummary;
+use crate::models::{ProcessingRequest, ScanReport};
+
+/// Runs kept on disk; the oldest are pruned when a new one starts.
+const MAX_RUNS: usize = 20;
+
+/// How a recorded run ended, written as `result.json` next to its log.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RunResult {
+    /// `"completed"`, `"cancelled"` or `"error"`.
+    pub status: String,
+    pub error: Option<String>,
+    pub finished_ms: u64,
+    #[serde(default)]
+    pub metrics: Option<MetricsSummary>,
+}
+
+/// The request, log and outcome of recent runs under
+/// `app_data_dir/runs/<run_id>`, so a failure can still be reported after
+/// the app restarts.
+#[derive(Clone, Default)]
+pub struct RunHistory {
+    current: Arc<Mutex<Option<(PathBuf, Instant)>>>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn runs_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path_resolver().app_data_dir().map(|dir| dir.join("runs"))
+}
+
+/// Recorded run ids, oldest first. Ids are start times in milliseconds.
+pub fn run_ids(app: &AppHandle) -> Vec<String> {
+    let Some(root) = runs_dir(app) else {
+        return Vec::new();
+    };
+    let mut ids: Vec<String> = fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| name.parse::<u64>().is_ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    ids.sort_by_key(|id| id.parse::<u64>().unwrap_or_default());
+    ids
+}
+
+/// Folder of a recorded run. Anything but a listed id is refused, so an id
+/// from the frontend can never reach outside `runs`.
+pub fn run_dir(app: &AppHandle, run_id: &str) -> Option<PathBuf> {
+    if run_id.contains(['/', '\\']) || !run_ids(app).iter().any(|id| id == run_id) {
+        return None;
+    }
+    Some(runs_dir(app)?.join(run_id))
+}
+
+pub fn read_result(app: &AppHandle, run_id: &str) -> Option<RunResult> {
+    let content = fs::read_to_string(run_dir(app, run_id)?.join("result.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// The most recent failed run, or the most recent run if none failed.
+pub fn latest_run(app: &AppHandle) -> Option<String> {
+    let ids = run_ids(app);
+    ids.iter()
+        .rev()
+        .find(|id| read_result(app, id).map(|result| result.status == "error").unwrap_or(false))
+        .or_else(|| ids.last())
+        .cloned()
+}
+
+impl RunHistory {
+    /// Start recording a run, returning its id.
+    pub fn begin(&self, app: &AppHandle, request: &ProcessingRequest) -> Option<String> {
+        let root = runs_dir(app)?;
+        let ids = run_ids(app);
+        for old in ids.iter().take((ids.len() + 1).saturating_sub(MAX_RUNS)) {
+            let _ = fs::remove_dir_all(root.join(old));
+        }
+
+        let run_id = now_ms().to_string();
+        let dir = root.join(&run_id);
+        fs::create_dir_all(&dir).ok()?;
+        if let Ok(json) = serde_json::to_vec_pretty(request) {
+            let _ = fs::write(dir.join("request.json"), json);
+        }
+        if let Ok(mut current) = self.current.lock() {
+            *current = Some((dir, Instant::now()));
+        }
+        Some(run_id)
+    }
+
+    /// Append a log line to the current run, if one is being recorded.
+    pub fn append_log(&self, log_type: &str, message: &str) {
+        let Ok(current) = self.current.lock() else {
+            return;
+        };
+        let Some((dir, started)) = current.as_ref() else {
+            return;
+        };
+        let line = format!(
+            "[{:>9.2}s] {:<7} {}\n",
+            started.elapsed().as_secs_f64(),
+            log_type,
+            message
+        );
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(dir.join("log.txt")) {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+
+    /// Append a folder's scan report to the current run's `scan-reports.jsonl`.
+    pub fn append_scan_report(&self, report: &ScanReport) {
+        let Ok(current) = self.current.lock() else {
+            return;
+        };
+        let Some((dir, _)) = current.as_ref() else {
+            return;
+        };
+        let Ok(mut line) = serde_json::to_string(report) else {
+            return;
+        };
+        line.push('\n');
+        if let Ok(mut file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join("scan-reports.jsonl"))
+        {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+
+    /// Record how the current run ended and stop recording.
+    pub fn finish(&self, status: &str, error: Option<&str>, metrics: &MetricsSummary) {
+        let Some((dir, _)) = self.current.lock().ok().and_then(|mut current| current.take()) else {
+            return;
+        };
+        let result = RunResult {
+            status: status.to_string(),
+            error: error.map(str::to_string),
+            finished_ms: now_ms(),
+            metrics: Some(metrics.clone()),
+        };
+        if let Ok(json) = serde_json::to_vec_pretty(&result) {
+            let _ = fs::write(dir.join("result.json"), json);
+        }
+    }
+}
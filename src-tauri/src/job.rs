@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::models::{ProcessingRequest, ToolPaths};
+use crate::utils::emit_log;
+
+/// Current job file schema. Bump when a field changes meaning; older files
+/// keep loading because new request fields default.
+pub const JOB_VERSION: u32 = 1;
+
+/// Request fields that name inputs which must exist on the running machine.
+const INPUT_FIELDS: &[&str] = &["hdrPath", "dvPath", "hdr10plusPath"];
+
+/// A `ProcessingRequest` saved to disk, queue and per-item overrides
+/// included. Tool paths are left out since they only hold on the machine
+/// that wrote the file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JobFile {
+    version: u32,
+    request: Value,
+}
+
+/// One referenced input that is not on this machine.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingInput {
+    /// Queue item the path belongs to; `None` for the request itself.
+    pub queue_id: Option<String>,
+    pub field: String,
+    pub path: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedJob {
+    pub request: ProcessingRequest,
+    pub missing: Vec<MissingInput>,
+}
+
+/// Write `request` as a job file at `path`.
+pub fn export_job(app: &AppHandle, request: &ProcessingRequest, path: &Path) -> Result<(), String> {
+    let mut value = serde_json::to_value(request).map_err(|e| e.to_string())?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("toolPaths");
+    }
+    let job = JobFile {
+        version: JOB_VERSION,
+        request: value,
+    };
+    let json = serde_json::to_vec_pretty(&job).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    emit_log(app, "success", format!("Job saved to {}", path.display()));
+    Ok(())
+}
+
+/// Parse a job file, filling in this machine's `tool_paths`.
+pub fn parse_job(content: &str, tool_paths: &ToolPaths) -> Result<ProcessingRequest, String> {
+    let job: JobFile = serde_json::from_str(content).map_err(|e| format!("Not a job file: {}", e))?;
+    if job.version > JOB_VERSION {
+        return Err(format!(
+            "Job file version {} is newer than this version of the app supports ({})",
+            job.version, JOB_VERSION
+        ));
+    }
+    let mut value = job.request;
+    let fields = value
+        .as_object_mut()
+        .ok_or("Job file request is not an object".to_string())?;
+    fields.insert(
+        "toolPaths".to_string(),
+        serde_json::to_value(tool_paths).map_err(|e| e.to_string())?,
+    );
+    serde_json::from_value(value).map_err(|e| format!("Invalid job request: {}", e))
+}
+
+/// Inputs the request refers to that do not exist here. Empty fields are
+/// unset rather than missing.
+pub fn missing_inputs(request: &ProcessingRequest) -> Vec<MissingInput> {
+    let Ok(value) = serde_json::to_value(request) else {
+        return Vec::new();
+    };
+    let mut missing = Vec::new();
+    let mut check = |queue_id: Option<&str>, fields: &Value| {
+        for field in INPUT_FIELDS {
+            let Some(path) = fields.get(*field).and_then(Value::as_str) else {
+                continue;
+            };
+            if !path.trim().is_empty() && !Path::new(path).exists() {
+                missing.push(MissingInput {
+                    queue_id: queue_id.map(str::to_string),
+                    field: field.to_string(),
+                    path: path.to_string(),
+                });
+            }
+        }
+    };
+    check(None, &value);
+    for item in value.get("queue").and_then(Value::as_array).into_iter().flatten() {
+        check(item.get("id").and_then(Value::as_str), item);
+    }
+    missing
+}
+
+/// Load a job file from `path` and report which of its inputs are missing.
+pub fn import_job(app: &AppHandle, path: &Path, tool_paths: &ToolPaths) -> Result<ImportedJob, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let request = parse_job(&content, tool_paths)?;
+    let missing = missing_inputs(&request);
+    for input in &missing {
+        emit_log(
+            app,
+            "warning",
+            match &input.queue_id {
+                Some(id) => format!("Job input not found ({} of {}): {}", input.field, id, input.path),
+                None => format!("Job input not found ({}): {}", input.field, input.path),
+            },
+        );
+    }
+    emit_log(
+        app,
+        "info",
+        format!(
+            "Loaded job {} ({} queue item(s), {} missing input(s))",
+            path.display(),
+            request.queue.len(),
+            missing.len()
+        ),
+    );
+    Ok(ImportedJob { request, missing })
+}
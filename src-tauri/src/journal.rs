@@ -0,0 +1,176 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::utils::emit_log;
+
+/// One completed pipeline step for one output file.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub step_index: usize,
+    pub step_name: String,
+    pub artifact: String,
+    pub size: u64,
+    pub modified_ms: u64,
+}
+
+/// Append-only record of the steps that finished for one output, kept next
+/// to its intermediates so a crashed run can tell which artifacts are
+/// trustworthy. Removed once the output is complete.
+pub struct StepJournal {
+    app: AppHandle,
+    path: PathBuf,
+    entries: Vec<JournalEntry>,
+}
+
+fn artifact_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis() as u64;
+    Some((metadata.len(), modified))
+}
+
+fn journal_path(output_path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}_journal.jsonl", output_path.to_string_lossy()))
+}
+
+/// Parse journal lines, dropping anything that does not decode. A crash in
+/// the middle of an append leaves at most a partial trailing line.
+fn read_entries(path: &Path) -> Vec<JournalEntry> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok())
+        .collect()
+}
+
+impl StepJournal {
+    /// Open the journal for `output_path`. Without `resume` any previous
+    /// journal is discarded so stale entries can never skip work.
+    pub fn open(app: &AppHandle, output_path: &Path, resume: bool) -> StepJournal {
+        let path = journal_path(output_path);
+        let entries = if resume {
+            read_entries(&path)
+        } else {
+            let _ = fs::remove_file(&path);
+            Vec::new()
+        };
+        StepJournal {
+            app: app.clone(),
+            path,
+            entries,
+        }
+    }
+
+    /// Whether `step_index` finished earlier and `artifact` is still the file
+    /// it produced (same size and modification time).
+    pub fn completed(&self, step_index: usize, artifact: &Path) -> bool {
+        let Some((size, modified_ms)) = artifact_stamp(artifact) else {
+            return false;
+        };
+        let artifact = artifact.to_string_lossy();
+        self.entries.iter().rev().any(|entry| {
+            entry.step_index == step_index
+                && entry.artifact == artifact
+                && entry.size == size
+                && entry.modified_ms == modified_ms
+        })
+    }
+
+    /// Append an entry for a finished step and flush it to disk. A journal
+    /// that cannot be written only costs a resumed run the step, so the
+    /// failure is logged as a warning rather than failing this run.
+    pub fn record(&mut self, step_index: usize, step_name: &str, artifact: &Path) {
+        if let Err(err) = self.append(step_index, step_name, artifact) {
+            emit_log(
+                &self.app,
+                "warning",
+                format!("Could not record {} in the step journal: {}", step_name, err),
+            );
+        }
+    }
+
+    /// Delete the journal once the output is verified, hashed and copied.
+    pub fn remove(&self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                emit_log(
+                    &self.app,
+                    "warning",
+                    format!("Could not remove the step journal {}: {}", self.path.display(), err),
+                );
+            }
+        }
+    }
+
+    fn append(&mut self, step_index: usize, step_name: &str, artifact: &Path) -> Result<(), String> {
+        let (size, modified_ms) = artifact_stamp(artifact)
+            .ok_or_else(|| format!("Step artifact missing: {}", artifact.display()))?;
+        let entry = JournalEntry {
+            step_index,
+            step_name: step_name.to_string(),
+            artifact: artifact.to_string_lossy().to_string(),
+            size,
+            modified_ms,
+        };
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open journal {}: {}", self.path.display(), e))?;
+        file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+        file.sync_data().map_err(|e| e.to_string())?;
+
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_trailing_line_keeps_earlier_entries() {
+        let path = std::env::temp_dir().join(format!("hybrid-dv-journal-{}.jsonl", std::process::id()));
+        let entry = |step_index: usize| JournalEntry {
+            step_index,
+            step_name: format!("Step {}", step_index + 1),
+            artifact: format!("/jobs/movie/step{}.hevc", step_index),
+            size: 1024,
+            modified_ms: 1_700_000_000_000,
+        };
+        let mut content = String::new();
+        for step_index in 0..2 {
+            content.push_str(&serde_json::to_string(&entry(step_index)).unwrap());
+            content.push('\n');
+        }
+        let partial = serde_json::to_string(&entry(2)).unwrap();
+        content.push_str(&partial[..partial.len() / 2]);
+        fs::write(&path, content).unwrap();
+
+        let entries = read_entries(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].step_index, 1);
+        assert_eq!(entries[1].artifact, "/jobs/movie/step1.hevc");
+    }
+}
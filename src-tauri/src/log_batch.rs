@@ -0,0 +1,105 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use tauri::AppHandle;
+
+use crate::models::LogPayload;
+use crate::utils::emit_event;
+
+/// How long a log line may wait for others to share its event.
+const LOG_BATCH_WINDOW: Duration = Duration::from_millis(100);
+/// A batch this large goes out without waiting for the window to close.
+const LOG_BATCH_MAX: usize = 250;
+
+/// Single emitter for log lines during a run. Info and debug lines arriving
+/// within `LOG_BATCH_WINDOW` of each other go out together as one
+/// `processing:log-batch` event, so scanning a large library does not flood
+/// the webview. Warnings and errors flush what is pending and go out at
+/// once as `processing:log`, keeping the order lines were logged in.
+#[derive(Clone, Default)]
+pub struct LogBatcher {
+    sender: Arc<Mutex<Option<Sender<LogPayload>>>>,
+    worker: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl LogBatcher {
+    /// Start the emitter for a run, replacing any previous one.
+    pub fn start(&self, app: &AppHandle) {
+        self.stop();
+        let (sender, receiver) = mpsc::channel::<LogPayload>();
+        let app = app.clone();
+        let worker = thread::spawn(move || {
+            let mut pending = Vec::new();
+            let mut deadline = Instant::now();
+            loop {
+                let received = if pending.is_empty() {
+                    receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+                } else {
+                    receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                };
+                match received {
+                    Ok(payload) if matches!(payload.log_type.as_str(), "warning" | "error") => {
+                        flush(&app, &mut pending);
+                        emit_event(&app, "processing:log", payload);
+                    }
+                    Ok(payload) => {
+                        if pending.is_empty() {
+                            deadline = Instant::now() + LOG_BATCH_WINDOW;
+                        }
+                        pending.push(payload);
+                        if pending.len() >= LOG_BATCH_MAX {
+                            flush(&app, &mut pending);
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => flush(&app, &mut pending),
+                    Err(RecvTimeoutError::Disconnected) => {
+                        flush(&app, &mut pending);
+                        break;
+                    }
+                }
+            }
+        });
+
+        if let Ok(mut guard) = self.sender.lock() {
+            *guard = Some(sender);
+        }
+        if let Ok(mut guard) = self.worker.lock() {
+            *guard = Some(worker);
+        }
+    }
+
+    /// Flush whatever is pending and stop the emitter. Returns once every
+    /// submitted line has been emitted.
+    pub fn stop(&self) {
+        if let Ok(mut guard) = self.sender.lock() {
+            guard.take();
+        }
+        let worker = self.worker.lock().ok().and_then(|mut guard| guard.take());
+        if let Some(worker) = worker {
+            let _ = worker.join();
+        }
+    }
+
+    /// Hand a line to the emitter. It is given back when no run is active.
+    pub fn submit(&self, payload: LogPayload) -> Option<LogPayload> {
+        let Ok(guard) = self.sender.lock() else {
+            return Some(payload);
+        };
+        match guard.as_ref() {
+            Some(sender) => sender.send(payload).err().map(|err| err.0),
+            None => Some(payload),
+        }
+    }
+}
+
+/// Emit the pending lines: a lone line as a plain `processing:log`, more as
+/// one `processing:log-batch`.
+fn flush(app: &AppHandle, pending: &mut Vec<LogPayload>) {
+    match pending.len() {
+        0 => {}
+        1 => emit_event(app, "processing:log", pending.remove(0)),
+        _ => emit_event(app, "processing:log-batch", std::mem::take(pending)),
+    }
+}
@@ -1,17 +1,88 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod analyze;
+mod artifacts;
+mod chapters;
+mod checksum;
+mod cleanup;
 mod commands;
+mod download;
+mod estimate;
+mod event_socket;
+mod history;
+mod job;
+mod journal;
+mod log_batch;
+mod metrics;
+mod mirror;
 mod models;
+mod probe;
 mod processing;
+mod schedule;
+mod selftest;
+mod progress;
+mod staging;
+mod support;
+mod taskbar;
+mod title_memo;
 mod utils;
+mod volume;
+mod watch;
+mod web_status;
 
-use commands::{cancel_processing, start_processing, download_file};
-use models::ProcessingState;
+use commands::{
+    cancel_processing, reset_state, start_processing, download_file, cancel_download, start_event_socket, stop_event_socket,
+    start_watch, stop_watch, watch_status,
+    estimate_queue, self_test, analyze_pair, export_job, import_job, export_support_bundle, delete_artifacts,
+    get_processing_status, list_title_memos, delete_title_memo,
+};
+use event_socket::EventSocket;
+use models::{ProcessingState, StopReason};
+use watch::WatchFolder;
+use tauri::{Manager, WindowEvent};
 
 fn main() {
     tauri::Builder::default()
         .manage(ProcessingState::default())
-        .invoke_handler(tauri::generate_handler![start_processing, cancel_processing, download_file])
+        .manage(EventSocket::default())
+        .manage(WatchFolder::default())
+        .setup(|app| {
+            // Sweep temp files earlier runs could not delete.
+            let handle = app.handle();
+            std::thread::spawn(move || cleanup::cleanup_temp(&handle));
+            Ok(())
+        })
+        .on_window_event(|event| {
+            if let WindowEvent::CloseRequested { .. } = event.event() {
+                // Let running tools be killed and files report why they stopped.
+                event
+                    .window()
+                    .state::<ProcessingState>()
+                    .request_stop(StopReason::ShuttingDown);
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            start_processing,
+            cancel_processing,
+            reset_state,
+            download_file,
+            cancel_download,
+            start_event_socket,
+            stop_event_socket,
+            start_watch,
+            stop_watch,
+            watch_status,
+            estimate_queue,
+            self_test,
+            analyze_pair,
+            export_job,
+            import_job,
+            export_support_bundle,
+            delete_artifacts,
+            get_processing_status,
+            list_title_memos,
+            delete_title_memo
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
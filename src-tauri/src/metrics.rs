@@ -0,0 +1,175 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::checksum::OutputSidecar;
+use crate::mirror::OutputCopy;
+
+/// Cumulative wall time of one step name across every file of a run.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StepTime {
+    pub name: String,
+    pub secs: f64,
+    pub runs: usize,
+}
+
+/// Sent as `processing:metrics` when a run ends and kept in its `result.json`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsSummary {
+    pub wall_secs: f64,
+    pub steps: Vec<StepTime>,
+    pub peak_workers: usize,
+    /// Largest total size of intermediates on disk at once.
+    pub peak_temp_bytes: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    /// Copies of finished outputs to `additional_outputs`, one per file and
+    /// destination.
+    #[serde(default)]
+    pub copies: Vec<OutputCopy>,
+    /// Size and SHA-256 of every hashed output.
+    #[serde(default)]
+    pub hashes: Vec<OutputSidecar>,
+}
+
+#[derive(Default)]
+struct MetricsState {
+    started: Option<Instant>,
+    steps: Vec<StepTime>,
+    workers: usize,
+    peak_workers: usize,
+    temp_bytes: u64,
+    peak_temp_bytes: u64,
+    bytes_read: u64,
+    bytes_written: u64,
+    copies: Vec<OutputCopy>,
+    hashes: Vec<OutputSidecar>,
+}
+
+/// Resource use of the current run, aggregated from what the pipeline
+/// already observes: step timings, worker counts and artifact sizes.
+#[derive(Clone, Default)]
+pub struct RunMetrics {
+    state: Arc<Mutex<MetricsState>>,
+}
+
+fn gib(bytes: u64) -> f64 {
+    bytes as f64 / 1_073_741_824.0
+}
+
+impl RunMetrics {
+    /// Forget the previous run without starting a new one.
+    pub fn reset(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            *state = MetricsState::default();
+        }
+    }
+
+    /// Forget the previous run and start the wall clock.
+    pub fn start(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            *state = MetricsState {
+                started: Some(Instant::now()),
+                ..MetricsState::default()
+            };
+        }
+    }
+
+    /// Add one finished tool run that read `read` bytes and wrote `written`.
+    /// `temp` marks its output as an intermediate that stays on disk until
+    /// the file's cleanup.
+    pub fn record_step(&self, name: &str, elapsed: Duration, read: u64, written: u64, temp: bool) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        match state.steps.iter_mut().find(|step| step.name == name) {
+            Some(step) => {
+                step.secs += elapsed.as_secs_f64();
+                step.runs += 1;
+            }
+            None => state.steps.push(StepTime {
+                name: name.to_string(),
+                secs: elapsed.as_secs_f64(),
+                runs: 1,
+            }),
+        }
+        state.bytes_read += read;
+        state.bytes_written += written;
+        if temp {
+            state.temp_bytes += written;
+            state.peak_temp_bytes = state.peak_temp_bytes.max(state.temp_bytes);
+        }
+    }
+
+    /// Intermediates of `bytes` were deleted.
+    pub fn release_temp(&self, bytes: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.temp_bytes = state.temp_bytes.saturating_sub(bytes);
+        }
+    }
+
+    pub fn record_copy(&self, copy: OutputCopy) {
+        if let Ok(mut state) = self.state.lock() {
+            state.copies.push(copy);
+        }
+    }
+
+    pub fn record_hash(&self, sidecar: OutputSidecar) {
+        if let Ok(mut state) = self.state.lock() {
+            state.hashes.push(sidecar);
+        }
+    }
+
+    pub fn worker_started(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.workers += 1;
+            state.peak_workers = state.peak_workers.max(state.workers);
+        }
+    }
+
+    pub fn worker_finished(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.workers = state.workers.saturating_sub(1);
+        }
+    }
+
+    pub fn summary(&self) -> MetricsSummary {
+        let Ok(state) = self.state.lock() else {
+            return MetricsSummary::default();
+        };
+        MetricsSummary {
+            wall_secs: state.started.map(|started| started.elapsed().as_secs_f64()).unwrap_or(0.0),
+            steps: state.steps.clone(),
+            peak_workers: state.peak_workers,
+            peak_temp_bytes: state.peak_temp_bytes,
+            bytes_read: state.bytes_read,
+            bytes_written: state.bytes_written,
+            copies: state.copies.clone(),
+            hashes: state.hashes.clone(),
+        }
+    }
+}
+
+impl MetricsSummary {
+    /// One-line summary for the final log.
+    pub fn describe(&self) -> String {
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| format!("{} {:.1}s", step.name, step.secs))
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!(
+            "wall {:.1}s | peak workers {} | peak temp {:.2} GiB | read {:.2} GiB, written {:.2} GiB | steps: {}",
+            self.wall_secs,
+            self.peak_workers,
+            gib(self.peak_temp_bytes),
+            gib(self.bytes_read),
+            gib(self.bytes_written),
+            if steps.is_empty() { "none".to_string() } else { steps }
+        )
+    }
+}
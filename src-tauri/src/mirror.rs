@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::models::{OverwritePolicy, ProcessingState};
+use crate::staging::{copy_chunked, CopyProgress};
+use crate::utils::{emit_log, emit_step};
+
+pub const COPY_STEP_ID: usize = 14;
+pub const COPY_STEP_NAME: &str = "Copy to Extra Destinations";
+
+/// How copying one finished file to an additional destination went. Listed
+/// in the run's metrics, so it reaches both `processing:metrics` and the
+/// run's `result.json`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputCopy {
+    pub source: String,
+    /// Where the copy ended up, or was meant to.
+    pub destination: String,
+    /// `"copied"` or `"failed"`.
+    pub status: String,
+    pub error: Option<String>,
+    pub bytes: u64,
+}
+
+/// First `name (n).ext` next to `target` that does not exist yet.
+fn free_name(target: &Path) -> PathBuf {
+    let stem = target.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = target
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut attempt = 1;
+    let mut candidate = target.to_path_buf();
+    while candidate.exists() {
+        attempt += 1;
+        candidate = target.with_file_name(format!("{} ({}){}", stem, attempt, extension));
+    }
+    candidate
+}
+
+/// Copy `source` into `dir` under a `.partial` name, renamed into place once
+/// complete so a destination never holds a truncated file under its real name.
+fn copy_one(
+    app: &AppHandle,
+    state: &ProcessingState,
+    source: &Path,
+    target: &Path,
+    progress: &mut CopyProgress,
+) -> Result<u64, String> {
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let mut partial = target.as_os_str().to_os_string();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let before = progress.copied;
+    let result = copy_chunked(app, state, source, &partial, progress)
+        .and_then(|_| fs::rename(&partial, target).map_err(|e| format!("Failed to rename {}: {}", partial.display(), e)));
+    if let Err(err) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+    Ok(progress.copied - before)
+}
+
+/// Copy a finished output (all of its parts when split) to every extra
+/// destination folder as one step. A destination that fails only produces a
+/// warning; the returned list says how each copy went. Errors only when the
+/// run is cancelled.
+pub fn copy_to_destinations(
+    app: &AppHandle,
+    state: &ProcessingState,
+    outputs: &[PathBuf],
+    destinations: &[PathBuf],
+    policy: OverwritePolicy,
+) -> Result<Vec<OutputCopy>, String> {
+    emit_step(app, COPY_STEP_ID, COPY_STEP_NAME, "active", 0);
+    let size: u64 = outputs
+        .iter()
+        .map(|output| fs::metadata(output).map(|m| m.len()).unwrap_or(0))
+        .sum();
+    let mut progress = CopyProgress::new(COPY_STEP_ID, COPY_STEP_NAME, size * destinations.len() as u64);
+    let mut copies = Vec::with_capacity(outputs.len() * destinations.len());
+
+    for destination in destinations {
+        for output in outputs {
+            let Some(name) = output.file_name() else {
+                continue;
+            };
+            let mut target = destination.join(name);
+            if policy == OverwritePolicy::Rename {
+                target = free_name(&target);
+            }
+            emit_log(
+                app,
+                "info",
+                format!("Copying {} -> {}", output.display(), target.display()),
+            );
+            let copy = match copy_one(app, state, output, &target, &mut progress) {
+                Ok(bytes) => OutputCopy {
+                    source: output.display().to_string(),
+                    destination: target.display().to_string(),
+                    status: "copied".to_string(),
+                    error: None,
+                    bytes,
+                },
+                Err(err) if err == "Processing cancelled" => {
+                    emit_step(app, COPY_STEP_ID, COPY_STEP_NAME, "pending", 0);
+                    return Err(err);
+                }
+                Err(err) => {
+                    emit_log(
+                        app,
+                        "warning",
+                        format!("Could not copy {} to {}: {}", output.display(), destination.display(), err),
+                    );
+                    OutputCopy {
+                        source: output.display().to_string(),
+                        destination: target.display().to_string(),
+                        status: "failed".to_string(),
+                        error: Some(err),
+                        bytes: 0,
+                    }
+                }
+            };
+            state.metrics.record_copy(copy.clone());
+            copies.push(copy);
+        }
+    }
+
+    let failed = copies.iter().filter(|copy| copy.status == "failed").count();
+    emit_step(app, COPY_STEP_ID, COPY_STEP_NAME, "completed", 100);
+    emit_log(
+        app,
+        if failed == 0 { "success" } else { "warning" },
+        format!(
+            "Copied to {} of {} extra destination file(s) in {:.0}s",
+            copies.len() - failed,
+            copies.len(),
+            progress.started.elapsed().as_secs_f64()
+        ),
+    );
+    Ok(copies)
+}
@@ -1,12 +1,480 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
+use crate::checksum::OutputSidecar;
+use crate::history::RunHistory;
+use crate::log_batch::LogBatcher;
+use crate::metrics::RunMetrics;
+use crate::probe::{MediaProbe, DEFAULT_FPS_TOLERANCE};
+use crate::progress::ProgressHub;
+use crate::schedule::ProcessingWindow;
+use crate::volume::VolumeCache;
+use crate::web_status::{WebStatus, WebStatusSettings};
+
 #[derive(Clone, Default)]
 pub struct ProcessingState {
     pub cancel_flag: Arc<Mutex<bool>>,
+    /// While set, no new step is started; running tools are left to finish.
+    pub pause_flag: Arc<Mutex<bool>>,
+    /// Last value sent as `processing:status`.
+    pub status: Arc<Mutex<String>>,
+    pub job_progress: Arc<Mutex<JobProgress>>,
+    pub probe: MediaProbe,
+    pub outputs: Arc<Mutex<OutputRegistry>>,
+    pub progress: ProgressHub,
+    pub logs: LogBatcher,
+    /// First reason recorded for the run stopping early.
+    pub stop_reason: Arc<Mutex<Option<StopReason>>>,
+    pub log_level: Arc<Mutex<LogLevel>>,
+    pub history: RunHistory,
+    pub metrics: RunMetrics,
+    /// Raised by `cancel_download`; checked between downloaded chunks.
+    pub download_cancel: Arc<Mutex<bool>>,
+    /// Filesystem of each output volume seen this run.
+    pub volumes: VolumeCache,
+    /// Status page for other devices, served during a run when enabled.
+    pub web: WebStatus,
+    /// Daily hours the current run may start files in.
+    pub window: Arc<Mutex<Option<ProcessingWindow>>>,
+}
+
+impl ProcessingState {
+    /// Record why the run is stopping, keeping the first reason given.
+    pub fn note_stop(&self, reason: StopReason) {
+        if let Ok(mut current) = self.stop_reason.lock() {
+            if current.is_none() {
+                *current = Some(reason);
+            }
+        }
+    }
+
+    /// Record `reason` and raise the cancel flag.
+    pub fn request_stop(&self, reason: StopReason) {
+        self.note_stop(reason);
+        if let Ok(mut flag) = self.cancel_flag.lock() {
+            *flag = true;
+        }
+    }
+
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason.lock().ok().and_then(|reason| reason.clone())
+    }
+
+    /// Whether a run is processing, paused, or waiting for its scheduled start.
+    pub fn is_running(&self) -> bool {
+        self.status
+            .lock()
+            .map(|status| matches!(status.as_str(), "processing" | "scheduled" | "paused"))
+            .unwrap_or(false)
+    }
+
+    /// Put everything a previous run may have left behind back to a clean
+    /// baseline: stop and pause flags, the stop reason, overall progress,
+    /// caches, claimed outputs, metrics and the run's emitters.
+    pub fn reset_run(&self) {
+        for flag in [&self.cancel_flag, &self.pause_flag, &self.download_cancel] {
+            if let Ok(mut flag) = flag.lock() {
+                *flag = false;
+            }
+        }
+        if let Ok(mut reason) = self.stop_reason.lock() {
+            *reason = None;
+        }
+        if let Ok(mut window) = self.window.lock() {
+            *window = None;
+        }
+        if let Ok(mut job) = self.job_progress.lock() {
+            job.reset();
+        }
+        if let Ok(mut outputs) = self.outputs.lock() {
+            outputs.reset();
+        }
+        self.probe.clear();
+        self.volumes.clear();
+        self.metrics.reset();
+        self.progress.stop();
+        self.logs.stop();
+        self.web.stop();
+    }
+}
+
+/// Lowest log type sent to the UI. `Debug` adds the exact command line of
+/// every tool run.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+}
+
+/// Why a file or the whole run stopped before finishing.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "kind", content = "file")]
+pub enum StopReason {
+    UserCancelled,
+    /// Another file in the same job failed.
+    SiblingFailed(String),
+    ShuttingDown,
+    /// Same HDR/DV pair as the given file or item earlier in the run.
+    Duplicate(String),
+    /// `end_step` was reached; later steps were left for another run.
+    EndStep(String),
+}
+
+impl StopReason {
+    pub fn describe(&self) -> String {
+        match self {
+            StopReason::UserCancelled => "cancelled".to_string(),
+            StopReason::SiblingFailed(file) => format!("stopped due to error in {}", file),
+            StopReason::ShuttingDown => "stopped because the app is shutting down".to_string(),
+            StopReason::Duplicate(original) => format!("skipped: duplicate of {}", original),
+            StopReason::EndStep(step) => format!("stopped after {} as requested (partial run)", step),
+        }
+    }
+}
+
+/// Output paths claimed so far in the current run, keyed case-insensitively
+/// on platforms whose filesystems are, and mapped to the input that claimed them.
+/// Also remembers which HDR/DV input pairs are already being processed.
+#[derive(Default)]
+pub struct OutputRegistry {
+    claimed: HashMap<String, PathBuf>,
+    pairs: HashMap<(String, String), String>,
+    /// Path each requested output/input combination was given, so claiming
+    /// it again later in the run returns the same file.
+    resolved: HashMap<(String, String), PathBuf>,
+}
+
+impl OutputRegistry {
+    pub fn reset(&mut self) {
+        self.claimed.clear();
+        self.pairs.clear();
+        self.resolved.clear();
+    }
+
+    /// The path `output` was already claimed as for `input` in this run.
+    pub fn claimed_for(&self, output: &Path, input: &Path) -> Option<PathBuf> {
+        self.resolved
+            .get(&(Self::key(output), Self::normalize(input)))
+            .cloned()
+    }
+
+    fn normalize(path: &Path) -> String {
+        let key = path.to_string_lossy().to_string();
+        if cfg!(any(target_os = "windows", target_os = "macos")) {
+            key.to_lowercase()
+        } else {
+            key
+        }
+    }
+
+    fn key(output: &Path) -> String {
+        let parent = output
+            .parent()
+            .and_then(|p| std::fs::canonicalize(p).ok())
+            .unwrap_or_else(|| output.parent().map(Path::to_path_buf).unwrap_or_default());
+        Self::normalize(&parent.join(output.file_name().unwrap_or_default()))
+    }
+
+    /// Reserve the `hdr`/`dv` pair for `owner`, or return the owner that
+    /// already has it in this run.
+    pub fn claim_pair(&mut self, hdr: &Path, dv: &Path, owner: &str) -> Option<String> {
+        let canonical = |path: &Path| {
+            Self::normalize(&std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+        };
+        match self.pairs.entry((canonical(hdr), canonical(dv))) {
+            std::collections::hash_map::Entry::Occupied(entry) => Some(entry.get().clone()),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(owner.to_string());
+                None
+            }
+        }
+    }
+
+    /// Reserve `output` for `input`. On collision, `Rename` picks the next free
+    /// `name (n).ext`; any other policy reports the conflicting inputs unless
+    /// `rename_clashes` allows renaming for clashes within this run. With
+    /// `resume`, a file already on disk is left to the step journal rather
+    /// than renamed around, so a resumed run finds its earlier output.
+    pub fn claim(
+        &mut self,
+        output: &Path,
+        input: &Path,
+        policy: OverwritePolicy,
+        rename_clashes: bool,
+        resume: bool,
+    ) -> Result<PathBuf, String> {
+        let mut candidate = output.to_path_buf();
+        let mut attempt = 1;
+        loop {
+            let key = Self::key(&candidate);
+            let taken_by = self.claimed.get(&key);
+            let exists_on_disk = policy == OverwritePolicy::Rename && !resume && candidate.exists();
+            if taken_by.is_none() && !exists_on_disk {
+                self.claimed.insert(key, input.to_path_buf());
+                self.resolved
+                    .insert((Self::key(output), Self::normalize(input)), candidate.clone());
+                return Ok(candidate);
+            }
+            if policy != OverwritePolicy::Rename && !rename_clashes {
+                let other = taken_by.map(|p| p.display().to_string()).unwrap_or_default();
+                return Err(format!(
+                    "Output {} would be written twice in this run (inputs: {} and {})",
+                    output.display(),
+                    other,
+                    input.display()
+                ));
+            }
+            attempt += 1;
+            let stem = output.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let extension = output
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            candidate = output.with_file_name(format!("{} ({}){}", stem, attempt, extension));
+        }
+    }
+}
+
+/// Step a run starts at, reusing intermediates a kept-temp run left behind.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResumeStep {
+    /// Mux the existing `_dv_hdr.hevc` and `_audiosubs.mka` again.
+    Mux,
+}
+
+/// A pipeline step named by its 1-based position in the step list, by a
+/// short alias (`audio`, `dv`, `rpu`, `hdr10`, `inject`, `mux`) or by its
+/// full name.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum StepRef {
+    Index(usize),
+    Name(String),
+}
+
+/// What to do when an output path is already taken.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OverwritePolicy {
+    /// Replace existing files; two inputs mapping to one output is an error.
+    #[default]
+    Overwrite,
+    /// Pick a free `name (n).ext` instead of replacing anything.
+    Rename,
+}
+
+/// How the final mux assembles the output container.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RemuxStrategy {
+    /// Mux the new video with the extracted audio/subtitle MKA.
+    #[default]
+    Rebuild,
+    /// Mux the new video with every non-video track, chapter, tag and
+    /// attachment of the HDR source, untouched.
+    ReplaceVideo,
+}
+
+/// Where the output's chapters come from.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(tag = "mode", content = "value", rename_all = "snake_case")]
+pub enum ChapterMode {
+    /// Whatever the source carries, editions and ordered chapters included.
+    #[default]
+    Keep,
+    /// One plain edition: the default one, unordered, without chapters that
+    /// link to other files.
+    DropEditions,
+    /// A chapters XML file supplied by the user.
+    Replace(String),
+}
+
+/// Which input the injected RPU is extracted from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RpuSource {
+    #[default]
+    DvFile,
+    HdrFile,
+    /// Whichever of the two `dovi_tool info` rates higher.
+    Best,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// What to do with a profile 7 source whose enhancement layer is FEL, which
+/// the profile 8 conversion cannot carry over.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FelPolicy {
+    /// Convert anyway and warn about the lost enhancement layer.
+    #[default]
+    Convert,
+    /// Fail the file when the EL is FEL.
+    Abort,
+    /// Only proceed when the EL is positively identified as MEL.
+    MelOnly,
+}
+
+/// Which HDR10+ metadata wins when the base stream already carries some and
+/// a donor is given too.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Hdr10PlusSource {
+    /// Inject the donor's metadata.
+    #[default]
+    Donor,
+    /// Keep the base stream's own metadata and ignore the donor.
+    Base,
+    /// The donor when its frame count matches the base video, else the base.
+    Auto,
+}
+
+/// What to do when the DV and HDR inputs differ in length by more than a
+/// couple of frames, which leaves the RPU ending before or after the video.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameCountPolicy {
+    /// Log both counts and inject as is.
+    #[default]
+    Warn,
+    /// Cut the RPU's extra frames, or repeat its last frame to the end of a
+    /// longer video; the video itself is never cut.
+    Trim,
+    /// Fail the file.
+    Fail,
+}
+
+/// Order in which a folder batch's pairs are queued.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchOrdering {
+    /// By HDR file name.
+    #[default]
+    Name,
+    SizeAscending,
+    SizeDescending,
+    /// Alternate the largest and smallest remaining pairs, so heavy files
+    /// do not run back to back.
+    Interleaved,
+}
+
+/// Where folder outputs land relative to the output root.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStructure {
+    /// Every output directly in the output root; clashing names get `(n)`.
+    #[default]
+    Flat,
+    /// Scan the input folders' subfolders too, and keep each source file's
+    /// subfolder path under the output root.
+    Mirror,
+}
+
+impl OutputStructure {
+    /// Whether folder scans descend into subfolders.
+    pub fn recursive(self) -> bool {
+        self == OutputStructure::Mirror
+    }
+}
+
+/// Job-wide progress accumulator shared by every worker of a run.
+///
+/// Each queue item registers how many files it expands to and their input
+/// size; every file then reports its own 0-100 progress, weighted by its own
+/// input size, so a long film moves the overall value more than a short
+/// episode. Without known sizes every file weighs the same.
+#[derive(Default)]
+pub struct JobProgress {
+    /// File count and combined input bytes per queue item.
+    expected_files: HashMap<String, (usize, u64)>,
+    file_progress: HashMap<String, u8>,
+    /// Input bytes per file key, registered when the file starts.
+    file_bytes: HashMap<String, u64>,
+    files_completed: usize,
+    files_failed: usize,
+    /// Attempts beyond the first, per file key.
+    retries: HashMap<String, u32>,
+}
+
+impl JobProgress {
+    pub fn reset(&mut self) {
+        *self = JobProgress::default();
+    }
+
+    pub fn set_expected(&mut self, item_id: &str, file_count: usize, bytes: u64) {
+        self.expected_files.insert(item_id.to_string(), (file_count.max(1), bytes));
+    }
+
+    pub fn set_file_bytes(&mut self, file_key: &str, bytes: u64) {
+        self.file_bytes.insert(file_key.to_string(), bytes);
+    }
+
+    pub fn update_file(&mut self, file_key: &str, progress: u8) {
+        self.file_progress.insert(file_key.to_string(), progress.min(100));
+    }
+
+    pub fn mark_completed(&mut self, file_key: &str) {
+        self.file_progress.insert(file_key.to_string(), 100);
+        self.files_completed += 1;
+    }
+
+    pub fn mark_failed(&mut self, file_key: &str) {
+        // A failed file still counts as finished work for the overall bar.
+        self.file_progress.insert(file_key.to_string(), 100);
+        self.files_failed += 1;
+    }
+
+    /// Take a failed file back out of the totals before it is queued again.
+    pub fn retry(&mut self, file_key: &str) {
+        self.file_progress.insert(file_key.to_string(), 0);
+        self.files_failed = self.files_failed.saturating_sub(1);
+        *self.retries.entry(file_key.to_string()).or_insert(0) += 1;
+    }
+
+    /// 1-based attempt number of a file.
+    pub fn attempt(&self, file_key: &str) -> u32 {
+        self.retries.get(file_key).copied().unwrap_or(0) + 1
+    }
+
+    pub fn payload(&self) -> OverallPayload {
+        let expected: usize = self.expected_files.values().map(|(count, _)| count).sum();
+        let files_total = expected.max(self.file_progress.len()).max(1);
+        let expected_bytes: u64 = self.expected_files.values().map(|(_, bytes)| bytes).sum();
+        // A file that has not registered its size weighs as much as the
+        // average expected file.
+        let average = (expected_bytes as f64 / files_total as f64).max(1.0);
+        let weight = |file_key: &String| match expected_bytes {
+            0 => 1.0,
+            _ => self.file_bytes.get(file_key).map(|bytes| *bytes as f64).unwrap_or(average),
+        };
+        let done: f64 = self
+            .file_progress
+            .iter()
+            .map(|(file_key, progress)| weight(file_key) * f64::from(*progress) / 100.0)
+            .sum();
+        let reported: f64 = self.file_progress.keys().map(weight).sum();
+        // Once every file has reported, their own sizes are the whole job.
+        let total = if expected_bytes == 0 {
+            files_total as f64
+        } else if self.file_progress.len() >= files_total {
+            reported
+        } else {
+            reported.max(expected_bytes as f64)
+        };
+        OverallPayload {
+            progress: (done / total.max(1.0) * 100.0).round().min(100.0) as u8,
+            files_completed: self.files_completed,
+            files_failed: self.files_failed,
+            files_total,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolPaths {
     pub dovi_tool: String,
@@ -16,16 +484,300 @@ pub struct ToolPaths {
     pub mediainfo: String,
     pub mp4box: String,
     pub hdr10plus_tool: String,
+    /// The single output folder of older settings, used for whichever of
+    /// the two below is empty.
+    #[serde(default)]
     pub default_output: String,
+    /// Where single files (movies) and folder batches (shows) go by default.
+    #[serde(default)]
+    pub default_output_single: String,
+    #[serde(default)]
+    pub default_output_batch: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl ToolPaths {
+    pub fn single_output_dir(&self) -> &str {
+        Some(self.default_output_single.as_str())
+            .filter(|dir| !dir.trim().is_empty())
+            .unwrap_or(&self.default_output)
+    }
+
+    pub fn batch_output_dir(&self) -> &str {
+        Some(self.default_output_batch.as_str())
+            .filter(|dir| !dir.trim().is_empty())
+            .unwrap_or(&self.default_output)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct QueueItem {
     pub id: String,
     pub hdr_path: String,
     pub dv_path: String,
     pub output_path: String,
+    /// Absent: use the request-level donor. `null` or `""`: no HDR10+ for this item.
+    /// Skipped when absent so a saved request reads back the same way.
+    #[serde(default, deserialize_with = "deserialize_present", skip_serializing_if = "Option::is_none")]
+    pub hdr10plus_path: Option<Option<String>>,
+    /// Overrides the request-level HDR10+ delay for this item.
+    #[serde(default)]
+    pub hdr10plus_delay_ms: Option<f64>,
+}
+
+impl QueueItem {
+    pub fn resolve_hdr10plus_path(&self, fallback: Option<&Path>) -> Option<PathBuf> {
+        match &self.hdr10plus_path {
+            None => fallback.map(Path::to_path_buf),
+            Some(Some(path)) if !path.is_empty() => Some(PathBuf::from(path)),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Deserialize a field so that a present `null` becomes `Some(None)`,
+/// leaving `None` (via `#[serde(default)]`) for a missing field.
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Subtitle variants left out of the output. Every kept track is
+/// stream-copied as-is, image or text.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleFilter {
+    #[serde(default)]
+    pub drop_forced: bool,
+    #[serde(default)]
+    pub drop_sdh: bool,
+}
+
+/// Default and forced flags to set on the output's tracks instead of the
+/// ones mkvmerge carries over. Languages are ISO 639-2 codes (`eng`, `ger`).
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackDefaults {
+    /// The first audio track in this language becomes the only default one.
+    #[serde(default)]
+    pub audio_language: Option<String>,
+    /// The first subtitle track in this language becomes the only default one.
+    #[serde(default)]
+    pub subtitle_language: Option<String>,
+    /// Also mark that subtitle track forced.
+    #[serde(default)]
+    pub subtitle_forced: bool,
+}
+
+impl TrackDefaults {
+    pub fn is_empty(&self) -> bool {
+        self.audio_language.is_none() && self.subtitle_language.is_none()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioTranscode {
+    /// ffmpeg encoder name, e.g. `eac3`, `ac3` or `aac`.
+    pub codec: String,
+    /// Per-track target bitrate such as `640k`; the encoder default when absent.
+    pub bitrate: Option<String>,
+}
+
+/// Which L5 active-area metadata wins when the DV RPU already carries some.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ActiveAreaMode {
+    /// Keep the RPU's own L5 data; only apply the computed crop when it has none.
+    #[default]
+    Auto,
+    /// Always replace the RPU's L5 data with the computed static crop.
+    Override,
+}
+
+/// How the final mux is split into several files (mkvmerge `--split`).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "mode", content = "value", rename_all = "lowercase")]
+pub enum SplitSpec {
+    /// Maximum part size, e.g. `4G` or `700M`.
+    Size(String),
+    /// Maximum part duration, e.g. `01:00:00` or `1800s`.
+    Duration(String),
+    /// Chapter numbers to split before, or `all`.
+    Chapters(String),
+}
+
+impl SplitSpec {
+    pub fn mkvmerge_arg(&self) -> Result<String, String> {
+        let (kind, value) = match self {
+            SplitSpec::Size(value) => ("size", value),
+            SplitSpec::Duration(value) => ("duration", value),
+            SplitSpec::Chapters(value) => ("chapters", value),
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(format!("Split by {} requires a value", kind));
+        }
+        Ok(format!("{}:{}", kind, value))
+    }
+
+    /// Part size in bytes for a size split, read the way mkvmerge does
+    /// (`k`/`M`/`G` are powers of 1024). `None` for other modes.
+    pub fn size_bytes(&self) -> Option<u64> {
+        let SplitSpec::Size(value) = self else {
+            return None;
+        };
+        let value = value.trim();
+        let (number, multiplier) = match value.chars().last()?.to_ascii_lowercase() {
+            'k' => (&value[..value.len() - 1], 1u64 << 10),
+            'm' => (&value[..value.len() - 1], 1u64 << 20),
+            'g' => (&value[..value.len() - 1], 1u64 << 30),
+            _ => (value, 1),
+        };
+        number.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+    }
+}
+
+/// Per-run options shared by every file the run processes.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineOptions {
+    pub audio_transcode: Option<AudioTranscode>,
+    pub active_area_mode: ActiveAreaMode,
+    pub split: Option<SplitSpec>,
+    pub overwrite_policy: OverwritePolicy,
+    /// Skip steps the journal shows finished with their artifacts intact.
+    pub resume: bool,
+    /// Where outputs go when neither the request nor the item names a path:
+    /// `default_output` for single files, `default_output_batch` for folders.
+    pub default_output: String,
+    pub default_output_batch: String,
+    pub remux_strategy: RemuxStrategy,
+    pub rpu_source: RpuSource,
+    pub subtitle_filter: SubtitleFilter,
+    pub track_defaults: TrackDefaults,
+    /// Audio languages to put first in the output, in this order.
+    pub audio_order: Vec<String>,
+    /// Run without the settings remembered for a DV input; the run's own
+    /// settings still replace them afterwards.
+    pub ignore_title_memos: bool,
+    /// Folders each finished output is also copied to.
+    pub additional_outputs: Vec<PathBuf>,
+    /// Largest frame rate difference between inputs that is not a mismatch,
+    /// beyond exact fractions and NTSC rounding.
+    pub fps_tolerance: f64,
+    /// Poster image, or a folder of posters named after each HDR input.
+    pub cover_art: Option<PathBuf>,
+    pub chapters: ChapterMode,
+    pub fel_policy: FelPolicy,
+    /// Fail the run on an unpaired HDR file instead of skipping it.
+    pub strict_pairing: bool,
+    /// Fail a file whose inputs look like different cuts instead of warning.
+    pub strict_validation: bool,
+    pub hdr10plus_source: Hdr10PlusSource,
+    pub frame_count_policy: FrameCountPolicy,
+    /// Extra passes over failed folder files once the queue has drained.
+    pub item_retry_count: u32,
+    pub item_retry_delay_secs: u64,
+    /// Local folder the HDR and DV inputs are copied to before processing.
+    pub staging_dir: Option<PathBuf>,
+    /// Hard-link inputs into the staging folder when it is on the same
+    /// volume, copying only when that fails.
+    pub stage_link: bool,
+    /// Measure baked-in letterbox bars with ffmpeg cropdetect when the
+    /// container heights match.
+    pub detect_letterbox: bool,
+    /// Map a DV frame of another resolution onto the HDR frame by width
+    /// instead of failing the file.
+    pub scale_dv_frame: bool,
+    pub output_structure: OutputStructure,
+    /// Queue order of folder pairs, by name or by input size.
+    pub ordering: BatchOrdering,
+    /// Video track to demux from the HDR / DV input instead of the first
+    /// HEVC track. Matroska track ids as listed by `mkvmerge -J`, MP4 track
+    /// ids as listed by MediaInfo.
+    pub hdr_video_track: Option<u32>,
+    pub dv_video_track: Option<u32>,
+    /// SHA-256 the final output(s) into their `<output>.json` sidecar, which
+    /// records the size either way.
+    pub hash_output: bool,
+    pub resume_from_step: Option<ResumeStep>,
+    /// First and last step to run; the others are skipped. Starting late
+    /// reuses intermediates from an earlier run.
+    pub start_step: Option<StepRef>,
+    pub end_step: Option<StepRef>,
+    /// Existing HDR10 stream and RPU for a run that starts after extracting
+    /// them, instead of the `_hdr10.hevc` / `_rpu.bin` next to the output.
+    pub hdr10_hevc_path: Option<PathBuf>,
+    pub rpu_path: Option<PathBuf>,
+    /// Prepared HDR10 stream to inject the RPU into instead of the one
+    /// extracted from the HDR input, which is then not extracted at all.
+    pub base_hevc_path: Option<PathBuf>,
+    /// A single run without an HDR input: the profile 8 DV file supplies the
+    /// base layer, audio and subtitles as well as the RPU.
+    pub dv_only: bool,
+    /// Shell command run after each successful mux, with `{output}` and
+    /// `{input}` replaced by the quoted paths.
+    pub post_hook: Option<String>,
+    /// Wait for the hook before moving on instead of leaving it running.
+    pub post_hook_blocking: bool,
+    /// `--ui-language` of mkvmerge/mkvextract. English keeps their
+    /// `Warning:` lines recognizable in the captured output.
+    pub mkv_ui_language: String,
+    /// Pass the HDR input's color primaries, transfer, matrix and range to
+    /// mkvmerge for the muxed video track.
+    pub write_color_metadata: bool,
+    /// Files of a folder item processed at once, already clamped to the
+    /// machine by `effective_parallel_tasks`.
+    pub parallel_tasks: usize,
+    /// Extract a file's audio and subtitles alongside its DV extraction and
+    /// RPU work instead of before them.
+    pub parallel_steps: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FileState {
+    #[default]
+    Queued,
+    Active,
+    Completed,
+    Failed,
+}
+
+/// One file of a folder item: its own progress and where it is in the queue.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSlot {
+    pub progress: u8,
+    pub state: FileState,
+}
+
+/// Per-file slots of a folder item, shared by its workers.
+pub type FileTracker = Arc<Mutex<Vec<FileSlot>>>;
+
+#[derive(Debug, Serialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCounts {
+    pub files_completed: usize,
+    pub files_failed: usize,
+    pub files_active: usize,
+    pub files_queued: usize,
+}
+
+impl FileCounts {
+    pub fn of(slots: &[FileSlot]) -> Self {
+        let mut counts = FileCounts::default();
+        for slot in slots {
+            match slot.state {
+                FileState::Queued => counts.files_queued += 1,
+                FileState::Active => counts.files_active += 1,
+                FileState::Completed => counts.files_completed += 1,
+                FileState::Failed => counts.files_failed += 1,
+            }
+        }
+        counts
+    }
 }
 
 #[derive(Clone)]
@@ -34,13 +786,14 @@ pub struct QueueContext {
     pub label: Option<String>,
     pub file_index: usize,
     pub file_total: usize,
-    pub tracker: Option<Arc<Mutex<Vec<u8>>>>,
+    pub tracker: Option<FileTracker>,
     pub active_workers: Option<Arc<Mutex<usize>>>,
     pub file_id: Option<String>,
     pub file_name: Option<String>,
+    pub attempt: u32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProcessingRequest {
     pub mode: String,
@@ -48,12 +801,257 @@ pub struct ProcessingRequest {
     pub dv_path: String,
     pub output_path: String,
     pub hdr10plus_path: String,
-    pub dv_delay_ms: f64,
+    /// `None` lets a title memo supply the delay; `Some(0.0)` forces none.
+    #[serde(default)]
+    pub dv_delay_ms: Option<f64>,
     pub hdr10plus_delay_ms: f64,
     pub keep_temp_files: bool,
     pub parallel_tasks: usize,
     pub tool_paths: ToolPaths,
     pub queue: Vec<QueueItem>,
+    #[serde(default)]
+    pub audio_transcode: Option<AudioTranscode>,
+    #[serde(default)]
+    pub active_area_mode: ActiveAreaMode,
+    #[serde(default)]
+    pub split: Option<SplitSpec>,
+    #[serde(default)]
+    pub overwrite_policy: OverwritePolicy,
+    #[serde(default)]
+    pub resume: bool,
+    /// Overrides both default output folders of `tool_paths` for this run only.
+    #[serde(default)]
+    pub output_dir: String,
+    #[serde(default)]
+    pub remux_strategy: RemuxStrategy,
+    #[serde(default)]
+    pub rpu_source: RpuSource,
+    #[serde(default)]
+    pub subtitle_filter: SubtitleFilter,
+    #[serde(default)]
+    pub track_defaults: TrackDefaults,
+    #[serde(default)]
+    pub audio_order: Vec<String>,
+    #[serde(default)]
+    pub ignore_title_memos: bool,
+    #[serde(default)]
+    pub additional_outputs: Vec<String>,
+    #[serde(default)]
+    pub fps_tolerance: Option<f64>,
+    /// Local ISO-8601 time to hold the run until, after checking it.
+    #[serde(default)]
+    pub start_at: Option<String>,
+    #[serde(default)]
+    pub allowed_window: Option<ProcessingWindow>,
+    #[serde(default)]
+    pub cover_art: Option<String>,
+    #[serde(default)]
+    pub chapters: ChapterMode,
+    #[serde(default)]
+    pub fel_policy: FelPolicy,
+    #[serde(default)]
+    pub strict_pairing: bool,
+    #[serde(default)]
+    pub strict_validation: bool,
+    #[serde(default)]
+    pub hdr10plus_source: Hdr10PlusSource,
+    #[serde(default)]
+    pub frame_count_policy: FrameCountPolicy,
+    #[serde(default)]
+    pub item_retry_count: u32,
+    #[serde(default)]
+    pub item_retry_delay_secs: u64,
+    #[serde(default)]
+    pub staging_dir: Option<String>,
+    #[serde(default)]
+    pub stage_link: bool,
+    #[serde(default)]
+    pub detect_letterbox: bool,
+    #[serde(default)]
+    pub scale_dv_frame: bool,
+    #[serde(default)]
+    pub output_structure: OutputStructure,
+    #[serde(default)]
+    pub ordering: BatchOrdering,
+    #[serde(default)]
+    pub hdr_video_track: Option<u32>,
+    #[serde(default)]
+    pub dv_video_track: Option<u32>,
+    #[serde(default)]
+    pub hash_output: bool,
+    #[serde(default)]
+    pub resume_from_step: Option<ResumeStep>,
+    #[serde(default)]
+    pub start_step: Option<StepRef>,
+    #[serde(default)]
+    pub end_step: Option<StepRef>,
+    #[serde(default)]
+    pub hdr10_hevc_path: Option<String>,
+    #[serde(default)]
+    pub rpu_path: Option<String>,
+    #[serde(default)]
+    pub base_hevc_path: Option<String>,
+    #[serde(default)]
+    pub post_hook: Option<String>,
+    #[serde(default)]
+    pub post_hook_blocking: bool,
+    /// mkvmerge/mkvextract message language; empty means English.
+    #[serde(default)]
+    pub mkv_ui_language: String,
+    /// Absent means on.
+    #[serde(default)]
+    pub write_color_metadata: Option<bool>,
+    /// Cap on concurrent MediaInfo processes; defaults to `PROBE_CONCURRENCY`.
+    #[serde(default)]
+    pub probe_concurrency: Option<usize>,
+    /// Extra MediaInfo attempts after a transient failure; defaults to
+    /// `PROBE_RETRIES`, and at most eight are made.
+    #[serde(default)]
+    pub probe_retries: Option<u32>,
+    /// Progress poll and event flush cadence in milliseconds; defaults to
+    /// `DEFAULT_PROGRESS_INTERVAL`.
+    #[serde(default)]
+    pub progress_interval_ms: Option<u64>,
+    #[serde(default)]
+    pub log_level: LogLevel,
+    #[serde(default)]
+    pub web_status: Option<WebStatusSettings>,
+    #[serde(default)]
+    pub parallel_steps: bool,
+}
+
+impl ProcessingRequest {
+    /// A single run with no HDR input, remuxing the DV file on its own.
+    pub fn dv_only(&self) -> bool {
+        self.mode == "single" && self.hdr_path.trim().is_empty()
+    }
+
+    pub fn pipeline_options(&self) -> PipelineOptions {
+        PipelineOptions {
+            audio_transcode: self
+                .audio_transcode
+                .clone()
+                .filter(|transcode| !transcode.codec.trim().is_empty()),
+            active_area_mode: self.active_area_mode,
+            split: self.split.clone(),
+            overwrite_policy: self.overwrite_policy,
+            resume: self.resume,
+            default_output: if self.output_dir.trim().is_empty() {
+                self.tool_paths.single_output_dir().to_string()
+            } else {
+                self.output_dir.clone()
+            },
+            default_output_batch: if self.output_dir.trim().is_empty() {
+                self.tool_paths.batch_output_dir().to_string()
+            } else {
+                self.output_dir.clone()
+            },
+            remux_strategy: self.remux_strategy,
+            // Without an HDR input the DV file is the only RPU there is.
+            rpu_source: if self.dv_only() { RpuSource::DvFile } else { self.rpu_source },
+            subtitle_filter: self.subtitle_filter,
+            track_defaults: self.track_defaults.clone(),
+            audio_order: self
+                .audio_order
+                .iter()
+                .map(|language| language.trim().to_string())
+                .filter(|language| !language.is_empty())
+                .collect(),
+            ignore_title_memos: self.ignore_title_memos,
+            additional_outputs: self
+                .additional_outputs
+                .iter()
+                .map(|dir| dir.trim())
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+            fps_tolerance: self
+                .fps_tolerance
+                .filter(|tolerance| tolerance.is_finite() && *tolerance >= 0.0)
+                .unwrap_or(DEFAULT_FPS_TOLERANCE),
+            cover_art: self
+                .cover_art
+                .as_deref()
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            chapters: match &self.chapters {
+                ChapterMode::Replace(path) if path.trim().is_empty() => ChapterMode::Keep,
+                ChapterMode::Replace(path) => ChapterMode::Replace(path.trim().to_string()),
+                mode => mode.clone(),
+            },
+            fel_policy: self.fel_policy,
+            strict_pairing: self.strict_pairing,
+            strict_validation: self.strict_validation,
+            hdr10plus_source: self.hdr10plus_source,
+            frame_count_policy: self.frame_count_policy,
+            item_retry_count: self.item_retry_count,
+            item_retry_delay_secs: self.item_retry_delay_secs,
+            staging_dir: self
+                .staging_dir
+                .as_deref()
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            stage_link: self.stage_link,
+            detect_letterbox: self.detect_letterbox,
+            scale_dv_frame: self.scale_dv_frame,
+            output_structure: self.output_structure,
+            ordering: self.ordering,
+            hdr_video_track: self.hdr_video_track,
+            dv_video_track: self.dv_video_track,
+            hash_output: self.hash_output,
+            resume_from_step: self.resume_from_step,
+            start_step: self.start_step.clone(),
+            end_step: self.end_step.clone(),
+            hdr10_hevc_path: self
+                .hdr10_hevc_path
+                .as_deref()
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            rpu_path: self
+                .rpu_path
+                .as_deref()
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            dv_only: self.dv_only(),
+            base_hevc_path: self
+                .base_hevc_path
+                .as_deref()
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            post_hook: self
+                .post_hook
+                .as_deref()
+                .map(str::trim)
+                .filter(|hook| !hook.is_empty())
+                .map(str::to_string),
+            post_hook_blocking: self.post_hook_blocking,
+            mkv_ui_language: Some(self.mkv_ui_language.trim())
+                .filter(|language| !language.is_empty())
+                .unwrap_or("en")
+                .to_string(),
+            write_color_metadata: self.write_color_metadata.unwrap_or(true),
+            parallel_tasks: self.parallel_tasks,
+            parallel_steps: self.parallel_steps,
+        }
+    }
+}
+
+/// Sent as `download:progress` while a tool downloads or unpacks.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgressPayload {
+    pub filename: String,
+    /// `"download"` or `"extract"`.
+    pub phase: String,
+    pub done: u64,
+    /// Unknown when the server sends no length.
+    pub total: Option<u64>,
+    pub progress: Option<u8>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -81,6 +1079,9 @@ pub struct QueuePayload {
     pub current_step: Option<String>,
     pub active_workers: Option<usize>,
     pub file_total: Option<usize>,
+    /// Folder items only.
+    #[serde(flatten)]
+    pub counts: Option<FileCounts>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -89,11 +1090,168 @@ pub struct FilePayload {
     pub id: String,
     pub queue_id: String,
     pub name: String,
+    pub status: String,
     pub progress: u8,
+    pub attempt: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<StopReason>,
+    /// Sidecars of the finished output(s), once the file has completed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hashes: Vec<OutputSidecar>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OverallPayload {
+    pub progress: u8,
+    pub files_completed: usize,
+    pub files_failed: usize,
+    pub files_total: usize,
+}
+
+/// Why a directory scan left a file out of the batch.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanSkipReason {
+    /// Hidden or not a video file.
+    Junk,
+    /// No DV file could be paired with it.
+    Unpaired,
+    /// Same HDR/DV pair as a file earlier in the run.
+    Duplicate,
+    /// The journal shows its output was already muxed.
+    AlreadyDone,
+    /// An output or intermediate a run wrote into the input folder.
+    OwnOutput,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanSkip {
+    pub file: String,
+    pub reason: ScanSkipReason,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanPair {
+    pub hdr_file: String,
+    pub dv_file: String,
+    pub matched_by: String,
+    pub output: String,
+}
+
+/// Sent as `processing:scan_report` once a folder's task list is built, so
+/// the whole batch can be checked before it runs.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanReport {
+    /// Queue item the folder belongs to; `None` for a folder run.
+    pub queue_id: Option<String>,
+    pub hdr_dir: String,
+    pub total_found: usize,
+    pub processed: usize,
+    pub skipped: Vec<ScanSkip>,
+    /// The pairs that will run, in order.
+    pub pairs: Vec<ScanPair>,
+    /// How that order was chosen.
+    pub ordering: BatchOrdering,
+    /// DV folder files no HDR file was paired with.
+    pub unmatched_dv: Vec<String>,
+}
+
+impl ScanReport {
+    pub fn skip(&mut self, file: impl Into<String>, reason: ScanSkipReason) {
+        self.skipped.push(ScanSkip {
+            file: file.into(),
+            reason,
+        });
+    }
+
+    pub fn add_pair(&mut self, hdr_file: &str, dv_file: &str, matched_by: &str, output: &Path) {
+        self.pairs.push(ScanPair {
+            hdr_file: hdr_file.to_string(),
+            dv_file: dv_file.to_string(),
+            matched_by: matched_by.to_string(),
+            output: output.to_string_lossy().to_string(),
+        });
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct StatusPayload {
     pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<StopReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Folder workers the run will use, sent with the initial status.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tasks: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overall_progress_is_weighted_by_input_size() {
+        let mut job = JobProgress::default();
+        job.set_expected("job", 2, 100);
+        job.set_file_bytes("job:0", 90);
+        job.update_file("job:0", 50);
+        // The unstarted 10-byte file keeps its share of the total.
+        assert_eq!(job.payload().progress, 45);
+        job.mark_completed("job:0");
+        assert_eq!(job.payload().progress, 90);
+        job.set_file_bytes("job:1", 10);
+        job.mark_completed("job:1");
+        assert_eq!(job.payload().progress, 100);
+    }
+
+    #[test]
+    fn overall_progress_counts_files_without_sizes() {
+        let mut job = JobProgress::default();
+        job.set_expected("job", 2, 0);
+        job.mark_completed("job:0");
+        assert_eq!(job.payload().progress, 50);
+    }
+
+    #[test]
+    fn claims_are_stable_within_a_run() {
+        let mut outputs = OutputRegistry::default();
+        let output = Path::new("/out/Movie.mkv");
+        let first = outputs
+            .claim(output, Path::new("/in/a/Movie.mkv"), OverwritePolicy::Overwrite, true, false)
+            .unwrap();
+        let second = outputs
+            .claim(output, Path::new("/in/b/Movie.mkv"), OverwritePolicy::Overwrite, true, false)
+            .unwrap();
+        assert_eq!(first, output);
+        assert_eq!(second, Path::new("/out/Movie (2).mkv"));
+        assert_eq!(outputs.claimed_for(output, Path::new("/in/b/Movie.mkv")), Some(second));
+        assert!(outputs
+            .claim(output, Path::new("/in/c/Movie.mkv"), OverwritePolicy::Overwrite, false, false)
+            .is_err());
+    }
+
+    #[test]
+    fn rename_leaves_existing_output_to_a_resumed_run() {
+        let dir = std::env::temp_dir().join(format!("claim-resume-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("Movie.mkv");
+        std::fs::write(&output, b"partial").unwrap();
+        let input = Path::new("/in/Movie.mkv");
+
+        let fresh = OutputRegistry::default()
+            .claim(&output, input, OverwritePolicy::Rename, false, false)
+            .unwrap();
+        let resumed = OutputRegistry::default()
+            .claim(&output, input, OverwritePolicy::Rename, false, true)
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(fresh, dir.join("Movie (2).mkv"));
+        assert_eq!(resumed, output);
+    }
 }
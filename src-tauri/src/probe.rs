@@ -0,0 +1,972 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::processing::tool_command;
+use crate::utils::{emit_log, identify_file, normalize_duration_string};
+
+/// Number of files probed at once when prefetching a folder, and the default
+/// cap on concurrent MediaInfo processes across all workers.
+pub const PROBE_CONCURRENCY: usize = 4;
+
+/// Default number of extra MediaInfo attempts after a transient failure.
+pub const PROBE_RETRIES: u32 = 2;
+
+/// Most extra MediaInfo attempts a run may ask for.
+const MAX_PROBE_RETRIES: u32 = 8;
+
+/// Longest pause between MediaInfo attempts, as a doubling of 500 ms.
+const MAX_BACKOFF_DOUBLINGS: u32 = 5;
+
+/// Default largest frame rate difference that still counts as the same rate.
+pub const DEFAULT_FPS_TOLERANCE: f64 = 0.001;
+
+/// NTSC rates (N×1000/1001), which tools round differently: 23.976,
+/// 23.976024 and 23.98 all mean 24000/1001.
+const NTSC_RATES: [f64; 5] = [
+    24000.0 / 1001.0,
+    30000.0 / 1001.0,
+    48000.0 / 1001.0,
+    60000.0 / 1001.0,
+    120000.0 / 1001.0,
+];
+/// How far a reported rate may be from an NTSC rate and still be read as it.
+const NTSC_ROUNDING: f64 = 0.005;
+
+/// MediaInfo and mkvmerge as a probe runs them: inside the job's work
+/// directory, like every other tool.
+#[derive(Debug, Clone)]
+pub struct ProbeTools {
+    pub mediainfo: PathBuf,
+    pub mkvmerge: PathBuf,
+    pub work_dir: PathBuf,
+}
+
+impl ProbeTools {
+    pub fn new(mediainfo: &Path, mkvmerge: &Path, work_dir: &Path) -> ProbeTools {
+        ProbeTools {
+            mediainfo: mediainfo.to_path_buf(),
+            mkvmerge: mkvmerge.to_path_buf(),
+            work_dir: work_dir.to_path_buf(),
+        }
+    }
+}
+
+/// Everything the pipeline needs to know about one input, gathered once per
+/// run from MediaInfo and mkvmerge identification.
+#[derive(Debug, Clone)]
+pub struct MediaReport {
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    /// Frame rate as MediaInfo's exact numerator and denominator, when given.
+    pub fps_rational: Option<(u64, u64)>,
+    pub track_id: Option<u32>,
+    pub language: Option<String>,
+    pub format: Option<String>,
+    /// MediaInfo `HDR_Format`, e.g. "Dolby Vision / SMPTE ST 2086".
+    pub hdr_format: Option<String>,
+    /// MediaInfo `HDR_Format_Profile`, e.g. "dvhe.08 / ".
+    pub hdr_format_profile: Option<String>,
+    /// MediaInfo `HDR_Format_Level`, e.g. "06 / ".
+    pub hdr_format_level: Option<String>,
+    /// MediaInfo `HDR_Format_Settings`, the Dolby Vision layers, e.g.
+    /// "BL+EL+RPU / " for a profile 7 dual-layer stream.
+    pub hdr_format_settings: Option<String>,
+    /// Video track duration in seconds.
+    pub duration_secs: Option<f64>,
+    /// MediaInfo `FrameCount` of the video track.
+    pub frame_count: Option<u64>,
+    pub bit_depth: Option<u32>,
+    /// Container start offset of the video track in seconds (MediaInfo `Delay`).
+    pub delay_secs: Option<f64>,
+    /// mkvmerge `--default-duration` value for the video track, if detectable.
+    pub default_duration: Result<String, String>,
+    pub color: ColorInfo,
+    pub mastering: MasteringInfo,
+    /// Chapter entries mkvmerge reports; 0 for chapterless or non-Matroska files.
+    pub chapter_count: u64,
+    /// Every video track, in file order; MP4s can carry a DV track and a
+    /// cross-compatible HEVC one side by side.
+    pub video_tracks: Vec<MediaVideoTrack>,
+}
+
+/// One video track as MediaInfo lists it.
+#[derive(Debug, Clone)]
+pub struct MediaVideoTrack {
+    pub id: Option<u32>,
+    /// Sample entry / codec id, e.g. `dvh1`, `dvhe`, `hvc1`.
+    pub codec_id: Option<String>,
+    pub hdr_format: Option<String>,
+}
+
+impl MediaVideoTrack {
+    /// Stored as a Dolby Vision sample entry rather than plain HEVC.
+    pub fn is_dolby_vision_entry(&self) -> bool {
+        self.codec_id
+            .as_deref()
+            .map(|codec| {
+                let codec = codec.to_ascii_lowercase();
+                codec.starts_with("dvh1") || codec.starts_with("dvhe")
+            })
+            .unwrap_or(false)
+    }
+
+    /// MediaInfo found Dolby Vision configuration on the track.
+    pub fn has_dolby_vision(&self) -> bool {
+        self.hdr_format
+            .as_deref()
+            .map(|f| f.contains("Dolby Vision"))
+            .unwrap_or(false)
+    }
+}
+
+/// Color description of the video track as MediaInfo names it, e.g.
+/// "BT.2020", "PQ", "BT.2020 non-constant", "Limited".
+#[derive(Debug, Clone, Default)]
+pub struct ColorInfo {
+    pub primaries: Option<String>,
+    pub transfer: Option<String>,
+    pub matrix: Option<String>,
+    pub range: Option<String>,
+}
+
+/// MediaInfo color names and their ITU-T H.273 code points, which is what
+/// mkvmerge's `--colour-*` options take. Matched case-insensitively as
+/// prefixes, so longer names come first.
+const PRIMARIES_CODES: &[(&str, u32)] = &[
+    ("BT.709", 1),
+    ("BT.470 System M", 4),
+    ("BT.601 PAL", 5),
+    ("BT.601 NTSC", 6),
+    ("SMPTE 240M", 7),
+    ("Generic film", 8),
+    ("BT.2020", 9),
+    ("DCI P3", 11),
+    ("Display P3", 12),
+];
+
+const TRANSFER_CODES: &[(&str, u32)] = &[
+    ("BT.709", 1),
+    ("BT.470 System M", 4),
+    ("BT.470 System B/G", 5),
+    ("BT.601", 6),
+    ("SMPTE 240M", 7),
+    ("Linear", 8),
+    ("xvYCC", 11),
+    ("BT.2020 (10-bit)", 14),
+    ("BT.2020 (12-bit)", 15),
+    ("BT.2020", 14),
+    ("PQ", 16),
+    ("SMPTE ST 2084", 16),
+    ("HLG", 18),
+];
+
+const MATRIX_CODES: &[(&str, u32)] = &[
+    ("Identity", 0),
+    ("RGB", 0),
+    ("BT.709", 1),
+    ("FCC 73.682", 4),
+    ("BT.470 System B/G", 5),
+    ("BT.601", 6),
+    ("SMPTE 240M", 7),
+    ("YCgCo", 8),
+    ("BT.2020 non-constant", 9),
+    ("BT.2020 constant", 10),
+    ("ICtCp", 14),
+];
+
+fn color_code(table: &[(&str, u32)], name: &str) -> Option<u32> {
+    let name = name.trim().to_ascii_lowercase();
+    table
+        .iter()
+        .find(|(label, _)| name.starts_with(&label.to_ascii_lowercase()))
+        .map(|(_, code)| *code)
+}
+
+impl ColorInfo {
+    /// mkvmerge options describing this color for track 0 of the next input,
+    /// each with the MediaInfo value it came from. Values mkvmerge has no
+    /// code for are left out.
+    pub fn mkvmerge_args(&self) -> Vec<(&'static str, u32, String)> {
+        let mut args = Vec::new();
+        let mut push = |option: &'static str, value: &Option<String>, code: Option<u32>| {
+            if let (Some(value), Some(code)) = (value, code) {
+                args.push((option, code, value.clone()));
+            }
+        };
+        push(
+            "--colour-primaries",
+            &self.primaries,
+            self.primaries.as_deref().and_then(|v| color_code(PRIMARIES_CODES, v)),
+        );
+        push(
+            "--colour-transfer-characteristics",
+            &self.transfer,
+            self.transfer.as_deref().and_then(|v| color_code(TRANSFER_CODES, v)),
+        );
+        push(
+            "--colour-matrix-coefficients",
+            &self.matrix,
+            self.matrix.as_deref().and_then(|v| color_code(MATRIX_CODES, v)),
+        );
+        let range = self.range.as_deref().map(str::to_ascii_lowercase);
+        push(
+            "--colour-range",
+            &self.range,
+            match range.as_deref() {
+                Some("limited") => Some(1),
+                Some("full") => Some(2),
+                _ => None,
+            },
+        );
+        args
+    }
+}
+
+/// SMPTE ST 2086 mastering display and content light levels as MediaInfo
+/// reports them, e.g. "Display P3", "min: 0.0001 cd/m2, max: 1000 cd/m2",
+/// "1000 cd/m2".
+#[derive(Debug, Clone, Default)]
+pub struct MasteringInfo {
+    pub primaries: Option<String>,
+    pub luminance: Option<String>,
+    pub max_cll: Option<String>,
+    pub max_fall: Option<String>,
+}
+
+/// Named mastering display primaries as x265 `master-display` chromaticity
+/// (units of 0.00002): green, blue, red, white point.
+const MASTERING_PRIMARIES: &[(&str, &str)] = &[
+    ("Display P3", "G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)"),
+    ("DCI P3", "G(13250,34500)B(7500,3000)R(34000,16000)WP(15700,17550)"),
+    ("BT.2020", "G(8500,39850)B(6550,2300)R(35400,14600)WP(15635,16450)"),
+    ("BT.709", "G(15000,30000)B(7500,3000)R(32000,16500)WP(15635,16450)"),
+];
+
+/// The number that follows `label` in `text`, e.g. 1000 for "max:" in
+/// "min: 0.0001 cd/m2, max: 1000 cd/m2".
+fn number_after(text: &str, label: &str) -> Option<f64> {
+    let rest = text[text.find(label)? + label.len()..].trim_start();
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+impl MasteringInfo {
+    /// x265 `master-display` value, from a named gamut or the explicit
+    /// "R: x=… y=…, G: …, B: …, White point: …" form. `None` unless both the
+    /// primaries and the luminance range are known.
+    pub fn x265_master_display(&self) -> Option<String> {
+        let primaries = self.primaries.as_deref()?.trim();
+        let luminance = self.luminance.as_deref()?;
+        let max = number_after(luminance, "max:")?;
+        let min = number_after(luminance, "min:")?;
+        let chromaticity = match MASTERING_PRIMARIES
+            .iter()
+            .find(|(name, _)| primaries.eq_ignore_ascii_case(name))
+        {
+            Some((_, value)) => value.to_string(),
+            None => {
+                let point = |label: &str| {
+                    let rest = &primaries[primaries.find(label)? + label.len()..];
+                    let x = number_after(rest, "x=")?;
+                    let y = number_after(rest, "y=")?;
+                    Some(format!("({},{})", (x * 50000.0).round(), (y * 50000.0).round()))
+                };
+                format!(
+                    "G{}B{}R{}WP{}",
+                    point("G:")?,
+                    point("B:")?,
+                    point("R:")?,
+                    point("White point:")?
+                )
+            }
+        };
+        Some(format!(
+            "{}L({},{})",
+            chromaticity,
+            (max * 10000.0).round(),
+            (min * 10000.0).round()
+        ))
+    }
+
+    /// x265 `max-cll` value, "MaxCLL,MaxFALL" in cd/m². `None` when neither
+    /// is known.
+    pub fn x265_max_cll(&self) -> Option<String> {
+        let level = |value: &Option<String>| value.as_deref().and_then(|v| number_after(v, ""));
+        let (cll, fall) = (level(&self.max_cll), level(&self.max_fall));
+        if cll.is_none() && fall.is_none() {
+            return None;
+        }
+        Some(format!("{},{}", cll.unwrap_or(0.0).round(), fall.unwrap_or(0.0).round()))
+    }
+}
+
+fn parse_u32_from_value(value: &Value) -> Option<u32> {
+    if let Some(v) = value.as_u64() {
+        return u32::try_from(v).ok();
+    }
+    let raw = value.as_str()?;
+    let digits: String = raw.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() {
+        None
+    } else {
+        digits.parse().ok()
+    }
+}
+
+fn parse_fractional_string(raw: &str) -> Option<f64> {
+    let filtered: String = raw
+        .trim()
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '/')
+        .collect();
+    if filtered.is_empty() {
+        return None;
+    }
+    if let Some((num, den)) = filtered.split_once('/') {
+        let num: f64 = num.parse().ok()?;
+        let den: f64 = den.parse().ok()?;
+        if den == 0.0 {
+            return None;
+        }
+        return Some(num / den);
+    }
+    filtered.parse().ok()
+}
+
+fn parse_f64_from_value(value: &Value) -> Option<f64> {
+    if let Some(v) = value.as_f64() {
+        return Some(v);
+    }
+    if let Some(raw) = value.as_str() {
+        return parse_fractional_string(raw);
+    }
+    None
+}
+
+fn video_track_values(json: &Value) -> impl Iterator<Item = &Value> {
+    json.get("media")
+        .and_then(|media| media.get("track"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|track| {
+            track
+                .get("@type")
+                .and_then(Value::as_str)
+                .or_else(|| track.get("type").and_then(Value::as_str))
+                .map(|t| t.eq_ignore_ascii_case("video"))
+                .unwrap_or(false)
+        })
+}
+
+fn get_video_track(json: &Value) -> Option<&Value> {
+    video_track_values(json).next()
+}
+
+fn parse_video_track(track: &Value) -> MediaVideoTrack {
+    let text = |key: &str| {
+        track
+            .get(key)
+            .and_then(Value::as_str)
+            .or_else(|| track.get(format!("{}/String", key).as_str()).and_then(Value::as_str))
+            .map(str::to_string)
+    };
+    MediaVideoTrack {
+        id: track
+            .get("ID")
+            .and_then(parse_u32_from_value)
+            .or_else(|| track.get("ID/String").and_then(parse_u32_from_value)),
+        codec_id: text("CodecID"),
+        hdr_format: text("HDR_Format"),
+    }
+}
+
+/// Why a MediaInfo run did not produce a report.
+enum MediaInfoError {
+    /// The binary cannot be started at all; no file will ever succeed.
+    Fatal(String),
+    /// Empty, failed or truncated output that may succeed on a retry.
+    Transient(String),
+    /// The file was read but lacks what the pipeline needs.
+    Invalid(String),
+}
+
+impl MediaInfoError {
+    fn into_message(self) -> String {
+        match self {
+            MediaInfoError::Fatal(message)
+            | MediaInfoError::Transient(message)
+            | MediaInfoError::Invalid(message) => message,
+        }
+    }
+}
+
+fn mediainfo_command(tools: &ProbeTools, file_path: &Path) -> std::process::Command {
+    let mut command = tool_command(&tools.mediainfo, &tools.work_dir);
+    command.arg("--Output=JSON").arg("-f").arg(file_path);
+    command
+}
+
+fn run_mediainfo(tools: &ProbeTools, file_path: &Path) -> Result<MediaReport, MediaInfoError> {
+    let tool_path = tools.mediainfo.as_path();
+    let output = mediainfo_command(tools, file_path)
+        .output()
+        .map_err(|e| {
+            let message = format!("Failed to run MediaInfo ({}): {}", tool_path.display(), e);
+            match e.kind() {
+                ErrorKind::NotFound | ErrorKind::PermissionDenied => MediaInfoError::Fatal(message),
+                _ => MediaInfoError::Transient(message),
+            }
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(MediaInfoError::Transient(format!(
+            "MediaInfo failed (tool: {}): {}",
+            tool_path.display(),
+            stderr.trim()
+        )));
+    }
+
+    if output.stdout.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(MediaInfoError::Transient(format!(
+            "MediaInfo returned empty output (tool: {}): {}",
+            tool_path.display(),
+            stderr.trim()
+        )));
+    }
+
+    let json: Value = serde_json::from_slice(&output.stdout).map_err(|e| {
+        MediaInfoError::Transient(format!("Failed to parse MediaInfo JSON: {}", e))
+    })?;
+
+    parse_mediainfo_report(&json).map_err(MediaInfoError::Invalid)
+}
+
+pub(crate) fn parse_mediainfo_report(json: &Value) -> Result<MediaReport, String> {
+    let track = get_video_track(json).ok_or("No video track found in MediaInfo output")?;
+
+    let width = track
+        .get("Width")
+        .and_then(parse_u32_from_value)
+        .ok_or("MediaInfo width missing")?;
+    let height = track
+        .get("Height")
+        .and_then(parse_u32_from_value)
+        .ok_or("MediaInfo height missing")?;
+
+    let fps = track
+        .get("FrameRate_Original_Num")
+        .and_then(parse_f64_from_value)
+        .zip(track.get("FrameRate_Original_Den").and_then(parse_f64_from_value))
+        .map(|(num, den)| num / den)
+        .or_else(|| {
+            track
+                .get("FrameRate_Num")
+                .and_then(parse_f64_from_value)
+                .zip(track.get("FrameRate_Den").and_then(parse_f64_from_value))
+                .map(|(num, den)| num / den)
+        })
+        .or_else(|| {
+            track
+                .get("FrameRate_Original")
+                .and_then(parse_f64_from_value)
+        })
+        .or_else(|| track.get("FrameRate").and_then(parse_f64_from_value))
+        .ok_or("MediaInfo frame rate missing")?;
+    let rational = |num: &str, den: &str| {
+        let num = track.get(num).and_then(parse_f64_from_value)?;
+        let den = track.get(den).and_then(parse_f64_from_value)?;
+        (num > 0.0 && den > 0.0 && num.fract() == 0.0 && den.fract() == 0.0).then_some((num as u64, den as u64))
+    };
+    let fps_rational = rational("FrameRate_Original_Num", "FrameRate_Original_Den")
+        .or_else(|| rational("FrameRate_Num", "FrameRate_Den"));
+
+    let track_id = track
+        .get("ID")
+        .and_then(parse_u32_from_value)
+        .or_else(|| track.get("ID/String").and_then(parse_u32_from_value));
+
+    let language = track
+        .get("Language")
+        .and_then(Value::as_str)
+        .map(|s| s.to_string());
+
+    let format = track
+        .get("Format")
+        .and_then(Value::as_str)
+        .or_else(|| track.get("Format/String").and_then(Value::as_str))
+        .map(|s| s.to_string());
+
+    let duration_secs = track.get("Duration").and_then(parse_f64_from_value);
+    let frame_count = track
+        .get("FrameCount")
+        .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.trim().parse().ok())));
+    let bit_depth = track.get("BitDepth").and_then(parse_u32_from_value);
+    let delay_secs = track.get("Delay").and_then(parse_f64_from_value);
+    let text = |key: &str| track.get(key).and_then(Value::as_str).map(str::to_string);
+    let hdr_format = text("HDR_Format");
+    let hdr_format_profile = text("HDR_Format_Profile");
+    let hdr_format_level = text("HDR_Format_Level");
+    let hdr_format_settings = text("HDR_Format_Settings");
+    let color = ColorInfo {
+        primaries: text("colour_primaries"),
+        transfer: text("transfer_characteristics"),
+        matrix: text("matrix_coefficients"),
+        range: text("colour_range"),
+    };
+    let mastering = MasteringInfo {
+        primaries: text("MasteringDisplay_ColorPrimaries"),
+        luminance: text("MasteringDisplay_Luminance"),
+        max_cll: text("MaxCLL"),
+        max_fall: text("MaxFALL"),
+    };
+
+    Ok(MediaReport {
+        width,
+        height,
+        fps,
+        fps_rational,
+        track_id,
+        language,
+        format,
+        hdr_format,
+        hdr_format_profile,
+        hdr_format_level,
+        hdr_format_settings,
+        duration_secs,
+        frame_count,
+        bit_depth,
+        delay_secs,
+        default_duration: Err("Not probed".to_string()),
+        color,
+        mastering,
+        chapter_count: 0,
+        video_tracks: video_track_values(json).map(parse_video_track).collect(),
+    })
+}
+
+fn get_default_duration(json: &Value) -> Result<String, String> {
+    let tracks = json["tracks"]
+        .as_array()
+        .ok_or("No tracks found in JSON output")?;
+
+    for track in tracks {
+        if track["type"] == "video" {
+            let props = &track["properties"];
+            
+            // Try string format (e.g., "23.976fps")
+            if let Some(duration) = props["default_duration"].as_str() {
+                return Ok(normalize_duration_string(duration));
+            }
+            
+            // Try numeric format (nanoseconds)
+            if let Some(duration_ns) = props["default_duration"].as_u64() {
+                return Ok(format!("{}ns", duration_ns));
+            }
+
+            // Fallback logic could go here, but default_duration is the standard mkvmerge way.
+            // We could try to calc from frame_rate if present, but relying on default_duration is safest.
+        }
+    }
+
+    // Log the JSON tracks to help debug if we fail
+    // We can't emit log here easily without AppHandle passed in, 
+    // so we include the tracks in the error message for debugging.
+    Err(format!("No video track with default_duration found (checked string and u64). Tracks: {:?}", tracks))
+}
+
+/// Cached files are keyed on path plus size and modification time so an
+/// input replaced mid-session is probed again.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ProbeKey {
+    path: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl MediaReport {
+    pub fn has_dolby_vision(&self) -> bool {
+        self.hdr_format
+            .as_deref()
+            .map(|f| f.contains("Dolby Vision"))
+            .unwrap_or(false)
+    }
+
+    /// HDR10+ dynamic metadata, reported by MediaInfo as SMPTE ST 2094 App 4.
+    pub fn has_hdr10plus(&self) -> bool {
+        self.hdr_format
+            .as_deref()
+            .map(|f| f.contains("2094") || f.contains("HDR10+"))
+            .unwrap_or(false)
+    }
+
+    /// Frame rate for messages, with the exact fraction when MediaInfo gave one.
+    pub fn fps_label(&self) -> String {
+        match self.fps_rational {
+            Some((num, den)) if den != 1 => format!("{:.3} ({}/{})", self.fps, num, den),
+            _ => format!("{:.3}", self.fps),
+        }
+    }
+
+    /// Dolby Vision profile from MediaInfo's `HDR_Format_Profile`, which
+    /// reads `dvhe.PP` (or MP4 `dvh1.PP`), e.g. 7 for `dvhe.07 / `.
+    pub fn dolby_vision_profile(&self) -> Option<u32> {
+        let profile = self.hdr_format_profile.as_deref()?;
+        let start = profile.find("dvhe.").or_else(|| profile.find("dvh1."))? + "dvhe.".len();
+        profile.get(start..start + 2)?.parse().ok()
+    }
+
+    /// Dolby Vision profile, level and layers for messages, e.g.
+    /// "dvhe.07, level 06, BL+EL+RPU".
+    pub fn dolby_vision_label(&self) -> Option<String> {
+        let first = |value: &Option<String>| {
+            value
+                .as_deref()
+                .and_then(|v| v.split('/').next())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        let mut parts = vec![first(&self.hdr_format_profile)?];
+        if let Some(level) = first(&self.hdr_format_level) {
+            parts.push(format!("level {}", level));
+        }
+        parts.extend(first(&self.hdr_format_settings));
+        Some(parts.join(", "))
+    }
+
+    /// Carries a Dolby Vision enhancement layer (`BL+EL+RPU`).
+    pub fn has_enhancement_layer(&self) -> bool {
+        self.hdr_format_settings
+            .as_deref()
+            .map(|settings| settings.contains("EL+"))
+            .unwrap_or(false)
+    }
+}
+
+/// Whether two streams run at the same frame rate: equal fractions, the
+/// same NTSC rate however each tool rounded it, or within `tolerance`.
+pub fn frame_rates_match(a: &MediaReport, b: &MediaReport, tolerance: f64) -> bool {
+    if let (Some((a_num, a_den)), Some((b_num, b_den))) = (a.fps_rational, b.fps_rational) {
+        if a_num as u128 * b_den as u128 == b_num as u128 * a_den as u128 {
+            return true;
+        }
+    }
+    let snap = |fps: f64| {
+        NTSC_RATES
+            .iter()
+            .copied()
+            .find(|rate| (fps - rate).abs() <= NTSC_ROUNDING)
+            .unwrap_or(fps)
+    };
+    (snap(a.fps) - snap(b.fps)).abs() <= tolerance
+}
+
+impl ProbeKey {
+    fn for_file(file_path: &Path) -> ProbeKey {
+        let metadata = std::fs::metadata(file_path).ok();
+        ProbeKey {
+            path: std::fs::canonicalize(file_path).unwrap_or_else(|_| file_path.to_path_buf()),
+            size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+            modified: metadata.and_then(|m| m.modified().ok()),
+        }
+    }
+}
+
+struct ProbeSettings {
+    concurrency: usize,
+    retries: u32,
+    /// Set once MediaInfo turns out to be missing; every later probe fails fast.
+    fatal: Option<String>,
+    app: Option<AppHandle>,
+}
+
+impl Default for ProbeSettings {
+    fn default() -> Self {
+        ProbeSettings {
+            concurrency: PROBE_CONCURRENCY,
+            retries: PROBE_RETRIES,
+            fatal: None,
+            app: None,
+        }
+    }
+}
+
+/// Run-scoped probe service: every consumer asks it for a [`MediaReport`]
+/// and each file is only handed to MediaInfo/mkvmerge once per run.
+#[derive(Clone, Default)]
+pub struct MediaProbe {
+    cache: Arc<Mutex<HashMap<ProbeKey, MediaReport>>>,
+    settings: Arc<Mutex<ProbeSettings>>,
+    /// Number of MediaInfo processes currently running, across all workers.
+    running: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl MediaProbe {
+    pub fn clear(&self) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.clear();
+        }
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.fatal = None;
+        }
+    }
+
+    /// Set the per-run MediaInfo concurrency cap and retry count. Retries are
+    /// logged to `app` at debug level.
+    pub fn configure(&self, app: &AppHandle, concurrency: Option<usize>, retries: Option<u32>) {
+        if let Ok(mut settings) = self.settings.lock() {
+            settings.concurrency = concurrency.unwrap_or(PROBE_CONCURRENCY).max(1);
+            settings.retries = retries.unwrap_or(PROBE_RETRIES).min(MAX_PROBE_RETRIES);
+            settings.app = Some(app.clone());
+        }
+    }
+
+    /// Run MediaInfo under the global concurrency cap, retrying transient
+    /// failures with a growing backoff. A failure that survives its retries
+    /// says how many attempts were made.
+    fn mediainfo_report(&self, tools: &ProbeTools, file_path: &Path) -> Result<MediaReport, String> {
+        let (limit, retries, app) = {
+            let settings = self.settings.lock().map_err(|_| "Probe lock failed")?;
+            if let Some(fatal) = &settings.fatal {
+                return Err(fatal.clone());
+            }
+            (settings.concurrency, settings.retries, settings.app.clone())
+        };
+
+        let mut attempt = 0;
+        loop {
+            let result = {
+                let (count, available) = &*self.running;
+                let mut running = count.lock().map_err(|_| "Probe lock failed")?;
+                while *running >= limit {
+                    running = available.wait(running).map_err(|_| "Probe lock failed")?;
+                }
+                *running += 1;
+                drop(running);
+
+                let result = run_mediainfo(tools, file_path);
+
+                if let Ok(mut running) = count.lock() {
+                    *running -= 1;
+                }
+                available.notify_one();
+                result
+            };
+
+            match result {
+                Ok(report) => return Ok(report),
+                Err(MediaInfoError::Transient(message)) if attempt < retries => {
+                    attempt += 1;
+                    if let Some(app) = &app {
+                        emit_log(
+                            app,
+                            "debug",
+                            format!(
+                                "MediaInfo retry {}/{} for {}: {}",
+                                attempt,
+                                retries,
+                                file_path.display(),
+                                message
+                            ),
+                        );
+                    }
+                    thread::sleep(Duration::from_millis(500u64.saturating_mul(1 << attempt.min(MAX_BACKOFF_DOUBLINGS))));
+                }
+                Err(MediaInfoError::Fatal(message)) => {
+                    if let Ok(mut settings) = self.settings.lock() {
+                        settings.fatal.get_or_insert_with(|| message.clone());
+                    }
+                    return Err(message);
+                }
+                Err(MediaInfoError::Transient(message)) if attempt > 0 => {
+                    return Err(format!("{} (gave up after {} attempts)", message, attempt + 1));
+                }
+                Err(err) => return Err(err.into_message()),
+            }
+        }
+    }
+
+    pub fn probe(&self, tools: &ProbeTools, file_path: &Path) -> Result<MediaReport, String> {
+        let key = ProbeKey::for_file(file_path);
+        if let Some(report) = self.cache.lock().ok().and_then(|c| c.get(&key).cloned()) {
+            return Ok(report);
+        }
+
+        let mut report = self.mediainfo_report(tools, file_path)?;
+        let identification = identify_file(&tools.mkvmerge, file_path, &tools.work_dir);
+        report.default_duration = identification.as_ref().map_err(Clone::clone).and_then(get_default_duration);
+        report.chapter_count = identification
+            .as_ref()
+            .ok()
+            .and_then(|json| json["chapters"].as_array())
+            .map(|editions| editions.iter().filter_map(|edition| edition["num_entries"].as_u64()).sum())
+            .unwrap_or(0);
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key, report.clone());
+        }
+        Ok(report)
+    }
+
+    /// Probe several files concurrently, at most `limit` at a time, filling
+    /// the cache. Results are returned in input order.
+    pub fn probe_many(
+        &self,
+        tools: &ProbeTools,
+        files: &[PathBuf],
+        limit: usize,
+    ) -> Vec<Result<MediaReport, String>> {
+        let mut results = Vec::with_capacity(files.len());
+        for chunk in files.chunks(limit.max(1)) {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|file| {
+                    let probe = self.clone();
+                    let tools = tools.clone();
+                    let file = file.clone();
+                    thread::spawn(move || probe.probe(&tools, &file))
+                })
+                .collect();
+            for handle in handles {
+                results.push(
+                    handle
+                        .join()
+                        .unwrap_or_else(|_| Err("Probe thread panicked".to_string())),
+                );
+            }
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Report parsed from a MediaInfo `--Output=JSON -f` capture in
+    /// `tests/fixtures`.
+    pub(crate) fn fixture_report(name: &str) -> MediaReport {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+        let content = std::fs::read_to_string(&path).expect("fixture is readable");
+        let json: Value = serde_json::from_str(&content).expect("fixture is JSON");
+        parse_mediainfo_report(&json).expect("fixture has a video track")
+    }
+
+    #[test]
+    fn mediainfo_runs_in_the_work_dir() {
+        let tools = ProbeTools::new(Path::new("mediainfo"), Path::new("mkvmerge"), Path::new("/jobs/movie"));
+        let command = mediainfo_command(&tools, Path::new("/media/Movie.mkv"));
+        assert_eq!(command.get_current_dir(), Some(Path::new("/jobs/movie")));
+    }
+
+    #[test]
+    fn reads_profile_8_from_hdr_format_profile() {
+        let report = fixture_report("mediainfo_dv_p8.json");
+        assert!(report.has_dolby_vision());
+        assert_eq!(report.dolby_vision_profile(), Some(8));
+        assert!(!report.has_enhancement_layer());
+        assert_eq!(report.dolby_vision_label().as_deref(), Some("dvhe.08, level 06, BL+RPU"));
+    }
+
+    #[test]
+    fn reads_profile_7_and_its_enhancement_layer() {
+        let report = fixture_report("mediainfo_dv_p7.json");
+        assert_eq!(report.dolby_vision_profile(), Some(7));
+        assert!(report.has_enhancement_layer());
+        assert_eq!(report.dolby_vision_label().as_deref(), Some("dvhe.07, level 06, BL+EL+RPU"));
+    }
+
+    #[test]
+    fn no_profile_without_dolby_vision() {
+        let json: Value = serde_json::json!({
+            "media": {"track": [
+                {"@type": "Video", "Width": "3840", "Height": "2160", "FrameRate": "24.000",
+                 "HDR_Format": "SMPTE ST 2086", "HDR_Format_Compatibility": "HDR10"}
+            ]}
+        });
+        let report = parse_mediainfo_report(&json).unwrap();
+        assert!(!report.has_dolby_vision());
+        assert_eq!(report.dolby_vision_profile(), None);
+        assert_eq!(report.dolby_vision_label(), None);
+    }
+
+    #[test]
+    fn x265_mastering_values_from_named_primaries() {
+        let report = fixture_report("mediainfo_dv_p7.json");
+        assert_eq!(
+            report.mastering.x265_master_display().as_deref(),
+            Some("G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)L(40000000,50)")
+        );
+        assert_eq!(report.mastering.x265_max_cll().as_deref(), Some("3241,532"));
+    }
+
+    #[test]
+    fn x265_mastering_values_from_explicit_primaries() {
+        let mastering = MasteringInfo {
+            primaries: Some(
+                "R: x=0.708000 y=0.292000, G: x=0.170000 y=0.797000, B: x=0.131000 y=0.046000, White point: x=0.312700 y=0.329000"
+                    .to_string(),
+            ),
+            luminance: Some("min: 0.0050 cd/m2, max: 1000 cd/m2".to_string()),
+            max_cll: None,
+            max_fall: Some("400 cd/m2".to_string()),
+        };
+        assert_eq!(
+            mastering.x265_master_display().as_deref(),
+            Some("G(8500,39850)B(6550,2300)R(35400,14600)WP(15635,16450)L(10000000,50)")
+        );
+        assert_eq!(mastering.x265_max_cll().as_deref(), Some("0,400"));
+        assert_eq!(MasteringInfo::default().x265_master_display(), None);
+        assert_eq!(MasteringInfo::default().x265_max_cll(), None);
+    }
+
+    fn at_rate(fps: f64, fps_rational: Option<(u64, u64)>) -> MediaReport {
+        MediaReport {
+            fps,
+            fps_rational,
+            ..fixture_report("mediainfo_dv_p8.json")
+        }
+    }
+
+    #[test]
+    fn exact_ntsc_rate_matches_its_rounding() {
+        let exact = at_rate(24000.0 / 1001.0, Some((24000, 1001)));
+        let rounded = at_rate(23.976, None);
+        assert!(frame_rates_match(&exact, &rounded, DEFAULT_FPS_TOLERANCE));
+    }
+
+    #[test]
+    fn differently_rounded_ntsc_rates_match() {
+        let mediainfo = at_rate(23.976, None);
+        let mkvmerge = at_rate(23.976024, None);
+        assert!(frame_rates_match(&mediainfo, &mkvmerge, DEFAULT_FPS_TOLERANCE));
+    }
+
+    #[test]
+    fn film_and_ntsc_match_only_within_a_wider_tolerance() {
+        let film = at_rate(24.0, Some((24, 1)));
+        let ntsc = at_rate(23.976, None);
+        assert!(!frame_rates_match(&film, &ntsc, DEFAULT_FPS_TOLERANCE));
+        assert!(frame_rates_match(&film, &ntsc, 0.05));
+    }
+
+    #[test]
+    fn pal_does_not_match_ntsc() {
+        let pal = at_rate(25.0, Some((25, 1)));
+        let ntsc = at_rate(23.976, None);
+        assert!(!frame_rates_match(&pal, &ntsc, DEFAULT_FPS_TOLERANCE));
+        assert!(!frame_rates_match(&pal, &ntsc, 0.05));
+    }
+}
@@ -1,13 +1,17 @@
 use std::fs;
 use std::ffi::OsStr;
+use std::collections::VecDeque;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use tauri::AppHandle;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
 use regex::Regex;
-use serde_json::{json, Value};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
@@ -15,7 +19,7 @@ use std::os::windows::process::CommandExt;
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-fn hide_console_window(command: &mut Command) {
+pub(crate) fn hide_console_window(command: &mut Command) {
     #[cfg(target_os = "windows")]
     {
         command.creation_flags(CREATE_NO_WINDOW);
@@ -23,13 +27,29 @@ fn hide_console_window(command: &mut Command) {
 }
 
 use crate::models::{
-    ProcessingState, ToolPaths, QueueItem, QueueContext, QueuePayload, FilePayload
+    ProcessingState, ToolPaths, QueueItem, QueueContext, QueuePayload, FilePayload,
+    PipelineOptions, AudioTranscode, ActiveAreaMode, RemuxStrategy, RpuSource, SubtitleFilter,
+    FelPolicy, TrackDefaults, StopReason, OutputStructure, ResumeStep, StepRef, FileCounts, FileSlot, FileState, FileTracker,
+    ScanReport, ScanPair, ScanSkipReason, BatchOrdering, Hdr10PlusSource, SplitSpec, FrameCountPolicy, ChapterMode
 };
 use crate::utils::{
-    emit_log, emit_step, emit_queue, emit_file, resolve_path,
-    compute_output_for_single, compute_output_for_batch, normalize_output_path,
-    find_matching_dv_file, get_video_metadata
+    emit_log, emit_step, emit_queue, emit_file, emit_overall, input_bytes, report_job_progress, resolve_path,
+    compute_output_for_single, compute_output_for_batch, normalize_output_path, matroska_output_path,
+    find_matching_dv_file, scan_folder_pairs, warn_output_in_inputs, FolderPair, FolderScan, get_audio_tracks, get_subtitle_tracks, get_video_tracks, identify_file, absolute_path,
+    shell_command_line, shell_quote, ensure_writable, emit_event, hdr_base_name
 };
+use crate::checksum::{write_sidecars, OutputSidecar, HASH_STEP_ID, HASH_STEP_NAME};
+use crate::artifacts::report_kept_artifacts;
+use crate::cleanup::remove_temp_files;
+use crate::estimate::record_throughput;
+use crate::journal::StepJournal;
+use crate::chapters::{drop_editions, read_chapters_file};
+use crate::mirror::{copy_to_destinations, COPY_STEP_ID, COPY_STEP_NAME};
+use crate::schedule::wait_for_window;
+use crate::staging::{stage_inputs, STAGE_STEP_ID, STAGE_STEP_NAME};
+use crate::title_memo::{apply_title_memo, find_title_memo, record_title_memo, title_key, CropMemo};
+use crate::probe::{frame_rates_match, MediaReport, MediaVideoTrack, ProbeTools, PROBE_CONCURRENCY};
+use crate::volume::{FAT_MAX_FILE_BYTES, FAT_SPLIT_SIZE};
 
 const STEP_NAMES: [&str; 6] = [
     "Extract Audio & Subtitles",
@@ -40,159 +60,47 @@ const STEP_NAMES: [&str; 6] = [
     "Mux Final Output",
 ];
 
-#[derive(Clone)]
-struct VideoInfo {
-    width: u32,
-    height: u32,
-    fps: f64,
-    track_id: Option<u32>,
-    language: Option<String>,
-    format: Option<String>,
-}
-
-fn parse_u32_from_value(value: &Value) -> Option<u32> {
-    if let Some(v) = value.as_u64() {
-        return u32::try_from(v).ok();
-    }
-    let raw = value.as_str()?;
-    let digits: String = raw.chars().filter(|c| c.is_ascii_digit()).collect();
-    if digits.is_empty() {
-        None
-    } else {
-        digits.parse().ok()
-    }
-}
+/// RPU editing only runs when crop or delay edits are needed, so it sits
+/// outside `STEP_NAMES` and shares the RPU extraction's progress slice.
+const RPU_EDIT_STEP_ID: usize = 7;
+const RPU_EDIT_STEP_NAME: &str = "Edit RPU Metadata";
 
-fn parse_fractional_string(raw: &str) -> Option<f64> {
-    let filtered: String = raw
-        .trim()
-        .chars()
-        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '/')
-        .collect();
-    if filtered.is_empty() {
-        return None;
-    }
-    if let Some((num, den)) = filtered.split_once('/') {
-        let num: f64 = num.parse().ok()?;
-        let den: f64 = den.parse().ok()?;
-        if den == 0.0 {
-            return None;
-        }
-        return Some(num / den);
-    }
-    filtered.parse().ok()
-}
+/// HDR10+ sub-steps between the HDR10 extract and the RPU inject. They only
+/// run with an HDR10+ donor and share the HDR10 extraction's progress slice;
+/// the edit step covers both the delay edit and the length adjustment.
+const HDR10PLUS_DEMUX_STEP: (usize, &str) = (10, "Demux HDR10+ Donor");
+const HDR10PLUS_EXTRACT_STEP: (usize, &str) = (11, "Extract HDR10+ Metadata");
+const HDR10PLUS_EDIT_STEP: (usize, &str) = (12, "Edit HDR10+ Metadata");
+const HDR10PLUS_INJECT_STEP: (usize, &str) = (13, "Inject HDR10+ Metadata");
+/// Re-encode of an HDR10 base shorter than the DV stream; only runs when
+/// the two heights differ.
+const PAD_STEP: (usize, &str) = (15, "Pad HDR10 Video");
+/// Re-encode of the extracted audio, run after the extraction (or on the
+/// audio lane) when audio transcoding is set.
+const TRANSCODE_STEP: (usize, &str) = (16, "Transcode Audio");
+/// Journal entry recorded once the muxed output passed its checks; after it
+/// only the hash and the extra copies are left.
+const VERIFIED_ENTRY: (usize, &str) = (6, "Verify Output");
 
-fn parse_f64_from_value(value: &Value) -> Option<f64> {
-    if let Some(v) = value.as_f64() {
-        return Some(v);
+/// x265 settings for the padded HDR10 base: PQ/BT.2020 signalling plus the
+/// source's mastering display and light levels when MediaInfo reports them.
+fn pad_x265_params(hdr_info: &MediaReport) -> String {
+    let mut params = "hdr10=1:repeat-headers=1:colorprim=bt2020:transfer=smpte2084:colormatrix=bt2020nc".to_string();
+    if let Some(master_display) = hdr_info.mastering.x265_master_display() {
+        params.push_str(&format!(":master-display={}", master_display));
     }
-    if let Some(raw) = value.as_str() {
-        return parse_fractional_string(raw);
+    if let Some(max_cll) = hdr_info.mastering.x265_max_cll() {
+        params.push_str(&format!(":max-cll={}", max_cll));
     }
-    None
-}
-
-fn get_video_track(json: &Value) -> Option<&Value> {
-    json.get("media")?
-        .get("track")?
-        .as_array()?
-        .iter()
-        .find(|track| {
-            track
-                .get("@type")
-                .and_then(Value::as_str)
-                .or_else(|| track.get("type").and_then(Value::as_str))
-                .map(|t| t.eq_ignore_ascii_case("video"))
-                .unwrap_or(false)
-        })
+    params
 }
 
-fn get_mediainfo(tool_path: &Path, file_path: &Path) -> Result<VideoInfo, String> {
-    let output = Command::new(tool_path)
-        .arg("--Output=JSON")
-        .arg("-f")
-        .arg(file_path)
-        .output()
-        .map_err(|e| format!("Failed to run MediaInfo: {}", e))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!(
-            "MediaInfo failed (tool: {}): {}",
-            tool_path.display(),
-            stderr.trim()
-        ));
-    }
-
-    if output.stdout.is_empty() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!(
-            "MediaInfo returned empty output (tool: {}): {}",
-            tool_path.display(),
-            stderr.trim()
-        ));
-    }
-
-    let json: Value = serde_json::from_slice(&output.stdout)
-        .map_err(|e| format!("Failed to parse MediaInfo JSON: {}", e))?;
-
-    let track = get_video_track(&json).ok_or("No video track found in MediaInfo output")?;
-
-    let width = track
-        .get("Width")
-        .and_then(parse_u32_from_value)
-        .ok_or("MediaInfo width missing")?;
-    let height = track
-        .get("Height")
-        .and_then(parse_u32_from_value)
-        .ok_or("MediaInfo height missing")?;
-
-    let fps = track
-        .get("FrameRate_Original_Num")
-        .and_then(parse_f64_from_value)
-        .zip(track.get("FrameRate_Original_Den").and_then(parse_f64_from_value))
-        .map(|(num, den)| num / den)
-        .or_else(|| {
-            track
-                .get("FrameRate_Num")
-                .and_then(parse_f64_from_value)
-                .zip(track.get("FrameRate_Den").and_then(parse_f64_from_value))
-                .map(|(num, den)| num / den)
-        })
-        .or_else(|| {
-            track
-                .get("FrameRate_Original")
-                .and_then(parse_f64_from_value)
-        })
-        .or_else(|| track.get("FrameRate").and_then(parse_f64_from_value))
-        .ok_or("MediaInfo frame rate missing")?;
-
-    let track_id = track
-        .get("ID")
-        .and_then(parse_u32_from_value)
-        .or_else(|| track.get("ID/String").and_then(parse_u32_from_value));
-
-    let language = track
-        .get("Language")
-        .and_then(Value::as_str)
-        .map(|s| s.to_string());
-
-    let format = track
-        .get("Format")
-        .and_then(Value::as_str)
-        .or_else(|| track.get("Format/String").and_then(Value::as_str))
-        .map(|s| s.to_string());
-
-    Ok(VideoInfo {
-        width,
-        height,
-        fps,
-        track_id,
-        language,
-        format,
-    })
-}
+const HDR10PLUS_STEPS: [(usize, &str); 4] = [
+    HDR10PLUS_DEMUX_STEP,
+    HDR10PLUS_EXTRACT_STEP,
+    HDR10PLUS_EDIT_STEP,
+    HDR10PLUS_INJECT_STEP,
+];
 
 fn is_mp4_container(path: &Path) -> bool {
     path.extension()
@@ -208,328 +116,3703 @@ fn is_hevc_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
-fn is_hevc_format(info: &VideoInfo) -> bool {
+fn is_hevc_format(info: &MediaReport) -> bool {
     info.format
         .as_ref()
         .map(|fmt| fmt.to_ascii_lowercase().contains("hevc") || fmt.to_ascii_lowercase().contains("h.265"))
         .unwrap_or(false)
 }
 
-fn delay_to_frames(delay_ms: f64, fps: f64) -> u32 {
-    ((delay_ms.abs() * fps) / 1000.0).round() as u32
+pub(crate) fn is_json_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
 }
 
-fn build_demux_command(
-    mkvextract: &Path,
-    mp4box: &Path,
-    input: &Path,
-    output: &Path,
-    track_id: Option<u32>,
-) -> Result<Command, String> {
-    if is_mp4_container(input) {
-        let id = track_id.ok_or("Missing track ID for MP4Box demux")?;
-        let mut cmd = Command::new(mp4box);
-        cmd.arg("-raw")
-            .arg(id.to_string())
-            .arg("-out")
-            .arg(output)
-            .arg(input);
-        return Ok(cmd);
-    }
+/// Smallest input worth handing to the tools; anything below is a
+/// placeholder or an aborted download, not a video.
+const MIN_INPUT_BYTES: u64 = 64 * 1024;
 
-    let mut cmd = Command::new(mkvextract);
-    cmd.arg(input).arg("tracks").arg(format!("0:{}", output.to_string_lossy()));
-    Ok(cmd)
+/// Suffixes browsers, torrent clients and sync tools give unfinished files.
+const PARTIAL_SUFFIXES: &[&str] = &[".part", ".crdownload", ".download", ".partial", ".!qb", ".tmp"];
+
+/// Whether `header` starts like a container or stream the pipeline reads:
+/// Matroska, ISO BMFF (MP4/MOV), MPEG-TS/M2TS, Annex B HEVC or JSON.
+fn known_input_header(header: &[u8]) -> bool {
+    let matroska = header.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]);
+    let iso_bmff = header.len() >= 8 && matches!(&header[4..8], b"ftyp" | b"moov" | b"mdat" | b"free" | b"wide");
+    let transport = header.first() == Some(&0x47) || header.get(4) == Some(&0x47);
+    let annex_b = header.starts_with(&[0, 0, 1]) || header.starts_with(&[0, 0, 0, 1]);
+    let json = header.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+    matroska || iso_bmff || transport || annex_b || json
 }
 
-fn noop_command() -> Command {
-    if cfg!(target_os = "windows") {
-        let mut cmd = Command::new("cmd");
-        cmd.args(["/C", "exit", "0"]);
-        cmd
-    } else {
-        Command::new("true")
+/// Reject inputs that are obviously incomplete (still downloading, a cloud
+/// placeholder, zero bytes) before MediaInfo fails on them with something
+/// cryptic. An unrecognised header is only a warning.
+fn check_input_file(app: &AppHandle, path: &Path, role: &str) -> Result<(), String> {
+    let metadata = fs::metadata(path)
+        .map_err(|e| validation_error(format!("{} {} is not readable: {}", role, path.display(), e)))?;
+    if !metadata.is_file() {
+        return Err(validation_error(format!("{} {} is not a file", role, path.display())));
+    }
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    if let Some(suffix) = PARTIAL_SUFFIXES.iter().find(|suffix| name.ends_with(*suffix)) {
+        return Err(validation_error(format!(
+            "{} {} looks like an unfinished download ({}); wait for it to complete",
+            role,
+            path.display(),
+            suffix
+        )));
+    }
+    let min_bytes = if is_json_file(path) { 2 } else { MIN_INPUT_BYTES };
+    if metadata.len() < min_bytes {
+        return Err(validation_error(format!(
+            "{} {} is only {} bytes; it looks incomplete (still downloading or syncing?)",
+            role,
+            path.display(),
+            metadata.len()
+        )));
     }
-}
 
-fn run_command(
-    state: &ProcessingState,
-    mut command: Command,
-    app: &AppHandle,
-    step_id: usize,
-    step_name: &str,
-    input_path: &Path,
-    output_path: &Path,
-    emit_progress: bool,
-    step_index: usize,
-    total_steps: usize,
-    queue_ctx: Option<&QueueContext>,
-) -> Result<(), String> {
-    if *state.cancel_flag.lock().map_err(|_| "State lock failed")? {
-        return Err("Processing cancelled".to_string());
+    let mut header = [0u8; 16];
+    let read = fs::File::open(path)
+        .and_then(|mut file| file.read(&mut header))
+        .map_err(|e| validation_error(format!("{} {} is not readable: {}", role, path.display(), e)))?;
+    let header = &header[..read];
+    if header.iter().all(|b| *b == 0) && !is_hevc_file(path) {
+        return Err(validation_error(format!(
+            "{} {} starts with zeros; it is probably a cloud placeholder or a partly written file",
+            role,
+            path.display()
+        )));
+    }
+    if !known_input_header(header) {
+        emit_log(
+            app,
+            "warning",
+            format!("{} {} has an unrecognised header; continuing anyway", role, path.display()),
+        );
     }
+    Ok(())
+}
 
-    emit_step(app, step_id, step_name, "active", 0);
-    emit_log(app, "info", format!("Step {}: {}", step_id, step_name));
+/// Largest fps difference between an HDR10+ donor and the base stream that
+/// still counts as the same rate.
+const HDR10PLUS_FPS_TOLERANCE: f64 = 0.01;
+/// Length mismatch, in seconds of frames, that is trimmed or padded at the
+/// end of the HDR10+ metadata instead of failing the file.
+const HDR10PLUS_TRAILING_TOLERANCE_SECS: f64 = 2.0;
 
-    let emit_queue_progress = |progress: u8| {
-        if let Some(ctx) = queue_ctx {
-            let file_progress = ((step_index as f64 + progress as f64 / 100.0)
-                / total_steps as f64)
-                * 100.0;
-
-            let overall_progress = if let Some(tracker) = &ctx.tracker {
-                if let Ok(mut guard) = tracker.lock() {
-                    if ctx.file_index < guard.len() {
-                        guard[ctx.file_index] = file_progress.round() as u8;
-                    }
-                    let sum: u32 = guard.iter().map(|v| *v as u32).sum();
-                    (sum as f64 / ctx.file_total as f64).round() as u8
-                } else {
-                    file_progress.round() as u8
-                }
-            } else {
-                file_progress.round() as u8
-            };
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Hdr10PlusSummary {
+    scene_frame_numbers: Vec<u64>,
+}
 
-            let step_label = match &ctx.label {
-                Some(label) => format!("{} - {}", label, step_name),
-                None => step_name.to_string(),
-            };
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Hdr10PlusMetadata {
+    scene_info_summary: Option<Hdr10PlusSummary>,
+    scene_info: Option<Vec<serde::de::IgnoredAny>>,
+}
 
-            emit_queue(
-                app,
-                QueuePayload {
-                    id: ctx.id.clone(),
-                    status: "processing".to_string(),
-                    progress: overall_progress,
-                    current_step: Some(step_label),
-                    active_workers: ctx
-                        .active_workers
-                        .as_ref()
-                        .and_then(|workers| workers.lock().ok().map(|v| *v)),
-                    file_total: Some(ctx.file_total),
-                },
-            );
+/// Number of frames an `hdr10plus_tool` JSON export covers.
+pub(crate) fn hdr10plus_frame_count(path: &Path) -> Result<u64, String> {
+    let file = fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let metadata: Hdr10PlusMetadata = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| format!("Unreadable HDR10+ metadata {}: {}", path.display(), e))?;
+    if let Some(summary) = metadata.scene_info_summary {
+        return Ok(summary.scene_frame_numbers.iter().sum());
+    }
+    metadata
+        .scene_info
+        .map(|frames| frames.len() as u64)
+        .ok_or_else(|| format!("{} has no scene information", path.display()))
+}
 
-            if let (Some(file_id), Some(file_name)) = (&ctx.file_id, &ctx.file_name) {
-                emit_file(
-                    app,
-                    FilePayload {
-                        id: file_id.clone(),
-                        queue_id: ctx.id.clone(),
-                        name: file_name.clone(),
-                        progress: file_progress.round() as u8,
-                    },
-                );
-            }
+/// Whether the HDR10+ donor actually carries dynamic metadata. Without it
+/// `hdr10plus_tool extract` writes an empty file and the inject fails, so an
+/// absent donor is logged and the run carries on as DV-only.
+fn hdr10plus_present(
+    app: &AppHandle,
+    state: &ProcessingState,
+    tools: &ProbeTools,
+    source: &Path,
+) -> Result<bool, String> {
+    let absent = if is_json_file(source) {
+        match hdr10plus_frame_count(source) {
+            Ok(0) => Some("it lists no frames".to_string()),
+            Ok(_) => None,
+            Err(err) => Some(err),
         }
+    } else {
+        let report = state.probe.probe(tools, source)?;
+        (!report.has_hdr10plus()).then(|| {
+            format!(
+                "MediaInfo reports no SMPTE ST 2094-40 metadata (HDR format: {})",
+                report.hdr_format.as_deref().unwrap_or("none")
+            )
+        })
     };
+    if let Some(reason) = &absent {
+        emit_log(
+            app,
+            "warning",
+            format!(
+                "Skipping HDR10+ injection from {}: {}; continuing with Dolby Vision only",
+                source.display(),
+                reason
+            ),
+        );
+    }
+    Ok(absent.is_none())
+}
 
-    hide_console_window(&mut command);
-    let mut child = command
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .map_err(|e| e.to_string())?;
-
-    let input_size = fs::metadata(input_path).map(|m| m.len()).unwrap_or(1);
-
-    let result = loop {
-        if *state.cancel_flag.lock().map_err(|_| "State lock failed")? {
-            let _ = child.kill();
-            return Err("Processing cancelled".to_string());
-        }
-
-        if emit_progress {
-            if let Ok(metadata) = fs::metadata(output_path) {
-                let percent = ((metadata.len() as f64 / input_size as f64) * 100.0)
-                    .min(95.0)
-                    .max(0.0) as u8;
-                emit_step(app, step_id, step_name, "active", percent);
-                emit_queue_progress(percent);
+/// Decide where the HDR10+ metadata comes from when the base stream may
+/// carry its own. Returns the donor to inject, or `None` to leave the base
+/// as it is (its own HDR10+ passes through the RPU injection untouched).
+fn select_hdr10plus_source<'a>(
+    app: &AppHandle,
+    state: &ProcessingState,
+    tools: &ProbeTools,
+    choice: Hdr10PlusSource,
+    base_info: &MediaReport,
+    donor: Option<&'a Path>,
+) -> Result<Option<&'a Path>, String> {
+    if !base_info.has_hdr10plus() {
+        if choice == Hdr10PlusSource::Base {
+            if let Some(donor) = donor {
+                emit_log(
+                    app,
+                    "warning",
+                    format!("HDR10+ source is set to the base, which has none; using the donor {}", donor.display()),
+                );
             }
         }
+        return Ok(donor);
+    }
+    let Some(donor) = donor else {
+        emit_log(app, "info", "HDR10+: keeping the base stream's own metadata");
+        return Ok(None);
+    };
 
-        match child.try_wait() {
-            Ok(Some(status)) => {
-                if status.success() {
-                    emit_step(app, step_id, step_name, "completed", 100);
-                    emit_queue_progress(100);
-                    emit_log(app, "success", format!("Step completed: {}", step_name));
-                    break Ok(());
-                } else {
-                    emit_step(app, step_id, step_name, "error", 0);
-                    emit_queue_progress(0);
-                    emit_log(app, "error", format!("Step failed: {}", step_name));
-                    break Err(format!("Step failed: {}", step_name));
-                }
-            }
-            Ok(None) => {
-                thread::sleep(Duration::from_millis(500));
-            }
-            Err(err) => {
-                emit_step(app, step_id, step_name, "error", 0);
-                break Err(err.to_string());
-            }
+    let donor_frames = if is_json_file(donor) {
+        hdr10plus_frame_count(donor).ok()
+    } else {
+        state.probe.probe(tools, donor)?.frame_count
+    };
+    let matches_base = match (donor_frames, base_info.frame_count) {
+        (Some(donor_frames), Some(base_frames)) if donor_frames != base_frames => {
+            emit_log(
+                app,
+                "warning",
+                format!(
+                    "Both the base and the donor carry HDR10+, and they disagree: donor {} frames, base {} frames",
+                    donor_frames, base_frames
+                ),
+            );
+            false
         }
+        _ => true,
     };
-
-    result
+    let (selected, reason) = match choice {
+        Hdr10PlusSource::Donor => (Some(donor), "the donor was chosen explicitly"),
+        Hdr10PlusSource::Base => (None, "the base was chosen explicitly"),
+        Hdr10PlusSource::Auto if matches_base => (Some(donor), "the donor matches the base frame count"),
+        Hdr10PlusSource::Auto => (None, "the donor's frame count does not match the base video"),
+    };
+    emit_log(
+        app,
+        "info",
+        format!(
+            "HDR10+: base and donor {} both carry metadata; using the {} because {}",
+            donor.display(),
+            if selected.is_some() { "donor" } else { "base" },
+            reason
+        ),
+    );
+    Ok(selected)
 }
 
-/// Execute the processing pipeline for a single file pair.
-///
-/// This function coordinates the extraction, processing, and merging steps:
-/// 1. Extract audio/subs
-/// 2. Extract DV video and RPU
-/// 3. Extract HDR10 video
-/// 4. Inject RPU into HDR10
-/// 5. Mux final output
-pub fn run_pipeline(
+/// Check a prepared HDR10 base layer stands in for the HDR input's video:
+/// an HEVC stream at the same frame rate and with the same number of frames,
+/// so the RPU lines up with it the way it would with the extracted stream.
+fn check_base_layer(
     app: &AppHandle,
     state: &ProcessingState,
-    tool_paths: &ToolPaths,
-    input_hdr: &Path,
-    input_dv: &Path,
-    hdr10plus_path: Option<&Path>,
-    output_path: &Path,
-    dv_delay_ms: f64,
-    hdr10plus_delay_ms: f64,
-    keep_temp: bool,
-    queue_id: Option<&str>,
-    queue_label: Option<&str>,
-    queue_file_name: Option<&str>,
-    queue_file_index: usize,
-    queue_file_total: usize,
-    queue_tracker: Option<Arc<Mutex<Vec<u8>>>>,
-    queue_active_workers: Option<Arc<Mutex<usize>>>,
+    tools: &ProbeTools,
+    base: &Path,
+    hdr_info: &MediaReport,
+    fps_tolerance: f64,
 ) -> Result<(), String> {
-    let dovi_tool = resolve_path(app, &tool_paths.dovi_tool);
-    let mkvmerge = resolve_path(app, &tool_paths.mkvmerge);
-    let mkvextract = resolve_path(app, &tool_paths.mkvextract);
-    let mediainfo = resolve_path(app, &tool_paths.mediainfo);
-    let mp4box = resolve_path(app, &tool_paths.mp4box);
-    let hdr10plus_tool = resolve_path(app, &tool_paths.hdr10plus_tool);
-
-    let output_base = output_path.to_string_lossy().to_string();
-    let audio_loc = PathBuf::from(format!("{}_audiosubs.mka", output_base));
-    let dv_hevc = PathBuf::from(format!("{}_dv.hevc", output_base));
-    let hdr10_hevc = PathBuf::from(format!("{}_hdr10.hevc", output_base));
-    let dv_hdr = PathBuf::from(format!("{}_dv_hdr.hevc", output_base));
-    let rpu_bin = PathBuf::from(format!("{}_rpu.bin", output_base));
-    let mut temp_files = vec![
-        audio_loc.clone(),
-        dv_hevc.clone(),
-        hdr10_hevc.clone(),
-        dv_hdr.clone(),
-        rpu_bin.clone(),
-    ];
-
-    if let Some(parent) = output_path.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    let base_info = state.probe.probe(tools, base)?;
+    if !is_hevc_file(base) || !is_hevc_format(&base_info) {
+        return Err(validation_error(format!(
+            "HDR10 base layer {} is not a raw HEVC stream",
+            base.display()
+        )));
+    }
+    if !frame_rates_match(&base_info, hdr_info, fps_tolerance) {
+        return Err(validation_error(format!(
+            "Frame rate mismatch - base layer: {} | HDR: {}",
+            base_info.fps_label(),
+            hdr_info.fps_label()
+        )));
+    }
+    match (base_info.frame_count, hdr_info.frame_count) {
+        (Some(base_frames), Some(hdr_frames)) if base_frames != hdr_frames => {
+            return Err(validation_error(format!(
+                "Frame count mismatch - base layer: {} | HDR: {}",
+                base_frames, hdr_frames
+            )));
         }
+        (Some(_), Some(_)) => {}
+        _ => emit_log(
+            app,
+            "warning",
+            "Could not read the frame count of the base layer or the HDR input; not comparing them",
+        ),
     }
+    emit_log(
+        app,
+        "info",
+        format!("Injecting the RPU into the prepared base layer {}", base.display()),
+    );
+    Ok(())
+}
 
-    // Detect Source Headers / FPS
-    let detected_duration = match get_video_metadata(&mkvmerge, input_hdr) {
-        Ok(d) => {
-            emit_log(app, "info", format!("Detected video duration/fps: {}", d));
-            Some(d)
-        },
-        Err(e) => {
-            emit_log(app, "warning", format!("Could not detect video FPS: {}. Defaulting to mkvmerge behavior.", e));
-            None
-        }
-    };
+/// How the HDR10 frame is fitted to the DV frame, as the lines of bars at
+/// the top and bottom.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum FrameFit {
+    Same,
+    /// The HDR10 base is padded to the taller DV frame.
+    Pad { top: u32, bottom: u32 },
+    /// The DV RPU crops the bars off the taller HDR10 frame.
+    Crop { top: u32, bottom: u32 },
+}
 
-    emit_log(app, "info", format!("Processing: {}", output_path.display()));
+pub(crate) fn frame_fit(hdr_height: u32, dv_height: u32) -> FrameFit {
+    if hdr_height < dv_height {
+        // The HDR10 base gets padded to the DV frame, so the active area
+        // must match the padding exactly. Keep the top bar even for 4:2:0.
+        let total_bars = dv_height - hdr_height;
+        let top = (total_bars / 2) & !1;
+        FrameFit::Pad {
+            top,
+            bottom: total_bars - top,
+        }
+    } else if hdr_height > dv_height {
+        let bars = (hdr_height - dv_height) / 2;
+        FrameFit::Crop { top: bars, bottom: bars }
+    } else {
+        FrameFit::Same
+    }
+}
 
-    let hdr_info = get_mediainfo(&mediainfo, input_hdr)?;
-    let dv_info = get_mediainfo(&mediainfo, input_dv)?;
+/// Frame widths this close are the same resolution class; the height
+/// difference between them is letterboxing.
+const WIDTH_CLASS_TOLERANCE: f64 = 0.01;
 
-    if (hdr_info.fps - dv_info.fps).abs() > 0.001 {
+/// Height of the DV frame in HDR pixels, for `frame_fit`. Frames of one width
+/// differ only by bars. A DV frame of another resolution (1080p against a
+/// 2160p HDR) would turn into a nonsense crop, so it is an error unless
+/// `scale` maps it onto the HDR frame by width.
+pub(crate) fn dv_height_on_hdr(hdr: &MediaReport, dv: &MediaReport, scale: bool) -> Result<u32, String> {
+    let (hdr_width, dv_width) = (hdr.width.max(1) as f64, dv.width.max(1) as f64);
+    if (hdr_width - dv_width).abs() / hdr_width <= WIDTH_CLASS_TOLERANCE {
+        return Ok(dv.height);
+    }
+    let aspect = |width: u32, height: u32| width as f64 / height.max(1) as f64;
+    let (hdr_aspect, dv_aspect) = (aspect(hdr.width, hdr.height), aspect(dv.width, dv.height));
+    if !scale {
+        let difference = if (hdr_aspect - dv_aspect).abs() / hdr_aspect <= WIDTH_CLASS_TOLERANCE {
+            "the same aspect ratio at a different resolution".to_string()
+        } else {
+            format!("a different resolution and aspect ratio ({:.2}:1 vs {:.2}:1)", dv_aspect, hdr_aspect)
+        };
         return Err(format!(
-            "Frame rate mismatch - DV: {:.3} | HDR: {:.3}",
-            dv_info.fps, hdr_info.fps
+            "DV is {}x{} and HDR {}x{}: {}, so the RPU cannot be cropped to fit; enable scaling the DV frame to map it onto the HDR frame by width",
+            dv.width, dv.height, hdr.width, hdr.height, difference
         ));
     }
+    // Keep the scaled height even, like every bar size.
+    Ok(((dv.height as f64 * hdr_width / dv_width / 2.0).round() as u32) * 2)
+}
 
-    let mut crop = false;
-    let mut crop_amount = 0u32;
-    if dv_info.height != hdr_info.height {
-        if hdr_info.height < dv_info.height {
-            crop_amount = (dv_info.height - hdr_info.height) / 2;
-            emit_log(
-                app,
+fn delay_to_frames(delay_ms: f64, fps: f64) -> u32 {
+    ((delay_ms.abs() * fps) / 1000.0).round() as u32
+}
+
+/// How the DV RPU is shifted for a delay: a negative delay drops the first
+/// `frames` (`remove`, as a dovi_tool range), a positive one duplicates the
+/// first frame `duplicate` times.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DelayPlan {
+    pub frames: u32,
+    pub remove: Option<String>,
+    pub duplicate: u32,
+}
+
+pub(crate) fn dv_delay_plan(dv_delay_ms: f64, fps: f64) -> DelayPlan {
+    if dv_delay_ms.abs() <= f64::EPSILON {
+        return DelayPlan::default();
+    }
+    let frames = delay_to_frames(dv_delay_ms, fps);
+    DelayPlan {
+        frames,
+        remove: (dv_delay_ms < 0.0 && frames > 0).then(|| format!("0-{}", frames - 1)),
+        duplicate: if dv_delay_ms > 0.0 { frames } else { 0 },
+    }
+}
+
+/// DV and HDR frame counts this close are left alone; container rounding
+/// alone can put a frame between two copies of the same cut.
+const FRAME_COUNT_TOLERANCE: u64 = 2;
+
+/// Frames in the video track: MediaInfo's count, else fps × duration.
+fn frame_total(info: &MediaReport) -> Option<u64> {
+    info.frame_count.or_else(|| {
+        info.duration_secs
+            .filter(|_| info.fps > 0.0)
+            .map(|secs| (secs * info.fps).round() as u64)
+    })
+}
+
+/// RPU edit that makes it end where the video does.
+#[derive(Debug, Clone, Copy)]
+enum RpuTail {
+    /// Drop frames `first..=last` of the extracted RPU.
+    Remove { first: u64, last: u64 },
+    /// Repeat frame `source`, counted after the delay's removals, `length`
+    /// times right after it.
+    Extend { source: u64, length: u64 },
+}
+
+/// Compare the RPU's length once the delay edits are applied with the HDR
+/// video's, and apply the frame count policy to a difference beyond
+/// `FRAME_COUNT_TOLERANCE`.
+fn check_frame_counts(
+    app: &AppHandle,
+    options: &PipelineOptions,
+    hdr_info: &MediaReport,
+    dv_info: &MediaReport,
+    delay: &DelayPlan,
+) -> Result<Option<RpuTail>, String> {
+    let (Some(video), Some(dv)) = (frame_total(hdr_info), frame_total(dv_info)) else {
+        emit_log(
+            app,
+            "warning",
+            "Could not read the frame count of the DV or HDR input; not comparing them",
+        );
+        return Ok(None);
+    };
+    let removed = if delay.remove.is_some() { u64::from(delay.frames) } else { 0 };
+    let kept = dv.saturating_sub(removed);
+    let rpu = kept + u64::from(delay.duplicate);
+    let difference = rpu.abs_diff(video);
+    let counts = if rpu == dv {
+        format!("DV: {} | HDR: {}", dv, video)
+    } else {
+        format!("DV: {} ({} after the delay) | HDR: {}", dv, rpu, video)
+    };
+    if difference <= FRAME_COUNT_TOLERANCE {
+        emit_log(app, "info", format!("Frame counts match - {}", counts));
+        return Ok(None);
+    }
+    let message = format!(
+        "Frame count mismatch - {}: the RPU ends {} frame(s) {} the video",
+        counts,
+        difference,
+        if rpu > video { "after" } else { "before" }
+    );
+    match options.frame_count_policy {
+        FrameCountPolicy::Fail => Err(validation_error(message)),
+        FrameCountPolicy::Warn if options.strict_validation => Err(validation_error(message)),
+        FrameCountPolicy::Warn => {
+            emit_log(app, "warning", message);
+            Ok(None)
+        }
+        FrameCountPolicy::Trim if rpu > video && difference >= kept => Err(validation_error(format!(
+            "{}, more than the DV input has left to trim",
+            message
+        ))),
+        FrameCountPolicy::Trim if kept == 0 => Err(validation_error(message)),
+        FrameCountPolicy::Trim => {
+            emit_log(app, "warning", format!("{}; matching the RPU to the video", message));
+            Ok(Some(if rpu > video {
+                RpuTail::Remove {
+                    first: dv - difference,
+                    last: dv - 1,
+                }
+            } else {
+                RpuTail::Extend {
+                    source: kept - 1,
+                    length: difference,
+                }
+            }))
+        }
+    }
+}
+
+/// A tool invocation that runs inside the job's work directory, so scratch
+/// files land next to our temp files instead of in the install directory.
+pub(crate) fn tool_command(program: impl AsRef<OsStr>, work_dir: &Path) -> Command {
+    let mut command = Command::new(program);
+    command.current_dir(work_dir);
+    command
+}
+
+/// An mkvmerge/mkvextract invocation with its messages in `ui_language` and
+/// every path and message it prints encoded as UTF-8.
+fn mkvtoolnix_command(program: impl AsRef<OsStr>, work_dir: &Path, ui_language: &str) -> Command {
+    let mut command = tool_command(program, work_dir);
+    command
+        .arg("--ui-language")
+        .arg(ui_language)
+        .arg("--output-charset")
+        .arg("utf-8");
+    command
+}
+
+/// `add-dv` mode takes one finished HDR10 Matroska file, whose other tracks
+/// are kept as they are, and a Dolby Vision source to take the RPU from.
+pub(crate) fn check_add_dv_inputs(hdr: &Path, dv: &Path) -> Result<(), String> {
+    if !hdr.is_file() || !is_matroska_file(hdr) {
+        return Err(validation_error(format!(
+            "Add DV mode needs one HDR10 MKV file, not {}",
+            hdr.display()
+        )));
+    }
+    if !dv.is_file() {
+        return Err(validation_error(format!(
+            "Add DV mode needs one Dolby Vision source file, not {}",
+            dv.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Without an HDR input the DV file's base layer becomes the output's video,
+/// which only works when that layer is plain HDR10: profile 8. Profile 7's
+/// base needs its enhancement layer and profile 5's is not HDR10 at all. It
+/// must also be a container, as its audio and subtitles are muxed too.
+fn check_dv_only_input(dv: &Path, dv_info: &MediaReport) -> Result<(), String> {
+    if is_hevc_file(dv) {
+        return Err(validation_error(format!(
+            "Without an HDR input the DV file supplies the audio too, so it must be an MKV or MP4, not {}",
+            dv.display()
+        )));
+    }
+    match dv_info.dolby_vision_profile() {
+        Some(8) => Ok(()),
+        Some(profile) => Err(validation_error(format!(
+            "Without an HDR input the DV file must be profile 8, whose base layer is HDR10; {} is profile {} ({})",
+            dv.display(),
+            profile,
+            dv_info.dolby_vision_label().unwrap_or_default()
+        ))),
+        None => Err(validation_error(format!(
+            "Without an HDR input the DV file must be profile 8; could not read the profile of {} (HDR format: {})",
+            dv.display(),
+            dv_info.hdr_format.as_deref().unwrap_or("none")
+        ))),
+    }
+}
+
+/// Chapter starts further apart than this, once the DV delay is taken out,
+/// mean the two inputs are different cuts.
+const CHAPTER_TOLERANCE_SECS: f64 = 1.0;
+
+fn is_matroska_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(|ext| matches!(ext.to_ascii_lowercase().as_str(), "mkv" | "mk3d"))
+        .unwrap_or(false)
+}
+
+/// Chapter start times in seconds, via `mkvextract chapters --simple`.
+fn chapter_starts(
+    mkvextract: &Path,
+    work_dir: &Path,
+    input: &Path,
+    target: &Path,
+    ui_language: &str,
+) -> Result<Vec<f64>, String> {
+    let mut command = mkvtoolnix_command(mkvextract, work_dir, ui_language);
+    command.arg(input).arg("chapters").arg("--simple").arg(target);
+    hide_console_window(&mut command);
+    let output = command.output().map_err(|e| format!("Failed to run mkvextract: {}", e))?;
+    let content = fs::read_to_string(target).unwrap_or_default();
+    let _ = fs::remove_file(target);
+    if !output.status.success() {
+        return Err(format!(
+            "mkvextract could not read the chapters of {}: {}",
+            input.display(),
+            String::from_utf8_lossy(&output.stdout).trim()
+        ));
+    }
+    let line_re = Regex::new(r"^CHAPTER\d+=(\d+):(\d+):(\d+(?:\.\d+)?)").map_err(|e| e.to_string())?;
+    Ok(content
+        .lines()
+        .filter_map(|line| line_re.captures(line.trim()))
+        .filter_map(|caps| {
+            let hours: f64 = caps[1].parse().ok()?;
+            let minutes: f64 = caps[2].parse().ok()?;
+            let seconds: f64 = caps[3].parse().ok()?;
+            Some(hours * 3600.0 + minutes * 60.0 + seconds)
+        })
+        .collect())
+}
+
+/// Chapters file to mux in place of the source's, per `options.chapters`;
+/// `None` keeps the source's own. Flattened editions are written to `target`.
+fn output_chapters(
+    app: &AppHandle,
+    mkvextract: &Path,
+    work_dir: &Path,
+    source: &Path,
+    target: &Path,
+    options: &PipelineOptions,
+) -> Result<Option<PathBuf>, String> {
+    match &options.chapters {
+        ChapterMode::Keep => Ok(None),
+        ChapterMode::Replace(path) => {
+            let path = absolute_path(Path::new(path));
+            let count = read_chapters_file(&path).map_err(validation_error)?;
+            emit_log(
+                app,
+                "info",
+                format!("Chapters: replacing the source's with {} ({} chapter(s))", path.display(), count),
+            );
+            Ok(Some(path))
+        }
+        ChapterMode::DropEditions => {
+            if !is_matroska_file(source) {
+                emit_log(
+                    app,
+                    "warning",
+                    format!("Dropping chapter editions needs a Matroska source; keeping the chapters of {}", source.display()),
+                );
+                return Ok(None);
+            }
+            let mut command = mkvtoolnix_command(mkvextract, work_dir, &options.mkv_ui_language);
+            command.arg(source).arg("chapters").arg(target);
+            hide_console_window(&mut command);
+            let output = command.output().map_err(|e| format!("Failed to run mkvextract: {}", e))?;
+            let xml = fs::read_to_string(target).unwrap_or_default();
+            let _ = fs::remove_file(target);
+            if !output.status.success() {
+                return Err(format!(
+                    "mkvextract could not read the chapters of {}: {}",
+                    source.display(),
+                    String::from_utf8_lossy(&output.stdout).trim()
+                ));
+            }
+            if xml.trim().is_empty() {
+                emit_log(app, "info", "Chapters: the source has none to flatten");
+                return Ok(None);
+            }
+            match drop_editions(&xml) {
+                Ok(Some((flattened, summary))) => {
+                    fs::write(target, flattened).map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+                    emit_log(app, "info", format!("Chapters: {}", summary));
+                    Ok(Some(target.to_path_buf()))
+                }
+                Ok(None) => {
+                    emit_log(app, "info", "Chapters: already one plain edition, kept as they are");
+                    Ok(None)
+                }
+                Err(err) => {
+                    emit_log(app, "warning", format!("Keeping the source's chapters: {}", err));
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
+
+/// Compare the chapter starts of two Matroska inputs and describe the first
+/// chapter that moved by more than the DV delay allows. Inputs with
+/// different cuts line up at the start and drift apart at the changed scene.
+fn chapter_divergence(
+    mkvextract: &Path,
+    work_dir: &Path,
+    input_hdr: &Path,
+    input_dv: &Path,
+    output_base: &str,
+    options: &PipelineOptions,
+    dv_delay_ms: f64,
+) -> Result<Option<String>, String> {
+    let hdr_target = PathBuf::from(format!("{}_chapters_hdr.txt", output_base));
+    let dv_target = PathBuf::from(format!("{}_chapters_dv.txt", output_base));
+    let hdr = chapter_starts(mkvextract, work_dir, input_hdr, &hdr_target, &options.mkv_ui_language)?;
+    let dv = chapter_starts(mkvextract, work_dir, input_dv, &dv_target, &options.mkv_ui_language)?;
+    let delay_secs = dv_delay_ms.abs() / 1000.0;
+    let diverging = hdr
+        .iter()
+        .zip(&dv)
+        .position(|(hdr_start, dv_start)| ((hdr_start - dv_start).abs() - delay_secs).abs() > CHAPTER_TOLERANCE_SECS);
+    Ok(diverging.map(|index| {
+        format!(
+            "HDR and DV inputs look like different cuts: chapter {} starts at {:.3}s in the HDR input but {:.3}s in the DV input{}",
+            index + 1,
+            hdr[index],
+            dv[index],
+            if hdr.len() == dv.len() {
+                String::new()
+            } else {
+                format!(" ({} vs {} chapters)", hdr.len(), dv.len())
+            }
+        )
+    }))
+}
+
+/// Resolve a tool only needed by some inputs, failing with what needed it
+/// when it is not installed.
+pub(crate) fn require_tool(app: &AppHandle, configured: &str, tool_name: &str, needed_for: &str) -> Result<PathBuf, String> {
+    let path = resolve_path(app, configured);
+    let installed = path.is_file() || (cfg!(target_os = "windows") && path.with_extension("exe").is_file());
+    if installed {
+        Ok(path)
+    } else {
+        Err(format!(
+            "{} requires {}, not found ({})",
+            needed_for,
+            tool_name,
+            path.display()
+        ))
+    }
+}
+
+/// Whether `path` is marked read-only, as files on optical discs and
+/// mounted images are.
+fn is_read_only(path: &Path) -> bool {
+    fs::metadata(path)
+        .map(|meta| meta.permissions().readonly())
+        .unwrap_or(false)
+}
+
+/// Folder to stage the inputs into: the chosen staging folder, else the work
+/// directory when any input is read-only. A resumed mux stages nothing.
+fn staging_dir_for(
+    options: &PipelineOptions,
+    resume_mux: bool,
+    read_only_inputs: &[&Path],
+    work_dir: &Path,
+) -> Option<PathBuf> {
+    match &options.staging_dir {
+        _ if resume_mux => None,
+        Some(staging_dir) => Some(absolute_path(staging_dir)),
+        None if !read_only_inputs.is_empty() => Some(work_dir.to_path_buf()),
+        None => None,
+    }
+}
+
+/// `mp4box` is only called for MP4 inputs, so a missing MP4Box does not
+/// affect MKV-only runs.
+fn build_demux_command(
+    mkvextract: &Path,
+    mp4box: &dyn Fn() -> Result<PathBuf, String>,
+    input: &Path,
+    output: &Path,
+    track_id: Option<u32>,
+    work_dir: &Path,
+    ui_language: &str,
+) -> Result<Command, String> {
+    if is_mp4_container(input) {
+        let id = track_id.ok_or("Missing track ID for MP4Box demux")?;
+        let mut cmd = tool_command(&mp4box()?, work_dir);
+        cmd.arg("-raw")
+            .arg(id.to_string())
+            .arg("-out")
+            .arg(output)
+            .arg(input);
+        return Ok(cmd);
+    }
+
+    let id = track_id.ok_or("Missing track ID for mkvextract demux")?;
+    let mut cmd = mkvtoolnix_command(mkvextract, work_dir, ui_language);
+    cmd.arg(input).arg("tracks").arg(format!("{}:{}", id, output.to_string_lossy()));
+    Ok(cmd)
+}
+
+/// Track id to demux the HEVC stream of `input` with: `requested` if given,
+/// otherwise the first HEVC video track. Matroska ids come from mkvmerge,
+/// MP4 ids from the MediaInfo probe.
+fn select_video_track(
+    app: &AppHandle,
+    tools: &ProbeTools,
+    input: &Path,
+    info: &MediaReport,
+    requested: Option<u32>,
+    role: &str,
+) -> Result<Option<u32>, String> {
+    if is_mp4_container(input) {
+        let track_id = requested.or(info.track_id);
+        emit_log(
+            app,
+            "info",
+            format!(
+                "{} video: MP4 track {} ({})",
+                role,
+                track_id.map(|id| id.to_string()).unwrap_or_else(|| "?".to_string()),
+                info.format.as_deref().unwrap_or("unknown codec")
+            ),
+        );
+        return Ok(track_id);
+    }
+
+    let tracks = get_video_tracks(&tools.mkvmerge, input, &tools.work_dir)?;
+    let listing = || {
+        tracks
+            .iter()
+            .map(|t| format!("{}: {}", t.id, t.codec))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let track = match requested {
+        Some(id) => tracks.iter().find(|t| t.id == id as u64).ok_or_else(|| {
+            validation_error(format!(
+                "{} has no video track {} (video tracks: {})",
+                input.display(),
+                id,
+                listing()
+            ))
+        })?,
+        None => tracks.iter().find(|t| t.hevc).ok_or_else(|| {
+            validation_error(format!(
+                "{} has no HEVC video track (video tracks: {})",
+                input.display(),
+                if tracks.is_empty() { "none".to_string() } else { listing() }
+            ))
+        })?,
+    };
+    if !track.hevc {
+        return Err(validation_error(format!(
+            "{} video track {} is {}, not HEVC",
+            input.display(),
+            track.id,
+            track.codec
+        )));
+    }
+    emit_log(
+        app,
+        "info",
+        format!("{} video: track {} ({})", role, track.id, track.codec),
+    );
+    Ok(Some(track.id as u32))
+}
+
+/// MP4 track of the DV input to take the RPU from. A `dvh1`/`dvhe` track is
+/// preferred: Apple TV sources pair one with a cross-compatible `hvc1` track
+/// that carries no RPU. An `hvc1`/`hev1` track only qualifies when MediaInfo
+/// finds Dolby Vision configuration on it.
+fn select_mp4_dv_track(app: &AppHandle, input: &Path, info: &MediaReport) -> Result<u32, String> {
+    let describe = |track: &MediaVideoTrack| {
+        format!(
+            "{}: {}{}",
+            track.id.map(|id| id.to_string()).unwrap_or_else(|| "?".to_string()),
+            track.codec_id.as_deref().unwrap_or("unknown codec"),
+            track.hdr_format.as_deref().map(|hdr| format!(" ({})", hdr)).unwrap_or_default()
+        )
+    };
+    let listing = || {
+        if info.video_tracks.is_empty() {
+            "none".to_string()
+        } else {
+            info.video_tracks.iter().map(describe).collect::<Vec<_>>().join(", ")
+        }
+    };
+    let candidates = || info.video_tracks.iter().filter(|track| track.id.is_some());
+    let (track, reason) = match candidates().find(|track| track.is_dolby_vision_entry()) {
+        Some(track) => (track, "Dolby Vision sample entry"),
+        None => match candidates().find(|track| track.has_dolby_vision()) {
+            Some(track) => (
+                track,
+                "no dvh1/dvhe track, but this cross-compatible track carries Dolby Vision configuration",
+            ),
+            None => {
+                return Err(validation_error(format!(
+                    "DV input {} has no Dolby Vision track (video tracks: {})",
+                    input.display(),
+                    listing()
+                )))
+            }
+        },
+    };
+    emit_log(
+        app,
+        "info",
+        format!("DV input video: MP4 track {} - {} (video tracks: {})", describe(track), reason, listing()),
+    );
+    Ok(track.id.unwrap_or_default())
+}
+
+const VALIDATION_ERROR_PREFIX: &str = "Invalid input: ";
+
+/// An error no retry can fix: the inputs or options themselves are wrong.
+fn validation_error(message: impl std::fmt::Display) -> String {
+    format!("{}{}", VALIDATION_ERROR_PREFIX, message)
+}
+
+pub(crate) fn is_validation_error(err: &str) -> bool {
+    err.starts_with(VALIDATION_ERROR_PREFIX)
+}
+
+/// Fold the failures of items or files that ran side by side into one error,
+/// listing every one of them. Cancellations only count when nothing actually
+/// failed, and a lone failure is returned unchanged so it can still be
+/// classified.
+pub fn failure_report(failures: Vec<(String, String)>) -> Result<(), String> {
+    let (cancelled, mut failed): (Vec<_>, Vec<_>) = failures
+        .into_iter()
+        .partition(|(_, err)| err == "Processing cancelled");
+    match failed.len() {
+        0 if cancelled.is_empty() => Ok(()),
+        0 => Err("Processing cancelled".to_string()),
+        1 => Err(failed.remove(0).1),
+        count => Err(format!(
+            "{} failures:\n{}",
+            count,
+            failed
+                .iter()
+                .map(|(name, err)| format!("  {}: {}", name, err))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )),
+    }
+}
+
+/// How a step's exit code is interpreted.
+#[derive(Clone, Copy, PartialEq)]
+enum ExitCodePolicy {
+    /// Any non-zero exit code fails the step.
+    Strict,
+    /// mkvmerge semantics: 1 means "completed with warnings", 2 and above are errors.
+    Mkvmerge,
+}
+
+/// mkvmerge warnings from the steps run so far. `exited_with_warnings` comes
+/// from the exit code, so it holds even when none of the lines were captured.
+#[derive(Debug, Default)]
+struct StepWarnings {
+    lines: Vec<String>,
+    exited_with_warnings: bool,
+}
+
+impl StepWarnings {
+    fn extend(&mut self, other: StepWarnings) {
+        self.lines.extend(other.lines);
+        self.exited_with_warnings |= other.exited_with_warnings;
+    }
+}
+
+struct ActiveAreaOffsets {
+    left: u64,
+    right: u64,
+    top: u64,
+    bottom: u64,
+}
+
+/// Export the RPU's L5 (active area) metadata with `dovi_tool export` and
+/// return its presets, or `None` when the RPU carries no letterbox offsets.
+fn read_rpu_level5(
+    dovi_tool: &Path,
+    rpu_path: &Path,
+    export_path: &Path,
+    work_dir: &Path,
+) -> Result<Option<Vec<ActiveAreaOffsets>>, String> {
+    let mut cmd = tool_command(dovi_tool, work_dir);
+    cmd.arg("export")
+        .arg("-i")
+        .arg(rpu_path)
+        .arg("-d")
+        .arg(format!("level5={}", export_path.to_string_lossy()));
+    hide_console_window(&mut cmd);
+    let output = cmd.output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let raw = fs::read(export_path).map_err(|e| e.to_string())?;
+    let exported: serde_json::Value = serde_json::from_slice(&raw).map_err(|e| e.to_string())?;
+    let presets = exported
+        .get("presets")
+        .or_else(|| exported.get("active_area").and_then(|a| a.get("presets")))
+        .and_then(serde_json::Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    let offsets: Vec<ActiveAreaOffsets> = presets
+        .iter()
+        .map(|preset| {
+            let field = |name: &str| preset.get(name).and_then(serde_json::Value::as_u64).unwrap_or(0);
+            ActiveAreaOffsets {
+                left: field("left"),
+                right: field("right"),
+                top: field("top"),
+                bottom: field("bottom"),
+            }
+        })
+        .filter(|o| o.left + o.right + o.top + o.bottom > 0)
+        .collect();
+
+    Ok(if offsets.is_empty() { None } else { Some(offsets) })
+}
+
+/// Encoders that cannot take more than 5.1 channels.
+const SIX_CHANNEL_CODECS: [&str; 2] = ["ac3", "eac3"];
+
+/// Build the ffmpeg command that re-encodes every audio track of the extracted
+/// audio/subtitle file while stream-copying everything else.
+fn build_audio_transcode_command(
+    app: &AppHandle,
+    ffmpeg: &Path,
+    mkvmerge: &Path,
+    input: &Path,
+    output: &Path,
+    transcode: &AudioTranscode,
+    work_dir: &Path,
+) -> Result<Command, String> {
+    let tracks = get_audio_tracks(mkvmerge, input, work_dir)?;
+    let codec = transcode.codec.trim().to_ascii_lowercase();
+
+    emit_log(
+        app,
+        "warning",
+        format!(
+            "Audio transcode enabled: {} audio track(s) will be re-encoded to {}",
+            tracks.len(),
+            codec
+        ),
+    );
+
+    let mut cmd = tool_command(ffmpeg, work_dir);
+    cmd.arg("-hide_banner")
+        .arg("-nostdin")
+        .arg("-y")
+        .arg("-i")
+        .arg(input)
+        // Only audio goes through ffmpeg; subtitles, chapters and attachments
+        // are muxed straight from the mkvmerge extraction.
+        .arg("-map")
+        .arg("0:a")
+        .arg("-map_chapters")
+        .arg("-1")
+        .arg("-c")
+        .arg("copy");
+
+    for (index, track) in tracks.iter().enumerate() {
+        cmd.arg(format!("-c:a:{}", index)).arg(&codec);
+        if let Some(bitrate) = &transcode.bitrate {
+            cmd.arg(format!("-b:a:{}", index)).arg(bitrate);
+        }
+
+        let mut target_channels = track.channels;
+        if SIX_CHANNEL_CODECS.contains(&codec.as_str()) && track.channels.unwrap_or(0) > 6 {
+            cmd.arg(format!("-ac:a:{}", index)).arg("6");
+            target_channels = Some(6);
+        }
+
+        emit_log(
+            app,
+            "info",
+            format!(
+                "Audio track {} ({}): {} {}ch -> {} {}ch",
+                index + 1,
+                track.language.as_deref().unwrap_or("und"),
+                track.codec,
+                track.channels.map(|c| c.to_string()).unwrap_or_else(|| "?".to_string()),
+                codec,
+                target_channels.map(|c| c.to_string()).unwrap_or_else(|| "?".to_string()),
+            ),
+        );
+    }
+
+    cmd.arg(output);
+    Ok(cmd)
+}
+
+/// Log the source's subtitle tracks and return the mkvmerge selection for
+/// the extraction: `None` keeps every track, otherwise the arguments to use.
+fn select_subtitle_tracks(
+    app: &AppHandle,
+    tools: &ProbeTools,
+    input: &Path,
+    filter: SubtitleFilter,
+) -> Result<Option<Vec<String>>, String> {
+    let tracks = get_subtitle_tracks(&tools.mkvmerge, input, &tools.work_dir)?;
+    let mut kept = Vec::new();
+    for track in &tracks {
+        let dropped = (filter.drop_forced && track.forced)
+            || (filter.drop_sdh && track.hearing_impaired);
+        emit_log(
+            app,
+            "info",
+            format!(
+                "Subtitle track {} ({}): {}, {}{}{}",
+                track.id,
+                track.language.as_deref().unwrap_or("und"),
+                track.codec,
+                if track.image_based { "image, stream-copied" } else { "text, encoding kept" },
+                if track.forced { ", forced" } else { "" },
+                if dropped { " - dropped" } else { "" },
+            ),
+        );
+        if !dropped {
+            kept.push(track.id.to_string());
+        }
+    }
+
+    if kept.len() == tracks.len() {
+        Ok(None)
+    } else if kept.is_empty() {
+        Ok(Some(vec!["--no-subtitles".to_string()]))
+    } else {
+        Ok(Some(vec!["--subtitle-tracks".to_string(), kept.join(",")]))
+    }
+}
+
+fn language_matches(language: Option<&str>, wanted: &str) -> bool {
+    language.is_some_and(|language| language.trim().eq_ignore_ascii_case(wanted.trim()))
+}
+
+/// mkvmerge `--default-track`/`--forced-track` arguments for the audio
+/// and/or subtitle tracks of one mux input, keyed by its track ids. The
+/// matching track becomes the only default one of its kind; a language with
+/// no matching track leaves that kind's flags to mkvmerge.
+fn track_flag_args(
+    app: &AppHandle,
+    tools: &ProbeTools,
+    source: &Path,
+    defaults: &TrackDefaults,
+    audio: bool,
+    subtitles: bool,
+) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut flag = |option: &str, id: u64, on: bool| {
+        args.push(option.to_string());
+        args.push(format!("{}:{}", id, if on { 1 } else { 0 }));
+    };
+
+    if let Some(wanted) = defaults.audio_language.as_deref().filter(|_| audio) {
+        let tracks = get_audio_tracks(&tools.mkvmerge, source, &tools.work_dir)?;
+        match tracks.iter().find(|track| language_matches(track.language.as_deref(), wanted)) {
+            Some(chosen) => {
+                for track in &tracks {
+                    flag("--default-track", track.id, track.id == chosen.id);
+                }
+                emit_log(
+                    app,
+                    "info",
+                    format!("Default audio: track {} ({}, {})", chosen.id, wanted, chosen.codec),
+                );
+            }
+            None => emit_log(
+                app,
+                "warning",
+                format!("No audio track in language {}; keeping the source's default audio", wanted),
+            ),
+        }
+    }
+
+    if let Some(wanted) = defaults.subtitle_language.as_deref().filter(|_| subtitles) {
+        let tracks = get_subtitle_tracks(&tools.mkvmerge, source, &tools.work_dir)?;
+        match tracks.iter().find(|track| language_matches(track.language.as_deref(), wanted)) {
+            Some(chosen) => {
+                for track in &tracks {
+                    flag("--default-track", track.id, track.id == chosen.id);
+                }
+                if defaults.subtitle_forced {
+                    flag("--forced-track", chosen.id, true);
+                }
+                emit_log(
+                    app,
+                    "info",
+                    format!(
+                        "Default subtitles: track {} ({}, {}){}",
+                        chosen.id,
+                        wanted,
+                        chosen.codec,
+                        if defaults.subtitle_forced { ", forced" } else { "" }
+                    ),
+                );
+            }
+            None => emit_log(
+                app,
+                "warning",
+                format!("No subtitle track in language {}; keeping the source's default subtitles", wanted),
+            ),
+        }
+    }
+    Ok(args)
+}
+
+/// Audio track ids of `source` with the tracks in `order`'s languages first,
+/// in that order, then the rest as they were. Empty when `order` is.
+fn ordered_audio_ids(app: &AppHandle, tools: &ProbeTools, source: &Path, order: &[String]) -> Result<Vec<u64>, String> {
+    if order.is_empty() {
+        return Ok(Vec::new());
+    }
+    let tracks = get_audio_tracks(&tools.mkvmerge, source, &tools.work_dir)?;
+    let mut ids = Vec::with_capacity(tracks.len());
+    for wanted in order {
+        let before = ids.len();
+        for track in &tracks {
+            if language_matches(track.language.as_deref(), wanted) && !ids.contains(&track.id) {
+                ids.push(track.id);
+            }
+        }
+        if ids.len() == before {
+            emit_log(
+                app,
+                "warning",
+                format!("No audio track in language {}; leaving it out of the audio order", wanted),
+            );
+        }
+    }
+    for track in &tracks {
+        if !ids.contains(&track.id) {
+            ids.push(track.id);
+        }
+    }
+    let described: Vec<String> = ids
+        .iter()
+        .filter_map(|id| tracks.iter().find(|track| track.id == *id))
+        .map(|track| format!("{} ({})", track.id, track.language.as_deref().unwrap_or("und")))
+        .collect();
+    emit_log(app, "info", format!("Audio order: {}", described.join(", ")));
+    Ok(ids)
+}
+
+/// Shorter delays for the first polls, so steps that finish in well under a
+/// second still show progress before they complete.
+const STARTUP_POLL_DELAYS: [Duration; 3] = [
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+];
+
+/// Lines of tool output kept per pipe; older lines are discarded.
+const CAPTURE_MAX_LINES: usize = 200;
+/// Longest single line kept; tools that redraw progress without newlines
+/// would otherwise grow one line forever.
+const CAPTURE_MAX_LINE_BYTES: usize = 4096;
+/// Trailing output lines logged when a step fails.
+const FAILURE_LOG_LINES: usize = 20;
+
+#[derive(Default)]
+struct CapturedOutput {
+    tail: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl CapturedOutput {
+    /// Prefer stderr for diagnostics, falling back to stdout when it is silent.
+    fn tail_or<'a>(&'a self, fallback: &'a CapturedOutput) -> &'a [String] {
+        if self.tail.is_empty() {
+            &fallback.tail
+        } else {
+            &self.tail
+        }
+    }
+}
+
+/// Consume a child pipe on its own thread until EOF, keeping only a bounded
+/// tail of lines (decoded lossily) plus any mkvmerge-style warnings.
+fn drain_pipe<R: Read + Send + 'static>(pipe: R) -> thread::JoinHandle<CapturedOutput> {
+    thread::spawn(move || {
+        let mut reader = pipe;
+        let mut captured = CapturedOutput::default();
+        let mut tail = VecDeque::with_capacity(CAPTURE_MAX_LINES);
+        let mut line = Vec::new();
+        let mut chunk = [0u8; 8192];
+
+        let mut finish_line = |line: &mut Vec<u8>, captured: &mut CapturedOutput| {
+            let text = String::from_utf8_lossy(line).trim().to_string();
+            line.clear();
+            if text.is_empty() {
+                return;
+            }
+            if text.starts_with("Warning:") && captured.warnings.len() < CAPTURE_MAX_LINES {
+                captured.warnings.push(text.clone());
+            }
+            if tail.len() == CAPTURE_MAX_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(text);
+        };
+
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            for byte in &chunk[..read] {
+                if *byte == b'\n' || *byte == b'\r' {
+                    finish_line(&mut line, &mut captured);
+                } else if line.len() < CAPTURE_MAX_LINE_BYTES {
+                    line.push(*byte);
+                }
+            }
+        }
+        finish_line(&mut line, &mut captured);
+
+        captured.tail = tail.into_iter().collect();
+        captured
+    })
+}
+
+/// Collect the `<stem>-001.<ext>`, `<stem>-002.<ext>`, ... files mkvmerge
+/// produces for a split output.
+fn find_split_parts(output_path: &Path) -> Vec<PathBuf> {
+    let stem = output_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = output_path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| output_path.with_file_name(format!("{}-{:03}{}", stem, n, extension)))
+        .take_while(|part| part.exists())
+        .collect()
+}
+
+/// Per-file numbering of the tool runs that feed its progress bar. The
+/// total is planned from what is known before the first step and corrected
+/// as optional steps are decided, so a file's progress runs from 0 to 100
+/// whichever steps it actually needs.
+struct StepPlan {
+    total: AtomicUsize,
+    started: AtomicUsize,
+    /// Highest progress reported, so two steps running at once never move
+    /// the bar back.
+    reported: AtomicU8,
+}
+
+impl StepPlan {
+    fn new(total: usize) -> Self {
+        StepPlan {
+            total: AtomicUsize::new(total),
+            started: AtomicUsize::new(0),
+            reported: AtomicU8::new(0),
+        }
+    }
+
+    /// Index and total for the step starting now.
+    fn start(&self) -> (usize, usize) {
+        let index = self.started.fetch_add(1, Ordering::SeqCst);
+        (index, self.total.load(Ordering::SeqCst).max(index + 1))
+    }
+
+    /// An unplanned step will run.
+    fn add(&self, steps: usize) {
+        self.total.fetch_add(steps, Ordering::SeqCst);
+    }
+
+    /// A planned step will not run.
+    fn skip(&self) {
+        let total = self.total.load(Ordering::SeqCst).saturating_sub(1);
+        self.total.store(total.max(self.started.load(Ordering::SeqCst)), Ordering::SeqCst);
+    }
+
+    /// `progress`, or the highest reported so far when that is more.
+    fn progress(&self, progress: u8) -> u8 {
+        self.reported.fetch_max(progress, Ordering::SeqCst).max(progress)
+    }
+}
+
+/// What the audio lane produced: mkvmerge warnings and the transcoded audio,
+/// when transcoding.
+type AudioLaneOutput = (StepWarnings, Option<PathBuf>);
+
+/// Audio/subtitle extraction, and the transcode after it, running on its own
+/// thread while the video steps go on. The lane runs with its own stop flag
+/// in place of the run's cancel flag, so a failed file can stop its lane
+/// without cancelling the other files of the run. Joined before the mux;
+/// dropped early, it stops the lane's tool and waits for the thread so
+/// nothing outlives the file's pipeline.
+struct AudioLane {
+    handle: Option<thread::JoinHandle<Result<AudioLaneOutput, String>>>,
+    stop: Arc<Mutex<bool>>,
+}
+
+impl AudioLane {
+    /// Start `work` on a thread with a copy of `state` whose cancel flag is
+    /// the lane's stop flag.
+    fn spawn<F>(state: &ProcessingState, work: F) -> Self
+    where
+        F: FnOnce(ProcessingState) -> Result<AudioLaneOutput, String> + Send + 'static,
+    {
+        let stop = Arc::new(Mutex::new(false));
+        let lane_state = ProcessingState {
+            cancel_flag: Arc::clone(&stop),
+            ..state.clone()
+        };
+        AudioLane {
+            handle: Some(thread::spawn(move || work(lane_state))),
+            stop,
+        }
+    }
+
+    fn stop(&self) {
+        if let Ok(mut flag) = self.stop.lock() {
+            *flag = true;
+        }
+    }
+
+    /// Wait for the lane, passing a cancel of the run on to it.
+    fn join(mut self, state: &ProcessingState) -> Result<AudioLaneOutput, String> {
+        let Some(handle) = self.handle.take() else {
+            return Ok((StepWarnings::default(), None));
+        };
+        while !handle.is_finished() {
+            if *state.cancel_flag.lock().map_err(|_| "State lock failed")? {
+                self.stop();
+            }
+            thread::sleep(Duration::from_millis(250));
+        }
+        handle
+            .join()
+            .map_err(|_| "The audio extraction thread panicked".to_string())?
+    }
+}
+
+impl Drop for AudioLane {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.stop();
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Hold before starting the next step while the job is paused. Steps that
+/// are already running are left to finish.
+fn wait_while_paused(state: &ProcessingState, app: &AppHandle) -> Result<(), String> {
+    let mut announced = false;
+    while *state.pause_flag.lock().map_err(|_| "State lock failed")? {
+        if *state.cancel_flag.lock().map_err(|_| "State lock failed")? {
+            return Err("Processing cancelled".to_string());
+        }
+        if !announced {
+            emit_log(app, "info", "Paused; waiting to resume before the next step");
+            announced = true;
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+    Ok(())
+}
+
+fn run_command(
+    state: &ProcessingState,
+    mut command: Command,
+    app: &AppHandle,
+    step_id: usize,
+    step_name: &str,
+    input_path: &Path,
+    output_path: &Path,
+    emit_progress: bool,
+    plan: &StepPlan,
+    queue_ctx: Option<&QueueContext>,
+    job_file: &str,
+    exit_policy: ExitCodePolicy,
+) -> Result<StepWarnings, String> {
+    wait_while_paused(state, app)?;
+    wait_for_window(app, state, true)?;
+    if *state.cancel_flag.lock().map_err(|_| "State lock failed")? {
+        return Err("Processing cancelled".to_string());
+    }
+
+    emit_step(app, step_id, step_name, "active", 0);
+    emit_log(app, "info", format!("Step {}: {}", step_id, step_name));
+
+    let (step_index, total_steps) = plan.start();
+    let emit_queue_progress = |progress: u8| {
+        let file_progress = ((step_index as f64 + progress as f64 / 100.0)
+            / total_steps as f64)
+            * 100.0;
+        let file_progress = plan.progress(file_progress.round() as u8);
+        report_job_progress(app, state, job_file, file_progress);
+
+        if let Some(ctx) = queue_ctx {
+            let step_label = match &ctx.label {
+                Some(label) => format!("{} - {}", label, step_name),
+                None => step_name.to_string(),
+            };
+            update_queue_file(app, ctx, Some(step_label), |slot| {
+                slot.progress = file_progress;
+            });
+
+            if let (Some(file_id), Some(file_name)) = (&ctx.file_id, &ctx.file_name) {
+                emit_file(
+                    app,
+                    FilePayload {
+                        id: file_id.clone(),
+                        queue_id: ctx.id.clone(),
+                        name: file_name.clone(),
+                        status: "processing".to_string(),
+                        progress: file_progress,
+                        attempt: ctx.attempt,
+                        reason: None,
+                        hashes: Vec::new(),
+                    },
+                );
+            }
+        }
+    };
+
+    emit_log(app, "debug", format!("{}: {}", step_name, shell_command_line(&command)));
+    hide_console_window(&mut command);
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // Both pipes are drained continuously so a chatty tool can never block on
+    // a full pipe buffer while we are only polling `try_wait`.
+    let stdout_reader = child.stdout.take().map(drain_pipe);
+    let stderr_reader = child.stderr.take().map(drain_pipe);
+    let collect_output = |stdout_reader: Option<thread::JoinHandle<CapturedOutput>>,
+                          stderr_reader: Option<thread::JoinHandle<CapturedOutput>>| {
+        let join = |reader: Option<thread::JoinHandle<CapturedOutput>>| {
+            reader.and_then(|handle| handle.join().ok()).unwrap_or_default()
+        };
+        (join(stdout_reader), join(stderr_reader))
+    };
+
+    let input_size = fs::metadata(input_path).map(|m| m.len()).unwrap_or(1);
+    emit_queue_progress(0);
+
+    let poll_interval = state.progress.interval();
+    let mut polls = 0usize;
+    let step_started = Instant::now();
+    let result = loop {
+        if *state.cancel_flag.lock().map_err(|_| "State lock failed")? {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err("Processing cancelled".to_string());
+        }
+
+        if emit_progress {
+            if let Ok(metadata) = fs::metadata(output_path) {
+                let percent = ((metadata.len() as f64 / input_size as f64) * 100.0)
+                    .min(95.0)
+                    .max(0.0) as u8;
+                emit_step(app, step_id, step_name, "active", percent);
+                emit_queue_progress(percent);
+            }
+        }
+
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                let (mut stdout, stderr) = collect_output(stdout_reader, stderr_reader);
+                // mkvmerge reports its warnings on stdout.
+                let lines = if exit_policy == ExitCodePolicy::Mkvmerge {
+                    std::mem::take(&mut stdout.warnings)
+                } else {
+                    Vec::new()
+                };
+                if status.success() {
+                    emit_step(app, step_id, step_name, "completed", 100);
+                    emit_queue_progress(100);
+                    emit_log(app, "success", format!("Step completed: {}", step_name));
+                    break Ok(StepWarnings { lines, exited_with_warnings: false });
+                } else if exit_policy == ExitCodePolicy::Mkvmerge && status.code() == Some(1) {
+                    emit_step(app, step_id, step_name, "completed", 100);
+                    emit_queue_progress(100);
+                    for warning in &lines {
+                        emit_log(app, "warning", format!("{}: {}", step_name, warning));
+                    }
+                    emit_log(
+                        app,
+                        "warning",
+                        format!("Step completed with warnings: {}", step_name),
+                    );
+                    break Ok(StepWarnings { lines, exited_with_warnings: true });
+                } else {
+                    emit_step(app, step_id, step_name, "error", 0);
+                    emit_queue_progress(0);
+                    let diagnostics = stderr.tail_or(&stdout);
+                    for line in diagnostics.iter().rev().take(FAILURE_LOG_LINES).rev() {
+                        emit_log(app, "error", format!("{}: {}", step_name, line));
+                    }
+                    emit_log(app, "error", format!("Step failed: {}", step_name));
+                    break Err(match diagnostics.last() {
+                        Some(last) => format!("Step failed: {} ({})", step_name, last),
+                        None => format!("Step failed: {}", step_name),
+                    });
+                }
+            }
+            Ok(None) => {
+                thread::sleep(
+                    STARTUP_POLL_DELAYS
+                        .get(polls)
+                        .copied()
+                        .unwrap_or(poll_interval)
+                        .min(poll_interval),
+                );
+                polls += 1;
+            }
+            Err(err) => {
+                emit_step(app, step_id, step_name, "error", 0);
+                break Err(err.to_string());
+            }
+        }
+    };
+
+    // No-op steps hand the input through as their output and move no bytes.
+    if result.is_ok() && input_path != output_path {
+        let written = fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+        state.metrics.record_step(
+            step_name,
+            step_started.elapsed(),
+            fs::metadata(input_path).map(|m| m.len()).unwrap_or(0),
+            written,
+            // Everything but the mux writes an intermediate.
+            step_id != 6,
+        );
+    }
+    result
+}
+
+/// Report HDR files that found no DV partner: an error under strict pairing,
+/// otherwise a warning and the files are skipped.
+pub fn check_unmatched(app: &AppHandle, options: &PipelineOptions, scan: &FolderScan) -> Result<(), String> {
+    let (hdr_extra, dv_extra) = (&scan.unmatched, &scan.unmatched_dv);
+    if hdr_extra.is_empty() && dv_extra.is_empty() {
+        return Ok(());
+    }
+    if options.strict_pairing {
+        let mut problems = Vec::new();
+        if !hdr_extra.is_empty() {
+            problems.push(format!("No DV file could be paired with: {}", hdr_extra.join(", ")));
+        }
+        if !dv_extra.is_empty() {
+            problems.push(format!("No HDR file could be paired with: {}", dv_extra.join(", ")));
+        }
+        return Err(problems.join("; "));
+    }
+    emit_log(
+        app,
+        "warning",
+        format!(
+            "Pairing: {} matched, {} HDR file(s) without a DV file, {} DV file(s) without an HDR file; only matched pairs run",
+            scan.pairs.len(),
+            hdr_extra.len(),
+            dv_extra.len()
+        ),
+    );
+    for hdr_file in hdr_extra {
+        emit_log(
+            app,
+            "warning",
+            format!("Skipping {}: no DV file could be paired with it", hdr_file),
+        );
+    }
+    for dv_file in dv_extra {
+        emit_log(
+            app,
+            "warning",
+            format!("Ignoring {}: no HDR file was paired with it", dv_file),
+        );
+    }
+    Ok(())
+}
+
+/// Finish a folder's scan report and send it as `processing:scan_report`.
+/// With `resume`, pairs whose journal shows a verified mux move to skipped.
+pub fn emit_scan_report(app: &AppHandle, options: &PipelineOptions, mut report: ScanReport) {
+    if options.resume {
+        let (done, pending): (Vec<ScanPair>, Vec<ScanPair>) = report.pairs.into_iter().partition(|pair| {
+            let output = Path::new(&pair.output);
+            StepJournal::open(app, output, true).completed(VERIFIED_ENTRY.0, &mux_artifact(output, options))
+        });
+        report.pairs = pending;
+        for pair in done {
+            report.skip(pair.hdr_file, ScanSkipReason::AlreadyDone);
+        }
+    }
+    report.processed = report.pairs.len();
+    report.ordering = options.ordering;
+
+    let count = |reason: ScanSkipReason| report.skipped.iter().filter(|skip| skip.reason == reason).count();
+    emit_log(
+        app,
+        "info",
+        format!(
+            "Scan of {}: {} found, {} to process, skipped {} junk, {} unpaired, {} duplicate, {} already done, {} own output(s)",
+            report.hdr_dir,
+            report.total_found,
+            report.processed,
+            count(ScanSkipReason::Junk),
+            count(ScanSkipReason::Unpaired),
+            count(ScanSkipReason::Duplicate),
+            count(ScanSkipReason::AlreadyDone),
+            count(ScanSkipReason::OwnOutput)
+        ),
+    );
+    if options.ordering != BatchOrdering::Name {
+        let order = report
+            .pairs
+            .iter()
+            .map(|pair| pair.hdr_file.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ");
+        emit_log(app, "info", format!("Queue order ({:?}): {}", options.ordering, order));
+    }
+    if let Some(state) = app.try_state::<ProcessingState>() {
+        state.history.append_scan_report(&report);
+    }
+    emit_event(app, "processing:scan_report", report);
+}
+
+/// Reserve an output path for the whole run, logging any automatic rename.
+pub fn claim_output(
+    app: &AppHandle,
+    state: &ProcessingState,
+    options: &PipelineOptions,
+    output_path: &Path,
+    input_path: &Path,
+) -> Result<PathBuf, String> {
+    let renamed = matroska_output_path(output_path);
+    let already = state
+        .outputs
+        .lock()
+        .map_err(|_| "State lock failed")?
+        .claimed_for(renamed.as_deref().unwrap_or(output_path), input_path);
+    if let Some(claimed) = already {
+        return Ok(claimed);
+    }
+    if let Some(renamed) = &renamed {
+        emit_log(
+            app,
+            "warning",
+            format!(
+                "Output {} does not end in .mkv but the result is always Matroska; writing {} instead",
+                output_path.display(),
+                renamed.display()
+            ),
+        );
+    }
+    let output_path = renamed.as_deref().unwrap_or(output_path);
+    let mut outputs = state.outputs.lock().map_err(|_| "State lock failed")?;
+    if let Some(claimed) = outputs.claimed_for(output_path, input_path) {
+        return Ok(claimed);
+    }
+    let claimed = outputs.claim(output_path, input_path, options.overwrite_policy, false, options.resume)?;
+    drop(outputs);
+    if claimed != output_path {
+        emit_log(
+            app,
+            "warning",
+            format!(
+                "Output {} is already taken, writing {} instead",
+                output_path.display(),
+                claimed.display()
+            ),
+        );
+    }
+    Ok(claimed)
+}
+
+/// Reserve the HDR/DV pair for `owner` (a queue item or folder file id).
+/// A pair already claimed earlier in the run is logged as a duplicate, and
+/// the id of its first claimant is returned.
+fn claim_pair(
+    app: &AppHandle,
+    state: &ProcessingState,
+    hdr: &Path,
+    dv: &Path,
+    owner: &str,
+) -> Result<Option<String>, String> {
+    let original = state
+        .outputs
+        .lock()
+        .map_err(|_| "State lock failed")?
+        .claim_pair(hdr, dv, owner);
+    if let Some(original) = &original {
+        emit_log(
+            app,
+            "warning",
+            format!(
+                "Skipping {}: {} + {} is already processed by {}",
+                owner,
+                hdr.display(),
+                dv.display(),
+                original
+            ),
+        );
+    }
+    Ok(original)
+}
+
+/// Compute and reserve the output for one file of a folder scan. In flat
+/// mode same-named files from different subfolders are auto-numbered rather
+/// than rejected.
+pub fn claim_batch_output(
+    app: &AppHandle,
+    state: &ProcessingState,
+    options: &PipelineOptions,
+    output_base: &str,
+    hdr_file: &str,
+    input_path: &Path,
+) -> Result<PathBuf, String> {
+    let output_path = compute_output_for_batch(output_base, hdr_file, options.output_structure);
+    let mut outputs = state.outputs.lock().map_err(|_| "State lock failed")?;
+    if let Some(claimed) = outputs.claimed_for(&output_path, input_path) {
+        return Ok(claimed);
+    }
+    if let Some(parent) = output_path.parent() {
+        ensure_writable(parent)?;
+    }
+    let claimed = outputs.claim(
+        &output_path,
+        input_path,
+        options.overwrite_policy,
+        options.output_structure == OutputStructure::Flat,
+        options.resume,
+    )?;
+    drop(outputs);
+    if claimed != output_path {
+        emit_log(
+            app,
+            "warning",
+            format!(
+                "Output {} is already taken, writing {} instead",
+                output_path.display(),
+                claimed.display()
+            ),
+        );
+    }
+    Ok(claimed)
+}
+
+/// Output a single-file queue item asks for, before any rename.
+fn queue_item_output(options: &PipelineOptions, item: &QueueItem, hdr_path: &Path) -> PathBuf {
+    if item.output_path.is_empty() {
+        compute_output_for_single(&options.default_output, "", hdr_path)
+    } else {
+        normalize_output_path(&options.default_output, &item.output_path)
+    }
+}
+
+/// Reserve the output of every queue item, and of every pair in its
+/// folders, before any worker starts, so a clash fails or renames up front
+/// rather than midway through the queue. The workers' own claims then get
+/// the same paths back.
+pub fn claim_queue_outputs(
+    app: &AppHandle,
+    state: &ProcessingState,
+    options: &PipelineOptions,
+    queue: &[QueueItem],
+) -> Result<(), String> {
+    for item in queue {
+        let hdr_path = PathBuf::from(&item.hdr_path);
+        let dv_path = PathBuf::from(&item.dv_path);
+        if hdr_path.is_dir() && dv_path.is_dir() {
+            let output_base = if item.output_path.is_empty() {
+                options.default_output_batch.clone()
+            } else {
+                item.output_path.clone()
+            };
+            for pair in scan_folder_pairs(&hdr_path, &dv_path, options.output_structure.recursive())?.pairs {
+                let hdr_file_path = hdr_path.join(&pair.hdr_file);
+                claim_batch_output(app, state, options, &output_base, &pair.hdr_file, &hdr_file_path)?;
+            }
+        } else {
+            claim_output(app, state, options, &queue_item_output(options, item, &hdr_path), &hdr_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Execute the processing pipeline for a single file pair.
+///
+/// Failures are recorded in the job-wide progress accumulator before being
+/// returned; see [`execute_pipeline`] for the individual steps.
+pub fn run_pipeline(
+    app: &AppHandle,
+    state: &ProcessingState,
+    tool_paths: &ToolPaths,
+    options: &PipelineOptions,
+    input_hdr: &Path,
+    input_dv: &Path,
+    hdr10plus_path: Option<&Path>,
+    output_path: &Path,
+    dv_delay_ms: Option<f64>,
+    hdr10plus_delay_ms: f64,
+    keep_temp: bool,
+    queue_id: Option<&str>,
+    queue_label: Option<&str>,
+    queue_file_name: Option<&str>,
+    queue_file_index: usize,
+    queue_file_total: usize,
+    queue_tracker: Option<FileTracker>,
+    queue_active_workers: Option<Arc<Mutex<usize>>>,
+) -> Result<(), String> {
+    let job_file = match queue_id {
+        Some(id) => format!("{}:{}", id, queue_file_index),
+        None => output_path.to_string_lossy().to_string(),
+    };
+    if let Ok(mut job) = state.job_progress.lock() {
+        job.set_file_bytes(&job_file, input_bytes(&[input_hdr, input_dv]));
+    }
+
+    // Files only start while the processing window is open.
+    let waited = wait_for_window(app, state, false);
+    let started = Instant::now();
+    state.metrics.worker_started();
+    let result = waited.and_then(|_| execute_pipeline(
+        app,
+        state,
+        tool_paths,
+        options,
+        input_hdr,
+        input_dv,
+        hdr10plus_path,
+        output_path,
+        dv_delay_ms,
+        hdr10plus_delay_ms,
+        keep_temp,
+        queue_id,
+        queue_label,
+        queue_file_name,
+        queue_file_index,
+        queue_file_total,
+        queue_tracker,
+        queue_active_workers,
+        &job_file,
+    ));
+    state.metrics.worker_finished();
+
+    let payload = state.job_progress.lock().ok().map(|mut job| {
+        match &result {
+            Ok(()) => job.mark_completed(&job_file),
+            Err(err) if err != "Processing cancelled" => job.mark_failed(&job_file),
+            Err(_) => {}
+        }
+        job.payload()
+    });
+    if let Some(payload) = payload {
+        emit_overall(app, payload);
+    }
+    if let (Err(err), Some(id), Some(name)) = (&result, queue_id, queue_file_name) {
+        let (status, reason) = if err == "Processing cancelled" {
+            ("cancelled", Some(state.stop_reason().unwrap_or(StopReason::UserCancelled)))
+        } else {
+            ("error", None)
+        };
+        emit_file(
+            app,
+            FilePayload {
+                id: job_file.clone(),
+                queue_id: id.to_string(),
+                name: name.to_string(),
+                status: status.to_string(),
+                progress: 0,
+                attempt: state.job_progress.lock().map(|job| job.attempt(&job_file)).unwrap_or(1),
+                reason,
+                hashes: Vec::new(),
+            },
+        );
+    }
+    if result.is_ok() {
+        let input_bytes = [input_hdr, input_dv]
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|m| m.len())
+            .sum();
+        record_throughput(app, input_bytes, started.elapsed());
+    }
+
+    result
+}
+
+/// Log the filesystem `output_dir` is on and check it can hold the output,
+/// estimated from the HDR input's size. FAT volumes cap files at 4 GiB, which
+/// mkvmerge only reports as a write error partway through the mux: with
+/// splitting on, a split that could leave a larger part is swapped for a
+/// 4 GB size split (returned); with it off, the file fails validation.
+fn fat_volume_split(
+    app: &AppHandle,
+    state: &ProcessingState,
+    options: &PipelineOptions,
+    output_dir: &Path,
+    input_hdr: &Path,
+) -> Result<Option<SplitSpec>, String> {
+    let Some(volume) = state.volumes.lookup(output_dir) else {
+        return Ok(None);
+    };
+    let location = match &volume.root {
+        Some(root) => format!("{} ({})", root, volume.filesystem),
+        None => volume.filesystem.clone(),
+    };
+    emit_log(app, "info", format!("Output volume: {}", location));
+
+    let estimated = fs::metadata(input_hdr).map(|metadata| metadata.len()).unwrap_or(0);
+    if !volume.has_4gb_limit() || estimated <= FAT_MAX_FILE_BYTES {
+        return Ok(None);
+    }
+    let gib = |bytes: u64| bytes as f64 / (1u64 << 30) as f64;
+    let Some(split) = &options.split else {
+        return Err(validation_error(format!(
+            "Output folder {} is on a {} volume, which cannot hold files over 4 GB, \
+             and the output is expected to be about {:.1} GB. Choose an output on an \
+             NTFS, exFAT or other volume, or turn on splitting.",
+            output_dir.display(),
+            volume.filesystem,
+            gib(estimated)
+        )));
+    };
+    if split.size_bytes().is_some_and(|size| size <= FAT_MAX_FILE_BYTES) {
+        return Ok(None);
+    }
+    emit_log(
+        app,
+        "warning",
+        format!(
+            "Output volume is {} and the output is expected to be about {:.1} GB; \
+             splitting by size at {} instead of the configured split",
+            volume.filesystem,
+            gib(estimated),
+            FAT_SPLIT_SIZE
+        ),
+    );
+    Ok(Some(SplitSpec::Size(FAT_SPLIT_SIZE.to_string())))
+}
+
+/// Run the individual steps for a single file pair.
+///
+/// This function coordinates the extraction, processing, and merging steps:
+/// 1. Extract audio/subs
+/// 2. Extract DV video and RPU
+/// 3. Extract HDR10 video
+/// 4. Inject RPU into HDR10
+/// 5. Mux final output
+fn execute_pipeline(
+    app: &AppHandle,
+    state: &ProcessingState,
+    tool_paths: &ToolPaths,
+    options: &PipelineOptions,
+    input_hdr: &Path,
+    input_dv: &Path,
+    hdr10plus_path: Option<&Path>,
+    output_path: &Path,
+    dv_delay_ms: Option<f64>,
+    hdr10plus_delay_ms: f64,
+    keep_temp: bool,
+    queue_id: Option<&str>,
+    queue_label: Option<&str>,
+    queue_file_name: Option<&str>,
+    queue_file_index: usize,
+    queue_file_total: usize,
+    queue_tracker: Option<FileTracker>,
+    queue_active_workers: Option<Arc<Mutex<usize>>>,
+    job_file: &str,
+) -> Result<(), String> {
+    let dovi_tool = resolve_path(app, &tool_paths.dovi_tool);
+    let mkvmerge = resolve_path(app, &tool_paths.mkvmerge);
+    let mkvextract = resolve_path(app, &tool_paths.mkvextract);
+    let ffmpeg = resolve_path(app, &tool_paths.ffmpeg);
+    let mediainfo = resolve_path(app, &tool_paths.mediainfo);
+    let mp4box = || require_tool(app, &tool_paths.mp4box, "MP4Box", "MP4 input");
+
+    // Tools run inside the work directory, so every path handed to them must
+    // be absolute to mean the same thing it did to the user.
+    let input_hdr = absolute_path(input_hdr);
+    let input_hdr = input_hdr.as_path();
+    let input_dv = absolute_path(input_dv);
+    let input_dv = input_dv.as_path();
+    let hdr10plus_path = hdr10plus_path
+        .filter(|path| !path.as_os_str().is_empty())
+        .map(absolute_path);
+    let hdr10plus_path = hdr10plus_path.as_deref();
+    check_input_file(app, input_hdr, "HDR input")?;
+    check_input_file(app, input_dv, "DV input")?;
+    if let Some(donor) = hdr10plus_path {
+        check_input_file(app, donor, "HDR10+ donor")?;
+    }
+    if options.dv_only && options.base_hevc_path.is_some() {
+        return Err(validation_error(
+            "A prepared HDR10 base layer needs the HDR input; without one the DV file's own base layer is used",
+        ));
+    }
+    if let ChapterMode::Replace(path) = &options.chapters {
+        read_chapters_file(&absolute_path(Path::new(path))).map_err(validation_error)?;
+    }
+    let base_hevc = options.base_hevc_path.as_deref().map(absolute_path);
+    if let Some(base) = &base_hevc {
+        check_input_file(app, base, "HDR10 base layer")?;
+        if options.rpu_source != RpuSource::DvFile {
+            return Err(validation_error(
+                "A prepared HDR10 base layer replaces the HDR input's video, so the RPU must come from the DV file",
+            ));
+        }
+    }
+    let output_path = absolute_path(output_path);
+    let output_path = output_path.as_path();
+    let work_dir = output_path
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| format!("Output has no parent directory: {}", output_path.display()))?;
+
+    let output_base = output_path.to_string_lossy().to_string();
+    let audio_loc = PathBuf::from(format!("{}_audiosubs.mka", output_base));
+    let dv_hevc = PathBuf::from(format!("{}_dv.hevc", output_base));
+    let hdr10_hevc = PathBuf::from(format!("{}_hdr10.hevc", output_base));
+    let dv_hdr = PathBuf::from(format!("{}_dv_hdr.hevc", output_base));
+    let rpu_bin = PathBuf::from(format!("{}_rpu.bin", output_base));
+    let transcoded_audio = PathBuf::from(format!("{}_audiosubs_transcoded.mka", output_base));
+    let mut temp_files = vec![
+        audio_loc.clone(),
+        dv_hevc.clone(),
+        hdr10_hevc.clone(),
+        dv_hdr.clone(),
+        rpu_bin.clone(),
+    ];
+
+    let cover_art = match &options.cover_art {
+        Some(cover_art) => resolve_cover_art(&absolute_path(cover_art), input_hdr)?,
+        None => None,
+    };
+
+    ensure_writable(&work_dir)?;
+    let tools = ProbeTools::new(&mediainfo, &mkvmerge, &work_dir);
+    let fat_options;
+    let options = match fat_volume_split(app, state, options, &work_dir, input_hdr)? {
+        Some(split) => {
+            fat_options = PipelineOptions {
+                split: Some(split),
+                ..options.clone()
+            };
+            &fat_options
+        }
+        None => options,
+    };
+
+    // A DV input processed before brings back the settings it was last run
+    // with, for whatever this request leaves unset.
+    let memo_key = match title_key(input_dv) {
+        Ok(key) => Some(key),
+        Err(err) => {
+            emit_log(app, "warning", format!("Skipping the title memo: {}", err));
+            None
+        }
+    };
+    let memo = memo_key
+        .as_deref()
+        .filter(|_| !options.ignore_title_memos)
+        .and_then(|key| find_title_memo(app, key));
+    let memo_options;
+    let (options, dv_delay_ms) = match &memo {
+        Some(memo) => {
+            let (reused, delay) = apply_title_memo(app, memo, options, dv_delay_ms);
+            memo_options = reused;
+            (&memo_options, delay)
+        }
+        None => (options, dv_delay_ms.unwrap_or(0.0)),
+    };
+    let memo_letterbox = memo.as_ref().and_then(|memo| memo.crop).filter(|crop| crop.detected);
+
+    emit_log(app, "info", format!("Processing: {}", output_path.display()));
+
+    let hdr_info = state.probe.probe(&tools, input_hdr)?;
+    let dv_info = state.probe.probe(&tools, input_dv)?;
+    if options.dv_only {
+        check_dv_only_input(input_dv, &dv_info)?;
+    }
+
+    // Detect Source Headers / FPS
+    let detected_duration = match &hdr_info.default_duration {
+        Ok(d) => {
+            emit_log(app, "info", format!("Detected video duration/fps: {}", d));
+            Some(d.clone())
+        },
+        Err(e) => {
+            emit_log(app, "warning", format!("Could not detect video FPS: {}. Defaulting to mkvmerge behavior.", e));
+            None
+        }
+    };
+
+    if !frame_rates_match(&hdr_info, &dv_info, options.fps_tolerance) {
+        return Err(validation_error(format!(
+            "Frame rate mismatch - DV: {} | HDR: {}",
+            dv_info.fps_label(),
+            hdr_info.fps_label()
+        )));
+    }
+    if let Some(base) = &base_hevc {
+        check_base_layer(app, state, &tools, base, &hdr_info, options.fps_tolerance)?;
+    }
+
+    let both_chaptered = hdr_info.chapter_count > 0 && dv_info.chapter_count > 0;
+    let mut chapter_warning = None;
+    if both_chaptered && is_matroska_file(input_hdr) && is_matroska_file(input_dv) {
+        match chapter_divergence(&mkvextract, &work_dir, input_hdr, input_dv, &output_base, options, dv_delay_ms) {
+            Ok(Some(message)) if options.strict_validation => return Err(validation_error(message)),
+            Ok(Some(message)) => {
+                emit_log(app, "warning", message.clone());
+                chapter_warning = Some(message);
+            }
+            Ok(None) => emit_log(app, "info", "Chapters of both inputs line up"),
+            Err(err) => emit_log(app, "warning", format!("Skipping chapter comparison: {}", err)),
+        }
+    }
+
+    let mut crop = false;
+    let mut crop_amount = 0u32;
+    let mut active_area_bottom = 0u32;
+    let mut pad_hdr_to = None;
+    let dv_height = dv_height_on_hdr(&hdr_info, &dv_info, options.scale_dv_frame).map_err(validation_error)?;
+    if dv_height != dv_info.height {
+        emit_log(
+            app,
+            "info",
+            format!(
+                "Scaling the DV frame {}x{} to the HDR width {}: {}px high",
+                dv_info.width, dv_info.height, hdr_info.width, dv_height
+            ),
+        );
+    }
+    match frame_fit(hdr_info.height, dv_height) {
+        FrameFit::Same => {}
+        FrameFit::Pad { top, bottom } => {
+            crop_amount = top;
+            active_area_bottom = bottom;
+            pad_hdr_to = Some((top, dv_height));
+            emit_log(
+                app,
+                "info",
+                format!(
+                    "Letterboxing needed - {} | HDR: {} | DV: {}",
+                    crop_amount, hdr_info.height, dv_height
+                ),
+            );
+        }
+        FrameFit::Crop { top, bottom } => {
+            crop = true;
+            crop_amount = top;
+            active_area_bottom = bottom;
+            emit_log(
+                app,
+                "info",
+                format!(
+                    "Cropping needed - {} | HDR: {} | DV: {}",
+                    crop_amount, hdr_info.height, dv_height
+                ),
+            );
+        }
+    }
+
+    // Steps outside this range are skipped. Starting at the mux reuses an
+    // already injected stream, so nothing before it runs.
+    let (first_step, last_step) = step_range(options)?;
+    let resume_mux = first_step == STEP_NAMES.len();
+
+    // Letterbox bars only feed the RPU edit.
+    let mut detected_bars = None;
+    if options.detect_letterbox && first_step <= 3 && dv_height == hdr_info.height {
+        let bars = match memo_letterbox {
+            Some(crop) => Ok(Some((crop.top, crop.bottom))),
+            None => detect_letterbox_bars(app, &ffmpeg, input_hdr, &hdr_info, &work_dir),
+        };
+        match bars {
+            Ok(Some((top, bottom))) => {
+                crop_amount = top;
+                active_area_bottom = bottom;
+                detected_bars = Some((top, bottom));
+            }
+            Ok(None) => {}
+            Err(err) => {
+                emit_log(
+                    app,
+                    "warning",
+                    format!("Letterbox detection failed, leaving the active area alone: {}", err),
+                );
+            }
+        }
+    }
+
+    let delay_plan = dv_delay_plan(dv_delay_ms, hdr_info.fps);
+    let dv_delay_frames = delay_plan.frames;
+    let mut dv_remove_frames = delay_plan.remove.clone().unwrap_or_default();
+    let mut dv_duplicate_length = delay_plan.duplicate;
+    if dv_delay_ms.abs() > f64::EPSILON {
+        emit_log(
+            app,
+            "info",
+            format!("Dolby Vision delay: {} frames", dv_delay_frames),
+        );
+    }
+    let mut rpu_tail = if options.rpu_source == RpuSource::HdrFile {
+        None
+    } else {
+        check_frame_counts(app, options, &hdr_info, &dv_info, &delay_plan)?
+    };
+
+    let queue_ctx = queue_id.map(|id| QueueContext {
+        id: id.to_string(),
+        label: queue_label.map(|label| label.to_string()),
+        file_index: queue_file_index,
+        file_total: queue_file_total,
+        tracker: queue_tracker,
+        active_workers: queue_active_workers,
+        file_id: Some(format!("{}:{}", id, queue_file_index)),
+        file_name: queue_file_name.map(|name| name.to_string()),
+        attempt: state
+            .job_progress
+            .lock()
+            .map(|job| job.attempt(job_file))
+            .unwrap_or(1),
+    });
+
+    if let Some(ctx) = &queue_ctx {
+        update_queue_file(app, ctx, ctx.label.clone(), |slot| {
+            *slot = FileSlot {
+                progress: 0,
+                state: FileState::Active,
+            };
+        });
+
+        if let (Some(file_id), Some(file_name)) = (&ctx.file_id, &ctx.file_name) {
+            emit_file(
+                app,
+                FilePayload {
+                    id: file_id.clone(),
+                    queue_id: ctx.id.clone(),
+                    name: file_name.clone(),
+                    status: "processing".to_string(),
+                    progress: 0,
+                    attempt: ctx.attempt,
+                    reason: None,
+                    hashes: Vec::new(),
+                },
+            );
+        }
+    }
+
+    let mut journal = StepJournal::open(app, output_path, options.resume);
+    if options.resume && journal.completed(VERIFIED_ENTRY.0, &mux_artifact(output_path, options)) {
+        emit_log(
+            app,
+            "info",
+            format!(
+                "Skipping the mux of {}: journal shows it was already muxed and verified",
+                output_path.display()
+            ),
+        );
+        let (sidecars, warnings) = finish_output(app, state, options, output_path)?;
+        journal.remove();
+        let status = if warnings.is_empty() { "completed" } else { "completed_with_warnings" };
+        emit_file_finished(app, queue_ctx.as_ref(), status, sidecars);
+        return Ok(());
+    }
+
+    // Every intermediate already lives next to the output, but read-only
+    // sources (discs, mounted images) are staged anyway so no tool can trip
+    // over them.
+    let read_only_inputs: Vec<&Path> = [input_hdr, input_dv]
+        .into_iter()
+        .filter(|input| is_read_only(input))
+        .collect();
+    let staging_dir = staging_dir_for(options, resume_mux, &read_only_inputs, &work_dir);
+    if options.staging_dir.is_none() && staging_dir.is_some() {
+        for input in &read_only_inputs {
+            emit_log(
+                app,
+                "warning",
+                format!("{} is read-only; staging inputs into {}", input.display(), work_dir.display()),
+            );
+        }
+    }
+
+    // The user's path, for anything reported after the staged copy is gone.
+    let source_hdr = input_hdr;
+    let source_dv = input_dv;
+    let mut staged = None;
+    let (input_hdr, input_dv) = match &staging_dir {
+        Some(staging_dir) => {
+            let prefix = output_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "input".to_string());
+            // A DV-only run reads one file as both inputs; stage it once.
+            let sources = if input_hdr == input_dv { vec![input_hdr] } else { vec![input_hdr, input_dv] };
+            let copies = staged.insert(stage_inputs(
+                app,
+                state,
+                &sources,
+                staging_dir,
+                &prefix,
+                options.stage_link,
+            )?);
+            temp_files.extend(copies.paths().iter().cloned());
+            let paths = copies.paths();
+            (paths[0].as_path(), paths[paths.len() - 1].as_path())
+        }
+        None => {
+            emit_step(app, STAGE_STEP_ID, STAGE_STEP_NAME, "completed", 100);
+            (input_hdr, input_dv)
+        }
+    };
+
+    let mut dv_extract_cmd = None;
+    let mut dv_extract_output = dv_hevc.clone();
+    let mut dv_hevc_path = dv_hevc.clone();
+    if is_hevc_file(input_dv) && is_hevc_format(&dv_info) {
+        dv_hevc_path = input_dv.to_path_buf();
+        dv_extract_output = input_dv.to_path_buf();
+    } else {
+        let dv_track = match options.dv_video_track {
+            None if is_mp4_container(input_dv) && options.rpu_source != RpuSource::HdrFile => {
+                Some(select_mp4_dv_track(app, input_dv, &dv_info)?)
+            }
+            requested => select_video_track(app, &tools, input_dv, &dv_info, requested, "DV input")?,
+        };
+        dv_extract_cmd = Some(build_demux_command(
+            &mkvextract,
+            &mp4box,
+            input_dv,
+            &dv_hevc,
+            dv_track,
+            &work_dir,
+            &options.mkv_ui_language,
+        )?);
+    }
+
+    let mut hdr_extract_cmd = None;
+    let mut hdr_extract_output = hdr10_hevc.clone();
+    let mut hdr_hevc_path = hdr10_hevc.clone();
+    if let Some(base) = &base_hevc {
+        hdr_hevc_path = base.clone();
+        hdr_extract_output = base.clone();
+    } else if options.dv_only {
+        // dovi_tool replaces the RPU already in the DV stream when injecting.
+        hdr_hevc_path = dv_hevc_path.clone();
+        hdr_extract_output = dv_extract_output.clone();
+    } else if is_hevc_file(input_hdr) && is_hevc_format(&hdr_info) {
+        hdr_hevc_path = input_hdr.to_path_buf();
+        hdr_extract_output = input_hdr.to_path_buf();
+    } else {
+        let hdr_track =
+            select_video_track(app, &tools, input_hdr, &hdr_info, options.hdr_video_track, "HDR input")?;
+        hdr_extract_cmd = Some(build_demux_command(
+            &mkvextract,
+            &mp4box,
+            input_hdr,
+            &hdr10_hevc,
+            hdr_track,
+            &work_dir,
+            &options.mkv_ui_language,
+        )?);
+    }
+
+    let replace_video = options.remux_strategy == RemuxStrategy::ReplaceVideo;
+    let mut cmd0 = mkvtoolnix_command(&mkvmerge, &work_dir, &options.mkv_ui_language);
+    cmd0
+        .arg("-o")
+        .arg(&audio_loc)
+        .arg("--no-video");
+    if !replace_video {
+        if let Some(selection) =
+            select_subtitle_tracks(app, &tools, input_hdr, options.subtitle_filter)?
+        {
+            cmd0.args(selection);
+        }
+    }
+    cmd0.arg(input_hdr);
+
+    // Plan the tool runs this file will take; elementary inputs need no
+    // extraction and the mux always runs once the earlier steps are done.
+    let runs = |step: usize| first_step <= step && step <= last_step;
+    let rpu_needs_hdr = match options.rpu_source {
+        RpuSource::DvFile => false,
+        RpuSource::HdrFile => true,
+        RpuSource::Best => hdr_info.has_dolby_vision(),
+    };
+    let likely_rpu_edit = crop_amount > 0
+        || active_area_bottom > 0
+        || !dv_remove_frames.is_empty()
+        || dv_duplicate_length > 0
+        || rpu_tail.is_some();
+    let planned_steps = if resume_mux {
+        1
+    } else {
+        [
+            runs(1) && !replace_video,
+            runs(1) && !replace_video && options.audio_transcode.is_some(),
+            runs(2) && dv_extract_cmd.is_some() && options.rpu_source != RpuSource::HdrFile,
+            runs(3),
+            runs(3) && options.rpu_source == RpuSource::Best && hdr_info.has_dolby_vision(),
+            runs(3) && likely_rpu_edit,
+            hdr_extract_cmd.is_some() && ((runs(3) && rpu_needs_hdr) || runs(4)),
+            last_step >= 5 && pad_hdr_to.is_some(),
+            last_step >= 5,
+            last_step == STEP_NAMES.len(),
+        ]
+        .into_iter()
+        .filter(|runs| *runs)
+        .count()
+    };
+    let plan = Arc::new(StepPlan::new(planned_steps));
+    // Problems found along the way; mkvmerge's own warnings are kept apart
+    // in `mkvmerge_warnings`.
+    let mut warnings: Vec<String> = Vec::new();
+    warnings.extend(chapter_warning);
+    let mut mkvmerge_warnings = StepWarnings::default();
+
+    let mut mux_audio = audio_loc.clone();
+    let mut audio_lane = None;
+    if resume_mux {
+        mux_audio = check_mux_intermediates(app, options, replace_video, &dv_hdr, &audio_loc, &transcoded_audio)?;
+    } else {
+        if first_step > 1 {
+            if options.audio_transcode.is_some() && !replace_video {
+                mux_audio = transcoded_audio.clone();
+            }
+            check_start_intermediates(
+                app,
+                options,
+                (first_step, last_step),
+                (!replace_video).then_some(mux_audio.as_path()),
+                &dv_hevc_path,
+                &rpu_bin,
+                &hdr_hevc_path,
+            )?;
+        } else if replace_video {
+            emit_step(app, 1, STEP_NAMES[0], "completed", 100);
+            emit_log(
+                app,
+                "info",
+                "Replacing only the video track: every other track, chapter, tag and attachment of the HDR source is kept",
+            );
+            if options.audio_transcode.is_some() {
+                emit_step(app, TRANSCODE_STEP.0, TRANSCODE_STEP.1, "skipped", 0);
+                emit_log(
+                    app,
+                    "warning",
+                    "Audio transcoding is ignored when only the video track is replaced",
+                );
+            }
+        } else if journal.completed(0, &audio_loc) {
+            skip_journaled_step(app, &plan, 1, STEP_NAMES[0], &audio_loc);
+        } else if options.parallel_steps && last_step == STEP_NAMES.len() {
+            // The audio lane reads only the HDR input and writes only the
+            // audio intermediates, so it can run beside the video steps.
+            emit_log(app, "info", "Extracting audio and subtitles alongside the video steps");
+            let transcode = options.audio_transcode.clone().map(|transcode| {
+                temp_files.push(transcoded_audio.clone());
+                (transcode, transcoded_audio.clone())
+            });
+            let (lane_app, lane_plan) = (app.clone(), Arc::clone(&plan));
+            let (lane_ctx, lane_job) = (queue_ctx.clone(), job_file.to_string());
+            let (lane_input, lane_audio) = (input_hdr.to_path_buf(), audio_loc.clone());
+            let (lane_ffmpeg, lane_mkvmerge, lane_work_dir) = (ffmpeg.clone(), mkvmerge.clone(), work_dir.clone());
+            audio_lane = Some(AudioLane::spawn(state, move |lane_state| {
+                let warnings = run_command(
+                    &lane_state,
+                    cmd0,
+                    &lane_app,
+                    1,
+                    STEP_NAMES[0],
+                    &lane_input,
+                    &lane_audio,
+                    true,
+                    &lane_plan,
+                    lane_ctx.as_ref(),
+                    &lane_job,
+                    ExitCodePolicy::Mkvmerge,
+                )?;
+                let Some((transcode, transcoded)) = transcode else {
+                    return Ok((warnings, None));
+                };
+                let transcode_cmd = build_audio_transcode_command(
+                    &lane_app,
+                    &lane_ffmpeg,
+                    &lane_mkvmerge,
+                    &lane_audio,
+                    &transcoded,
+                    &transcode,
+                    &lane_work_dir,
+                )?;
+                run_command(
+                    &lane_state,
+                    transcode_cmd,
+                    &lane_app,
+                    TRANSCODE_STEP.0,
+                    TRANSCODE_STEP.1,
+                    &lane_audio,
+                    &transcoded,
+                    false,
+                    &lane_plan,
+                    lane_ctx.as_ref(),
+                    &lane_job,
+                    ExitCodePolicy::Strict,
+                )?;
+                Ok((warnings, Some(transcoded)))
+            }));
+        } else {
+            mkvmerge_warnings.extend(run_command(
+                state,
+                cmd0,
+                app,
+                1,
+                STEP_NAMES[0],
+                input_hdr,
+                &audio_loc,
+                true,
+                &plan,
+                queue_ctx.as_ref(),
+                job_file,
+                ExitCodePolicy::Mkvmerge,
+            )?);
+            journal.record(0, STEP_NAMES[0], &audio_loc);
+        }
+
+        if let Some(transcode) = options
+            .audio_transcode
+            .as_ref()
+            .filter(|_| !replace_video && first_step == 1 && audio_lane.is_none())
+        {
+            let transcoded = transcoded_audio.clone();
+            let transcode_cmd = build_audio_transcode_command(
+                app,
+                &ffmpeg,
+                &mkvmerge,
+                &audio_loc,
+                &transcoded,
+                transcode,
+                &work_dir,
+            )?;
+            run_command(
+                state,
+                transcode_cmd,
+                app,
+                TRANSCODE_STEP.0,
+                TRANSCODE_STEP.1,
+                &audio_loc,
+                &transcoded,
+                false,
+                &plan,
+                queue_ctx.as_ref(),
+                job_file,
+                ExitCodePolicy::Strict,
+            )?;
+            temp_files.push(transcoded.clone());
+            mux_audio = transcoded;
+        } else if options.audio_transcode.is_none() {
+            emit_step(app, TRANSCODE_STEP.0, TRANSCODE_STEP.1, "completed", 100);
+        }
+        if last_step == 1 {
+            return finish_partial(app, queue_ctx.as_ref(), last_step, &temp_files);
+        }
+
+        let run_hdr_extract = |command: Option<Command>, journal: &mut StepJournal| -> Result<(), String> {
+            let Some(command) = command else {
+                // Elementary HEVC input, prepared base or the DV file's own
+                // base layer: nothing to extract.
+                let status = if options.dv_only { "skipped" } else { "completed" };
+                emit_step(app, 4, STEP_NAMES[3], status, 100);
+                journal.record(3, STEP_NAMES[3], &hdr_extract_output);
+                return Ok(());
+            };
+            if journal.completed(3, &hdr_extract_output) {
+                skip_journaled_step(app, &plan, 4, STEP_NAMES[3], &hdr_extract_output);
+                return Ok(());
+            }
+            run_command(
+                state,
+                command,
+                app,
+                4,
+                STEP_NAMES[3],
+                input_hdr,
+                &hdr_extract_output,
+                true,
+                &plan,
+                queue_ctx.as_ref(),
+                job_file,
+                ExitCodePolicy::Strict,
+            )?;
+            journal.record(3, STEP_NAMES[3], &hdr_extract_output);
+            Ok(())
+        };
+        let run_rpu_extract = |hevc: &Path,
+                               rpu_out: &Path,
+                               step_name: &str,
+                               journal: &mut StepJournal|
+         -> Result<(), String> {
+            if journal.completed(2, rpu_out) {
+                skip_journaled_step(app, &plan, 3, step_name, rpu_out);
+                return Ok(());
+            }
+            run_command(
+                state,
+                build_rpu_extract_command(&dovi_tool, hevc, rpu_out, &work_dir),
+                app,
+                3,
+                step_name,
+                hevc,
+                rpu_out,
+                false,
+                &plan,
+                queue_ctx.as_ref(),
+                job_file,
+                ExitCodePolicy::Strict,
+            )?;
+            journal.record(2, step_name, rpu_out);
+            Ok(())
+        };
+        let mut hdr_extract = Some(hdr_extract_cmd);
+
+        let rpu_path = if first_step > 3 {
+            options.rpu_path.clone().unwrap_or_else(|| rpu_bin.clone())
+        } else {
+            let rpu_source = match options.rpu_source {
+                RpuSource::DvFile => RpuSource::DvFile,
+                RpuSource::HdrFile if hdr_info.has_dolby_vision() => RpuSource::HdrFile,
+                RpuSource::HdrFile => {
+                    return Err(validation_error(format!(
+                        "RPU source is the HDR file, but {} has no Dolby Vision metadata",
+                        input_hdr.display()
+                    )));
+                }
+                RpuSource::Best if hdr_info.has_dolby_vision() => RpuSource::Best,
+                RpuSource::Best => {
+                    emit_log(
+                        app,
+                        "info",
+                        "HDR source has no Dolby Vision metadata; using the RPU from the DV file",
+                    );
+                    RpuSource::DvFile
+                }
+            };
+
+            if rpu_source == RpuSource::HdrFile {
+                emit_step(app, 2, STEP_NAMES[1], "completed", 100);
+            } else if first_step > 2 {
+                emit_step(app, 2, STEP_NAMES[1], "skipped", 0);
+            } else if dv_extract_cmd.is_none() {
+                // Elementary HEVC input: nothing to extract.
+                emit_step(app, 2, STEP_NAMES[1], "completed", 100);
+                journal.record(1, STEP_NAMES[1], &dv_extract_output);
+            } else if journal.completed(1, &dv_extract_output) {
+                skip_journaled_step(app, &plan, 2, STEP_NAMES[1], &dv_extract_output);
+            } else if let Some(command) = dv_extract_cmd {
+                run_command(
+                    state,
+                    command,
+                    app,
+                    2,
+                    STEP_NAMES[1],
+                    input_dv,
+                    &dv_extract_output,
+                    true,
+                    &plan,
+                    queue_ctx.as_ref(),
+                    job_file,
+                    ExitCodePolicy::Strict,
+                )?;
+                journal.record(1, STEP_NAMES[1], &dv_extract_output);
+            }
+            if last_step == 2 {
+                return finish_partial(app, queue_ctx.as_ref(), last_step, &temp_files);
+            }
+
+            // The RPU has to come out of the HDR10 stream, so extract that first.
+            if rpu_source != RpuSource::DvFile {
+                if let Some(command) = hdr_extract.take() {
+                    run_hdr_extract(command, &mut journal)?;
+                }
+            }
+
+            let fel_candidates = match rpu_source {
+                RpuSource::DvFile => vec![("DV file", &dv_hevc_path, &dv_info)],
+                RpuSource::HdrFile => vec![("HDR file", &hdr_hevc_path, &hdr_info)],
+                RpuSource::Best => vec![
+                    ("DV file", &dv_hevc_path, &dv_info),
+                    ("HDR file", &hdr_hevc_path, &hdr_info),
+                ],
+            };
+            for (label, hevc, info) in fel_candidates {
+                if info.dolby_vision_profile() != Some(7) && !info.has_enhancement_layer() {
+                    continue;
+                }
+                let probe_rpu = PathBuf::from(format!("{}_el_probe.bin", output_base));
+                temp_files.push(probe_rpu.clone());
+                let enhancement = detect_enhancement_layer(&dovi_tool, hevc, &probe_rpu, &work_dir)?;
+                check_fel_policy(app, options.fel_policy, label, enhancement.as_deref())?;
+            }
+
+            let mut rpu_from_hdr = false;
+            match rpu_source {
+                RpuSource::HdrFile => {
+                    run_rpu_extract(&hdr_hevc_path, &rpu_bin, STEP_NAMES[2], &mut journal)?;
+                    rpu_from_hdr = true;
+                }
+                RpuSource::DvFile => {
+                    run_rpu_extract(&dv_hevc_path, &rpu_bin, STEP_NAMES[2], &mut journal)?;
+                }
+                RpuSource::Best => {
+                    let hdr_rpu_bin = PathBuf::from(format!("{}_hdr_rpu.bin", output_base));
+                    temp_files.push(hdr_rpu_bin.clone());
+                    run_rpu_extract(&dv_hevc_path, &rpu_bin, STEP_NAMES[2], &mut journal)?;
+                    run_rpu_extract(
+                        &hdr_hevc_path,
+                        &hdr_rpu_bin,
+                        "Extract RPU Data (HDR source)",
+                        &mut journal,
+                    )?;
+
+                    let dv_summary = rpu_summary(&dovi_tool, &rpu_bin, &work_dir)?;
+                    let hdr_summary = rpu_summary(&dovi_tool, &hdr_rpu_bin, &work_dir)?;
+                    emit_log(
+                        app,
+                        "info",
+                        format!("RPU candidates - DV file: {} | HDR file: {}", dv_summary, hdr_summary),
+                    );
+                    if hdr_summary.fidelity() > dv_summary.fidelity() {
+                        fs::copy(&hdr_rpu_bin, &rpu_bin).map_err(|e| e.to_string())?;
+                        rpu_from_hdr = true;
+                    }
+                }
+            }
+            emit_log(
+                app,
                 "info",
                 format!(
-                    "Letterboxing needed - {} | HDR: {} | DV: {}",
-                    crop_amount, hdr_info.height, dv_info.height
+                    "Using the RPU from the {} ({})",
+                    if rpu_from_hdr { "HDR file" } else { "DV file" },
+                    if rpu_from_hdr { input_hdr.display() } else { input_dv.display() }
+                ),
+            );
+
+            // An RPU taken from the HDR source already matches its geometry and timing.
+            if rpu_from_hdr && (crop_amount > 0 || active_area_bottom > 0 || dv_delay_frames > 0 || rpu_tail.is_some()) {
+                emit_log(
+                    app,
+                    "info",
+                    "Skipping padding, active area and delay edits: the RPU already belongs to the HDR source",
+                );
+                crop_amount = 0;
+                active_area_bottom = 0;
+                pad_hdr_to = None;
+                dv_remove_frames.clear();
+                dv_duplicate_length = 0;
+                rpu_tail = None;
+            }
+
+            let has_active_area = crop_amount > 0 || active_area_bottom > 0;
+            let mut apply_active_area = has_active_area;
+            if has_active_area {
+                match options.active_area_mode {
+                    ActiveAreaMode::Override => {
+                        emit_log(
+                            app,
+                            "info",
+                            format!(
+                                "Active area: override requested, applying computed offsets top/bottom {}",
+                                crop_amount
+                            ),
+                        );
+                    }
+                    ActiveAreaMode::Auto => {
+                        let l5_export = PathBuf::from(format!("{}_rpu_l5.json", output_base));
+                        let existing = read_rpu_level5(&dovi_tool, &rpu_bin, &l5_export, &work_dir);
+                        temp_files.push(l5_export);
+                        match existing {
+                            Ok(Some(presets)) => {
+                                apply_active_area = false;
+                                let summary = presets
+                                    .iter()
+                                    .map(|p| format!("top {} / bottom {} / left {} / right {}", p.top, p.bottom, p.left, p.right))
+                                    .collect::<Vec<String>>()
+                                    .join("; ");
+                                emit_log(
+                                    app,
+                                    "info",
+                                    format!(
+                                        "Active area: keeping existing RPU L5 metadata ({}); computed offset {} not applied",
+                                        summary, crop_amount
+                                    ),
+                                );
+                            }
+                            Ok(None) => {
+                                emit_log(
+                                    app,
+                                    "info",
+                                    format!(
+                                        "Active area: RPU has no L5 data, applying computed offsets top/bottom {}",
+                                        crop_amount
+                                    ),
+                                );
+                            }
+                            Err(err) => {
+                                emit_log(
+                                    app,
+                                    "warning",
+                                    format!(
+                                        "Active area: could not read RPU L5 metadata ({}); applying computed offsets top/bottom {}",
+                                        err, crop_amount
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+
+            let mut rpu_path = rpu_bin.clone();
+            let needs_rpu_edit =
+                apply_active_area || !dv_remove_frames.is_empty() || dv_duplicate_length > 0 || rpu_tail.is_some();
+            match (likely_rpu_edit, needs_rpu_edit) {
+                (false, true) => plan.add(1),
+                (true, false) => plan.skip(),
+                _ => {}
+            }
+            if needs_rpu_edit {
+                let rpu_json_path = PathBuf::from(format!("{}_rpu.json", output_base));
+                let rpu_edited = PathBuf::from(format!("{}_rpu_edited.bin", output_base));
+                // Removals count frames of the extracted RPU; duplicates run
+                // in order afterwards, so the tail goes in before the delay
+                // shifts every frame.
+                let mut remove = Vec::new();
+                let mut duplicate = Vec::new();
+                if !dv_remove_frames.is_empty() {
+                    remove.push(json!(dv_remove_frames));
+                }
+                match rpu_tail {
+                    Some(RpuTail::Remove { first, last }) => remove.push(json!(format!("{}-{}", first, last))),
+                    Some(RpuTail::Extend { source, length }) => duplicate.push(json!({
+                        "source": source,
+                        "offset": source + 1,
+                        "length": length
+                    })),
+                    None => {}
+                }
+                if dv_duplicate_length > 0 {
+                    duplicate.push(json!({
+                        "source": 0,
+                        "offset": 0,
+                        "length": dv_duplicate_length
+                    }));
+                }
+                let mut rpu_json = json!({
+                    "remove": remove,
+                    "duplicate": duplicate
+                });
+                if apply_active_area {
+                    rpu_json["active_area"] = json!({
+                        "crop": crop,
+                        "presets": [{
+                            "id": 0,
+                            "left": 0,
+                            "right": 0,
+                            "top": crop_amount,
+                            "bottom": active_area_bottom
+                        }]
+                    });
+                }
+
+                fs::write(&rpu_json_path, serde_json::to_vec_pretty(&rpu_json).map_err(|e| e.to_string())?)
+                    .map_err(|e| e.to_string())?;
+
+                let mut edits = Vec::new();
+                if apply_active_area {
+                    edits.push(format!(
+                        "active area top {} / bottom {} ({})",
+                        crop_amount,
+                        active_area_bottom,
+                        if crop { "crop" } else { "letterbox" }
+                    ));
+                }
+                if !dv_remove_frames.is_empty() {
+                    edits.push(format!("remove frames {}", dv_remove_frames));
+                }
+                if dv_duplicate_length > 0 {
+                    edits.push(format!("duplicate first frame {} time(s)", dv_duplicate_length));
+                }
+                match rpu_tail {
+                    Some(RpuTail::Remove { first, last }) => {
+                        edits.push(format!("remove trailing frames {}-{}", first, last));
+                    }
+                    Some(RpuTail::Extend { length, .. }) => {
+                        edits.push(format!("repeat last frame {} time(s)", length));
+                    }
+                    None => {}
+                }
+                let edits = edits.join(", ");
+                emit_log(app, "info", format!("Editing RPU metadata: {}", edits));
+
+                let mut rpu_edit_cmd = tool_command(&dovi_tool, &work_dir);
+                rpu_edit_cmd
+                    .arg("editor")
+                    .arg("-i")
+                    .arg(&rpu_path)
+                    .arg("-o")
+                    .arg(&rpu_edited)
+                    .arg("-j")
+                    .arg(&rpu_json_path);
+                run_command(
+                    state,
+                    rpu_edit_cmd,
+                    app,
+                    RPU_EDIT_STEP_ID,
+                    RPU_EDIT_STEP_NAME,
+                    &rpu_path,
+                    &rpu_edited,
+                    false,
+                    &plan,
+                    queue_ctx.as_ref(),
+                    job_file,
+                    ExitCodePolicy::Strict,
+                )?;
+                emit_log(app, "success", format!("RPU edits applied: {}", edits));
+                rpu_path = rpu_edited.clone();
+                temp_files.push(rpu_json_path);
+                temp_files.push(rpu_edited);
+            } else {
+                emit_step(app, RPU_EDIT_STEP_ID, RPU_EDIT_STEP_NAME, "completed", 100);
+            }
+            if last_step == 3 {
+                return finish_partial(app, queue_ctx.as_ref(), last_step, &temp_files);
+            }
+            rpu_path
+        };
+
+        if first_step > 4 {
+            if let Some(hdr10_hevc) = &options.hdr10_hevc_path {
+                hdr_hevc_path = hdr10_hevc.clone();
+            }
+        } else if let Some(command) = hdr_extract.take() {
+            run_hdr_extract(command, &mut journal)?;
+        }
+        if last_step == 4 {
+            return finish_partial(app, queue_ctx.as_ref(), last_step, &temp_files);
+        }
+
+        let mut hdr10_for_dv = hdr_hevc_path.clone();
+        if let Some((pad_top, target_height)) = pad_hdr_to {
+            let padded = PathBuf::from(format!("{}_hdr10_padded.hevc", output_base));
+            emit_log(
+                app,
+                "warning",
+                format!(
+                    "HDR10 base ({}px) is shorter than DV ({}px): re-encoding with {}px top / {}px bottom padding",
+                    hdr_info.height,
+                    target_height,
+                    pad_top,
+                    target_height - hdr_info.height - pad_top
+                ),
+            );
+            if hdr_info.mastering.x265_master_display().is_none() {
+                emit_log(
+                    app,
+                    "warning",
+                    "HDR10 base reports no mastering display metadata; the padded encode carries none",
+                );
+            }
+            let mut pad_cmd = tool_command(&ffmpeg, &work_dir);
+            pad_cmd
+                .arg("-hide_banner")
+                .arg("-nostdin")
+                .arg("-y")
+                .arg("-i")
+                .arg(&hdr10_for_dv)
+                .arg("-vf")
+                .arg(format!("pad=iw:{}:0:{}:black", target_height, pad_top))
+                .arg("-c:v")
+                .arg("libx265")
+                .arg("-pix_fmt")
+                .arg("yuv420p10le")
+                .arg("-crf")
+                .arg("14")
+                .arg("-x265-params")
+                .arg(pad_x265_params(&hdr_info))
+                .arg(&padded);
+            run_command(
+                state,
+                pad_cmd,
+                app,
+                PAD_STEP.0,
+                PAD_STEP.1,
+                &hdr10_for_dv,
+                &padded,
+                true,
+                &plan,
+                queue_ctx.as_ref(),
+                job_file,
+                ExitCodePolicy::Strict,
+            )?;
+            temp_files.push(padded.clone());
+
+            let padded_info = state.probe.probe(&tools, &padded)?;
+            emit_log(
+                app,
+                "info",
+                format!(
+                    "Padded HDR10 base: {}x{} | DV: {}x{}",
+                    padded_info.width, padded_info.height, dv_info.width, dv_info.height
                 ),
             );
+            if padded_info.height != dv_info.height {
+                return Err(format!(
+                    "Padded HDR10 height {} does not match DV height {}",
+                    padded_info.height, dv_info.height
+                ));
+            }
+            hdr10_for_dv = padded;
         } else {
-            crop = true;
-            crop_amount = (hdr_info.height - dv_info.height) / 2;
+            emit_step(app, PAD_STEP.0, PAD_STEP.1, "completed", 100);
+        }
+        let donor = match hdr10plus_path.filter(|source| !source.as_os_str().is_empty()) {
+            Some(source) if hdr10plus_present(app, state, &tools, source)? => Some(source),
+            _ => None,
+        };
+        let hdr10plus_source = select_hdr10plus_source(
+            app,
+            state,
+            &tools,
+            options.hdr10plus_source,
+            &hdr_info,
+            donor,
+        )?;
+        if let Some(hdr10plus_source) = hdr10plus_source {
+            // The inject, plus the extract for a video donor.
+            plan.add(if is_json_file(hdr10plus_source) { 1 } else { 2 });
+            let hdr10plus_tool =
+                require_tool(app, &tool_paths.hdr10plus_tool, "hdr10plus_tool", "HDR10+ metadata")?;
+            let (hdr10plus_metadata, hdr10plus_fps) = if is_json_file(hdr10plus_source) {
+                emit_log(
+                    app,
+                    "info",
+                    format!(
+                        "Using pre-extracted HDR10+ metadata: {} (frame rate unknown, assuming the base {:.3} fps)",
+                        hdr10plus_source.display(),
+                        hdr_info.fps
+                    ),
+                );
+                for (step_id, step_name) in [HDR10PLUS_DEMUX_STEP, HDR10PLUS_EXTRACT_STEP] {
+                    emit_step(app, step_id, step_name, "completed", 100);
+                }
+                (hdr10plus_source.to_path_buf(), hdr_info.fps)
+            } else {
+                emit_log(app, "info", "Extracting HDR10+ metadata...");
+                let hdr10plus_info = state.probe.probe(&tools, hdr10plus_source)?;
+                let mut hdr10plus_hevc_path = hdr10plus_source.to_path_buf();
+
+                if !(is_hevc_file(hdr10plus_source) && is_hevc_format(&hdr10plus_info)) {
+                    let hdr10plus_demux = PathBuf::from(format!("{}_hdr10plus.hevc", output_base));
+                    let hdr10plus_track =
+                        select_video_track(app, &tools, hdr10plus_source, &hdr10plus_info, None, "HDR10+ donor")?;
+                    let demux_cmd = build_demux_command(
+                        &mkvextract,
+                        &mp4box,
+                        hdr10plus_source,
+                        &hdr10plus_demux,
+                        hdr10plus_track,
+                        &work_dir,
+                        &options.mkv_ui_language,
+                    )?;
+                    temp_files.push(hdr10plus_demux.clone());
+                    plan.add(1);
+                    run_command(
+                        state,
+                        demux_cmd,
+                        app,
+                        HDR10PLUS_DEMUX_STEP.0,
+                        HDR10PLUS_DEMUX_STEP.1,
+                        hdr10plus_source,
+                        &hdr10plus_demux,
+                        true,
+                        &plan,
+                        queue_ctx.as_ref(),
+                        job_file,
+                        ExitCodePolicy::Strict,
+                    )?;
+                    hdr10plus_hevc_path = hdr10plus_demux;
+                } else {
+                    let (step_id, step_name) = HDR10PLUS_DEMUX_STEP;
+                    emit_step(app, step_id, step_name, "completed", 100);
+                }
+
+                let hdr10plus_metadata = PathBuf::from(format!("{}_hdr10plus.json", output_base));
+                let mut hdr10plus_extract_cmd = tool_command(&hdr10plus_tool, &work_dir);
+                hdr10plus_extract_cmd
+                    .arg("extract")
+                    .arg(&hdr10plus_hevc_path)
+                    .arg("-o")
+                    .arg(&hdr10plus_metadata);
+                temp_files.push(hdr10plus_metadata.clone());
+                run_command(
+                    state,
+                    hdr10plus_extract_cmd,
+                    app,
+                    HDR10PLUS_EXTRACT_STEP.0,
+                    HDR10PLUS_EXTRACT_STEP.1,
+                    &hdr10plus_hevc_path,
+                    &hdr10plus_metadata,
+                    false,
+                    &plan,
+                    queue_ctx.as_ref(),
+                    job_file,
+                    ExitCodePolicy::Strict,
+                )?;
+                (hdr10plus_metadata, hdr10plus_info.fps)
+            };
+
+            if (hdr10plus_fps - hdr_info.fps).abs() > HDR10PLUS_FPS_TOLERANCE {
+                return Err(validation_error(format!(
+                    "HDR10+ donor runs at {:.3} fps but the base HDR stream at {:.3} fps; its per-frame metadata would drift",
+                    hdr10plus_fps, hdr_info.fps
+                )));
+            }
+
+            let mut hdr10plus_metadata_path = hdr10plus_metadata.clone();
+            if hdr10plus_delay_ms.abs() > f64::EPSILON {
+                let hdr10plus_delay_frames = delay_to_frames(hdr10plus_delay_ms, hdr10plus_fps);
+                let mut hdr10plus_remove_frames = String::new();
+                let mut hdr10plus_duplicate_length = 0u32;
+
+                if hdr10plus_delay_ms < 0.0 && hdr10plus_delay_frames > 0 {
+                    hdr10plus_remove_frames = format!("0-{}", hdr10plus_delay_frames - 1);
+                } else if hdr10plus_delay_ms > 0.0 {
+                    hdr10plus_duplicate_length = hdr10plus_delay_frames;
+                }
+
+                if !hdr10plus_remove_frames.is_empty() || hdr10plus_duplicate_length > 0 {
+                    let hdr10plus_edits = PathBuf::from(format!("{}_hdr10plus_edits.json", output_base));
+                    let hdr10plus_edited = PathBuf::from(format!("{}_hdr10plus_edited.json", output_base));
+                    let edits_json = json!({
+                        "remove": [hdr10plus_remove_frames],
+                        "duplicate": [{
+                            "source": 0,
+                            "offset": 0,
+                            "length": hdr10plus_duplicate_length
+                        }]
+                    });
+                    fs::write(&hdr10plus_edits, serde_json::to_vec_pretty(&edits_json).map_err(|e| e.to_string())?)
+                        .map_err(|e| e.to_string())?;
+
+                    let mut hdr10plus_edit_cmd = tool_command(&hdr10plus_tool, &work_dir);
+                    hdr10plus_edit_cmd
+                        .arg("editor")
+                        .arg(&hdr10plus_metadata)
+                        .arg("-j")
+                        .arg(&hdr10plus_edits)
+                        .arg("-o")
+                        .arg(&hdr10plus_edited);
+                    temp_files.push(hdr10plus_edits);
+                    plan.add(1);
+                    run_command(
+                        state,
+                        hdr10plus_edit_cmd,
+                        app,
+                        HDR10PLUS_EDIT_STEP.0,
+                        HDR10PLUS_EDIT_STEP.1,
+                        &hdr10plus_metadata,
+                        &hdr10plus_edited,
+                        false,
+                        &plan,
+                        queue_ctx.as_ref(),
+                        job_file,
+                        ExitCodePolicy::Strict,
+                    )?;
+                    hdr10plus_metadata_path = hdr10plus_edited.clone();
+                    temp_files.push(hdr10plus_edited);
+                }
+            }
+
+            let donor_frames = hdr10plus_frame_count(&hdr10plus_metadata_path)?;
+            match hdr_info.frame_count {
+                Some(base_frames) => {
+                    emit_log(
+                        app,
+                        "info",
+                        format!(
+                            "HDR10+ donor: {} frames @ {:.3} fps; base: {} frames @ {:.3} fps",
+                            donor_frames, hdr10plus_fps, base_frames, hdr_info.fps
+                        ),
+                    );
+                    if donor_frames != base_frames {
+                        let difference = donor_frames.abs_diff(base_frames);
+                        let tolerance = (hdr_info.fps * HDR10PLUS_TRAILING_TOLERANCE_SECS).ceil() as u64;
+                        if difference > tolerance || donor_frames == 0 {
+                            return Err(validation_error(format!(
+                                "HDR10+ donor has {} frames but the base HDR stream has {} ({} apart, more than the {} trailing frames that are adjusted automatically)",
+                                donor_frames, base_frames, difference, tolerance
+                            )));
+                        }
+
+                        let edits_json = if donor_frames > base_frames {
+                            json!({ "remove": [format!("{}-{}", base_frames, donor_frames - 1)] })
+                        } else {
+                            json!({ "duplicate": [{
+                                "source": donor_frames - 1,
+                                "offset": donor_frames,
+                                "length": difference
+                            }] })
+                        };
+                        let trim_edits = PathBuf::from(format!("{}_hdr10plus_trim.json", output_base));
+                        let trimmed = PathBuf::from(format!("{}_hdr10plus_trimmed.json", output_base));
+                        fs::write(&trim_edits, serde_json::to_vec_pretty(&edits_json).map_err(|e| e.to_string())?)
+                            .map_err(|e| e.to_string())?;
+
+                        emit_log(
+                            app,
+                            "warning",
+                            format!(
+                                "HDR10+ metadata is {} {} trailing frame(s); adjusting its end to match the base",
+                                if donor_frames > base_frames { "longer by" } else { "short by" },
+                                difference
+                            ),
+                        );
+                        let mut trim_cmd = tool_command(&hdr10plus_tool, &work_dir);
+                        trim_cmd
+                            .arg("editor")
+                            .arg(&hdr10plus_metadata_path)
+                            .arg("-j")
+                            .arg(&trim_edits)
+                            .arg("-o")
+                            .arg(&trimmed);
+                        temp_files.push(trim_edits);
+                        plan.add(1);
+                        run_command(
+                            state,
+                            trim_cmd,
+                            app,
+                            HDR10PLUS_EDIT_STEP.0,
+                            HDR10PLUS_EDIT_STEP.1,
+                            &hdr10plus_metadata_path,
+                            &trimmed,
+                            false,
+                            &plan,
+                            queue_ctx.as_ref(),
+                            job_file,
+                            ExitCodePolicy::Strict,
+                        )?;
+                        hdr10plus_metadata_path = trimmed.clone();
+                        temp_files.push(trimmed);
+                    }
+                }
+                None => emit_log(
+                    app,
+                    "warning",
+                    format!(
+                        "HDR10+ donor: {} frames @ {:.3} fps; base frame count unknown, length not checked",
+                        donor_frames, hdr10plus_fps
+                    ),
+                ),
+            }
+
+            if hdr10plus_metadata_path == hdr10plus_metadata {
+                let (step_id, step_name) = HDR10PLUS_EDIT_STEP;
+                emit_step(app, step_id, step_name, "completed", 100);
+            }
+
+            let hdr10plus_injected = PathBuf::from(format!("{}_hdr10plus_injected.hevc", output_base));
+            let mut hdr10plus_inject_cmd = tool_command(&hdr10plus_tool, &work_dir);
+            hdr10plus_inject_cmd
+                .arg("inject")
+                .arg("-i")
+                .arg(&hdr10_for_dv)
+                .arg("-j")
+                .arg(&hdr10plus_metadata_path)
+                .arg("-o")
+                .arg(&hdr10plus_injected);
+            temp_files.push(hdr10plus_injected.clone());
+            run_command(
+                state,
+                hdr10plus_inject_cmd,
+                app,
+                HDR10PLUS_INJECT_STEP.0,
+                HDR10PLUS_INJECT_STEP.1,
+                &hdr10_for_dv,
+                &hdr10plus_injected,
+                true,
+                &plan,
+                queue_ctx.as_ref(),
+                job_file,
+                ExitCodePolicy::Strict,
+            )?;
+            hdr10_for_dv = hdr10plus_injected;
+        } else {
+            for (step_id, step_name) in HDR10PLUS_STEPS {
+                emit_step(app, step_id, step_name, "completed", 100);
+            }
+        }
+
+        let mut cmd4 = tool_command(&dovi_tool, &work_dir);
+        cmd4
+            .arg("inject-rpu")
+            .arg("-i")
+            .arg(&hdr10_for_dv)
+            .arg("--rpu-in")
+            .arg(&rpu_path)
+            .arg("-o")
+            .arg(&dv_hdr);
+
+        run_command(
+            state,
+            cmd4,
+            app,
+            5,
+            STEP_NAMES[4],
+            &hdr10_for_dv,
+            &dv_hdr,
+            false,
+            &plan,
+            queue_ctx.as_ref(),
+            job_file,
+            ExitCodePolicy::Strict,
+        )?;
+        journal.record(4, STEP_NAMES[4], &dv_hdr);
+        if last_step == 5 {
+            return finish_partial(app, queue_ctx.as_ref(), last_step, &temp_files);
+        }
+    }
+
+    if let Some(lane) = audio_lane.take() {
+        let (lane_warnings, transcoded) = lane.join(state)?;
+        mkvmerge_warnings.extend(lane_warnings);
+        journal.record(0, STEP_NAMES[0], &audio_loc);
+        if let Some(transcoded) = transcoded {
+            mux_audio = transcoded;
+        }
+    }
+
+    let chapters_xml = PathBuf::from(format!("{}_chapters.xml", output_base));
+    let chapter_file = output_chapters(app, &mkvextract, &work_dir, input_hdr, &chapters_xml, options)?;
+    if chapter_file.as_deref() == Some(chapters_xml.as_path()) {
+        temp_files.push(chapters_xml.clone());
+    }
+
+    let mut cmd5 = mkvtoolnix_command(&mkvmerge, &work_dir, &options.mkv_ui_language);
+    cmd5
+        .arg("--no-date")
+        .arg("--output")
+        .arg(output_path);
+    // Replacement chapters go in on their own; every source's are left out.
+    let no_chapters: &[&str] = if chapter_file.is_some() { &["--no-chapters"] } else { &[] };
+    if let Some(chapters) = &chapter_file {
+        cmd5.arg("--chapters").arg(chapters);
+    }
+
+    if let Some(duration) = detected_duration {
+        cmd5.arg("--default-duration").arg(format!("0:{}", duration));
+    }
+    if options.write_color_metadata {
+        // The HEVC from dovi_tool may lose the container color flags some
+        // players need, so restate the HDR input's color description.
+        let color_args = hdr_info.color.mkvmerge_args();
+        if color_args.is_empty() {
+            emit_log(app, "warning", "No color metadata found on the HDR input; leaving it to mkvmerge");
+        } else {
+            let mut applied = Vec::with_capacity(color_args.len());
+            for (option, code, value) in color_args {
+                cmd5.arg(option).arg(format!("0:{}", code));
+                applied.push(format!("{} {} ({})", option.trim_start_matches("--"), code, value));
+            }
+            emit_log(app, "info", format!("Color metadata: {}", applied.join(", ")));
+        }
+    }
+
+    if let Some(split) = &options.split {
+        let split_arg = split.mkvmerge_arg()?;
+        emit_log(app, "info", format!("Splitting output ({})", split_arg));
+        cmd5.arg("--split").arg(split_arg);
+    }
+
+    if let Some(cover) = &cover_art {
+        let mime = cover_art_mime(cover).unwrap_or("image/jpeg");
+        let name = if mime == "image/png" { "cover.png" } else { "cover.jpg" };
+        emit_log(app, "info", format!("Attaching cover art: {}", cover.display()));
+        cmd5.arg("--attachment-name")
+            .arg(name)
+            .arg("--attachment-mime-type")
+            .arg(mime)
+            .arg("--attach-file")
+            .arg(cover);
+    }
+
+    let mut expected_track_count = 0;
+    let defaults = &options.track_defaults;
+    if replace_video {
+        let audio_ids = ordered_audio_ids(app, &tools, input_hdr, &options.audio_order)?;
+        let (track_order, count) = replace_video_track_order(&tools, input_hdr, &audio_ids)?;
+        expected_track_count = count;
+        cmd5.arg(&dv_hdr);
+        if !defaults.is_empty() {
+            cmd5.args(track_flag_args(app, &tools, input_hdr, defaults, true, true)?);
+        }
+        cmd5
+            .arg("--no-video")
+            .args(no_chapters)
+            .arg(input_hdr)
+            .arg("--track-order")
+            .arg(track_order);
+    } else {
+        let separate_subtitles = mux_audio != audio_loc;
+        cmd5.arg(&dv_hdr);
+        if !defaults.is_empty() {
+            cmd5.args(track_flag_args(app, &tools, &mux_audio, defaults, true, !separate_subtitles)?);
+        }
+        cmd5.args(no_chapters).arg(&mux_audio);
+        let audio_ids = ordered_audio_ids(app, &tools, &mux_audio, &options.audio_order)?;
+        if !audio_ids.is_empty() {
+            let order: Vec<String> = std::iter::once("0:0".to_string())
+                .chain(audio_ids.iter().map(|id| format!("1:{}", id)))
+                .collect();
+            cmd5.arg("--track-order").arg(order.join(","));
+        }
+        if separate_subtitles {
+            // Subtitles, chapters and attachments from the untouched extraction.
+            if !defaults.is_empty() {
+                cmd5.args(track_flag_args(app, &tools, &audio_loc, defaults, false, true)?);
+            }
+            cmd5.arg("--no-audio").args(no_chapters).arg(&audio_loc);
+        }
+    }
+
+    mkvmerge_warnings.extend(run_command(
+        state,
+        cmd5,
+        app,
+        6,
+        STEP_NAMES[5],
+        &dv_hdr,
+        output_path,
+        // mkvmerge writes numbered parts instead of `output_path` when splitting.
+        options.split.is_none(),
+        &plan,
+        queue_ctx.as_ref(),
+        job_file,
+        ExitCodePolicy::Mkvmerge,
+    )?);
+    journal.record(5, STEP_NAMES[5], &mux_artifact(output_path, options));
+
+    if replace_video {
+        let muxed = mux_artifact(output_path, options);
+        let output_track_count = identify_file(&mkvmerge, &muxed, &work_dir)?
+            .get("tracks")
+            .and_then(|t| t.as_array())
+            .map(|tracks| tracks.len())
+            .unwrap_or(0);
+        if output_track_count != expected_track_count {
+            return Err(format!(
+                "{} has {} track(s) but should have {}: the new video plus the HDR source's other tracks",
+                muxed.display(),
+                output_track_count,
+                expected_track_count
+            ));
+        }
+        emit_log(
+            app,
+            "info",
+            format!(
+                "Track count verified: {} track(s), the new video plus the HDR source's other tracks",
+                output_track_count
+            ),
+        );
+    }
+
+    if options.split.is_none() {
+        let problem = match state.probe.probe(&tools, output_path) {
+            Ok(report) if report.has_dolby_vision() => {
+                emit_log(
+                    app,
+                    "info",
+                    format!(
+                        "Dolby Vision configuration verified: {}",
+                        report.hdr_format.as_deref().unwrap_or("Dolby Vision")
+                    ),
+                );
+                None
+            }
+            Ok(_) => Some(missing_dv_warning(&mkvmerge, output_path)),
+            Err(err) => Some(format!("Could not verify {}: {}", output_path.display(), err)),
+        };
+        if let Some(problem) = problem {
+            emit_log(app, "warning", problem.clone());
+            warnings.push(problem);
+        }
+    } else {
+        let parts = find_split_parts(output_path);
+        if parts.is_empty() {
+            return Err(format!("No split parts found for {}", output_path.display()));
+        }
+        emit_log(app, "info", format!("Output split into {} part(s):", parts.len()));
+        for part in &parts {
+            let size = fs::metadata(part).map(|m| m.len()).unwrap_or(0);
             emit_log(
                 app,
                 "info",
-                format!(
-                    "Cropping needed - {} | HDR: {} | DV: {}",
-                    crop_amount, hdr_info.height, dv_info.height
-                ),
+                format!("  {} ({:.2} GiB)", part.display(), size as f64 / 1_073_741_824.0),
             );
+            let problem = match state.probe.probe(&tools, part) {
+                Ok(report) if report.has_dolby_vision() => None,
+                Ok(_) => Some(missing_dv_warning(&mkvmerge, part)),
+                Err(err) => Some(format!(
+                    "Could not verify split part {}: {}",
+                    part.display(),
+                    err
+                )),
+            };
+            if let Some(problem) = problem {
+                emit_log(app, "warning", problem.clone());
+                warnings.push(problem);
+            }
         }
     }
 
-    let mut dv_delay_frames = 0u32;
-    let mut dv_remove_frames = String::new();
-    let mut dv_duplicate_length = 0u32;
+    journal.record(VERIFIED_ENTRY.0, VERIFIED_ENTRY.1, &mux_artifact(output_path, options));
 
-    if dv_delay_ms.abs() > f64::EPSILON {
-        dv_delay_frames = delay_to_frames(dv_delay_ms, hdr_info.fps);
+    let (sidecars, copy_warnings) = finish_output(app, state, options, output_path)?;
+    warnings.extend(copy_warnings);
+
+    if let Some(staged) = staged {
+        staged.keep();
+    }
+    if !keep_temp {
+        let released = remove_temp_files(app, &temp_files);
+        state.metrics.release_temp(released);
+        emit_log(app, "info", "Temporary files cleaned up.");
+    } else {
+        report_kept_artifacts(app, output_path, &temp_files);
+    }
+    // Hashed and copied: nothing is left to resume.
+    journal.remove();
+
+    if mkvmerge_warnings.exited_with_warnings {
         emit_log(
             app,
-            "info",
-            format!("Dolby Vision delay: {} frames", dv_delay_frames),
+            "warning",
+            format!(
+                "{} finished with mkvmerge warnings ({} captured)",
+                output_path.display(),
+                mkvmerge_warnings.lines.len()
+            ),
+        );
+    }
+    if !warnings.is_empty() {
+        emit_log(
+            app,
+            "warning",
+            format!("{} finished with {} warning(s)", output_path.display(), warnings.len()),
         );
     }
+    let final_status = if mkvmerge_warnings.exited_with_warnings || !warnings.is_empty() {
+        "completed_with_warnings"
+    } else {
+        "completed"
+    };
 
-    if dv_delay_ms < 0.0 && dv_delay_frames > 0 {
-        dv_remove_frames = format!("0-{}", dv_delay_frames - 1);
-    } else if dv_delay_ms > 0.0 {
-        dv_duplicate_length = dv_delay_frames;
+    if let Some(key) = &memo_key {
+        let crop = match detected_bars {
+            Some((top, bottom)) => Some(CropMemo { top, bottom, detected: true }),
+            None => (crop_amount > 0 || active_area_bottom > 0).then_some(CropMemo {
+                top: crop_amount,
+                bottom: active_area_bottom,
+                detected: false,
+            }),
+        };
+        record_title_memo(app, key, source_hdr, source_dv, options, dv_delay_ms, crop);
     }
 
-    let queue_ctx = queue_id.map(|id| QueueContext {
-        id: id.to_string(),
-        label: queue_label.map(|label| label.to_string()),
-        file_index: queue_file_index,
-        file_total: queue_file_total,
-        tracker: queue_tracker,
-        active_workers: queue_active_workers,
-        file_id: Some(format!("{}:{}", id, queue_file_index)),
-        file_name: queue_file_name.map(|name| name.to_string()),
-    });
+    if let Some(hook) = &options.post_hook {
+        run_post_hook(app, hook, options.post_hook_blocking, source_hdr, output_path);
+    }
 
-    if let Some(ctx) = &queue_ctx {
-        let current_step = ctx.label.clone();
-        emit_queue(
-            app,
-            QueuePayload {
-                id: ctx.id.clone(),
-                status: "processing".to_string(),
-                progress: 0,
-                current_step,
-                active_workers: ctx
-                    .active_workers
-                    .as_ref()
-                    .and_then(|workers| workers.lock().ok().map(|v| *v)),
-                file_total: Some(ctx.file_total),
-            },
-        );
+    emit_file_finished(app, queue_ctx.as_ref(), final_status, sidecars);
 
+    Ok(())
+}
+
+/// Run the post-processing hook for a finished output through the platform
+/// shell. The output is already complete, so a failing hook is only logged.
+fn run_post_hook(app: &AppHandle, template: &str, blocking: bool, input: &Path, output: &Path) {
+    let line = template
+        .replace("{output}", &shell_quote(&output.to_string_lossy()))
+        .replace("{input}", &shell_quote(&input.to_string_lossy()));
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        // cmd does its own parsing, so the line must reach it unescaped.
+        #[cfg(target_os = "windows")]
+        command.raw_arg(&line);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&line);
+        command
+    };
+    if let Some(dir) = output.parent() {
+        command.current_dir(dir);
+    }
+    hide_console_window(&mut command);
+    command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    emit_log(app, "info", format!("Running post hook: {}", line));
+    let child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            emit_log(app, "warning", format!("Post hook could not start: {}", err));
+            return;
+        }
+    };
+    let app = app.clone();
+    let output = output.to_path_buf();
+    let wait = move || match child.wait_with_output() {
+        Ok(result) if result.status.success() => {
+            emit_log(&app, "success", format!("Post hook finished for {}", output.display()));
+        }
+        Ok(result) => {
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            let last = stderr
+                .lines()
+                .rfind(|line| !line.trim().is_empty())
+                .unwrap_or("no output")
+                .trim()
+                .to_string();
+            emit_log(
+                &app,
+                "warning",
+                format!(
+                    "Post hook exited with code {} ({})",
+                    result.status.code().map(|code| code.to_string()).unwrap_or_else(|| "none".to_string()),
+                    last
+                ),
+            );
+        }
+        Err(err) => emit_log(&app, "warning", format!("Post hook failed: {}", err)),
+    };
+    if blocking {
+        wait();
+    } else {
+        thread::spawn(wait);
+    }
+}
+
+fn emit_file_finished(
+    app: &AppHandle,
+    queue_ctx: Option<&QueueContext>,
+    status: &str,
+    hashes: Vec<OutputSidecar>,
+) {
+    if let Some(ctx) = queue_ctx {
         if let (Some(file_id), Some(file_name)) = (&ctx.file_id, &ctx.file_name) {
             emit_file(
                 app,
@@ -537,363 +3820,754 @@ pub fn run_pipeline(
                     id: file_id.clone(),
                     queue_id: ctx.id.clone(),
                     name: file_name.clone(),
-                    progress: 0,
+                    status: status.to_string(),
+                    progress: 100,
+                    attempt: ctx.attempt,
+                    reason: None,
+                    hashes,
+                },
+            );
+        }
+        // Folder items are completed by their worker loop once every file is done.
+        if ctx.tracker.is_none() {
+            emit_queue(
+                app,
+                QueuePayload {
+                    id: ctx.id.clone(),
+                    status: "completed".to_string(),
+                    progress: 100,
+                    current_step: None,
+                    active_workers: Some(0),
+                    file_total: Some(ctx.file_total),
+                    counts: None,
                 },
             );
         }
     }
+}
 
-    let mut dv_extract_cmd = None;
-    let mut dv_extract_output = dv_hevc.clone();
-    let mut dv_hevc_path = dv_hevc.clone();
-    if is_hevc_file(input_dv) && is_hevc_format(&dv_info) {
-        dv_hevc_path = input_dv.to_path_buf();
-        dv_extract_output = input_dv.to_path_buf();
-    } else {
-        dv_extract_cmd = Some(build_demux_command(
-            &mkvextract,
-            &mp4box,
-            input_dv,
-            &dv_hevc,
-            dv_info.track_id,
-        )?);
+/// Context for updating one file's slot from outside its pipeline run.
+fn folder_file_context(
+    queue_id: &str,
+    file_index: usize,
+    file_total: usize,
+    tracker: &FileTracker,
+    active_workers: &Arc<Mutex<usize>>,
+) -> QueueContext {
+    QueueContext {
+        id: queue_id.to_string(),
+        label: None,
+        file_index,
+        file_total,
+        tracker: Some(Arc::clone(tracker)),
+        active_workers: Some(Arc::clone(active_workers)),
+        file_id: None,
+        file_name: None,
+        attempt: 1,
     }
+}
 
-    let mut hdr_extract_cmd = None;
-    let mut hdr_extract_output = hdr10_hevc.clone();
-    let mut hdr_hevc_path = hdr10_hevc.clone();
-    if is_hevc_file(input_hdr) && is_hevc_format(&hdr_info) {
-        hdr_hevc_path = input_hdr.to_path_buf();
-        hdr_extract_output = input_hdr.to_path_buf();
+/// Apply `update` to this file's slot and emit the item's overall progress
+/// and file counts. The payload is submitted while the tracker is still
+/// locked so two workers can never hand the emitter their updates in the
+/// opposite order.
+fn update_queue_file(
+    app: &AppHandle,
+    ctx: &QueueContext,
+    current_step: Option<String>,
+    update: impl FnOnce(&mut FileSlot),
+) {
+    let active_workers = ctx
+        .active_workers
+        .as_ref()
+        .and_then(|workers| workers.lock().ok().map(|v| *v));
+    let payload = |progress: u8, counts: Option<FileCounts>| QueuePayload {
+        id: ctx.id.clone(),
+        status: "processing".to_string(),
+        progress,
+        current_step: current_step.clone(),
+        active_workers,
+        file_total: Some(ctx.file_total),
+        counts,
+    };
+
+    match ctx.tracker.as_ref().and_then(|tracker| tracker.lock().ok()) {
+        Some(mut slots) => {
+            if let Some(slot) = slots.get_mut(ctx.file_index) {
+                update(slot);
+            }
+            let sum: u32 = slots.iter().map(|slot| slot.progress as u32).sum();
+            let overall_progress = (sum as f64 / ctx.file_total as f64).round() as u8;
+            emit_queue(app, payload(overall_progress, Some(FileCounts::of(&slots))));
+        }
+        None => {
+            let mut slot = FileSlot::default();
+            update(&mut slot);
+            emit_queue(app, payload(slot.progress, None));
+        }
+    }
+}
+
+/// Short names accepted for `start_step` / `end_step`, in `STEP_NAMES` order.
+const STEP_ALIASES: [&str; 6] = ["audio", "dv", "rpu", "hdr10", "inject", "mux"];
+
+fn resolve_step(step: &StepRef) -> Result<usize, String> {
+    let (index, shown) = match step {
+        StepRef::Index(index) => (
+            Some(*index).filter(|index| (1..=STEP_NAMES.len()).contains(index)),
+            index.to_string(),
+        ),
+        StepRef::Name(name) => (
+            STEP_ALIASES
+                .iter()
+                .zip(STEP_NAMES.iter())
+                .position(|(alias, full)| {
+                    alias.eq_ignore_ascii_case(name.trim()) || full.eq_ignore_ascii_case(name.trim())
+                })
+                .map(|position| position + 1),
+            format!("\"{}\"", name),
+        ),
+    };
+    index.ok_or_else(|| {
+        validation_error(format!(
+            "Unknown step {}: use 1-{} or one of {}",
+            shown,
+            STEP_NAMES.len(),
+            STEP_ALIASES.join(", ")
+        ))
+    })
+}
+
+/// First and last step a run executes, 1-based and inclusive.
+pub fn step_range(options: &PipelineOptions) -> Result<(usize, usize), String> {
+    let resume_mux = (options.resume_from_step == Some(ResumeStep::Mux)).then_some(STEP_NAMES.len());
+    let first = options
+        .start_step
+        .as_ref()
+        .map(resolve_step)
+        .transpose()?
+        .or(resume_mux)
+        .unwrap_or(1);
+    let last = options
+        .end_step
+        .as_ref()
+        .map(resolve_step)
+        .transpose()?
+        .unwrap_or(STEP_NAMES.len());
+    if first > last {
+        return Err(validation_error(format!(
+            "Start step \"{}\" comes after end step \"{}\"",
+            STEP_NAMES[first - 1],
+            STEP_NAMES[last - 1]
+        )));
+    }
+    Ok((first, last))
+}
+
+/// The step a partial run stops after, when `end_step` ends it before the mux.
+pub fn partial_end_step(options: &PipelineOptions) -> Option<&'static str> {
+    match step_range(options) {
+        Ok((_, last)) if last < STEP_NAMES.len() => Some(STEP_NAMES[last - 1]),
+        _ => None,
+    }
+}
+
+/// Check what a run starting at `first_step` (2 to 5) takes from an earlier
+/// run instead of producing it and mark the steps before it skipped.
+/// `mux_audio` is the audio the mux will use, `None` when replacing only
+/// the video track.
+fn check_start_intermediates(
+    app: &AppHandle,
+    options: &PipelineOptions,
+    (first_step, last_step): (usize, usize),
+    mux_audio: Option<&Path>,
+    dv_hevc: &Path,
+    rpu_bin: &Path,
+    hdr10_hevc: &Path,
+) -> Result<(), String> {
+    let rpu = options.rpu_path.as_deref().unwrap_or(rpu_bin);
+    let hdr10_hevc = options.hdr10_hevc_path.as_deref().unwrap_or(hdr10_hevc);
+    let mut required = Vec::new();
+    if last_step == STEP_NAMES.len() {
+        required.extend(mux_audio);
+    }
+    if first_step == 3 {
+        required.push(dv_hevc);
+    }
+    if first_step >= 4 && last_step >= 5 {
+        required.push(rpu);
+    }
+    if first_step == 5 {
+        required.push(hdr10_hevc);
+    }
+    for path in required {
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if size == 0 {
+            return Err(validation_error(format!(
+                "Cannot start at {}: {} is missing or empty (run the earlier steps with keep temp files enabled, or pass it explicitly)",
+                STEP_NAMES[first_step - 1],
+                path.display()
+            )));
+        }
+    }
+
+    for (index, step_name) in STEP_NAMES.iter().enumerate().take(first_step - 1) {
+        // The DV extract is reported by the RPU section, which still runs at step 3.
+        if index == 1 && first_step <= 3 {
+            continue;
+        }
+        emit_step(app, index + 1, step_name, "skipped", 0);
+    }
+    if options.audio_transcode.is_some() {
+        emit_step(app, TRANSCODE_STEP.0, TRANSCODE_STEP.1, "skipped", 0);
+    }
+    if first_step > 3 {
+        emit_step(app, RPU_EDIT_STEP_ID, RPU_EDIT_STEP_NAME, "skipped", 0);
+    }
+    emit_log(
+        app,
+        "info",
+        format!("Starting at {} with existing intermediates", STEP_NAMES[first_step - 1]),
+    );
+    Ok(())
+}
+
+/// End a run that reached its `end_step`: later steps are marked skipped,
+/// every intermediate is kept for the run that picks up from here, and the
+/// file is reported as a partial completion.
+fn finish_partial(
+    app: &AppHandle,
+    queue_ctx: Option<&QueueContext>,
+    last_step: usize,
+    temp_files: &[PathBuf],
+) -> Result<(), String> {
+    for (index, step_name) in STEP_NAMES.iter().enumerate().skip(last_step) {
+        emit_step(app, index + 1, step_name, "skipped", 0);
+    }
+    if last_step < 3 {
+        emit_step(app, RPU_EDIT_STEP_ID, RPU_EDIT_STEP_NAME, "skipped", 0);
+    }
+    if last_step < 5 {
+        emit_step(app, PAD_STEP.0, PAD_STEP.1, "skipped", 0);
+        for (step_id, step_name) in HDR10PLUS_STEPS {
+            emit_step(app, step_id, step_name, "skipped", 0);
+        }
+    }
+    emit_step(app, HASH_STEP_ID, HASH_STEP_NAME, "skipped", 0);
+    emit_step(app, COPY_STEP_ID, COPY_STEP_NAME, "skipped", 0);
+
+    let kept: Vec<String> = temp_files
+        .iter()
+        .filter(|file| fs::metadata(file).map(|m| m.is_file()).unwrap_or(false))
+        .map(|file| file.display().to_string())
+        .collect();
+    emit_log(
+        app,
+        "info",
+        format!(
+            "Stopped after {} as requested; intermediates kept: {}",
+            STEP_NAMES[last_step - 1],
+            if kept.is_empty() { "none".to_string() } else { kept.join(", ") }
+        ),
+    );
+    emit_file_finished(app, queue_ctx, "partial", Vec::new());
+    Ok(())
+}
+
+/// Check the intermediates a mux-only resume needs are where this output's
+/// earlier run left them, mark the skipped steps done, and return the audio
+/// file to mux.
+fn check_mux_intermediates(
+    app: &AppHandle,
+    options: &PipelineOptions,
+    replace_video: bool,
+    dv_hdr: &Path,
+    audio_loc: &Path,
+    transcoded_audio: &Path,
+) -> Result<PathBuf, String> {
+    let mux_audio = if replace_video {
+        None
+    } else if options.audio_transcode.is_some() {
+        Some(transcoded_audio)
     } else {
-        hdr_extract_cmd = Some(build_demux_command(
-            &mkvextract,
-            &mp4box,
-            input_hdr,
-            &hdr10_hevc,
-            hdr_info.track_id,
-        )?);
+        Some(audio_loc)
+    };
+    for required in std::iter::once(dv_hdr).chain(mux_audio) {
+        let size = fs::metadata(required).map(|m| m.len()).unwrap_or(0);
+        if size == 0 {
+            return Err(validation_error(format!(
+                "Cannot resume at the mux: {} is missing or empty (run once with keep temp files enabled)",
+                required.display()
+            )));
+        }
     }
 
-    let mut cmd0 = Command::new(&mkvmerge);
-    cmd0
-        .arg("-o")
-        .arg(&audio_loc)
-        .arg("--no-video")
-        .arg(input_hdr);
+    for (step_id, step_name) in STEP_NAMES.iter().take(5).enumerate() {
+        emit_step(app, step_id + 1, step_name, "completed", 100);
+    }
+    emit_step(app, RPU_EDIT_STEP_ID, RPU_EDIT_STEP_NAME, "completed", 100);
+    emit_step(app, TRANSCODE_STEP.0, TRANSCODE_STEP.1, "completed", 100);
+    emit_step(app, PAD_STEP.0, PAD_STEP.1, "completed", 100);
+    for (step_id, step_name) in HDR10PLUS_STEPS {
+        emit_step(app, step_id, step_name, "completed", 100);
+    }
+    emit_log(
+        app,
+        "info",
+        format!("Resuming at the mux with existing {}", dv_hdr.display()),
+    );
+    Ok(mux_audio.unwrap_or(audio_loc).to_path_buf())
+}
+
+/// Report a step as done without running it: the journal shows its artifact
+/// from an earlier run is still intact.
+fn skip_journaled_step(app: &AppHandle, plan: &StepPlan, step_id: usize, step_name: &str, artifact: &Path) {
+    plan.skip();
+    emit_step(app, step_id, step_name, "completed", 100);
+    emit_log(
+        app,
+        "info",
+        format!(
+            "Step {}: {} already completed, reusing {}",
+            step_id,
+            step_name,
+            artifact.display()
+        ),
+    );
+}
 
-    let dv_emit_progress = dv_extract_cmd.is_some();
-    let cmd1 = dv_extract_cmd.unwrap_or_else(noop_command);
+/// `--track-order` for a replace_video mux: the new video (file 0) first, then
+/// every non-video track of the source (file 1) in its original order, with
+/// the audio slots refilled from `audio_ids` when it is not empty. Also
+/// returns the track count the output should have: the new video plus the
+/// source's non-video tracks, as every source video track is replaced.
+fn replace_video_track_order(tools: &ProbeTools, source: &Path, audio_ids: &[u64]) -> Result<(String, usize), String> {
+    let identification = identify_file(&tools.mkvmerge, source, &tools.work_dir)?;
+    let tracks = identification
+        .get("tracks")
+        .and_then(|t| t.as_array())
+        .ok_or_else(|| format!("No tracks reported for {}", source.display()))?;
+
+    let mut order = vec!["0:0".to_string()];
+    let mut reordered_audio = audio_ids.iter();
+    let mut expected = 1;
+    for track in tracks {
+        let kind = track.get("type").and_then(|t| t.as_str());
+        if kind == Some("video") {
+            continue;
+        }
+        expected += 1;
+        let reordered = if kind == Some("audio") { reordered_audio.next() } else { None };
+        let id = match reordered {
+            Some(id) => Some(*id),
+            None => track.get("id").and_then(|id| id.as_u64()),
+        };
+        if let Some(id) = id {
+            order.push(format!("1:{}", id));
+        }
+    }
+    Ok((order.join(","), expected))
+}
 
-    let mut cmd2 = Command::new(&dovi_tool);
-    cmd2
+fn build_rpu_extract_command(dovi_tool: &Path, hevc: &Path, rpu_out: &Path, work_dir: &Path) -> Command {
+    let mut command = tool_command(dovi_tool, work_dir);
+    command
         .arg("-m")
         .arg("3")
         .arg("extract-rpu")
-        .arg(&dv_hevc_path)
+        .arg(hevc)
         .arg("-o")
-        .arg(&rpu_bin);
+        .arg(rpu_out);
+    command
+}
 
-    let hdr_emit_progress = hdr_extract_cmd.is_some();
-    let cmd3 = hdr_extract_cmd.unwrap_or_else(noop_command);
+/// The parts of `dovi_tool info --summary` that decide which RPU to keep.
+struct RpuSummary {
+    profile: u32,
+    enhancement: Option<String>,
+    dm_version: u32,
+}
 
-    run_command(
-        state,
-        cmd0,
-        app,
-        1,
-        STEP_NAMES[0],
-        input_hdr,
-        &audio_loc,
-        true,
-        0,
-        STEP_NAMES.len(),
-        queue_ctx.as_ref(),
-    )?;
+impl RpuSummary {
+    /// Higher is better: CM v4.0 beats CM v2.9, then FEL > MEL > profile 8 > 5.
+    fn fidelity(&self) -> (u32, u32) {
+        let profile_rank = match (self.profile, self.enhancement.as_deref()) {
+            (7, Some("FEL")) => 4,
+            (7, _) => 3,
+            (8, _) => 2,
+            (5, _) => 1,
+            _ => 0,
+        };
+        (self.dm_version, profile_rank)
+    }
+}
 
-    run_command(
-        state,
-        cmd1,
-        app,
-        2,
-        STEP_NAMES[1],
-        input_dv,
-        &dv_extract_output,
-        dv_emit_progress,
-        1,
-        STEP_NAMES.len(),
-        queue_ctx.as_ref(),
-    )?;
+impl std::fmt::Display for RpuSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "profile {}", self.profile)?;
+        if let Some(enhancement) = &self.enhancement {
+            write!(f, " {}", enhancement)?;
+        }
+        write!(f, ", DM v{}", self.dm_version)
+    }
+}
 
-    run_command(
-        state,
-        cmd2,
+fn rpu_summary(dovi_tool: &Path, rpu: &Path, work_dir: &Path) -> Result<RpuSummary, String> {
+    let mut command = tool_command(dovi_tool, work_dir);
+    command.arg("info").arg("-i").arg(rpu).arg("--summary");
+    hide_console_window(&mut command);
+    let output = command.output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("dovi_tool info failed for {}", rpu.display()));
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+
+    let profile_re = Regex::new(r"Profile:\s*(\d+)(?:\s*\((FEL|MEL)\))?").map_err(|e| e.to_string())?;
+    let dm_re = Regex::new(r"DM version:\s*(\d+)").map_err(|e| e.to_string())?;
+    let profile = profile_re
+        .captures(&text)
+        .ok_or_else(|| format!("No profile reported for {}", rpu.display()))?;
+
+    Ok(RpuSummary {
+        profile: profile[1].parse().unwrap_or(0),
+        enhancement: profile.get(2).map(|m| m.as_str().to_string()),
+        dm_version: dm_re
+            .captures(&text)
+            .and_then(|c| c[1].parse().ok())
+            .unwrap_or(0),
+    })
+}
+
+/// Frames sampled when checking a profile 7 enhancement layer.
+const FEL_PROBE_FRAMES: u32 = 24;
+
+/// Identify the enhancement layer of a profile 7 stream (`FEL` or `MEL`) from
+/// its first unconverted RPUs.
+fn detect_enhancement_layer(
+    dovi_tool: &Path,
+    hevc: &Path,
+    probe_rpu: &Path,
+    work_dir: &Path,
+) -> Result<Option<String>, String> {
+    let mut command = tool_command(dovi_tool, work_dir);
+    command
+        .arg("extract-rpu")
+        .arg("--limit")
+        .arg(FEL_PROBE_FRAMES.to_string())
+        .arg(hevc)
+        .arg("-o")
+        .arg(probe_rpu);
+    hide_console_window(&mut command);
+    let output = command.output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "Could not sample RPUs from {}: {}",
+            hevc.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(rpu_summary(dovi_tool, probe_rpu, work_dir)?.enhancement)
+}
+
+/// Apply the FEL policy to a profile 7 input, logging what was detected.
+fn check_fel_policy(
+    app: &AppHandle,
+    policy: FelPolicy,
+    source_label: &str,
+    enhancement: Option<&str>,
+) -> Result<(), String> {
+    let detected = enhancement.unwrap_or("unknown");
+    let policy_name = match policy {
+        FelPolicy::Convert => "convert",
+        FelPolicy::Abort => "abort",
+        FelPolicy::MelOnly => "mel_only",
+    };
+    emit_log(
         app,
-        3,
-        STEP_NAMES[2],
-        &dv_hevc_path,
-        &rpu_bin,
-        false,
-        2,
-        STEP_NAMES.len(),
-        queue_ctx.as_ref(),
-    )?;
+        "info",
+        format!(
+            "{}: Dolby Vision profile 7, enhancement layer {} (FEL policy: {})",
+            source_label, detected, policy_name
+        ),
+    );
+    match (policy, enhancement) {
+        (_, Some("MEL")) => Ok(()),
+        (FelPolicy::Convert, _) => {
+            emit_log(
+                app,
+                "warning",
+                format!(
+                    "{}: converting to profile 8.1 discards the {} enhancement layer",
+                    source_label, detected
+                ),
+            );
+            Ok(())
+        }
+        (FelPolicy::Abort, Some("FEL")) => Err(validation_error(format!(
+            "{} is profile 7 FEL; converting it would discard the full enhancement layer (FEL policy: abort)",
+            source_label
+        ))),
+        (FelPolicy::Abort, _) => Ok(()),
+        (FelPolicy::MelOnly, _) => Err(validation_error(format!(
+            "{} has a {} enhancement layer; only MEL sources are processed (FEL policy: mel_only)",
+            source_label, detected
+        ))),
+    }
+}
 
-    let mut rpu_path = rpu_bin.clone();
-    let needs_rpu_edit = crop_amount > 0 || !dv_remove_frames.is_empty() || dv_duplicate_length > 0;
-    if needs_rpu_edit {
-        let rpu_json_path = PathBuf::from(format!("{}_rpu.json", output_base));
-        let rpu_edited = PathBuf::from(format!("{}_rpu_edited.bin", output_base));
-        let rpu_json = json!({
-            "active_area": {
-                "crop": crop,
-                "presets": [{
-                    "id": 0,
-                    "left": 0,
-                    "right": 0,
-                    "top": crop_amount,
-                    "bottom": crop_amount
-                }]
-            },
-            "remove": [dv_remove_frames],
-            "duplicate": [{
-                "source": 0,
-                "offset": 0,
-                "length": dv_duplicate_length
-            }]
-        });
+/// Points (as fractions of the runtime) sampled by cropdetect.
+const CROPDETECT_SAMPLES: [f64; 5] = [0.1, 0.3, 0.5, 0.7, 0.9];
+const CROPDETECT_SAMPLE_SECS: u32 = 2;
+/// Largest spread, in pixels, between samples that still counts as one
+/// consistent letterbox.
+const CROPDETECT_TOLERANCE: u32 = 4;
 
-        fs::write(&rpu_json_path, serde_json::to_vec_pretty(&rpu_json).map_err(|e| e.to_string())?)
-            .map_err(|e| e.to_string())?;
+/// Sample the HDR stream with ffmpeg cropdetect and return the consensus
+/// `(top, bottom)` bar heights, or `None` when there are no bars or the
+/// samples disagree (variable aspect ratio).
+pub(crate) fn detect_letterbox_bars(
+    app: &AppHandle,
+    ffmpeg: &Path,
+    input: &Path,
+    info: &MediaReport,
+    work_dir: &Path,
+) -> Result<Option<(u32, u32)>, String> {
+    let duration = info
+        .duration_secs
+        .filter(|d| *d > 0.0)
+        .ok_or("Letterbox detection needs the HDR duration, which MediaInfo did not report")?;
+    let crop_re = Regex::new(r"crop=(\d+):(\d+):(\d+):(\d+)").map_err(|e| e.to_string())?;
 
-        emit_log(app, "info", "Editing RPU metadata...");
-        let mut rpu_edit_cmd = Command::new(&dovi_tool);
-        rpu_edit_cmd
-            .arg("editor")
+    let mut samples = Vec::new();
+    for fraction in CROPDETECT_SAMPLES {
+        let start = duration * fraction;
+        let mut command = tool_command(ffmpeg, work_dir);
+        command
+            .arg("-hide_banner")
+            .arg("-nostdin")
+            .arg("-ss")
+            .arg(format!("{:.3}", start))
             .arg("-i")
-            .arg(&rpu_path)
-            .arg("-o")
-            .arg(&rpu_edited)
-            .arg("-j")
-            .arg(&rpu_json_path);
-        hide_console_window(&mut rpu_edit_cmd);
-        let status = rpu_edit_cmd.status().map_err(|e| e.to_string())?;
+            .arg(input)
+            .arg("-t")
+            .arg(CROPDETECT_SAMPLE_SECS.to_string())
+            .arg("-map")
+            .arg("0:v:0")
+            .arg("-vf")
+            .arg("cropdetect=limit=0.1:round=2:reset=0")
+            .arg("-f")
+            .arg("null")
+            .arg("-");
+        hide_console_window(&mut command);
+        let output = command.output().map_err(|e| e.to_string())?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let Some(caps) = crop_re.captures_iter(&stderr).last() else {
+            emit_log(
+                app,
+                "warning",
+                format!("cropdetect at {:.0}s produced no result", start),
+            );
+            continue;
+        };
+        let height: u32 = caps[2].parse().unwrap_or(info.height);
+        let y: u32 = caps[4].parse().unwrap_or(0);
+        let top = y;
+        let bottom = info.height.saturating_sub(height + y);
+        emit_log(
+            app,
+            "info",
+            format!(
+                "cropdetect {:.0}s-{:.0}s: top {} / bottom {}",
+                start,
+                start + CROPDETECT_SAMPLE_SECS as f64,
+                top,
+                bottom
+            ),
+        );
+        samples.push((top, bottom));
+    }
 
-        if !status.success() {
-            return Err("RPU edit failed".to_string());
-        }
-        rpu_path = rpu_edited.clone();
-        temp_files.push(rpu_json_path);
-        temp_files.push(rpu_edited);
+    if samples.is_empty() {
+        return Err("cropdetect produced no usable samples".to_string());
+    }
+    let spread = |values: Vec<u32>| {
+        let min = values.iter().min().copied().unwrap_or(0);
+        let max = values.iter().max().copied().unwrap_or(0);
+        (min, max)
+    };
+    let (top_min, top_max) = spread(samples.iter().map(|s| s.0).collect());
+    let (bottom_min, bottom_max) = spread(samples.iter().map(|s| s.1).collect());
+    if top_max - top_min > CROPDETECT_TOLERANCE || bottom_max - bottom_min > CROPDETECT_TOLERANCE {
+        emit_log(
+            app,
+            "warning",
+            format!(
+                "Letterbox samples disagree (top {}-{}, bottom {}-{}); likely a variable aspect ratio, leaving the active area alone",
+                top_min, top_max, bottom_min, bottom_max
+            ),
+        );
+        return Ok(None);
     }
 
-    run_command(
-        state,
-        cmd3,
+    // The smallest bars seen are the ones that never cut into the picture.
+    let consensus = (top_min & !1, bottom_min & !1);
+    emit_log(
         app,
-        4,
-        STEP_NAMES[3],
-        input_hdr,
-        &hdr_extract_output,
-        hdr_emit_progress,
-        3,
-        STEP_NAMES.len(),
-        queue_ctx.as_ref(),
-    )?;
+        "info",
+        format!("Letterbox consensus: top {} / bottom {}", consensus.0, consensus.1),
+    );
+    Ok(if consensus == (0, 0) { None } else { Some(consensus) })
+}
 
-    let mut hdr10_for_dv = hdr_hevc_path.clone();
-    if let Some(hdr10plus_source) = hdr10plus_path {
-        if !hdr10plus_source.as_os_str().is_empty() {
-            emit_log(app, "info", "Extracting HDR10+ metadata...");
-            let hdr10plus_info = get_mediainfo(&mediainfo, hdr10plus_source)?;
-            let mut hdr10plus_hevc_path = hdr10plus_source.to_path_buf();
-
-            if !(is_hevc_file(hdr10plus_source) && is_hevc_format(&hdr10plus_info)) {
-                let hdr10plus_demux = PathBuf::from(format!("{}_hdr10plus.hevc", output_base));
-                let mut demux_cmd = build_demux_command(
-                    &mkvextract,
-                    &mp4box,
-                    hdr10plus_source,
-                    &hdr10plus_demux,
-                    hdr10plus_info.track_id,
-                )?;
-                hide_console_window(&mut demux_cmd);
-                let status = demux_cmd.status().map_err(|e| e.to_string())?;
-                if !status.success() {
-                    return Err("HDR10+ demux failed".to_string());
-                }
-                hdr10plus_hevc_path = hdr10plus_demux;
-                temp_files.push(hdr10plus_hevc_path.clone());
-            }
+/// Oldest mkvmerge expected to carry the Dolby Vision configuration record
+/// over from a raw HEVC stream. Older ones are warned about before a run.
+const MIN_MKVMERGE_VERSION: (u32, u32) = (65, 0);
 
-            let hdr10plus_metadata = PathBuf::from(format!("{}_hdr10plus.json", output_base));
-            let mut hdr10plus_extract_cmd = Command::new(&hdr10plus_tool);
-            hdr10plus_extract_cmd
-                .arg("extract")
-                .arg(&hdr10plus_hevc_path)
-                .arg("-o")
-                .arg(&hdr10plus_metadata);
-            hide_console_window(&mut hdr10plus_extract_cmd);
-            let status = hdr10plus_extract_cmd.status().map_err(|e| e.to_string())?;
+/// Parsed `mkvmerge --version` and the line it came from.
+fn mkvmerge_version(mkvmerge: &Path) -> Option<((u32, u32), String)> {
+    let mut command = Command::new(mkvmerge);
+    command.arg("--version");
+    hide_console_window(&mut command);
+    let output = command.output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().find(|line| line.contains("mkvmerge"))?.trim().to_string();
+    let caps = Regex::new(r"v(\d+)\.(\d+)").ok()?.captures(&line)?;
+    let version = (caps[1].parse().ok()?, caps[2].parse().ok()?);
+    Some((version, line))
+}
 
-            if !status.success() {
-                return Err("HDR10+ metadata extraction failed".to_string());
-            }
-            temp_files.push(hdr10plus_metadata.clone());
+/// Warn up front when mkvmerge is older than [`MIN_MKVMERGE_VERSION`].
+pub fn check_mkvmerge_version(app: &AppHandle, mkvmerge: &Path) {
+    if let Some((version, line)) = mkvmerge_version(mkvmerge) {
+        if version < MIN_MKVMERGE_VERSION {
+            emit_log(
+                app,
+                "warning",
+                format!(
+                    "{} is older than v{}.{}; outputs may lose their Dolby Vision configuration record",
+                    line, MIN_MKVMERGE_VERSION.0, MIN_MKVMERGE_VERSION.1
+                ),
+            );
+        }
+    }
+}
 
-            let mut hdr10plus_metadata_path = hdr10plus_metadata.clone();
-            if hdr10plus_delay_ms.abs() > f64::EPSILON {
-                let hdr10plus_delay_frames = delay_to_frames(hdr10plus_delay_ms, hdr10plus_info.fps);
-                let mut hdr10plus_remove_frames = String::new();
-                let mut hdr10plus_duplicate_length = 0u32;
+fn missing_dv_warning(mkvmerge: &Path, file: &Path) -> String {
+    let version = mkvmerge_version(mkvmerge)
+        .map(|(_, line)| line)
+        .unwrap_or_else(|| "version unknown".to_string());
+    format!(
+        "{} has no Dolby Vision configuration record; mkvmerge ({}) may be too old to carry it over, upgrade to v{}.{} or newer",
+        file.display(),
+        version,
+        MIN_MKVMERGE_VERSION.0,
+        MIN_MKVMERGE_VERSION.1
+    )
+}
 
-                if hdr10plus_delay_ms < 0.0 && hdr10plus_delay_frames > 0 {
-                    hdr10plus_remove_frames = format!("0-{}", hdr10plus_delay_frames - 1);
-                } else if hdr10plus_delay_ms > 0.0 {
-                    hdr10plus_duplicate_length = hdr10plus_delay_frames;
-                }
+/// MIME type of a supported cover image, by extension.
+fn cover_art_mime(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        _ => None,
+    }
+}
 
-                if !hdr10plus_remove_frames.is_empty() || hdr10plus_duplicate_length > 0 {
-                    let hdr10plus_edits = PathBuf::from(format!("{}_hdr10plus_edits.json", output_base));
-                    let hdr10plus_edited = PathBuf::from(format!("{}_hdr10plus_edited.json", output_base));
-                    let edits_json = json!({
-                        "remove": [hdr10plus_remove_frames],
-                        "duplicate": [{
-                            "source": 0,
-                            "offset": 0,
-                            "length": hdr10plus_duplicate_length
-                        }]
-                    });
-                    fs::write(&hdr10plus_edits, serde_json::to_vec_pretty(&edits_json).map_err(|e| e.to_string())?)
-                        .map_err(|e| e.to_string())?;
+/// Pick the poster for one input: the configured image itself, or from a
+/// poster folder the image named like the HDR input (with or without its
+/// `.HDR...` suffix). A folder without a match means no cover.
+fn resolve_cover_art(cover_art: &Path, input_hdr: &Path) -> Result<Option<PathBuf>, String> {
+    if cover_art.is_dir() {
+        let file_name = input_hdr
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let stem = input_hdr
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let base = Some(hdr_base_name(&file_name));
 
-                    emit_log(app, "info", "Editing HDR10+ metadata...");
-                    let mut hdr10plus_edit_cmd = Command::new(&hdr10plus_tool);
-                    hdr10plus_edit_cmd
-                        .arg("editor")
-                        .arg(&hdr10plus_metadata)
-                        .arg("-j")
-                        .arg(&hdr10plus_edits)
-                        .arg("-o")
-                        .arg(&hdr10plus_edited);
-                    hide_console_window(&mut hdr10plus_edit_cmd);
-                    let status = hdr10plus_edit_cmd.status().map_err(|e| e.to_string())?;
-                    if !status.success() {
-                        return Err("HDR10+ metadata edit failed".to_string());
-                    }
-                    hdr10plus_metadata_path = hdr10plus_edited.clone();
-                    temp_files.push(hdr10plus_edits);
-                    temp_files.push(hdr10plus_edited);
+        for name in std::iter::once(stem).chain(base) {
+            for extension in ["jpg", "jpeg", "png"] {
+                let candidate = cover_art.join(format!("{}.{}", name, extension));
+                if candidate.is_file() {
+                    return Ok(Some(candidate));
                 }
             }
-
-            emit_log(app, "info", "Injecting HDR10+ metadata...");
-            let hdr10plus_injected = PathBuf::from(format!("{}_hdr10plus_injected.hevc", output_base));
-            let mut hdr10plus_inject_cmd = Command::new(&hdr10plus_tool);
-            hdr10plus_inject_cmd
-                .arg("inject")
-                .arg("-i")
-                .arg(&hdr10_for_dv)
-                .arg("-j")
-                .arg(&hdr10plus_metadata_path)
-                .arg("-o")
-                .arg(&hdr10plus_injected);
-            hide_console_window(&mut hdr10plus_inject_cmd);
-            let status = hdr10plus_inject_cmd.status().map_err(|e| e.to_string())?;
-
-            if !status.success() {
-                return Err("HDR10+ metadata injection failed".to_string());
-            }
-            hdr10_for_dv = hdr10plus_injected;
-            temp_files.push(hdr10_for_dv.clone());
         }
+        return Ok(None);
     }
 
-    let mut cmd4 = Command::new(&dovi_tool);
-    cmd4
-        .arg("inject-rpu")
-        .arg("-i")
-        .arg(&hdr10_for_dv)
-        .arg("--rpu-in")
-        .arg(&rpu_path)
-        .arg("-o")
-        .arg(&dv_hdr);
-
-    run_command(
-        state,
-        cmd4,
-        app,
-        5,
-        STEP_NAMES[4],
-        &hdr10_for_dv,
-        &dv_hdr,
-        false,
-        4,
-        STEP_NAMES.len(),
-        queue_ctx.as_ref(),
-    )?;
-
-    let mut cmd5 = Command::new(&mkvmerge);
-    cmd5
-        .arg("--ui-language")
-        .arg("en")
-        .arg("--no-date")
-        .arg("--output")
-        .arg(output_path);
-
-    if let Some(duration) = detected_duration {
-        cmd5.arg("--default-duration").arg(format!("0:{}", duration));
+    if !cover_art.is_file() {
+        return Err(validation_error(format!("Cover art not found: {}", cover_art.display())));
     }
+    if cover_art_mime(cover_art).is_none() {
+        return Err(validation_error(format!(
+            "Unsupported cover art type (use JPEG or PNG): {}",
+            cover_art.display()
+        )));
+    }
+    Ok(Some(cover_art.to_path_buf()))
+}
 
-    cmd5
-        .arg(&dv_hdr)
-        .arg(&audio_loc);
-
-    run_command(
-        state,
-        cmd5,
-        app,
-        6,
-        STEP_NAMES[5],
-        &dv_hdr,
-        output_path,
-        true,
-        5,
-        STEP_NAMES.len(),
-        queue_ctx.as_ref(),
-    )?;
+/// Write the verified output's sidecars, hashing it if asked, and copy it to
+/// the extra destinations. Returns the sidecars and the copies that failed,
+/// as warnings.
+fn finish_output(
+    app: &AppHandle,
+    state: &ProcessingState,
+    options: &PipelineOptions,
+    output_path: &Path,
+) -> Result<(Vec<OutputSidecar>, Vec<String>), String> {
+    let outputs = if options.split.is_some() {
+        find_split_parts(output_path)
+    } else {
+        vec![output_path.to_path_buf()]
+    };
+    let sidecars = write_sidecars(app, state, &outputs, options.hash_output)?;
 
-    if !keep_temp {
-        for file in temp_files.iter() {
-            let _ = fs::remove_file(file);
-        }
-        emit_log(app, "info", "Temporary files cleaned up.");
+    if options.additional_outputs.is_empty() {
+        emit_step(app, COPY_STEP_ID, COPY_STEP_NAME, "completed", 100);
+        return Ok((sidecars, Vec::new()));
     }
+    let copies = copy_to_destinations(app, state, &outputs, &options.additional_outputs, options.overwrite_policy)?;
+    let warnings = copies
+        .iter()
+        .filter(|copy| copy.status == "failed")
+        .map(|copy| {
+            format!(
+                "Copy to {} failed: {}",
+                copy.destination,
+                copy.error.as_deref().unwrap_or("unknown error")
+            )
+        })
+        .collect();
+    Ok((sidecars, warnings))
+}
 
-    if let Some(ctx) = &queue_ctx {
-        emit_queue(
-            app,
-            QueuePayload {
-                id: ctx.id.clone(),
-                status: "completed".to_string(),
-                progress: 100,
-                current_step: None,
-                active_workers: Some(0),
-                file_total: Some(ctx.file_total),
-            },
-        );
+/// The file the mux step leaves behind: the first part when splitting.
+fn mux_artifact(output_path: &Path, options: &PipelineOptions) -> PathBuf {
+    if options.split.is_some() {
+        find_split_parts(output_path)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| output_path.to_path_buf())
+    } else {
+        output_path.to_path_buf()
     }
-
-    Ok(())
 }
 
 pub fn process_queue_item(
     app_handle: AppHandle,
     state: ProcessingState,
     tool_paths: ToolPaths,
+    options: PipelineOptions,
     item: QueueItem,
     hdr10plus_path: Option<PathBuf>,
-    dv_delay_ms: f64,
+    dv_delay_ms: Option<f64>,
     hdr10plus_delay_ms: f64,
     keep_temp_files: bool,
 ) -> Result<(), String> {
@@ -917,35 +4591,42 @@ pub fn process_queue_item(
         } else {
             Vec::new()
         };
-        let mut hdr_files = fs::read_dir(&hdr_path)
-            .map_err(|e| e.to_string())?
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| entry.file_name().into_string().ok())
-            .collect::<Vec<String>>();
-
-        let mut dv_files = fs::read_dir(&dv_path)
-            .map_err(|e| e.to_string())?
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| entry.file_name().into_string().ok())
-            .collect::<Vec<String>>();
-
-        hdr_files.sort();
-        dv_files.sort();
+        let mut scan = scan_folder_pairs(&hdr_path, &dv_path, options.output_structure.recursive())?;
+        scan.order_pairs(&hdr_path, &dv_path, options.ordering);
         hdr10plus_files.sort();
 
         emit_log(
             &app_handle,
             "info",
-            format!("Found {} HDR files in {}", hdr_files.len(), hdr_path.display()),
+            format!("Found {} HDR files in {}", scan.hdr_file_count, hdr_path.display()),
         );
 
         let output_base = if item.output_path.is_empty() {
-            tool_paths.default_output.clone()
+            options.default_output_batch.clone()
         } else {
             item.output_path.clone()
         };
+        warn_output_in_inputs(&app_handle, Path::new(&output_base), &[&hdr_path, &dv_path]);
+
+        for pair in &scan.pairs {
+            emit_log(
+                &app_handle,
+                "info",
+                format!("Paired {} with {} (by {})", pair.hdr_file, pair.dv_file, pair.matched_by),
+            );
+        }
+        let mut report = scan.report(Some(&item.id), &hdr_path);
+        check_unmatched(&app_handle, &options, &scan)?;
+        let pairs = scan.pairs;
 
-        let total_files = hdr_files.len().max(1);
+        let total_files = pairs.len().max(1);
+        let bytes = pairs
+            .iter()
+            .map(|pair| input_bytes(&[&hdr_path.join(&pair.hdr_file), &dv_path.join(&pair.dv_file)]))
+            .sum();
+        if let Ok(mut job) = state.job_progress.lock() {
+            job.set_expected(&item.id, total_files, bytes);
+        }
         emit_queue(
             &app_handle,
             QueuePayload {
@@ -955,136 +4636,343 @@ pub fn process_queue_item(
                 current_step: Some("Scanning folders".to_string()),
                 active_workers: Some(0),
                 file_total: Some(total_files),
+                counts: Some(FileCounts {
+                    files_queued: total_files,
+                    ..FileCounts::default()
+                }),
             },
         );
 
         let mut tasks = Vec::new();
-        for (index, hdr_file) in hdr_files.iter().enumerate() {
-            let base_regex = Regex::new(r"(.*)\.(HDR)+.*").map_err(|e| e.to_string())?;
-            let base = base_regex
-                .captures(hdr_file)
-                .and_then(|c| c.get(1).map(|m| m.as_str()))
-                .unwrap_or_else(|| hdr_file.split('.').next().unwrap_or(hdr_file));
-
-            let dv_file = find_matching_dv_file(&dv_files, base)
-                .or_else(|| dv_files.get(index).cloned())
-                .ok_or_else(|| format!("No DV file available for {}", hdr_file))?;
-
-            let hdr_file_path = hdr_path.join(hdr_file);
+        let mut duplicates = Vec::new();
+        for (index, pair) in pairs.into_iter().enumerate() {
+            let FolderPair {
+                hdr_index,
+                hdr_file,
+                base,
+                dv_file,
+                matched_by,
+            } = pair;
+            let hdr_file_path = hdr_path.join(&hdr_file);
             let hdr10plus_file_path = if let Some(dir) = hdr10plus_dir {
                 if dir == &hdr_path {
                     Some(hdr_file_path.clone())
                 } else {
-                    find_matching_dv_file(&hdr10plus_files, base)
-                        .or_else(|| hdr10plus_files.get(index).cloned())
+                    find_matching_dv_file(&hdr10plus_files, &base)
+                        .or_else(|| hdr10plus_files.get(hdr_index).cloned())
                         .map(|name| dir.join(name))
                 }
             } else {
                 hdr10plus_path.clone()
             };
-            let dv_file_path = dv_path.join(dv_file);
-            let output_path = compute_output_for_batch(&output_base, hdr_file);
+            let dv_file_path = dv_path.join(&dv_file);
+            let file_key = format!("{}:{}", item.id, index);
+            if let Some(original) = claim_pair(&app_handle, &state, &hdr_file_path, &dv_file_path, &file_key)? {
+                if let Ok(mut job) = state.job_progress.lock() {
+                    job.mark_completed(&file_key);
+                }
+                emit_file(
+                    &app_handle,
+                    FilePayload {
+                        id: file_key,
+                        queue_id: item.id.clone(),
+                        name: hdr_file.clone(),
+                        status: "skipped".to_string(),
+                        progress: 100,
+                        attempt: 1,
+                        reason: Some(StopReason::Duplicate(original)),
+                        hashes: Vec::new(),
+                    },
+                );
+                report.skip(hdr_file, ScanSkipReason::Duplicate);
+                duplicates.push(index);
+                continue;
+            }
+            let output_path = claim_batch_output(
+                &app_handle,
+                &state,
+                &options,
+                &output_base,
+                &hdr_file,
+                &hdr_file_path,
+            )?;
+            report.add_pair(&hdr_file, &dv_file, &matched_by, &output_path);
             let label = format!("{}/{} {}", index + 1, total_files, hdr_file);
 
             tasks.push((
                 index,
                 label,
-                hdr_file.to_string(),
+                hdr_file,
                 hdr_file_path,
                 hdr10plus_file_path,
                 dv_file_path,
                 output_path,
             ));
         }
+        emit_scan_report(&app_handle, &options, report);
 
-        let worker_count = total_files;
+        // Probe every input up front so workers start from the run's cache.
+        // The claims above created the output folder the probes run in.
+        let tools = ProbeTools::new(
+            &resolve_path(&app_handle, &tool_paths.mediainfo),
+            &resolve_path(&app_handle, &tool_paths.mkvmerge),
+            Path::new(&output_base),
+        );
+        let probe_targets: Vec<PathBuf> = tasks
+            .iter()
+            .flat_map(|task| [task.3.clone(), task.5.clone()])
+            .collect();
+        let probe_failures = state
+            .probe
+            .probe_many(&tools, &probe_targets, PROBE_CONCURRENCY)
+            .iter()
+            .filter(|result| result.is_err())
+            .count();
+        emit_log(
+            &app_handle,
+            "info",
+            format!(
+                "Probed {} input(s) ({} failed, will be retried per file)",
+                probe_targets.len(),
+                probe_failures
+            ),
+        );
+
+        let worker_count = options.parallel_tasks.clamp(1, total_files.max(1));
         let task_queue = Arc::new(Mutex::new(std::collections::VecDeque::from(tasks)));
-        let tracker = Arc::new(Mutex::new(vec![0u8; total_files]));
+        let mut slots = vec![FileSlot::default(); total_files];
+        for index in duplicates {
+            slots[index] = FileSlot {
+                progress: 100,
+                state: FileState::Completed,
+            };
+        }
+        let tracker: FileTracker = Arc::new(Mutex::new(slots));
         let active_workers = Arc::new(Mutex::new(0usize));
-        let error_state = Arc::new(Mutex::new(None::<String>));
+        let error_state = Arc::new(Mutex::new(Vec::<(String, String)>::new()));
+        // With retries enabled, failures are parked here instead of stopping the queue.
+        let failed_tasks = Arc::new(Mutex::new(Vec::new()));
         let queue_id = item.id.clone();
+        let retry_limit = options.item_retry_count;
 
-        let mut handles = Vec::new();
-        for _ in 0..worker_count {
-            let task_queue = Arc::clone(&task_queue);
-            let error_state = Arc::clone(&error_state);
-            let tracker = Arc::clone(&tracker);
-            let active_workers = Arc::clone(&active_workers);
-            let app_handle = app_handle.clone();
-            let state = state.clone();
-            let tool_paths = tool_paths.clone();
-            let queue_id = queue_id.clone();
-            let hdr10plus_path = hdr10plus_path.clone();
-
-            let handle = thread::spawn(move || loop {
-                if let Ok(flag) = state.cancel_flag.lock() {
-                    if *flag {
-                        break;
+        let mut pass = 0;
+        loop {
+            let mut handles = Vec::new();
+            for _ in 0..worker_count {
+                let task_queue = Arc::clone(&task_queue);
+                let error_state = Arc::clone(&error_state);
+                let failed_tasks = Arc::clone(&failed_tasks);
+                let tracker = Arc::clone(&tracker);
+                let active_workers = Arc::clone(&active_workers);
+                let app_handle = app_handle.clone();
+                let state = state.clone();
+                let tool_paths = tool_paths.clone();
+                let options = options.clone();
+                let queue_id = queue_id.clone();
+
+                let handle = thread::spawn(move || loop {
+                    if let Ok(flag) = state.cancel_flag.lock() {
+                        if *flag {
+                            break;
+                        }
                     }
-                }
 
-                if error_state.lock().map(|e| e.is_some()).unwrap_or(true) {
-                    break;
-                }
+                    if error_state.lock().map(|e| !e.is_empty()).unwrap_or(true) {
+                        break;
+                    }
 
-                let task = {
-                    let mut guard = task_queue.lock().unwrap();
-                    guard.pop_front()
-                };
+                    let task = {
+                        let mut guard = task_queue.lock().unwrap();
+                        guard.pop_front()
+                    };
 
-                let Some((index, label, file_name, hdr_file_path, hdr10plus_file_path, dv_file_path, output_path)) =
-                    task
-                else {
-                    break;
-                };
+                    let Some(task) = task else {
+                        break;
+                    };
+                    let (index, label, file_name, hdr_file_path, hdr10plus_file_path, dv_file_path, output_path) =
+                        &task;
 
-                if let Ok(mut count) = active_workers.lock() {
-                    *count += 1;
-                }
+                    if let Ok(mut count) = active_workers.lock() {
+                        *count += 1;
+                    }
 
-                let result = run_pipeline(
-                    &app_handle,
-                    &state,
-                    &tool_paths,
-                    &hdr_file_path,
-                    &dv_file_path,
-                    hdr10plus_file_path.as_deref(),
-                    &output_path,
-                    dv_delay_ms,
-                    hdr10plus_delay_ms,
-                    keep_temp_files,
-                    Some(&queue_id),
-                    Some(&label),
-                    Some(&file_name),
-                    index,
-                    total_files,
-                    Some(Arc::clone(&tracker)),
-                    Some(Arc::clone(&active_workers)),
-                );
+                    let result = run_pipeline(
+                        &app_handle,
+                        &state,
+                        &tool_paths,
+                        &options,
+                        hdr_file_path,
+                        dv_file_path,
+                        hdr10plus_file_path.as_deref(),
+                        output_path,
+                        dv_delay_ms,
+                        hdr10plus_delay_ms,
+                        keep_temp_files,
+                        Some(&queue_id),
+                        Some(label),
+                        Some(file_name),
+                        *index,
+                        total_files,
+                        Some(Arc::clone(&tracker)),
+                        Some(Arc::clone(&active_workers)),
+                    );
 
-                if let Ok(mut count) = active_workers.lock() {
-                    *count = count.saturating_sub(1);
-                }
+                    if let Ok(mut count) = active_workers.lock() {
+                        *count = count.saturating_sub(1);
+                    }
 
-                if let Err(err) = result {
-                    let _ = error_state.lock().map(|mut e| {
-                        if e.is_none() {
-                            *e = Some(err);
+                    // Cancelled files never finished, so they count as queued.
+                    let outcome = match &result {
+                        Ok(()) => FileState::Completed,
+                        Err(err) if err == "Processing cancelled" => FileState::Queued,
+                        Err(_) => FileState::Failed,
+                    };
+                    let ctx = folder_file_context(&queue_id, *index, total_files, &tracker, &active_workers);
+                    update_queue_file(&app_handle, &ctx, None, |slot| {
+                        slot.state = outcome;
+                        if outcome != FileState::Queued {
+                            slot.progress = 100;
                         }
                     });
-                    break;
+
+                    if let Err(err) = result {
+                        if retry_limit > 0 && err != "Processing cancelled" && !is_validation_error(&err) {
+                            emit_log(&app_handle, "warning", format!("{} failed: {}", label, err));
+                            if let Ok(mut failed) = failed_tasks.lock() {
+                                failed.push((task, err));
+                            }
+                            continue;
+                        }
+                        if err != "Processing cancelled" {
+                            state.note_stop(StopReason::SiblingFailed(task.2.clone()));
+                        }
+                        if let Ok(mut errors) = error_state.lock() {
+                            errors.push((task.2.clone(), err));
+                        }
+                        break;
+                    }
+                });
+                handles.push(handle);
+            }
+
+            for handle in handles {
+                let _ = handle.join();
+            }
+
+            // Files that never started get a terminal event saying why.
+            let stop_reason = state.stop_reason();
+            if let Some(reason) = &stop_reason {
+                let unstarted: Vec<_> = task_queue
+                    .lock()
+                    .map(|mut queue| queue.drain(..).collect())
+                    .unwrap_or_default();
+                for task in unstarted {
+                    let file_key = format!("{}:{}", queue_id, task.0);
+                    emit_file(
+                        &app_handle,
+                        FilePayload {
+                            attempt: state.job_progress.lock().map(|job| job.attempt(&file_key)).unwrap_or(1),
+                            id: file_key,
+                            queue_id: queue_id.clone(),
+                            name: task.2.clone(),
+                            status: "stopped".to_string(),
+                            progress: 0,
+                            reason: Some(reason.clone()),
+                            hashes: Vec::new(),
+                        },
+                    );
                 }
-            });
-            handles.push(handle);
-        }
+            }
 
-        for handle in handles {
-            let _ = handle.join();
+            let failed = std::mem::take(&mut *failed_tasks.lock().map_err(|_| "State lock failed")?);
+            let errors = std::mem::take(&mut *error_state.lock().map_err(|_| "State lock failed")?);
+            if !errors.is_empty() {
+                // Files parked for a retry that will not happen failed too.
+                let parked = failed.iter().map(|(task, err)| (task.2.clone(), err.clone()));
+                failure_report(errors.into_iter().chain(parked).collect())?;
+            }
+            if *state.cancel_flag.lock().map_err(|_| "State lock failed")? {
+                return Err("Processing cancelled".to_string());
+            }
+
+            if failed.is_empty() {
+                break;
+            }
+            if pass >= retry_limit {
+                let attempts = retry_limit + 1;
+                failure_report(
+                    failed
+                        .iter()
+                        .map(|(task, err)| {
+                            (
+                                task.2.clone(),
+                                format!("{} still failing after {} attempt(s): {}", task.2, attempts, err),
+                            )
+                        })
+                        .collect(),
+                )?;
+            }
+            pass += 1;
+
+            emit_log(
+                &app_handle,
+                "warning",
+                format!(
+                    "Retrying {} failed file(s) in {}s (retry pass {}/{})",
+                    failed.len(),
+                    options.item_retry_delay_secs,
+                    pass,
+                    retry_limit
+                ),
+            );
+            let resume_at = std::time::Instant::now() + Duration::from_secs(options.item_retry_delay_secs);
+            while std::time::Instant::now() < resume_at {
+                if *state.cancel_flag.lock().map_err(|_| "State lock failed")? {
+                    return Err("Processing cancelled".to_string());
+                }
+                thread::sleep(Duration::from_millis(250));
+            }
+
+            let mut queue = task_queue.lock().map_err(|_| "State lock failed")?;
+            for (task, _) in failed {
+                let file_key = format!("{}:{}", queue_id, task.0);
+                let attempt = state
+                    .job_progress
+                    .lock()
+                    .map(|mut job| {
+                        job.retry(&file_key);
+                        job.attempt(&file_key)
+                    })
+                    .unwrap_or(1);
+                let ctx = folder_file_context(&queue_id, task.0, total_files, &tracker, &active_workers);
+                update_queue_file(&app_handle, &ctx, None, |slot| *slot = FileSlot::default());
+                emit_file(
+                    &app_handle,
+                    FilePayload {
+                        id: file_key,
+                        queue_id: queue_id.clone(),
+                        name: task.2.clone(),
+                        status: "queued".to_string(),
+                        progress: 0,
+                        attempt,
+                        reason: None,
+                        hashes: Vec::new(),
+                    },
+                );
+                queue.push_back(task);
+            }
         }
 
-        if let Ok(mut guard) = error_state.lock() {
-            if let Some(err) = guard.take() {
-                return Err(err);
+        if let Ok(job) = state.job_progress.lock() {
+            for index in 0..total_files {
+                let attempts = job.attempt(&format!("{}:{}", queue_id, index));
+                if attempts > 1 {
+                    emit_log(
+                        &app_handle,
+                        "info",
+                        format!("File {}/{} needed {} attempts", index + 1, total_files, attempts),
+                    );
+                }
             }
         }
 
@@ -1097,19 +4985,36 @@ pub fn process_queue_item(
                 current_step: None,
                 active_workers: Some(0),
                 file_total: Some(total_files),
+                counts: tracker.lock().ok().map(|slots| FileCounts::of(&slots)),
             },
         );
     } else {
-        let output_path = if item.output_path.is_empty() {
-            compute_output_for_single(&tool_paths.default_output, "", &hdr_path)
-        } else {
-            normalize_output_path(&tool_paths.default_output, &item.output_path)
-        };
+        if let Some(original) = claim_pair(&app_handle, &state, &hdr_path, &dv_path, &item.id)? {
+            if let Ok(mut job) = state.job_progress.lock() {
+                job.mark_completed(&format!("{}:0", item.id));
+            }
+            emit_queue(
+                &app_handle,
+                QueuePayload {
+                    id: item.id.clone(),
+                    status: "skipped".to_string(),
+                    progress: 100,
+                    current_step: Some(StopReason::Duplicate(original).describe()),
+                    active_workers: Some(0),
+                    file_total: Some(1),
+                    counts: None,
+                },
+            );
+            return Ok(());
+        }
+        let output_path = queue_item_output(&options, &item, &hdr_path);
+        let output_path = claim_output(&app_handle, &state, &options, &output_path, &hdr_path)?;
 
         run_pipeline(
             &app_handle,
             &state,
             &tool_paths,
+            &options,
             &hdr_path,
             &dv_path,
             hdr10plus_path.as_deref(),
@@ -1129,3 +5034,100 @@ pub fn process_queue_item(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::tests::fixture_report;
+
+    #[test]
+    fn dv_only_accepts_profile_8() {
+        let report = fixture_report("mediainfo_dv_p8.json");
+        assert_eq!(check_dv_only_input(Path::new("Movie.DV.mkv"), &report), Ok(()));
+    }
+
+    #[test]
+    fn dv_only_rejects_profile_7() {
+        let report = fixture_report("mediainfo_dv_p7.json");
+        let err = check_dv_only_input(Path::new("Movie.DV.mkv"), &report).unwrap_err();
+        assert!(is_validation_error(&err));
+        assert!(err.contains("is profile 7 (dvhe.07, level 06, BL+EL+RPU)"), "{}", err);
+    }
+
+    #[test]
+    fn dv_only_rejects_raw_hevc() {
+        let report = fixture_report("mediainfo_dv_p8.json");
+        let err = check_dv_only_input(Path::new("Movie.DV.hevc"), &report).unwrap_err();
+        assert!(err.contains("must be an MKV or MP4"), "{}", err);
+    }
+
+    #[test]
+    fn pad_encode_carries_source_mastering_metadata() {
+        let report = fixture_report("mediainfo_dv_p8.json");
+        assert_eq!(
+            pad_x265_params(&report),
+            "hdr10=1:repeat-headers=1:colorprim=bt2020:transfer=smpte2084:colormatrix=bt2020nc\
+             :master-display=G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)L(10000000,1)\
+             :max-cll=1000,400"
+        );
+    }
+
+    #[test]
+    fn read_only_input_is_staged_into_the_work_dir() {
+        let input = std::env::temp_dir().join(format!("hybrid-dv-read-only-{}.mkv", std::process::id()));
+        fs::write(&input, b"").unwrap();
+        let writable = fs::metadata(&input).unwrap().permissions();
+        let mut permissions = writable.clone();
+        permissions.set_readonly(true);
+        fs::set_permissions(&input, permissions).unwrap();
+        let read_only = is_read_only(&input);
+        fs::set_permissions(&input, writable).unwrap();
+        let _ = fs::remove_file(&input);
+        assert!(read_only);
+
+        let work_dir = Path::new("/jobs/movie");
+        let options = PipelineOptions::default();
+        let read_only_inputs = [input.as_path()];
+        assert_eq!(
+            staging_dir_for(&options, false, &read_only_inputs, work_dir),
+            Some(work_dir.to_path_buf())
+        );
+        assert_eq!(staging_dir_for(&options, true, &read_only_inputs, work_dir), None);
+        assert_eq!(staging_dir_for(&options, false, &[], work_dir), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn chatty_tool_is_drained_without_blocking() {
+        // Far more than a pipe buffer on both streams, ending in a progress
+        // line that never gets a newline.
+        let script = "i=0; while [ $i -lt 5000 ]; do echo \"Warning: line $i\"; echo \"progress $i\" >&2; \
+                      i=$((i+1)); done; printf '%020000d' 0";
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = drain_pipe(child.stdout.take().unwrap());
+        let stderr = drain_pipe(child.stderr.take().unwrap());
+
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while child.try_wait().unwrap().is_none() {
+            assert!(Instant::now() < deadline, "the tool blocked on a full pipe");
+            thread::sleep(Duration::from_millis(20));
+        }
+        let (stdout, stderr) = (stdout.join().unwrap(), stderr.join().unwrap());
+
+        assert_eq!(stdout.tail.len(), CAPTURE_MAX_LINES);
+        assert_eq!(stdout.warnings.len(), CAPTURE_MAX_LINES);
+        assert_eq!(stdout.warnings[0], "Warning: line 0");
+        let last = stdout.tail.last().unwrap();
+        assert_eq!(last.len(), CAPTURE_MAX_LINE_BYTES);
+        assert_eq!(stdout.tail[CAPTURE_MAX_LINES - 2], "Warning: line 4999");
+        assert_eq!(stderr.tail.len(), CAPTURE_MAX_LINES);
+        assert_eq!(stderr.tail.last().map(String::as_str), Some("progress 4999"));
+        assert!(stderr.warnings.is_empty());
+    }
+}
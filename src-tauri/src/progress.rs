@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use tauri::AppHandle;
+
+use crate::models::{FileCounts, FilePayload, QueuePayload};
+use crate::utils::emit_event;
+
+/// Default progress cadence: how often running steps are polled and how
+/// often coalesced progress events are flushed.
+pub const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+const MIN_PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+const MAX_PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+pub enum ProgressUpdate {
+    File(FilePayload),
+    Queue(QueuePayload),
+}
+
+/// Single emitter for `processing:file` and `processing:queue` during a run.
+/// Workers submit updates over a channel; "processing" updates are coalesced
+/// to the latest one per file/queue and flushed once per interval, skipping
+/// any that would not change what the UI shows. Every other status is a
+/// state change and goes out immediately, in submission order.
+#[derive(Clone, Default)]
+pub struct ProgressHub {
+    sender: Arc<Mutex<Option<Sender<ProgressUpdate>>>>,
+    worker: Arc<Mutex<Option<JoinHandle<()>>>>,
+    interval: Arc<Mutex<Option<Duration>>>,
+}
+
+impl ProgressHub {
+    /// Start the emitter for a run, replacing any previous one.
+    pub fn start(&self, app: &AppHandle, interval: Option<Duration>) {
+        self.stop();
+        let interval = interval
+            .unwrap_or(DEFAULT_PROGRESS_INTERVAL)
+            .clamp(MIN_PROGRESS_INTERVAL, MAX_PROGRESS_INTERVAL);
+        let (sender, receiver) = mpsc::channel();
+        let app = app.clone();
+        let worker = thread::spawn(move || {
+            let mut emitter = Emitter::default();
+            let mut next_flush = Instant::now() + interval;
+            loop {
+                let timeout = next_flush.saturating_duration_since(Instant::now());
+                match receiver.recv_timeout(timeout) {
+                    Ok(update) => {
+                        if let Some(update) = emitter.accept(update) {
+                            emit_update(&app, update);
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        emitter.flush().into_iter().for_each(|update| emit_update(&app, update));
+                        next_flush = Instant::now() + interval;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        emitter.flush().into_iter().for_each(|update| emit_update(&app, update));
+                        break;
+                    }
+                }
+            }
+        });
+
+        if let Ok(mut guard) = self.sender.lock() {
+            *guard = Some(sender);
+        }
+        if let Ok(mut guard) = self.worker.lock() {
+            *guard = Some(worker);
+        }
+        if let Ok(mut guard) = self.interval.lock() {
+            *guard = Some(interval);
+        }
+    }
+
+    /// Flush whatever is pending and stop the emitter. Returns once every
+    /// submitted update has been emitted.
+    pub fn stop(&self) {
+        if let Ok(mut guard) = self.sender.lock() {
+            guard.take();
+        }
+        let worker = self.worker.lock().ok().and_then(|mut guard| guard.take());
+        if let Some(worker) = worker {
+            let _ = worker.join();
+        }
+    }
+
+    /// Hand an update to the emitter. It is given back when no run is active.
+    pub fn submit(&self, update: ProgressUpdate) -> Option<ProgressUpdate> {
+        let Ok(guard) = self.sender.lock() else {
+            return Some(update);
+        };
+        match guard.as_ref() {
+            Some(sender) => sender.send(update).err().map(|err| err.0),
+            None => Some(update),
+        }
+    }
+
+    /// Poll/flush cadence of the current run.
+    pub fn interval(&self) -> Duration {
+        self.interval
+            .lock()
+            .ok()
+            .and_then(|guard| *guard)
+            .unwrap_or(DEFAULT_PROGRESS_INTERVAL)
+    }
+}
+
+#[derive(Default)]
+struct Emitter {
+    pending_files: HashMap<String, FilePayload>,
+    pending_queues: HashMap<String, QueuePayload>,
+    shown_files: HashMap<String, (String, u8)>,
+    shown_queues: HashMap<String, QueueKey>,
+}
+
+type QueueKey = (String, u8, Option<String>, Option<usize>, Option<FileCounts>);
+
+fn file_key(payload: &FilePayload) -> (String, u8) {
+    (payload.status.clone(), payload.progress)
+}
+
+fn queue_key(payload: &QueuePayload) -> QueueKey {
+    (
+        payload.status.clone(),
+        payload.progress,
+        payload.current_step.clone(),
+        payload.active_workers,
+        payload.counts,
+    )
+}
+
+impl Emitter {
+    /// The update to emit right away, if any; "processing" ones wait for the
+    /// next flush.
+    fn accept(&mut self, update: ProgressUpdate) -> Option<ProgressUpdate> {
+        match update {
+            ProgressUpdate::File(payload) if payload.status == "processing" => {
+                self.pending_files.insert(payload.id.clone(), payload);
+                None
+            }
+            ProgressUpdate::File(payload) => {
+                self.pending_files.remove(&payload.id);
+                Some(self.shown_file(payload))
+            }
+            ProgressUpdate::Queue(payload) if payload.status == "processing" => {
+                self.pending_queues.insert(payload.id.clone(), payload);
+                None
+            }
+            ProgressUpdate::Queue(payload) => {
+                self.pending_queues.remove(&payload.id);
+                Some(self.shown_queue(payload))
+            }
+        }
+    }
+
+    /// Pending updates that change what the UI shows, queues first.
+    fn flush(&mut self) -> Vec<ProgressUpdate> {
+        let mut updates = Vec::new();
+        for (_, payload) in std::mem::take(&mut self.pending_queues) {
+            if self.shown_queues.get(&payload.id) != Some(&queue_key(&payload)) {
+                updates.push(self.shown_queue(payload));
+            }
+        }
+        for (_, payload) in std::mem::take(&mut self.pending_files) {
+            if self.shown_files.get(&payload.id) != Some(&file_key(&payload)) {
+                updates.push(self.shown_file(payload));
+            }
+        }
+        updates
+    }
+
+    fn shown_file(&mut self, payload: FilePayload) -> ProgressUpdate {
+        self.shown_files.insert(payload.id.clone(), file_key(&payload));
+        ProgressUpdate::File(payload)
+    }
+
+    fn shown_queue(&mut self, payload: QueuePayload) -> ProgressUpdate {
+        self.shown_queues.insert(payload.id.clone(), queue_key(&payload));
+        ProgressUpdate::Queue(payload)
+    }
+}
+
+/// Send an update to the UI and the other listeners as-is.
+pub fn emit_update(app: &AppHandle, update: ProgressUpdate) {
+    match update {
+        ProgressUpdate::File(payload) => emit_event(app, "processing:file", payload),
+        ProgressUpdate::Queue(payload) => emit_event(app, "processing:queue", payload),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(id: &str, status: &str, progress: u8) -> ProgressUpdate {
+        ProgressUpdate::File(FilePayload {
+            id: id.to_string(),
+            queue_id: "q1".to_string(),
+            name: format!("{}.mkv", id),
+            status: status.to_string(),
+            progress,
+            attempt: 1,
+            reason: None,
+            hashes: Vec::new(),
+        })
+    }
+
+    fn file_progress(updates: &[ProgressUpdate]) -> Vec<(String, String, u8)> {
+        updates
+            .iter()
+            .filter_map(|update| match update {
+                ProgressUpdate::File(payload) => Some((payload.id.clone(), payload.status.clone(), payload.progress)),
+                ProgressUpdate::Queue(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn processing_updates_coalesce_to_the_latest() {
+        let mut emitter = Emitter::default();
+        for progress in [10, 20, 30] {
+            assert!(emitter.accept(file("a", "processing", progress)).is_none());
+        }
+        assert!(emitter.accept(file("b", "processing", 5)).is_none());
+        let mut flushed = file_progress(&emitter.flush());
+        flushed.sort();
+        assert_eq!(
+            flushed,
+            vec![
+                ("a".to_string(), "processing".to_string(), 30),
+                ("b".to_string(), "processing".to_string(), 5),
+            ]
+        );
+        assert!(emitter.flush().is_empty());
+    }
+
+    #[test]
+    fn unchanged_progress_is_not_flushed_again() {
+        let mut emitter = Emitter::default();
+        emitter.accept(file("a", "processing", 40));
+        assert_eq!(emitter.flush().len(), 1);
+        emitter.accept(file("a", "processing", 40));
+        assert!(emitter.flush().is_empty());
+    }
+
+    #[test]
+    fn state_changes_go_out_at_once_and_drop_pending_progress() {
+        let mut emitter = Emitter::default();
+        emitter.accept(file("a", "processing", 90));
+        let finished = emitter.accept(file("a", "completed", 100));
+        assert_eq!(
+            file_progress(finished.as_slice()),
+            vec![("a".to_string(), "completed".to_string(), 100)]
+        );
+        assert!(emitter.flush().is_empty());
+    }
+}
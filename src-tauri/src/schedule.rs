@@ -0,0 +1,285 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::estimate::collect_pairs;
+use crate::models::{PipelineOptions, ProcessingRequest, ProcessingState};
+use crate::processing::require_tool;
+use crate::utils::{emit_event, emit_log, emit_status, emit_status_message};
+use crate::volume::available_bytes;
+
+/// How often the wait checks for a cancel.
+const WAIT_POLL: Duration = Duration::from_millis(250);
+/// Local date-time layouts accepted besides RFC 3339 with an offset.
+const LOCAL_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"];
+
+/// Sent as `processing:scheduled` once a second while a run waits to start.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledPayload {
+    pub start_at: String,
+    pub remaining_secs: u64,
+}
+
+/// Daily hours a run may work in, as local `HH:MM`. An end before the start
+/// spans midnight.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessingWindow {
+    pub start: String,
+    pub end: String,
+    /// Once the window closes, also pause running files before their next
+    /// step instead of only holding back files not started yet.
+    #[serde(default)]
+    pub pause_at_boundary: bool,
+}
+
+impl ProcessingWindow {
+    pub fn bounds(&self) -> Result<(NaiveTime, NaiveTime), String> {
+        let parse = |value: &str| {
+            NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                .map_err(|_| format!("Processing window time {} is not HH:MM", value.trim()))
+        };
+        let (start, end) = (parse(&self.start)?, parse(&self.end)?);
+        if start == end {
+            return Err("The processing window starts and ends at the same time".to_string());
+        }
+        Ok((start, end))
+    }
+
+    /// When the window next opens, or `None` while it is open at `now`.
+    pub fn closed_until(&self, now: NaiveTime) -> Result<Option<NaiveTime>, String> {
+        let (start, end) = self.bounds()?;
+        let open = if start < end {
+            start <= now && now < end
+        } else {
+            now >= start || now < end
+        };
+        Ok((!open).then_some(start))
+    }
+}
+
+/// Parse `start_at` as ISO-8601: local time such as `2024-05-01T01:30`, or
+/// with an explicit offset. Times that have already passed are rejected.
+pub fn parse_start_at(value: &str) -> Result<DateTime<Local>, String> {
+    let value = value.trim();
+    let start = match DateTime::parse_from_rfc3339(value) {
+        Ok(time) => time.with_timezone(&Local),
+        Err(_) => {
+            let naive = LOCAL_FORMATS
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+                .ok_or_else(|| {
+                    format!("Start time {} is not an ISO-8601 date and time, e.g. 2024-05-01T01:30", value)
+                })?;
+            Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .ok_or_else(|| format!("Start time {} does not exist in the local time zone", value))?
+        }
+    };
+    if start <= Local::now() {
+        return Err(format!("Start time {} has already passed", start.format("%Y-%m-%d %H:%M:%S")));
+    }
+    Ok(start)
+}
+
+/// Nearest existing folder at or above `path`, where free space can be read.
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find(|dir| dir.is_dir()).map(Path::to_path_buf)
+}
+
+/// The checks a run would otherwise only hit once it starts: tools
+/// installed, inputs present and paired, and room on the output volume for
+/// the outputs plus the largest file's intermediates.
+pub fn preflight(app: &AppHandle, request: &ProcessingRequest, options: &PipelineOptions) -> Result<(), String> {
+    let tools = &request.tool_paths;
+    require_tool(app, &tools.dovi_tool, "dovi_tool", "Processing")?;
+    require_tool(app, &tools.mkvmerge, "mkvmerge", "Processing")?;
+    require_tool(app, &tools.mkvextract, "mkvextract", "Processing")?;
+    require_tool(app, &tools.mediainfo, "MediaInfo", "Processing")?;
+    if options.audio_transcode.is_some() {
+        require_tool(app, &tools.ffmpeg, "ffmpeg", "Audio transcoding")?;
+    }
+    if options.detect_letterbox {
+        require_tool(app, &tools.ffmpeg, "ffmpeg", "Letterbox detection")?;
+    }
+    if !request.hdr10plus_path.trim().is_empty() {
+        require_tool(app, &tools.hdr10plus_tool, "hdr10plus_tool", "HDR10+ metadata")?;
+    }
+
+    let (pairs, unmatched) = collect_pairs(request)?;
+    if pairs.is_empty() {
+        return Err("Nothing to process: no HDR/DV pairs were found".to_string());
+    }
+    if options.strict_pairing && !unmatched.is_empty() {
+        return Err(format!("No DV file could be paired with: {}", unmatched.join(", ")));
+    }
+    let mut total = 0u64;
+    let mut largest = 0u64;
+    let input_size = |path: &Path, role: &str| {
+        std::fs::metadata(path)
+            .map(|metadata| metadata.len())
+            .map_err(|e| format!("{} {} is not readable: {}", role, path.display(), e))
+    };
+    for (_, hdr, dv) in &pairs {
+        let hdr_bytes = input_size(hdr, "HDR input")?;
+        let dv_bytes = input_size(dv, "DV input")?;
+        total += hdr_bytes;
+        largest = largest.max(hdr_bytes + dv_bytes);
+    }
+
+    let output = if !request.output_path.is_empty() {
+        PathBuf::from(&request.output_path)
+    } else if pairs.len() > 1 || request.mode == "batch" {
+        PathBuf::from(&options.default_output_batch)
+    } else {
+        PathBuf::from(&options.default_output)
+    };
+    let needed = total + largest * 2;
+    match existing_ancestor(&output).and_then(|dir| available_bytes(&dir).map(|free| (dir, free))) {
+        Some((dir, free)) if free < needed => {
+            return Err(format!(
+                "Not enough free space in {}: about {:.1} GB needed, {:.1} GB free",
+                dir.display(),
+                needed as f64 / (1u64 << 30) as f64,
+                free as f64 / (1u64 << 30) as f64
+            ));
+        }
+        Some(_) => {}
+        None => emit_log(app, "warning", "Could not read the free space of the output folder; not checking it"),
+    }
+    emit_log(
+        app,
+        "info",
+        format!("Pre-flight checks passed for {} pair(s)", pairs.len()),
+    );
+    Ok(())
+}
+
+/// Hold the run until `start`, emitting a `scheduled` status and a countdown.
+/// A cancel while waiting ends the run before anything has been touched.
+pub fn wait_until(app: &AppHandle, state: &ProcessingState, start: DateTime<Local>) -> Result<(), String> {
+    let start_at = start.to_rfc3339();
+    emit_status(app, "scheduled");
+    emit_log(
+        app,
+        "info",
+        format!("Run scheduled for {}", start.format("%Y-%m-%d %H:%M:%S")),
+    );
+    let mut last_sent = None;
+    loop {
+        if state.cancel_flag.lock().map(|flag| *flag).unwrap_or(false) {
+            return Err("Processing cancelled".to_string());
+        }
+        let remaining_ms = (start - Local::now()).num_milliseconds();
+        if remaining_ms <= 0 {
+            break;
+        }
+        let remaining = (remaining_ms as u64).div_ceil(1000);
+        if last_sent != Some(remaining) {
+            last_sent = Some(remaining);
+            emit_event(
+                app,
+                "processing:scheduled",
+                ScheduledPayload {
+                    start_at: start_at.clone(),
+                    remaining_secs: remaining,
+                },
+            );
+        }
+        thread::sleep(WAIT_POLL);
+    }
+    emit_log(app, "info", "Scheduled start time reached");
+    Ok(())
+}
+
+/// Hold a worker while the run's processing window is closed, with a
+/// `paused` status saying when it reopens. `boundary` marks a check between
+/// the steps of a file already running, which only waits when the window
+/// pauses at its boundary. A cancel ends the wait.
+pub fn wait_for_window(app: &AppHandle, state: &ProcessingState, boundary: bool) -> Result<(), String> {
+    let Some(window) = state.window.lock().ok().and_then(|window| window.clone()) else {
+        return Ok(());
+    };
+    if boundary && !window.pause_at_boundary {
+        return Ok(());
+    }
+    let mut announced = false;
+    loop {
+        if state.cancel_flag.lock().map(|flag| *flag).unwrap_or(false) {
+            return Err("Processing cancelled".to_string());
+        }
+        let Some(opens) = window.closed_until(Local::now().time())? else {
+            break;
+        };
+        if !announced {
+            announced = true;
+            let message = format!("paused (outside processing window, resumes at {})", opens.format("%H:%M"));
+            emit_log(app, "info", format!("Processing window closed; {}", message));
+            if state.status.lock().map(|status| status.as_str() != "paused").unwrap_or(true) {
+                emit_status_message(app, "paused", message);
+            }
+        }
+        thread::sleep(WAIT_POLL);
+    }
+    if announced && state.status.lock().map(|status| status.as_str() == "paused").unwrap_or(false) {
+        emit_log(app, "info", "Processing window open; resuming");
+        emit_status(app, "processing");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(start: &str, end: &str) -> ProcessingWindow {
+        ProcessingWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+            pause_at_boundary: false,
+        }
+    }
+
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn window_bounds_are_parsed() {
+        assert_eq!(window(" 01:30", "07:00 ").bounds(), Ok((at(1, 30), at(7, 0))));
+        assert!(window("1.30", "07:00").bounds().is_err());
+        assert!(window("25:00", "07:00").bounds().is_err());
+        assert!(window("07:00", "07:00").bounds().is_err());
+    }
+
+    #[test]
+    fn daytime_window_opens_at_its_start() {
+        let window = window("09:00", "17:00");
+        assert_eq!(window.closed_until(at(12, 0)), Ok(None));
+        assert_eq!(window.closed_until(at(8, 59)), Ok(Some(at(9, 0))));
+        assert_eq!(window.closed_until(at(17, 0)), Ok(Some(at(9, 0))));
+    }
+
+    #[test]
+    fn overnight_window_spans_midnight() {
+        let window = window("22:00", "06:00");
+        assert_eq!(window.closed_until(at(23, 0)), Ok(None));
+        assert_eq!(window.closed_until(at(3, 0)), Ok(None));
+        assert_eq!(window.closed_until(at(12, 0)), Ok(Some(at(22, 0))));
+    }
+
+    #[test]
+    fn start_times_must_be_iso_and_in_the_future() {
+        assert!(parse_start_at("2999-05-01T01:30").is_ok());
+        assert!(parse_start_at("2999-05-01 01:30:15").is_ok());
+        assert!(parse_start_at("2999-05-01T01:30:00+02:00").is_ok());
+        assert!(parse_start_at("2000-05-01T01:30").unwrap_err().contains("already passed"));
+        assert!(parse_start_at("tomorrow").unwrap_err().contains("ISO-8601"));
+    }
+}
@@ -0,0 +1,279 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::json;
+use tauri::AppHandle;
+
+use crate::models::{ProcessingState, ToolPaths};
+use crate::probe::ProbeTools;
+use crate::processing::{hide_console_window, require_tool, tool_command};
+use crate::utils::{emit_log, resolve_path};
+
+/// Length of the synthetic clip; long enough for every tool to see several
+/// GOPs, short enough to finish in seconds.
+const CLIP_SECONDS: u32 = 2;
+const CLIP_FPS: u32 = 24;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestCheck {
+    pub tool: String,
+    pub step: String,
+    /// `"passed"`, `"failed"` or `"skipped"`.
+    pub status: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+struct SelfTest<'a> {
+    app: &'a AppHandle,
+    work_dir: PathBuf,
+    checks: Vec<SelfTestCheck>,
+    /// Set once a step fails; every later step depends on its output.
+    broken: bool,
+}
+
+impl SelfTest<'_> {
+    fn record(&mut self, tool: &str, step: &str, status: &str, detail: String) {
+        let log_type = match status {
+            "passed" => "success",
+            "failed" => "error",
+            _ => "warning",
+        };
+        emit_log(self.app, log_type, format!("Self-test {} ({}): {} {}", tool, step, status, detail));
+        self.checks.push(SelfTestCheck {
+            tool: tool.to_string(),
+            step: step.to_string(),
+            status: status.to_string(),
+            detail,
+        });
+    }
+
+    /// Run `command` as one pipeline step and check it produced `artifact`.
+    fn step(&mut self, tool: &str, step: &str, mut command: Command, artifact: Option<&Path>) -> bool {
+        if self.broken {
+            self.record(tool, step, "skipped", "an earlier step failed".to_string());
+            return false;
+        }
+        hide_console_window(&mut command);
+        let result = match command.output() {
+            Ok(output) if output.status.success() => match artifact {
+                Some(path) if fs::metadata(path).map(|m| m.len() == 0).unwrap_or(true) => {
+                    Err(format!("{} was not written", path.display()))
+                }
+                _ => Ok(()),
+            },
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let last = stdout
+                    .lines()
+                    .chain(stderr.lines())
+                    .rfind(|line| !line.trim().is_empty())
+                    .unwrap_or("no output")
+                    .trim()
+                    .to_string();
+                Err(format!("exit {}: {}", output.status, last))
+            }
+            Err(err) => Err(format!("could not start: {}", err)),
+        };
+        match result {
+            Ok(()) => {
+                self.record(tool, step, "passed", String::new());
+                true
+            }
+            Err(detail) => {
+                self.broken = true;
+                self.record(tool, step, "failed", detail);
+                false
+            }
+        }
+    }
+
+    fn command(&self, program: &Path) -> Command {
+        tool_command(program, &self.work_dir)
+    }
+}
+
+/// Build a tiny HDR10 clip, turn it into a DV hybrid with the same tools and
+/// arguments a real run uses, and check the result carries an RPU.
+pub fn run_self_test(app: &AppHandle, state: &ProcessingState, tool_paths: &ToolPaths) -> Result<SelfTestReport, String> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let root = app
+        .path_resolver()
+        .app_cache_dir()
+        .unwrap_or_else(std::env::temp_dir);
+    let work_dir = root.join(format!("selftest-{}", stamp));
+    fs::create_dir_all(&work_dir).map_err(|e| format!("Failed to create {}: {}", work_dir.display(), e))?;
+    emit_log(app, "info", format!("Running self-test in {}", work_dir.display()));
+
+    let mut test = SelfTest {
+        app,
+        work_dir: work_dir.clone(),
+        checks: Vec::new(),
+        broken: false,
+    };
+    run_steps(&mut test, state, tool_paths);
+
+    if let Err(err) = fs::remove_dir_all(&work_dir) {
+        emit_log(
+            app,
+            "warning",
+            format!("Could not remove self-test folder {}: {}", work_dir.display(), err),
+        );
+    }
+
+    let passed = test.checks.iter().all(|check| check.status != "failed");
+    emit_log(
+        app,
+        if passed { "success" } else { "error" },
+        format!("Self-test {}", if passed { "passed" } else { "failed" }),
+    );
+    Ok(SelfTestReport {
+        passed,
+        checks: test.checks,
+    })
+}
+
+fn run_steps(test: &mut SelfTest, state: &ProcessingState, tool_paths: &ToolPaths) {
+    let app = test.app;
+    let ffmpeg = resolve_path(app, &tool_paths.ffmpeg);
+    let mkvmerge = resolve_path(app, &tool_paths.mkvmerge);
+    let mkvextract = resolve_path(app, &tool_paths.mkvextract);
+    let dovi_tool = resolve_path(app, &tool_paths.dovi_tool);
+    let mediainfo = resolve_path(app, &tool_paths.mediainfo);
+
+    let source = test.work_dir.join("source.HDR.mkv");
+    let base_hevc = test.work_dir.join("base.hevc");
+    let rpu_config = test.work_dir.join("rpu_config.json");
+    let rpu = test.work_dir.join("rpu.bin");
+    let dv_hevc = test.work_dir.join("dv_hdr.hevc");
+    let output = test.work_dir.join("output.DV.HDR.mkv");
+    let output_hevc = test.work_dir.join("output.hevc");
+    let output_rpu = test.work_dir.join("output_rpu.bin");
+
+    let mut cmd = test.command(&ffmpeg);
+    cmd.args(["-hide_banner", "-nostdin", "-y", "-f", "lavfi", "-i"])
+        .arg(format!("testsrc2=size=640x360:rate={}", CLIP_FPS))
+        .args(["-f", "lavfi", "-i"])
+        .arg(format!("sine=frequency=440:duration={}", CLIP_SECONDS))
+        .arg("-t")
+        .arg(CLIP_SECONDS.to_string())
+        .args([
+            "-c:v",
+            "libx265",
+            "-pix_fmt",
+            "yuv420p10le",
+            "-x265-params",
+            "colorprim=bt2020:transfer=smpte2084:colormatrix=bt2020nc:log-level=error",
+            "-c:a",
+            "aac",
+        ])
+        .arg(&source);
+    test.step("ffmpeg", "Generate HDR10 test clip", cmd, Some(&source));
+
+    let mut cmd = test.command(&mkvextract);
+    cmd.arg(&source)
+        .arg("tracks")
+        .arg(format!("0:{}", base_hevc.to_string_lossy()));
+    test.step("mkvextract", "Extract HEVC track", cmd, Some(&base_hevc));
+
+    let config = json!({
+        "cm_version": "V40",
+        "length": CLIP_SECONDS * CLIP_FPS,
+        "level6": {
+            "max_display_mastering_luminance": 1000,
+            "min_display_mastering_luminance": 1,
+            "max_content_light_level": 1000,
+            "max_frame_average_light_level": 400
+        }
+    });
+    match fs::write(&rpu_config, config.to_string()) {
+        Ok(()) => {
+            let mut cmd = test.command(&dovi_tool);
+            cmd.arg("generate").arg("-j").arg(&rpu_config).arg("-o").arg(&rpu);
+            test.step("dovi_tool", "Generate RPU", cmd, Some(&rpu));
+        }
+        Err(err) => {
+            test.broken = true;
+            test.record("dovi_tool", "Generate RPU", "failed", err.to_string());
+        }
+    }
+
+    let mut cmd = test.command(&dovi_tool);
+    cmd.arg("inject-rpu")
+        .arg("-i")
+        .arg(&base_hevc)
+        .arg("--rpu-in")
+        .arg(&rpu)
+        .arg("-o")
+        .arg(&dv_hevc);
+    test.step("dovi_tool", "Inject RPU", cmd, Some(&dv_hevc));
+
+    let mut cmd = test.command(&mkvmerge);
+    cmd.arg("-o")
+        .arg(&output)
+        .arg(&dv_hevc)
+        .arg("--no-video")
+        .arg(&source);
+    test.step("mkvmerge", "Mux output", cmd, Some(&output));
+
+    let mut cmd = test.command(&mkvextract);
+    cmd.arg(&output)
+        .arg("tracks")
+        .arg(format!("0:{}", output_hevc.to_string_lossy()));
+    test.step("mkvextract", "Extract muxed video", cmd, Some(&output_hevc));
+
+    let mut cmd = test.command(&dovi_tool);
+    cmd.arg("extract-rpu").arg(&output_hevc).arg("-o").arg(&output_rpu);
+    test.step("dovi_tool", "Verify output RPU", cmd, Some(&output_rpu));
+
+    if test.broken {
+        test.record("MediaInfo", "Detect Dolby Vision", "skipped", "an earlier step failed".to_string());
+    } else {
+        match state.probe.probe(&ProbeTools::new(&mediainfo, &mkvmerge, &test.work_dir), &output) {
+            Ok(report) if report.dolby_vision_profile().is_some() => {
+                test.record("MediaInfo", "Detect Dolby Vision", "passed", String::new());
+            }
+            Ok(report) => test.record(
+                "MediaInfo",
+                "Detect Dolby Vision",
+                "failed",
+                format!("HDR format reported as {:?}", report.hdr_format),
+            ),
+            Err(err) => test.record("MediaInfo", "Detect Dolby Vision", "failed", err),
+        }
+    }
+
+    // Only some inputs need these, so a missing one is reported, not failed.
+    let optional = [
+        (&tool_paths.mp4box, "MP4Box", "-version"),
+        (&tool_paths.hdr10plus_tool, "hdr10plus_tool", "--version"),
+    ];
+    for (configured, name, version_arg) in optional {
+        match require_tool(app, configured, name, "Self-test") {
+            Ok(path) => {
+                let mut cmd = test.command(&path);
+                cmd.arg(version_arg);
+                hide_console_window(&mut cmd);
+                match cmd.output() {
+                    Ok(_) => test.record(name, "Start", "passed", String::new()),
+                    Err(err) => test.record(name, "Start", "failed", format!("could not start: {}", err)),
+                }
+            }
+            Err(err) => test.record(name, "Start", "skipped", err),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use tauri::AppHandle;
+
+use crate::models::ProcessingState;
+use crate::utils::{emit_log, emit_step};
+
+pub const STAGE_STEP_ID: usize = 8;
+pub const STAGE_STEP_NAME: &str = "Stage Inputs";
+
+const STAGE_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+const STAGE_REPORT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Local copies of a run's inputs. Unless `keep` is called they are deleted
+/// when dropped, so a run that errors or is cancelled after staging does not
+/// leave multi-GB copies behind in the staging folder.
+pub struct StagedInputs {
+    paths: Vec<PathBuf>,
+    armed: bool,
+}
+
+impl StagedInputs {
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Leave the copies on disk; the run's temp-file cleanup owns them now.
+    pub fn keep(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for StagedInputs {
+    fn drop(&mut self) {
+        if self.armed {
+            for path in &self.paths {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// Copy `sources` into `staging_dir` as one step, returning the local copies
+/// in the same order. Copies are named after `prefix` so parallel workers
+/// staging same-named files never collide. With `link`, writable sources on
+/// the staging volume are hard-linked instead, which takes no time or space.
+pub fn stage_inputs(
+    app: &AppHandle,
+    state: &ProcessingState,
+    sources: &[&Path],
+    staging_dir: &Path,
+    prefix: &str,
+    link: bool,
+) -> Result<StagedInputs, String> {
+    fs::create_dir_all(staging_dir).map_err(|e| e.to_string())?;
+    emit_step(app, STAGE_STEP_ID, STAGE_STEP_NAME, "active", 0);
+
+    let total: u64 = sources
+        .iter()
+        .map(|source| fs::metadata(source).map(|m| m.len()).unwrap_or(0))
+        .sum();
+    let mut progress = CopyProgress::new(STAGE_STEP_ID, STAGE_STEP_NAME, total);
+    let mut staged = StagedInputs {
+        paths: Vec::with_capacity(sources.len()),
+        armed: true,
+    };
+
+    for source in sources {
+        let name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| format!("Cannot stage {}", source.display()))?;
+        let target = staging_dir.join(format!("{}_{}", prefix, name));
+
+        if link && try_hard_link(app, source, &target) {
+            progress.copied += fs::metadata(&target).map(|m| m.len()).unwrap_or(0);
+            staged.paths.push(target);
+            continue;
+        }
+
+        emit_log(
+            app,
+            "info",
+            format!("Staging {} -> {}", source.display(), target.display()),
+        );
+        let result = copy_chunked(app, state, source, &target, &mut progress);
+        if let Err(err) = result {
+            // Earlier copies go with `staged` when it drops.
+            let _ = fs::remove_file(&target);
+            let status = if err == "Processing cancelled" { "pending" } else { "error" };
+            emit_step(app, STAGE_STEP_ID, STAGE_STEP_NAME, status, 0);
+            return Err(err);
+        }
+        staged.paths.push(target);
+    }
+
+    emit_step(app, STAGE_STEP_ID, STAGE_STEP_NAME, "completed", 100);
+    emit_log(
+        app,
+        "success",
+        format!(
+            "Staged {:.2} GiB in {:.0}s",
+            total as f64 / 1_073_741_824.0,
+            progress.started.elapsed().as_secs_f64()
+        ),
+    );
+    Ok(staged)
+}
+
+/// Hard-link `source` to `target`, reporting whether it worked. Read-only
+/// sources are always copied: staging them exists so no tool works on the
+/// original, and a link would share its inode and permissions.
+fn try_hard_link(app: &AppHandle, source: &Path, target: &Path) -> bool {
+    let read_only = fs::metadata(source)
+        .map(|m| m.permissions().readonly())
+        .unwrap_or(true);
+    if read_only {
+        return false;
+    }
+    let _ = fs::remove_file(target);
+    match fs::hard_link(source, target) {
+        Ok(()) => {
+            emit_log(
+                app,
+                "info",
+                format!("Staged {} -> {} (hard link)", source.display(), target.display()),
+            );
+            true
+        }
+        Err(err) => {
+            emit_log(
+                app,
+                "info",
+                format!("Cannot hard-link {} ({}); copying instead", source.display(), err),
+            );
+            false
+        }
+    }
+}
+
+/// Bytes copied so far by a step made of one or more chunked copies, which
+/// it reports as its progress and throughput.
+pub struct CopyProgress {
+    step_id: usize,
+    step_name: &'static str,
+    pub copied: u64,
+    total: u64,
+    pub started: Instant,
+}
+
+impl CopyProgress {
+    pub fn new(step_id: usize, step_name: &'static str, total: u64) -> Self {
+        Self {
+            step_id,
+            step_name,
+            copied: 0,
+            total,
+            started: Instant::now(),
+        }
+    }
+}
+
+/// Copy `source` to `target` in chunks, checking for a cancel between them.
+pub fn copy_chunked(
+    app: &AppHandle,
+    state: &ProcessingState,
+    source: &Path,
+    target: &Path,
+    progress: &mut CopyProgress,
+) -> Result<(), String> {
+    let mut reader = File::open(source).map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+    let mut writer =
+        File::create(target).map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    let size = reader.metadata().map(|m| m.len()).unwrap_or(0);
+    // Reserve the full size up front so the copy is not fragmented.
+    writer.set_len(size).map_err(|e| e.to_string())?;
+
+    let mut buffer = vec![0u8; STAGE_CHUNK_BYTES];
+    let mut last_report = Instant::now();
+    loop {
+        if *state.cancel_flag.lock().map_err(|_| "State lock failed")? {
+            return Err("Processing cancelled".to_string());
+        }
+        let read = reader.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read]).map_err(|e| e.to_string())?;
+        progress.copied += read as u64;
+
+        if last_report.elapsed() >= STAGE_REPORT_INTERVAL {
+            last_report = Instant::now();
+            let percent = ((progress.copied as f64 / progress.total.max(1) as f64) * 100.0).min(99.0) as u8;
+            let speed = progress.copied as f64 / 1_048_576.0 / progress.started.elapsed().as_secs_f64().max(0.001);
+            emit_step(
+                app,
+                progress.step_id,
+                &format!("{} ({:.1} MB/s)", progress.step_name, speed),
+                "active",
+                percent,
+            );
+        }
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
@@ -0,0 +1,200 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tauri::AppHandle;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::history::{latest_run, run_dir};
+use crate::processing::{hide_console_window, tool_command};
+use crate::utils::{emit_log, resolve_path};
+
+/// Tools listed in `toolPaths` and the flag that makes each print its version.
+const TOOL_VERSION_ARGS: &[(&str, &str)] = &[
+    ("doviTool", "--version"),
+    ("mkvmerge", "--version"),
+    ("mkvextract", "--version"),
+    ("ffmpeg", "-version"),
+    ("mediainfo", "--Version"),
+    ("mp4box", "-version"),
+    ("hdr10plusTool", "--version"),
+];
+
+/// Request fields that name input files worth probing.
+const INPUT_FIELDS: &[&str] = &["hdrPath", "dvPath", "hdr10plusPath"];
+
+fn file_name_of(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn looks_like_path(value: &str) -> bool {
+    value.contains('/') || value.contains('\\')
+}
+
+/// Replace every path-like string in `value` with its file name.
+fn anonymize_value(value: &mut Value) {
+    match value {
+        Value::String(text) if looks_like_path(text) => *text = file_name_of(text),
+        Value::Array(items) => items.iter_mut().for_each(anonymize_value),
+        Value::Object(map) => map.values_mut().for_each(anonymize_value),
+        _ => {}
+    }
+}
+
+/// Every path-like string in `value`, for scrubbing the plain-text log.
+fn collect_paths(value: &Value, paths: &mut Vec<String>) {
+    match value {
+        Value::String(text) if looks_like_path(text) => paths.push(text.clone()),
+        Value::Array(items) => items.iter().for_each(|item| collect_paths(item, paths)),
+        Value::Object(map) => map.values().for_each(|item| collect_paths(item, paths)),
+        _ => {}
+    }
+}
+
+/// Input files named by the request, including those of queue items.
+fn input_files(request: &Value) -> Vec<PathBuf> {
+    let entries = std::iter::once(request).chain(
+        request
+            .get("queue")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten(),
+    );
+    let mut files: Vec<PathBuf> = entries
+        .flat_map(|entry| INPUT_FIELDS.iter().filter_map(|field| entry.get(*field)?.as_str()))
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .collect();
+    files.dedup();
+    files
+}
+
+fn tool_versions(app: &AppHandle, request: &Value, work_dir: &Path) -> String {
+    let mut report = String::new();
+    for (key, version_arg) in TOOL_VERSION_ARGS {
+        let Some(configured) = request.pointer(&format!("/toolPaths/{}", key)).and_then(Value::as_str) else {
+            continue;
+        };
+        let path = resolve_path(app, configured);
+        let mut command = tool_command(&path, work_dir);
+        command.arg(version_arg);
+        hide_console_window(&mut command);
+        let version = match command.output() {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                stdout
+                    .lines()
+                    .find(|line| !line.trim().is_empty())
+                    .unwrap_or("no version output")
+                    .trim()
+                    .to_string()
+            }
+            Err(err) => format!("not runnable: {}", err),
+        };
+        report.push_str(&format!("{}: {} ({})\n", key, version, path.display()));
+    }
+    report
+}
+
+fn probe_json(app: &AppHandle, request: &Value, file: &Path, work_dir: &Path) -> Result<Value, String> {
+    let configured = request
+        .pointer("/toolPaths/mediainfo")
+        .and_then(Value::as_str)
+        .unwrap_or("mediainfo");
+    let mut command = tool_command(resolve_path(app, configured), work_dir);
+    command.arg("--Output=JSON").arg(file);
+    hide_console_window(&mut command);
+    let output = command.output().map_err(|e| e.to_string())?;
+    serde_json::from_slice(&output.stdout).map_err(|e| format!("Unreadable MediaInfo output: {}", e))
+}
+
+/// Zip what a bug report needs about run `run_id` (the latest failed run by
+/// default) into `destination`: its log, request, outcome, scan reports, tool
+/// versions and MediaInfo of each input. With `anonymize`, paths are cut to
+/// file names.
+pub fn export_support_bundle(
+    app: &AppHandle,
+    run_id: Option<String>,
+    destination: &Path,
+    anonymize: bool,
+) -> Result<PathBuf, String> {
+    let run_id = run_id
+        .or_else(|| latest_run(app))
+        .ok_or("No recorded runs to export")?;
+    let dir = run_dir(app, &run_id).ok_or_else(|| format!("Run {} is not recorded", run_id))?;
+
+    let mut request: Value = fs::read_to_string(dir.join("request.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or(Value::Null);
+    let mut log = fs::read_to_string(dir.join("log.txt")).unwrap_or_default();
+    let result = fs::read_to_string(dir.join("result.json")).unwrap_or_default();
+    let mut scan_reports: Vec<Value> = fs::read_to_string(dir.join("scan-reports.jsonl"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    let versions = tool_versions(app, &request, &dir);
+    let mut probes = Vec::new();
+    for (index, file) in input_files(&request).iter().enumerate() {
+        let mut probe = probe_json(app, &request, file, &dir)
+            .unwrap_or_else(|err| serde_json::json!({ "error": err }));
+        let name = file_name_of(&file.to_string_lossy());
+        if anonymize {
+            anonymize_value(&mut probe);
+        }
+        probes.push((format!("probes/{}-{}.json", index + 1, name), probe));
+    }
+
+    if anonymize {
+        let mut paths = Vec::new();
+        collect_paths(&request, &mut paths);
+        // Longest first so a folder never leaves half of a longer path behind.
+        paths.sort_by_key(|path| std::cmp::Reverse(path.len()));
+        for path in paths {
+            log = log.replace(&path, &file_name_of(&path));
+        }
+        anonymize_value(&mut request);
+        scan_reports.iter_mut().for_each(anonymize_value);
+    }
+
+    let target = if destination.is_dir() {
+        destination.join(format!("hybrid-dv-hdr-support-{}.zip", run_id))
+    } else {
+        destination.to_path_buf()
+    };
+    let file = File::create(&target).map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default();
+    let mut add = |name: &str, content: &[u8]| -> Result<(), String> {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(content).map_err(|e| e.to_string())
+    };
+    add("log.txt", log.as_bytes())?;
+    add(
+        "request.json",
+        &serde_json::to_vec_pretty(&request).map_err(|e| e.to_string())?,
+    )?;
+    add("result.json", result.as_bytes())?;
+    if !scan_reports.is_empty() {
+        add(
+            "scan-reports.json",
+            &serde_json::to_vec_pretty(&scan_reports).map_err(|e| e.to_string())?,
+        )?;
+    }
+    add("tool-versions.txt", versions.as_bytes())?;
+    for (name, probe) in &probes {
+        add(name, &serde_json::to_vec_pretty(probe).map_err(|e| e.to_string())?)?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    emit_log(app, "success", format!("Support bundle written to {}", target.display()));
+    Ok(target)
+}
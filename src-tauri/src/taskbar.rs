@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicU16, Ordering};
+use tauri::AppHandle;
+
+static LAST_SHOWN: AtomicU16 = AtomicU16::new(u16::MAX);
+
+/// What the OS-level progress indicator (Windows taskbar button, macOS dock
+/// badge) should currently show.
+#[derive(Clone, Copy)]
+pub enum TaskbarProgress {
+    Progress(u8),
+    Error,
+    Clear,
+}
+
+impl TaskbarProgress {
+    fn encode(self) -> u16 {
+        match self {
+            TaskbarProgress::Progress(value) => value as u16,
+            TaskbarProgress::Error => 1000,
+            TaskbarProgress::Clear => 2000,
+        }
+    }
+}
+
+pub fn set_taskbar_progress(app: &AppHandle, progress: TaskbarProgress) {
+    // Aggregate progress fires on every poll of every worker; only touch the
+    // OS indicator when what it shows actually changes.
+    if LAST_SHOWN.swap(progress.encode(), Ordering::Relaxed) == progress.encode() {
+        return;
+    }
+
+    #[cfg(target_os = "windows")]
+    windows_impl::apply(app, progress);
+
+    #[cfg(target_os = "macos")]
+    macos_impl::apply(app, progress);
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let _ = (app, progress);
+}
+
+#[cfg(target_os = "windows")]
+mod windows_impl {
+    use super::TaskbarProgress;
+    use tauri::{AppHandle, Manager};
+    use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+    use windows::Win32::UI::Shell::{
+        ITaskbarList3, TaskbarList, TBPF_ERROR, TBPF_NOPROGRESS, TBPF_NORMAL,
+    };
+
+    pub fn apply(app: &AppHandle, progress: TaskbarProgress) {
+        let Some(window) = app.get_window("main") else {
+            return;
+        };
+        let Ok(hwnd) = window.hwnd() else {
+            return;
+        };
+
+        // The shell's taskbar object lives in the UI thread's COM apartment.
+        let _ = app.run_on_main_thread(move || unsafe {
+            let Ok(taskbar) =
+                CoCreateInstance::<_, ITaskbarList3>(&TaskbarList, None, CLSCTX_INPROC_SERVER)
+            else {
+                return;
+            };
+            if taskbar.HrInit().is_err() {
+                return;
+            }
+            match progress {
+                TaskbarProgress::Progress(value) => {
+                    let _ = taskbar.SetProgressState(hwnd, TBPF_NORMAL);
+                    let _ = taskbar.SetProgressValue(hwnd, value as u64, 100);
+                }
+                TaskbarProgress::Error => {
+                    let _ = taskbar.SetProgressState(hwnd, TBPF_ERROR);
+                }
+                TaskbarProgress::Clear => {
+                    let _ = taskbar.SetProgressState(hwnd, TBPF_NOPROGRESS);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos_impl {
+    use super::TaskbarProgress;
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::NSString;
+    use objc::{class, msg_send, sel, sel_impl};
+    use tauri::AppHandle;
+
+    pub fn apply(app: &AppHandle, progress: TaskbarProgress) {
+        let label = match progress {
+            TaskbarProgress::Progress(value) => format!("{}%", value),
+            TaskbarProgress::Error => "!".to_string(),
+            TaskbarProgress::Clear => String::new(),
+        };
+
+        // AppKit objects may only be touched from the main thread.
+        let _ = app.run_on_main_thread(move || unsafe {
+            let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+            let dock_tile: id = msg_send![ns_app, dockTile];
+            let badge: id = if label.is_empty() {
+                nil
+            } else {
+                NSString::alloc(nil).init_str(&label)
+            };
+            let _: () = msg_send![dock_tile, setBadgeLabel: badge];
+        });
+    }
+}
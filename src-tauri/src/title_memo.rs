@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+
+use crate::models::{PipelineOptions, TrackDefaults};
+use crate::utils::{emit_log, hdr_base_name};
+
+/// Bytes of the DV input hashed into a title's key; enough to tell releases
+/// apart without reading the whole file.
+const KEY_BYTES: u64 = 16 * 1024 * 1024;
+
+static MEMO_LOCK: Mutex<()> = Mutex::new(());
+
+/// Active-area offsets a title was processed with.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct CropMemo {
+    pub top: u32,
+    pub bottom: u32,
+    /// Measured by letterbox detection, so a later run can reuse it instead
+    /// of measuring again.
+    pub detected: bool,
+}
+
+/// What a title was last processed with, recorded after each successful run
+/// and offered to the next run of the same DV input.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TitleMemo {
+    /// Base name of the HDR input, for display.
+    pub title: String,
+    pub dv_file: String,
+    /// RFC 3339 time of the run that wrote the memo.
+    pub updated: String,
+    pub dv_delay_ms: f64,
+    pub crop: Option<CropMemo>,
+    #[serde(default)]
+    pub audio_order: Vec<String>,
+    #[serde(default)]
+    pub track_defaults: TrackDefaults,
+}
+
+/// A memo with the key `delete_title_memo` takes.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TitleMemoEntry {
+    pub key: String,
+    #[serde(flatten)]
+    pub memo: TitleMemo,
+}
+
+fn memo_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path_resolver()
+        .app_data_dir()
+        .map(|dir| dir.join("title_memos.json"))
+}
+
+fn load_memos(path: &Path) -> BTreeMap<String, TitleMemo> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_memos(path: &Path, memos: &BTreeMap<String, TitleMemo>) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(memos).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// SHA-256 of the first `KEY_BYTES` of the DV input, which stays the same
+/// when only the HDR release or its audio changes.
+pub fn title_key(dv_input: &Path) -> Result<String, String> {
+    let file = File::open(dv_input).map_err(|e| format!("Failed to open {}: {}", dv_input.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let mut reader = file.take(KEY_BYTES);
+    loop {
+        let read = reader.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+pub fn find_title_memo(app: &AppHandle, key: &str) -> Option<TitleMemo> {
+    let path = memo_path(app)?;
+    let _guard = MEMO_LOCK.lock().ok()?;
+    load_memos(&path).remove(key)
+}
+
+/// Replace the memo for `key` with what this run used.
+pub fn record_title_memo(
+    app: &AppHandle,
+    key: &str,
+    input_hdr: &Path,
+    input_dv: &Path,
+    options: &PipelineOptions,
+    dv_delay_ms: f64,
+    crop: Option<CropMemo>,
+) {
+    let Some(path) = memo_path(app) else {
+        return;
+    };
+    let Ok(_guard) = MEMO_LOCK.lock() else {
+        return;
+    };
+    let mut memos = load_memos(&path);
+    let name = |path: &Path| path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    memos.insert(
+        key.to_string(),
+        TitleMemo {
+            title: hdr_base_name(&name(input_hdr)),
+            dv_file: name(input_dv),
+            updated: Local::now().to_rfc3339(),
+            dv_delay_ms,
+            crop,
+            audio_order: options.audio_order.clone(),
+            track_defaults: options.track_defaults.clone(),
+        },
+    );
+    if let Err(err) = save_memos(&path, &memos) {
+        emit_log(app, "warning", format!("Could not save the title memo: {}", err));
+    }
+}
+
+pub fn list_title_memos(app: &AppHandle) -> Result<Vec<TitleMemoEntry>, String> {
+    let path = memo_path(app).ok_or("Could not resolve app data directory".to_string())?;
+    let _guard = MEMO_LOCK.lock().map_err(|_| "Title memo lock failed")?;
+    let mut entries: Vec<TitleMemoEntry> = load_memos(&path)
+        .into_iter()
+        .map(|(key, memo)| TitleMemoEntry { key, memo })
+        .collect();
+    entries.sort_by(|a, b| b.memo.updated.cmp(&a.memo.updated));
+    Ok(entries)
+}
+
+pub fn delete_title_memo(app: &AppHandle, key: &str) -> Result<(), String> {
+    let path = memo_path(app).ok_or("Could not resolve app data directory".to_string())?;
+    let _guard = MEMO_LOCK.lock().map_err(|_| "Title memo lock failed")?;
+    let mut memos = load_memos(&path);
+    if memos.remove(key).is_none() {
+        return Err(format!("No title memo with key {}", key));
+    }
+    save_memos(&path, &memos)
+}
+
+/// Fill the values the request left unset from `memo`: the DV delay when
+/// none was given, the audio order and track defaults when empty. Returns
+/// the options and delay to run with, logging what was reused.
+pub fn apply_title_memo(
+    app: &AppHandle,
+    memo: &TitleMemo,
+    options: &PipelineOptions,
+    dv_delay_ms: Option<f64>,
+) -> (PipelineOptions, f64) {
+    let mut options = options.clone();
+    let mut delay = dv_delay_ms.unwrap_or(0.0);
+    let mut reused = Vec::new();
+    if dv_delay_ms.is_none() && memo.dv_delay_ms.abs() > f64::EPSILON {
+        delay = memo.dv_delay_ms;
+        reused.push(format!("DV delay {} ms", delay));
+    }
+    if options.audio_order.is_empty() && !memo.audio_order.is_empty() {
+        options.audio_order = memo.audio_order.clone();
+        reused.push(format!("audio order {}", memo.audio_order.join(", ")));
+    }
+    if options.track_defaults.is_empty() && !memo.track_defaults.is_empty() {
+        options.track_defaults = memo.track_defaults.clone();
+        reused.push("default tracks".to_string());
+    }
+    if let Some(crop) = memo.crop.filter(|crop| crop.detected && options.detect_letterbox) {
+        reused.push(format!("letterbox top {} / bottom {}", crop.top, crop.bottom));
+    }
+    if !reused.is_empty() {
+        let date = DateTime::parse_from_rfc3339(&memo.updated)
+            .map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|_| memo.updated.clone());
+        emit_log(
+            app,
+            "info",
+            format!("Reusing previous settings from {} for {}: {}", date, memo.title, reused.join("; ")),
+        );
+    }
+    (options, delay)
+}
@@ -1,20 +1,120 @@
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use regex::Regex;
+use serde::Serialize;
 use tauri::{AppHandle, Manager};
-use crate::models::{LogPayload, StepPayload, QueuePayload, FilePayload, StatusPayload};
+use crate::event_socket::EventSocket;
+use crate::processing::tool_command;
+use crate::progress::{emit_update, ProgressUpdate};
+use crate::taskbar::{set_taskbar_progress, TaskbarProgress};
+use crate::models::{
+    LogPayload, StepPayload, QueuePayload, FilePayload, StatusPayload, OverallPayload, ProcessingState,
+    StopReason, OutputStructure, LogLevel, ScanReport, ScanSkipReason, BatchOrdering
+};
+
+fn debug_logging(app: &AppHandle) -> bool {
+    app.try_state::<ProcessingState>()
+        .and_then(|state| state.log_level.lock().ok().map(|level| *level == LogLevel::Debug))
+        .unwrap_or(false)
+}
+
+/// Quote one argument for the platform's shell (cmd on Windows, POSIX sh
+/// elsewhere), leaving plain words alone.
+pub(crate) fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || "-_.:/=+@".contains(c)
+                || (c == '\\' && cfg!(target_os = "windows"))
+        });
+    if plain {
+        return arg.to_string();
+    }
+    if cfg!(target_os = "windows") {
+        // CommandLineToArgvW rules: backslashes are literal unless they end
+        // up in front of a quote, where they must be doubled.
+        let mut quoted = String::from("\"");
+        let mut backslashes = 0;
+        for c in arg.chars() {
+            match c {
+                '\\' => backslashes += 1,
+                '"' => {
+                    quoted.push_str(&"\\".repeat(backslashes + 1));
+                    backslashes = 0;
+                }
+                _ => backslashes = 0,
+            }
+            quoted.push(c);
+        }
+        quoted.push_str(&"\\".repeat(backslashes));
+        quoted.push('"');
+        quoted
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// `command` as a line that can be pasted into a terminal: working
+/// directory, explicitly set environment, program and arguments, all read
+/// back from the `Command` itself.
+pub fn shell_command_line(command: &Command) -> String {
+    let mut parts = Vec::new();
+    if let Some(dir) = command.get_current_dir() {
+        parts.push(format!("cd {} &&", shell_quote(&dir.to_string_lossy())));
+    }
+    for (key, value) in command.get_envs() {
+        let key = key.to_string_lossy();
+        match value {
+            Some(value) if cfg!(target_os = "windows") => {
+                parts.push(format!("set {}={} &&", key, shell_quote(&value.to_string_lossy())))
+            }
+            Some(value) => parts.push(format!("{}={}", key, shell_quote(&value.to_string_lossy()))),
+            None => {}
+        }
+    }
+    parts.push(shell_quote(&command.get_program().to_string_lossy()));
+    parts.extend(command.get_args().map(|arg| shell_quote(&arg.to_string_lossy())));
+    parts.join(" ")
+}
+
+/// Emit a processing event to the webview and mirror it to the event socket.
+pub(crate) fn emit_event<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Some(socket) = app.try_state::<EventSocket>() {
+        socket.broadcast(event, &payload);
+    }
+    if let Some(state) = app.try_state::<ProcessingState>() {
+        state.web.observe(event, &payload);
+    }
+    let _ = app.emit_all(event, payload);
+}
 
 pub fn emit_log(app: &AppHandle, log_type: &str, message: impl Into<String>) {
-    let _ = app.emit_all(
-        "processing:log",
-        LogPayload {
-            log_type: log_type.to_string(),
-            message: message.into(),
+    let message = message.into();
+    // The on-disk run log keeps debug lines even when the UI hides them.
+    if let Some(state) = app.try_state::<ProcessingState>() {
+        state.history.append_log(log_type, &message);
+    }
+    if log_type == "debug" && !debug_logging(app) {
+        return;
+    }
+    let payload = LogPayload {
+        log_type: log_type.to_string(),
+        message,
+    };
+    // During a run, lines go through the batcher so bursts are coalesced.
+    let payload = match app.try_state::<ProcessingState>() {
+        Some(state) => match state.logs.submit(payload) {
+            Some(payload) => payload,
+            None => return,
         },
-    );
+        None => payload,
+    };
+    emit_event(app, "processing:log", payload);
 }
 
 pub fn emit_step(app: &AppHandle, step_id: usize, name: &str, status: &str, progress: u8) {
-    let _ = app.emit_all(
+    emit_event(
+        app,
         "processing:step",
         StepPayload {
             step_id,
@@ -25,23 +125,136 @@ pub fn emit_step(app: &AppHandle, step_id: usize, name: &str, status: &str, prog
     );
 }
 
+/// Queue and file updates go through the run's progress hub when one is
+/// active, so they are coalesced and emitted in order.
+fn submit_progress(app: &AppHandle, update: ProgressUpdate) {
+    let update = match app.try_state::<ProcessingState>() {
+        Some(state) => match state.progress.submit(update) {
+            Some(update) => update,
+            None => return,
+        },
+        None => update,
+    };
+    emit_update(app, update);
+}
+
 pub fn emit_queue(app: &AppHandle, payload: QueuePayload) {
-    let _ = app.emit_all("processing:queue", payload);
+    submit_progress(app, ProgressUpdate::Queue(payload));
 }
 
 pub fn emit_file(app: &AppHandle, payload: FilePayload) {
-    let _ = app.emit_all("processing:file", payload);
+    submit_progress(app, ProgressUpdate::File(payload));
+}
+
+pub fn emit_overall(app: &AppHandle, payload: OverallPayload) {
+    set_taskbar_progress(app, TaskbarProgress::Progress(payload.progress));
+    emit_event(app, "processing:overall", payload);
+}
+
+/// Update the job-wide accumulator for one file and emit the aggregate.
+pub fn report_job_progress(app: &AppHandle, state: &ProcessingState, file_key: &str, progress: u8) {
+    let payload = match state.job_progress.lock() {
+        Ok(mut job) => {
+            job.update_file(file_key, progress);
+            job.payload()
+        }
+        Err(_) => return,
+    };
+    emit_overall(app, payload);
 }
 
 pub fn emit_status(app: &AppHandle, status: &str) {
-    let _ = app.emit_all(
-        "processing:status",
+    emit_status_with_reason(app, status, None);
+}
+
+/// Emit a terminal status that says why the run stopped early.
+pub fn emit_status_with_reason(app: &AppHandle, status: &str, reason: Option<StopReason>) {
+    match status {
+        "error" => set_taskbar_progress(app, TaskbarProgress::Error),
+        "completed" | "idle" => set_taskbar_progress(app, TaskbarProgress::Clear),
+        _ => {}
+    }
+    send_status(
+        app,
+        StatusPayload {
+            status: status.to_string(),
+            message: reason.as_ref().map(StopReason::describe),
+            reason,
+            parallel_tasks: None,
+        },
+    );
+}
+
+/// Emit the initial `processing` status with the worker count the run settled on.
+pub fn emit_processing_status(app: &AppHandle, parallel_tasks: usize) {
+    send_status(
+        app,
+        StatusPayload {
+            status: "processing".to_string(),
+            reason: None,
+            message: None,
+            parallel_tasks: Some(parallel_tasks),
+        },
+    );
+}
+
+/// Emit a status with a message for the UI, such as why a run is paused.
+pub fn emit_status_message(app: &AppHandle, status: &str, message: String) {
+    send_status(
+        app,
         StatusPayload {
             status: status.to_string(),
+            reason: None,
+            message: Some(message),
+            parallel_tasks: None,
         },
     );
 }
 
+fn send_status(app: &AppHandle, payload: StatusPayload) {
+    if let Some(state) = app.try_state::<ProcessingState>() {
+        if let Ok(mut current) = state.status.lock() {
+            *current = payload.status.clone();
+        }
+    }
+    emit_event(app, "processing:status", payload);
+}
+
+/// Rough peak memory of one worker, dominated by dovi_tool injecting an RPU.
+const WORKER_MEMORY_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Memory the OS reports as available, where it can be read without a
+/// system-info crate (Linux `/proc/meminfo`).
+fn available_memory_bytes() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Clamp the requested number of parallel folder workers to what this
+/// machine can run, mapping 0 to a default. Returns the effective value and
+/// a note on how it was chosen, for the log.
+pub fn effective_parallel_tasks(requested: usize) -> (usize, String) {
+    let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let memory = available_memory_bytes();
+    let memory_cap = memory.map(|bytes| ((bytes / WORKER_MEMORY_BYTES) as usize).max(1));
+    let limit = memory_cap.map_or(cpus, |cap| cap.min(cpus));
+    let memory_note = match memory {
+        Some(bytes) => format!("{:.1} GiB RAM available", bytes as f64 / 1_073_741_824.0),
+        None => "RAM unknown".to_string(),
+    };
+    let system = format!("{} CPU(s), {}", cpus, memory_note);
+    if requested == 0 {
+        let default = (cpus / 2).clamp(1, limit);
+        (default, format!("default for {}", system))
+    } else if requested > limit {
+        (limit, format!("{} requested, capped for {}", requested, system))
+    } else {
+        (requested, format!("as requested; {}", system))
+    }
+}
+
 pub fn resolve_path(app: &AppHandle, path: &str) -> PathBuf {
     let path_buf = PathBuf::from(path);
     if path_buf.is_absolute() {
@@ -59,6 +272,50 @@ pub fn resolve_path(app: &AppHandle, path: &str) -> PathBuf {
     path_buf
 }
 
+/// Anchor a relative path at the app's current directory.
+pub fn absolute_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    std::env::current_dir()
+        .map(|dir| dir.join(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Map a failed write in an output folder to what the user has to fix.
+fn describe_write_error(dir: &Path, err: &std::io::Error) -> String {
+    // EROFS / ENOSPC on Unix, ERROR_WRITE_PROTECT / ERROR_HANDLE_DISK_FULL /
+    // ERROR_DISK_FULL on Windows.
+    let (read_only, disk_full): (&[i32], &[i32]) = if cfg!(target_os = "windows") {
+        (&[19], &[39, 112])
+    } else {
+        (&[30], &[28])
+    };
+    let code = err.raw_os_error().unwrap_or_default();
+    let problem = if read_only.contains(&code) {
+        "is on a read-only volume"
+    } else if disk_full.contains(&code) {
+        "is on a full disk"
+    } else if err.kind() == std::io::ErrorKind::NotFound {
+        "does not exist and cannot be created (check the drive or parent folder)"
+    } else if err.kind() == std::io::ErrorKind::PermissionDenied {
+        "is not writable (permission denied)"
+    } else {
+        "cannot be written"
+    };
+    format!("Output folder {} {}: {}", dir.display(), problem, err)
+}
+
+/// Create `dir` if needed and check a file can be written in it, so a bad
+/// output location fails before any work starts, with the reason spelled out.
+pub fn ensure_writable(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|err| describe_write_error(dir, &err))?;
+    let probe = dir.join(format!(".hybrid-dv-write-test-{}", std::process::id()));
+    std::fs::write(&probe, b"").map_err(|err| describe_write_error(dir, &err))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
 pub fn normalize_output_path(default_output: &str, output_path: &str) -> PathBuf {
     let candidate = PathBuf::from(output_path);
     if output_path.is_empty() {
@@ -70,6 +327,58 @@ pub fn normalize_output_path(default_output: &str, output_path: &str) -> PathBuf
     Path::new(default_output).join(candidate)
 }
 
+/// Ending of every output name the app picks itself.
+const OUTPUT_SUFFIX: &str = ".DV.HDR.H.265-NOGRP.mkv";
+
+/// Outputs named by [`OUTPUT_SUFFIX`], split parts and renamed copies
+/// included, the `<output>_*` intermediates next to them, and copies still
+/// being written.
+const OWN_OUTPUT: &str = r"(?i)\.DV\.HDR\.H\.265-NOGRP(?:-\d{3})?(?: \(\d+\))?\.mkv(?:_.*|\.partial)?$";
+
+/// Whether a folder entry is something a run wrote, so a scan of a folder
+/// that is also the output folder does not pick it up as an input.
+pub fn is_own_output(name: &str) -> bool {
+    Regex::new(OWN_OUTPUT).map(|re| re.is_match(name)).unwrap_or(false)
+}
+
+/// Combined size of the input files in `paths`, counting a path given twice
+/// once. Folders and missing files count as nothing.
+pub fn input_bytes(paths: &[&Path]) -> u64 {
+    let mut seen: Vec<&Path> = Vec::with_capacity(paths.len());
+    let mut bytes = 0;
+    for path in paths {
+        if seen.contains(path) {
+            continue;
+        }
+        seen.push(path);
+        bytes += std::fs::metadata(path)
+            .ok()
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+    }
+    bytes
+}
+
+/// Warn when `output_dir` is one of the scanned input folders or inside
+/// one. Scans already skip what runs write there, but a separate output
+/// folder keeps the inputs clean.
+pub fn warn_output_in_inputs(app: &AppHandle, output_dir: &Path, input_dirs: &[&Path]) {
+    let normalize = |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| absolute_path(path));
+    let output = normalize(output_dir);
+    if let Some(input) = input_dirs.iter().map(|dir| normalize(dir)).find(|input| output.starts_with(input)) {
+        emit_log(
+            app,
+            "warning",
+            format!(
+                "Output folder {} is inside the input folder {}; outputs and intermediates written there are left out of scans, but a separate output folder is recommended",
+                output.display(),
+                input.display()
+            ),
+        );
+    }
+}
+
 pub fn compute_output_for_single(
     default_output: &str,
     output_path: &str,
@@ -79,11 +388,8 @@ pub fn compute_output_for_single(
         .file_name()
         .and_then(|s| s.to_str())
         .unwrap_or("output");
-    let regex = Regex::new(r"(.*)\.(HDR)+.*").ok();
-    let base = regex
-        .and_then(|re| re.captures(filename).and_then(|c| c.get(1).map(|m| m.as_str())))
-        .unwrap_or_else(|| filename.split('.').next().unwrap_or("output"));
-    let default_filename = format!("{}.DV.HDR.H.265-NOGRP.mkv", base);
+    let base = hdr_base_name(filename);
+    let default_filename = format!("{}{}", base, OUTPUT_SUFFIX);
 
     if !output_path.is_empty() {
         let candidate = PathBuf::from(output_path);
@@ -95,32 +401,426 @@ pub fn compute_output_for_single(
     Path::new(default_output).join(default_filename)
 }
 
-pub fn compute_output_for_batch(default_output: &str, hdr_file: &str) -> PathBuf {
-    let regex = Regex::new(r"(.*)\.(HDR)+.*").ok();
-    let base = regex
-        .and_then(|re| re.captures(hdr_file).and_then(|c| c.get(1).map(|m| m.as_str())))
-        .unwrap_or_else(|| hdr_file.split('.').next().unwrap_or(hdr_file));
-    let filename = format!("{}.DV.HDR.H.265-NOGRP.mkv", base);
-    Path::new(default_output).join(filename)
+/// Extensions of other containers a user might type for the output. The mux
+/// always writes Matroska, so these are swapped for `.mkv`.
+const NON_MATROSKA_EXTENSIONS: &[&str] = &[
+    "mp4", "m4v", "mov", "ts", "m2ts", "mts", "avi", "webm", "hevc", "h265", "265",
+];
+
+/// `output_path` with a Matroska extension, or `None` if it already has one.
+/// Known foreign container extensions are replaced; anything else (including
+/// dotted release names like `Movie.DV.HDR`) gets `.mkv` appended.
+pub fn matroska_output_path(output_path: &Path) -> Option<PathBuf> {
+    let extension = output_path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("mkv") => None,
+        Some(ext) if NON_MATROSKA_EXTENSIONS.contains(&ext) => Some(output_path.with_extension("mkv")),
+        _ => {
+            let mut name = output_path.as_os_str().to_os_string();
+            name.push(".mkv");
+            Some(PathBuf::from(name))
+        }
+    }
 }
 
+/// Output for `hdr_file`, a path relative to the scanned HDR folder. `Mirror`
+/// keeps its subfolders under `default_output`; `Flat` drops them.
+pub fn compute_output_for_batch(
+    default_output: &str,
+    hdr_file: &str,
+    structure: OutputStructure,
+) -> PathBuf {
+    let relative = Path::new(hdr_file);
+    let name = relative
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(hdr_file);
+    let base = hdr_base_name(name);
+    let filename = format!("{}{}", base, OUTPUT_SUFFIX);
+    let root = Path::new(default_output);
+    match (structure, relative.parent()) {
+        (OutputStructure::Mirror, Some(subfolder)) => root.join(subfolder).join(filename),
+        _ => root.join(filename),
+    }
+}
+
+/// Words that start the release details of a file name rather than its title.
+const RELEASE_TAGS: &[&str] = &[
+    "bluray", "bdrip", "brrip", "bdremux", "remux", "uhd", "web", "webdl", "webrip", "hdtv", "dv", "dovi",
+    "hdr", "hdr10", "hdr10plus", "sdr", "hevc", "avc", "h264", "h265", "x264", "x265", "proper", "repack",
+];
+
+/// Lowercase title words of a file name: the words before its resolution or
+/// first release tag, ending at the year when there is one, plus the
+/// normalized episode. `Movie (2021) [Remastered].mkv` and
+/// `Movie.2021.2160p.WEB-DL.DV.mkv` both give `movie 2021`.
+pub fn title_tokens(file_name: &str) -> Vec<String> {
+    let name = Path::new(file_name)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = match name.rsplit_once('.') {
+        Some((stem, extension)) if VIDEO_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()) => stem,
+        _ => name.as_str(),
+    };
+    let Ok(release_re) = Regex::new(r"^(?:\d{3,4}[pi]|[48]k|s\d{1,2}(?:e\d{1,3})?|\d{1,2}x\d{2,3})$") else {
+        return Vec::new();
+    };
+    let is_year = |word: &str| word.len() == 4 && (word.starts_with("19") || word.starts_with("20"));
+
+    let mut tokens = Vec::new();
+    for word in stem.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()) {
+        let word = word.to_lowercase();
+        if !tokens.is_empty() {
+            if is_year(&word) {
+                tokens.push(word);
+                break;
+            }
+            if release_re.is_match(&word) || RELEASE_TAGS.contains(&word.as_str()) {
+                break;
+            }
+        }
+        tokens.push(word);
+    }
+    tokens.extend(episode_token(stem).map(|token| token.to_lowercase()));
+    tokens
+}
 
+/// The file in `dv_files` with the same title words as `base` (see
+/// [`title_tokens`]), whatever its source or release tags.
 pub fn find_matching_dv_file(dv_files: &[String], base: &str) -> Option<String> {
-    let re = Regex::new(base).ok()?;
-    dv_files.iter().find(|f| re.is_match(f)).cloned()
+    let title = title_tokens(base);
+    if title.is_empty() {
+        return None;
+    }
+    dv_files.iter().find(|file| title_tokens(file) == title).cloned()
+}
+
+/// Normalized `SxxEyy` token of a file name (from `S1E2` or `1x02` style
+/// numbering), if it has one.
+pub fn episode_token(file_name: &str) -> Option<String> {
+    let patterns = [
+        r"(?i)S(\d{1,2})[ ._-]?E(\d{1,3})",
+        r"(?i)(?:^|[^a-z0-9])(\d{1,2})x(\d{2,3})(?:[^0-9]|$)",
+    ];
+    patterns.iter().find_map(|pattern| {
+        let caps = Regex::new(pattern).ok()?.captures(file_name)?;
+        let season: u32 = caps[1].parse().ok()?;
+        let episode: u32 = caps[2].parse().ok()?;
+        Some(format!("S{:02}E{:02}", season, episode))
+    })
+}
+
+/// A DV file chosen for an HDR file, and how it was chosen.
+pub struct DvPairing {
+    pub dv_file: String,
+    pub matched_by: String,
+}
+
+/// Pick the DV file for `hdr_files[index]`. An HDR file with an episode
+/// token only pairs with a DV file carrying the same token, which also
+/// covers the old "same position, same episode" fallback. Without a token
+/// it pairs by name only.
+pub fn pair_dv_file(
+    hdr_files: &[String],
+    dv_files: &[String],
+    index: usize,
+    base: &str,
+) -> Option<DvPairing> {
+    let hdr_file = hdr_files.get(index)?;
+    if let Some(token) = episode_token(hdr_file) {
+        let dv_file = dv_files
+            .iter()
+            .find(|dv| episode_token(dv).as_deref() == Some(token.as_str()))?;
+        return Some(DvPairing {
+            dv_file: dv_file.clone(),
+            matched_by: format!("episode {}", token),
+        });
+    }
+
+    find_matching_dv_file(dv_files, base).map(|dv_file| DvPairing {
+        dv_file,
+        matched_by: "name".to_string(),
+    })
 }
 
-pub fn get_video_metadata(tool_path: &Path, file_path: &Path) -> Result<String, String> {
-    use std::process::Command;
-    
-    let output = Command::new(tool_path)
+/// Sorted file names in `dir`.
+pub fn list_dir_files(dir: &Path) -> Result<Vec<String>, String> {
+    let mut files = std::fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<String>>();
+    files.sort();
+    Ok(files)
+}
+
+/// Sorted paths, relative to `dir`, of the files in `dir` and its
+/// subfolders. Hidden and symlinked folders are not entered, and a subfolder
+/// that cannot be read is left out.
+pub fn list_tree_files(dir: &Path) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let entries = match std::fs::read_dir(dir.join(&relative)) {
+            Ok(entries) => entries,
+            Err(err) if relative.as_os_str().is_empty() => return Err(err.to_string()),
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            let path = relative.join(&name);
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => {
+                    if !name.starts_with('.') {
+                        pending.push(path);
+                    }
+                }
+                _ => files.push(path.to_string_lossy().to_string()),
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Trailing video format tags (`.DV`, `.HDR10`, `.H.265`, `.x265-GROUP`, ...)
+/// that the output name adds back itself.
+const FORMAT_SUFFIX: &str = r"(?i)(?:\.(?:DV|DoVi|HDR10\+|HDR10Plus|HDR10|HDR|SDR|HEVC|AVC|[xh]\.?26[45]))+(?:-[^.\s]+)?$";
+
+/// Release name of an HDR file, i.e. everything before its `.HDR` tag. A
+/// name without one keeps its year, resolution and source, losing only a
+/// video extension and trailing format tags: `Movie.2021.2160p.BluRay.x265.mkv`
+/// gives `Movie.2021.2160p.BluRay`.
+pub fn hdr_base_name(hdr_file: &str) -> String {
+    if let Some(base) = Regex::new(r"(.*)\.(HDR)+.*")
+        .ok()
+        .and_then(|re| re.captures(hdr_file).and_then(|c| c.get(1).map(|m| m.as_str().to_string())))
+    {
+        return base;
+    }
+    let stem = match hdr_file.rsplit_once('.') {
+        Some((stem, extension)) if VIDEO_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()) => stem,
+        _ => hdr_file,
+    };
+    let base = Regex::new(FORMAT_SUFFIX)
+        .map(|re| re.replace(stem, "").into_owned())
+        .unwrap_or_else(|_| stem.to_string());
+    if base.is_empty() {
+        stem.to_string()
+    } else {
+        base
+    }
+}
+
+/// One HDR/DV pair found by [`scan_folder_pairs`].
+pub struct FolderPair {
+    pub hdr_index: usize,
+    pub hdr_file: String,
+    pub base: String,
+    pub dv_file: String,
+    pub matched_by: String,
+}
+
+/// Result of pairing an HDR folder against a DV folder.
+pub struct FolderScan {
+    pub pairs: Vec<FolderPair>,
+    /// HDR files with no DV partner, and DV files no HDR file claimed.
+    pub unmatched: Vec<String>,
+    pub unmatched_dv: Vec<String>,
+    /// HDR folder entries that are hidden or not video files.
+    pub junk: Vec<String>,
+    /// HDR folder entries a run wrote, when the output folder is an input one.
+    pub own_outputs: Vec<String>,
+    pub hdr_file_count: usize,
+}
+
+impl FolderScan {
+    /// Start the scan report for this folder with the files it already
+    /// left out; pairs are added as outputs are claimed.
+    pub fn report(&self, queue_id: Option<&str>, hdr_dir: &Path) -> ScanReport {
+        let mut report = ScanReport {
+            queue_id: queue_id.map(str::to_string),
+            hdr_dir: hdr_dir.to_string_lossy().to_string(),
+            total_found: self.hdr_file_count,
+            processed: 0,
+            skipped: Vec::new(),
+            pairs: Vec::new(),
+            ordering: BatchOrdering::default(),
+            unmatched_dv: self.unmatched_dv.clone(),
+        };
+        for file in &self.junk {
+            report.skip(file.clone(), ScanSkipReason::Junk);
+        }
+        for file in &self.own_outputs {
+            report.skip(file.clone(), ScanSkipReason::OwnOutput);
+        }
+        for file in &self.unmatched {
+            report.skip(file.clone(), ScanSkipReason::Unpaired);
+        }
+        report
+    }
+
+    /// Reorder the pairs for queueing. Sizes are the HDR and DV input sizes
+    /// together; a pair whose files cannot be read counts as empty.
+    pub fn order_pairs(&mut self, hdr_dir: &Path, dv_dir: &Path, ordering: BatchOrdering) {
+        if ordering == BatchOrdering::Name {
+            return;
+        }
+        let file_size = |path: PathBuf| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let mut sized: Vec<(u64, FolderPair)> = std::mem::take(&mut self.pairs)
+            .into_iter()
+            .map(|pair| {
+                let size = file_size(hdr_dir.join(&pair.hdr_file)) + file_size(dv_dir.join(&pair.dv_file));
+                (size, pair)
+            })
+            .collect();
+        // Stable sorts, so equal sizes keep their name order.
+        match ordering {
+            BatchOrdering::SizeAscending => sized.sort_by_key(|(size, _)| *size),
+            _ => sized.sort_by_key(|(size, _)| std::cmp::Reverse(*size)),
+        }
+        if ordering == BatchOrdering::Interleaved {
+            let mut remaining: std::collections::VecDeque<_> = sized.into();
+            let mut interleaved = Vec::with_capacity(remaining.len());
+            while let Some(largest) = remaining.pop_front() {
+                interleaved.push(largest);
+                if let Some(smallest) = remaining.pop_back() {
+                    interleaved.push(smallest);
+                }
+            }
+            sized = interleaved;
+        }
+        self.pairs = sized.into_iter().map(|(_, pair)| pair).collect();
+    }
+}
+
+/// Containers and raw streams a folder scan pairs; anything else in the
+/// folders (NFOs, subtitles, thumbnails, `.DS_Store`) is left alone.
+const VIDEO_EXTENSIONS: &[&str] = &["mkv", "mp4", "m4v", "mov", "ts", "m2ts", "hevc", "h265"];
+
+fn is_junk_file(name: &str) -> bool {
+    let extension = Path::new(name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    name.starts_with('.')
+        || !extension
+            .as_deref()
+            .map(|ext| VIDEO_EXTENSIONS.contains(&ext))
+            .unwrap_or(false)
+}
+
+/// List both folders and pair every HDR file with its DV file. A recursive
+/// scan also lists their subfolders, and each file is then named by its path
+/// relative to the folder.
+pub fn scan_folder_pairs(hdr_dir: &Path, dv_dir: &Path, recursive: bool) -> Result<FolderScan, String> {
+    let list = |dir: &Path| if recursive { list_tree_files(dir) } else { list_dir_files(dir) };
+    let file_name = |path: &str| {
+        Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let all_hdr_files = list(hdr_dir)?;
+    let hdr_file_count = all_hdr_files.len();
+    let (own_outputs, hdr_files): (Vec<String>, Vec<String>) =
+        all_hdr_files.into_iter().partition(|path| is_own_output(&file_name(path)));
+    let (junk, hdr_files): (Vec<String>, Vec<String>) =
+        hdr_files.into_iter().partition(|path| is_junk_file(&file_name(path)));
+    let dv_files: Vec<String> = list(dv_dir)?
+        .into_iter()
+        .filter(|path| !is_junk_file(&file_name(path)) && !is_own_output(&file_name(path)))
+        .collect();
+
+    let mut pairs = Vec::new();
+    let mut unmatched = Vec::new();
+    for (hdr_index, hdr_file) in hdr_files.iter().enumerate() {
+        let base = hdr_base_name(&file_name(hdr_file));
+        match pair_dv_file(&hdr_files, &dv_files, hdr_index, &base) {
+            Some(pairing) => pairs.push(FolderPair {
+                hdr_index,
+                hdr_file: hdr_file.clone(),
+                base,
+                dv_file: pairing.dv_file,
+                matched_by: pairing.matched_by,
+            }),
+            None => unmatched.push(hdr_file.clone()),
+        }
+    }
+    let unmatched_dv = dv_files
+        .into_iter()
+        .filter(|dv_file| !pairs.iter().any(|pair: &FolderPair| &pair.dv_file == dv_file))
+        .collect();
+    Ok(FolderScan {
+        pairs,
+        unmatched,
+        unmatched_dv,
+        junk,
+        own_outputs,
+        hdr_file_count,
+    })
+}
+
+/// Parse `mkvmerge -J` output, tolerating a UTF-8 BOM or stray bytes before the JSON.
+fn parse_identification_json(raw: &[u8]) -> Result<serde_json::Value, String> {
+    let without_bom = raw.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(raw);
+    let start = without_bom
+        .iter()
+        .position(|b| *b == b'{')
+        .unwrap_or(0);
+    let body = &without_bom[start..];
+
+    serde_json::from_slice(body).map_err(|e| {
+        let preview_len = raw.len().min(200);
+        format!(
+            "Failed to parse mkvmerge identification JSON: {} (first {} bytes: {:?})",
+            e,
+            preview_len,
+            String::from_utf8_lossy(&raw[..preview_len])
+        )
+    })
+}
+
+/// Older mkvmerge builds can emit locale-formatted nanosecond counts
+/// (e.g. "41.708.333" or "41 708 333"); reduce those to a plain `<n>ns` value.
+pub fn normalize_duration_string(raw: &str) -> String {
+    let trimmed = raw.trim();
+    let is_grouped_number = !trimmed.is_empty()
+        && trimmed
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | ' ' | '\u{a0}' | '\u{202f}' | '\''));
+    if is_grouped_number {
+        let digits: String = trimmed.chars().filter(|c| c.is_ascii_digit()).collect();
+        // Frame durations are at least a millisecond, so anything shorter than
+        // seven digits is a plain rate like "23.976" rather than nanoseconds.
+        if digits.len() >= 7 {
+            return format!("{}ns", digits);
+        }
+    }
+    trimmed.to_string()
+}
+
+/// `mkvmerge -J` on a file, run from the job's work directory.
+fn identify_command(tool_path: &Path, file_path: &Path, work_dir: &Path) -> Command {
+    let mut command = tool_command(tool_path, work_dir);
+    command
         .arg("--identify")
         .arg("--ui-language")
         .arg("en")
         .arg("--output-charset")
         .arg("utf-8")
         .arg("-J")
-        .arg(file_path)
+        .arg(file_path);
+    command
+}
+
+/// Run `mkvmerge -J` on a file and return the parsed identification JSON.
+pub fn identify_file(tool_path: &Path, file_path: &Path, work_dir: &Path) -> Result<serde_json::Value, String> {
+    let output = identify_command(tool_path, file_path, work_dir)
         .output()
         .map_err(|e| format!("Failed to run identification: {}", e))?;
 
@@ -128,34 +828,262 @@ pub fn get_video_metadata(tool_path: &Path, file_path: &Path) -> Result<String,
         return Err("mkvmerge identification failed".to_string());
     }
 
-    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
-        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    parse_identification_json(&output.stdout)
+}
+
+#[derive(Debug, Clone)]
+pub struct VideoTrackInfo {
+    pub id: u64,
+    pub codec: String,
+    pub hevc: bool,
+}
 
+/// List the video tracks of a file in mkvmerge's track order.
+pub fn get_video_tracks(tool_path: &Path, file_path: &Path, work_dir: &Path) -> Result<Vec<VideoTrackInfo>, String> {
+    let json = identify_file(tool_path, file_path, work_dir)?;
     let tracks = json["tracks"]
         .as_array()
         .ok_or("No tracks found in JSON output")?;
 
-    for track in tracks {
-        if track["type"] == "video" {
-            let props = &track["properties"];
-            
-            // Try string format (e.g., "23.976fps")
-            if let Some(duration) = props["default_duration"].as_str() {
-                return Ok(duration.to_string());
+    Ok(tracks
+        .iter()
+        .filter(|track| track["type"] == "video")
+        .map(|track| {
+            let codec = track["codec"].as_str().unwrap_or("unknown").to_string();
+            let codec_id = track["properties"]["codec_id"].as_str().unwrap_or_default();
+            VideoTrackInfo {
+                id: track["id"].as_u64().unwrap_or_default(),
+                hevc: codec_id == "V_MPEGH/ISO/HEVC" || codec.contains("HEVC") || codec.contains("H.265"),
+                codec,
             }
-            
-            // Try numeric format (nanoseconds)
-            if let Some(duration_ns) = props["default_duration"].as_u64() {
-                return Ok(format!("{}ns", duration_ns));
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioTrackInfo {
+    pub id: u64,
+    pub codec: String,
+    pub channels: Option<u64>,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubtitleTrackInfo {
+    pub id: u64,
+    pub codec: String,
+    /// Bitmap formats (PGS, VobSub, DVB) as opposed to text.
+    pub image_based: bool,
+    pub forced: bool,
+    pub hearing_impaired: bool,
+    pub language: Option<String>,
+}
+
+/// List the subtitle tracks of a file in mkvmerge's track order.
+pub fn get_subtitle_tracks(tool_path: &Path, file_path: &Path, work_dir: &Path) -> Result<Vec<SubtitleTrackInfo>, String> {
+    let json = identify_file(tool_path, file_path, work_dir)?;
+    let tracks = json["tracks"]
+        .as_array()
+        .ok_or("No tracks found in JSON output")?;
+
+    Ok(tracks
+        .iter()
+        .filter(|track| track["type"] == "subtitles")
+        .map(|track| {
+            let props = &track["properties"];
+            let codec_id = props["codec_id"].as_str().unwrap_or_default();
+            let name = props["track_name"].as_str().unwrap_or_default().to_ascii_lowercase();
+            SubtitleTrackInfo {
+                id: track["id"].as_u64().unwrap_or_default(),
+                codec: track["codec"].as_str().unwrap_or("unknown").to_string(),
+                image_based: ["S_HDMV/PGS", "S_VOBSUB", "S_DVBSUB"]
+                    .iter()
+                    .any(|id| codec_id.starts_with(id)),
+                forced: props["forced_track"].as_bool().unwrap_or(false),
+                hearing_impaired: props["flag_hearing_impaired"].as_bool().unwrap_or(false)
+                    || name.contains("sdh")
+                    || name.contains("hearing impaired"),
+                language: props["language"].as_str().map(|s| s.to_string()),
             }
+        })
+        .collect())
+}
+
+/// List the audio tracks of a Matroska file in mkvmerge's track order.
+pub fn get_audio_tracks(tool_path: &Path, file_path: &Path, work_dir: &Path) -> Result<Vec<AudioTrackInfo>, String> {
+    let json = identify_file(tool_path, file_path, work_dir)?;
+    let tracks = json["tracks"]
+        .as_array()
+        .ok_or("No tracks found in JSON output")?;
+
+    Ok(tracks
+        .iter()
+        .filter(|track| track["type"] == "audio")
+        .map(|track| AudioTrackInfo {
+            id: track["id"].as_u64().unwrap_or(0),
+            codec: track["codec"].as_str().unwrap_or("unknown").to_string(),
+            channels: track["properties"]["audio_channels"].as_u64(),
+            language: track["properties"]["language"]
+                .as_str()
+                .map(|s| s.to_string()),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identify_runs_in_the_work_dir() {
+        let command = identify_command(Path::new("mkvmerge"), Path::new("/media/Movie.mkv"), Path::new("/jobs/movie"));
+        assert_eq!(command.get_current_dir(), Some(Path::new("/jobs/movie")));
+    }
+
+    #[test]
+    fn identification_json_after_a_bom_parses() {
+        let json = parse_identification_json(b"\xEF\xBB\xBF{\"container\": {\"recognized\": true}}").unwrap();
+        assert_eq!(json["container"]["recognized"], true);
+    }
+
+    #[test]
+    fn identification_json_skips_text_before_the_object() {
+        let json = parse_identification_json(b"Warning: locale not found\n{\"tracks\": []}").unwrap();
+        assert!(json["tracks"].as_array().unwrap().is_empty());
+    }
 
-            // Fallback logic could go here, but default_duration is the standard mkvmerge way.
-            // We could try to calc from frame_rate if present, but relying on default_duration is safest.
+    #[test]
+    fn truncated_identification_json_is_an_error() {
+        let err = parse_identification_json(b"\xEF\xBB\xBF{\"tracks\": [{\"id\": 0,").unwrap_err();
+        assert!(err.starts_with("Failed to parse mkvmerge identification JSON"), "{}", err);
+        assert!(parse_identification_json(b"").is_err());
+    }
+
+    #[test]
+    fn grouped_nanosecond_durations_are_normalized() {
+        assert_eq!(normalize_duration_string("41.708.333"), "41708333ns");
+        assert_eq!(normalize_duration_string(" 41 708 333 "), "41708333ns");
+        assert_eq!(normalize_duration_string("41\u{a0}708\u{a0}333"), "41708333ns");
+        assert_eq!(normalize_duration_string("41708333ns"), "41708333ns");
+    }
+
+    #[test]
+    fn short_or_truncated_durations_are_left_alone() {
+        assert_eq!(normalize_duration_string("23.976"), "23.976");
+        assert_eq!(normalize_duration_string("41.708."), "41.708.");
+        assert_eq!(normalize_duration_string(""), "");
+    }
+
+    #[test]
+    fn base_name_keeps_year_resolution_and_source() {
+        assert_eq!(hdr_base_name("Movie.2021.2160p.BluRay.mkv"), "Movie.2021.2160p.BluRay");
+        assert_eq!(hdr_base_name("Movie.2021.2160p.BluRay.x265-GRP.mkv"), "Movie.2021.2160p.BluRay");
+    }
+
+    #[test]
+    fn base_name_ends_before_the_hdr_tag() {
+        assert_eq!(hdr_base_name("Movie.2021.2160p.BluRay.HDR.mkv"), "Movie.2021.2160p.BluRay");
+        assert_eq!(hdr_base_name("Movie.2021.2160p.BluRay.HDR.HEVC-GRP.mkv"), "Movie.2021.2160p.BluRay");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ensure_writable_reports_a_read_only_folder() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Root writes through the permission bits, so there is nothing to see.
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("hybrid-dv-read-only-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+        let result = ensure_writable(&dir);
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        let err = result.unwrap_err();
+        assert!(err.contains("is not writable (permission denied)"), "{}", err);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn ensure_writable_reports_a_missing_drive() {
+        let dir = Path::new(r"Q:\hybrid-dv-missing-drive\out");
+        if dir.ancestors().last().map(Path::exists).unwrap_or(false) {
+            return;
+        }
+        let err = ensure_writable(dir).unwrap_err();
+        assert!(err.contains("does not exist and cannot be created"), "{}", err);
+    }
+
+    #[test]
+    fn mirror_scans_subfolders_and_keeps_them_in_the_output() {
+        let root = std::env::temp_dir().join(format!("hybrid-dv-tree-{}", std::process::id()));
+        let (hdr_dir, dv_dir) = (root.join("hdr"), root.join("dv"));
+        for (dir, file) in [
+            (hdr_dir.join("Season 1"), "Show.S01E01.2160p.HDR.mkv"),
+            (hdr_dir.join(".partial"), "Show.S01E02.2160p.HDR.mkv"),
+            (dv_dir.join("Season 1"), "Show.S01E01.2160p.DV.mkv"),
+        ] {
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join(file), b"").unwrap();
         }
+
+        let flat = scan_folder_pairs(&hdr_dir, &dv_dir, false);
+        let nested = scan_folder_pairs(&hdr_dir, &dv_dir, true);
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(flat.unwrap().pairs.is_empty());
+        let nested = nested.unwrap();
+        assert_eq!(nested.pairs.len(), 1);
+        let pair = &nested.pairs[0];
+        let season = Path::new("Season 1");
+        assert_eq!(Path::new(&pair.hdr_file), season.join("Show.S01E01.2160p.HDR.mkv"));
+        assert_eq!(Path::new(&pair.dv_file), season.join("Show.S01E01.2160p.DV.mkv"));
+        assert_eq!(
+            compute_output_for_batch("/out", &pair.hdr_file, OutputStructure::Mirror),
+            Path::new("/out").join(season).join(format!("Show.S01E01.2160p{}", OUTPUT_SUFFIX))
+        );
+        assert_eq!(
+            compute_output_for_batch("/out", &pair.hdr_file, OutputStructure::Flat),
+            Path::new("/out").join(format!("Show.S01E01.2160p{}", OUTPUT_SUFFIX))
+        );
+    }
+
+    #[test]
+    fn bluray_hdr_pairs_with_a_web_dl_dv_release() {
+        let dv_files = vec![
+            "Other.Movie.2021.2160p.WEB-DL.DV.mkv".to_string(),
+            "Movie.2021.2160p.WEB-DL.DDP5.1.DV.HEVC-GRP.mkv".to_string(),
+        ];
+        let base = hdr_base_name("Movie.2021.2160p.BluRay.REMUX.HDR.mkv");
+        assert_eq!(
+            find_matching_dv_file(&dv_files, &base).as_deref(),
+            Some("Movie.2021.2160p.WEB-DL.DDP5.1.DV.HEVC-GRP.mkv")
+        );
     }
 
-    // Log the JSON tracks to help debug if we fail
-    // We can't emit log here easily without AppHandle passed in, 
-    // so we include the tracks in the error message for debugging.
-    Err(format!("No video track with default_duration found (checked string and u64). Tracks: {:?}", tracks))
+    #[test]
+    fn titles_with_regex_characters_pair() {
+        let dv_files = vec![
+            "Movie.Plus.2019.2160p.DV.mkv".to_string(),
+            "Movie+ (2019) [DV].mkv".to_string(),
+            "Amelie (2001) [Criterion] DV.mkv".to_string(),
+        ];
+        let base = hdr_base_name("Amelie (2001) [Criterion].mkv");
+        assert_eq!(
+            find_matching_dv_file(&dv_files, &base).as_deref(),
+            Some("Amelie (2001) [Criterion] DV.mkv")
+        );
+        let base = hdr_base_name("Movie+.2019.2160p.BluRay.HDR.mkv");
+        assert_eq!(find_matching_dv_file(&dv_files, &base).as_deref(), Some("Movie+ (2019) [DV].mkv"));
+        assert_eq!(find_matching_dv_file(&dv_files, &hdr_base_name("Movie (.mkv")), None);
+    }
+
+    #[test]
+    fn title_tokens_stop_at_the_year_or_release_tags() {
+        assert_eq!(title_tokens("Movie.2021.2160p.BluRay.mkv"), ["movie", "2021"]);
+        assert_eq!(title_tokens("Show.S01E02.1080p.WEB-DL.DV.mkv"), ["show", "s01e02"]);
+        assert_eq!(title_tokens("2001.A.Space.Odyssey.1968.UHD.mkv"), ["2001", "a", "space", "odyssey", "1968"]);
+    }
 }
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Largest single file a FAT12/16/32 volume can hold.
+pub const FAT_MAX_FILE_BYTES: u64 = 0xFFFF_FFFF;
+
+/// Part size used when splitting is turned on to fit a FAT volume; kept
+/// under 4 GiB so container overhead never tips a part over the limit.
+pub const FAT_SPLIT_SIZE: &str = "4000M";
+
+/// Filesystem of the volume an output folder lives on.
+#[derive(Debug, Clone)]
+pub struct VolumeInfo {
+    /// Mount point or drive root, where the platform reports one.
+    pub root: Option<String>,
+    /// Filesystem name as the platform reports it (`vfat`, `FAT32`, `exfat`, ...).
+    pub filesystem: String,
+}
+
+impl VolumeInfo {
+    /// FAT12/16/32 cap files at 4 GiB; exFAT does not.
+    pub fn has_4gb_limit(&self) -> bool {
+        matches!(
+            self.filesystem.to_ascii_lowercase().as_str(),
+            "fat" | "fat12" | "fat16" | "fat32" | "vfat" | "msdos"
+        )
+    }
+}
+
+/// Filesystem lookups for the current run, keyed by volume so a batch
+/// writing many outputs to one drive only asks the OS once.
+#[derive(Clone, Default)]
+pub struct VolumeCache {
+    volumes: Arc<Mutex<HashMap<String, VolumeInfo>>>,
+}
+
+impl VolumeCache {
+    pub fn clear(&self) {
+        if let Ok(mut volumes) = self.volumes.lock() {
+            volumes.clear();
+        }
+    }
+
+    /// Filesystem holding `dir`, which must already exist. `None` when the
+    /// platform cannot tell.
+    pub fn lookup(&self, dir: &Path) -> Option<VolumeInfo> {
+        let key = platform::volume_key(dir)?;
+        if let Some(info) = self.volumes.lock().ok()?.get(&key) {
+            return Some(info.clone());
+        }
+        let info = platform::detect(dir)?;
+        if let Ok(mut volumes) = self.volumes.lock() {
+            volumes.insert(key, info.clone());
+        }
+        Some(info)
+    }
+}
+
+/// Bytes the current user can still write on the volume holding `dir`.
+pub fn available_bytes(dir: &Path) -> Option<u64> {
+    platform::available_bytes(dir)
+}
+
+#[cfg(unix)]
+mod platform {
+    use super::VolumeInfo;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    pub fn volume_key(dir: &Path) -> Option<String> {
+        std::fs::metadata(dir).ok().map(|metadata| metadata.dev().to_string())
+    }
+
+    pub fn available_bytes(dir: &Path) -> Option<u64> {
+        let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        (stat.f_bavail as u64).checked_mul(stat.f_frsize as u64)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn statfs(dir: &Path) -> Option<libc::statfs> {
+        let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        Some(stat)
+    }
+
+    /// Linux only reports a magic number; name the ones users write to.
+    #[cfg(target_os = "linux")]
+    pub fn detect(dir: &Path) -> Option<VolumeInfo> {
+        let stat = statfs(dir)?;
+        let magic = stat.f_type as u64 & 0xFFFF_FFFF;
+        let filesystem = match magic {
+            0x4d44 => "vfat".to_string(),
+            0x2011_bab0 => "exfat".to_string(),
+            0x5346_544e => "ntfs".to_string(),
+            0xef53 => "ext4".to_string(),
+            0x9123_683e => "btrfs".to_string(),
+            0x5846_5342 => "xfs".to_string(),
+            0x0102_1994 => "tmpfs".to_string(),
+            0x6969 => "nfs".to_string(),
+            0xfe53_4d42 | 0xff53_4d42 => "smb".to_string(),
+            0x6573_5546 => "fuseblk".to_string(),
+            other => format!("0x{:x}", other),
+        };
+        Some(VolumeInfo { root: None, filesystem })
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn detect(dir: &Path) -> Option<VolumeInfo> {
+        let stat = statfs(dir)?;
+        let name = |field: &[libc::c_char]| unsafe {
+            std::ffi::CStr::from_ptr(field.as_ptr()).to_string_lossy().into_owned()
+        };
+        Some(VolumeInfo {
+            root: Some(name(&stat.f_mntonname)),
+            filesystem: name(&stat.f_fstypename),
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    pub fn detect(_dir: &Path) -> Option<VolumeInfo> {
+        None
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::VolumeInfo;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::{GetDiskFreeSpaceExW, GetVolumeInformationW, GetVolumePathNameW};
+
+    fn wide(text: &[u16]) -> String {
+        let end = text.iter().position(|c| *c == 0).unwrap_or(text.len());
+        String::from_utf16_lossy(&text[..end])
+    }
+
+    /// Drive or mount-point root holding `dir`, NUL-terminated.
+    fn volume_root(dir: &Path) -> Option<Vec<u16>> {
+        let path: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut root = vec![0u16; 1024];
+        if !unsafe { GetVolumePathNameW(PCWSTR(path.as_ptr()), &mut root) }.as_bool() {
+            return None;
+        }
+        let end = root.iter().position(|c| *c == 0)?;
+        root.truncate(end + 1);
+        Some(root)
+    }
+
+    pub fn volume_key(dir: &Path) -> Option<String> {
+        volume_root(dir).map(|root| wide(&root).to_ascii_uppercase())
+    }
+
+    pub fn available_bytes(dir: &Path) -> Option<u64> {
+        let path: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut available = 0u64;
+        let ok = unsafe {
+            GetDiskFreeSpaceExW(
+                PCWSTR(path.as_ptr()),
+                &mut available,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        ok.as_bool().then_some(available)
+    }
+
+    pub fn detect(dir: &Path) -> Option<VolumeInfo> {
+        let root = volume_root(dir)?;
+        let mut name = [0u16; 261];
+        let mut filesystem = [0u16; 261];
+        let ok = unsafe {
+            GetVolumeInformationW(
+                PCWSTR(root.as_ptr()),
+                &mut name,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                &mut filesystem,
+            )
+        };
+        if !ok.as_bool() {
+            return None;
+        }
+        Some(VolumeInfo {
+            root: Some(wide(&root)),
+            filesystem: wide(&filesystem),
+        })
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use super::VolumeInfo;
+    use std::path::Path;
+
+    pub fn volume_key(_dir: &Path) -> Option<String> {
+        None
+    }
+
+    pub fn available_bytes(_dir: &Path) -> Option<u64> {
+        None
+    }
+
+    pub fn detect(_dir: &Path) -> Option<VolumeInfo> {
+        None
+    }
+}
@@ -0,0 +1,492 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::models::{PipelineOptions, ProcessingRequest, ProcessingState, QueueItem, StopReason};
+use crate::processing::{is_validation_error, process_queue_item};
+use crate::utils::{emit_log, scan_folder_pairs, warn_output_in_inputs};
+
+/// Default seconds a pair's sizes must hold still before it is processed.
+/// Each change in the drop folders also pushes the next scan out this far.
+const DEFAULT_SETTLE_SECS: u64 = 10;
+/// Default runs of a pair before it is given up on.
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// How a watched pair ended, kept in the ledger so a restart does not
+/// process it again.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchEntry {
+    /// `"completed"` or `"failed"`.
+    pub status: String,
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub finished_ms: u64,
+}
+
+/// `app_data_dir/watch/ledger.json`: finished pairs keyed by HDR file path.
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct WatchLedger {
+    entries: BTreeMap<String, WatchEntry>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchStatus {
+    pub running: bool,
+    pub hdr_dir: Option<String>,
+    pub dv_dir: Option<String>,
+    pub settle_secs: u64,
+    /// HDR file being processed right now.
+    pub current: Option<String>,
+    pub completed: usize,
+    pub failed: usize,
+    /// Pairs that failed transiently and are waiting for another attempt.
+    pub retrying: usize,
+    pub last_scan_ms: Option<u64>,
+    /// Where finished pairs are recorded, one line each.
+    pub results_log: Option<String>,
+}
+
+struct WatchRunner {
+    stop: Arc<AtomicBool>,
+}
+
+/// Drop-folder daemon: watches an HDR and a DV folder, runs every new pair
+/// through `process_queue_item` one at a time, retries transient failures
+/// and records results under `app_data_dir/watch`.
+#[derive(Clone, Default)]
+pub struct WatchFolder {
+    runner: Arc<Mutex<Option<WatchRunner>>>,
+    status: Arc<Mutex<WatchStatus>>,
+}
+
+/// A pair not yet finished: sizes at the last scan, so files still being
+/// copied in are left alone, and the failed attempts so far.
+#[derive(Default)]
+struct PendingPair {
+    sizes: Option<(u64, u64)>,
+    attempts: u32,
+    retry_at: Option<Instant>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn watch_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or("Could not resolve app data directory".to_string())?
+        .join("watch");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+fn load_ledger(path: &Path) -> WatchLedger {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_ledger(path: &Path, ledger: &WatchLedger) {
+    if let Ok(json) = serde_json::to_vec_pretty(ledger) {
+        let _ = fs::write(path, json);
+    }
+}
+
+fn append_result(path: &Path, hdr_file: &Path, entry: &WatchEntry) {
+    let line = format!(
+        "{}\t{}\t{}\tattempts={}\t{}\n",
+        entry.finished_ms,
+        entry.status,
+        hdr_file.display(),
+        entry.attempts,
+        entry.error.as_deref().unwrap_or("")
+    );
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Reset the per-run parts of the shared state before a watched pair, the
+/// way `start_processing` does for a run.
+fn begin_pair(app: &AppHandle, state: &ProcessingState, request: &ProcessingRequest) {
+    if let Ok(mut reason) = state.stop_reason.lock() {
+        *reason = None;
+    }
+    if let Ok(mut job) = state.job_progress.lock() {
+        job.reset();
+    }
+    if let Ok(mut outputs) = state.outputs.lock() {
+        outputs.reset();
+    }
+    // A file replaced under the same name must be probed afresh.
+    state.probe.clear();
+    state.history.begin(app, request);
+    state.metrics.start();
+}
+
+impl WatchFolder {
+    pub fn status(&self) -> WatchStatus {
+        self.status.lock().map(|status| status.clone()).unwrap_or_default()
+    }
+
+    fn update(&self, change: impl FnOnce(&mut WatchStatus)) {
+        if let Ok(mut status) = self.status.lock() {
+            change(&mut status);
+        }
+    }
+
+    /// Start watching `request.hdr_path` and `request.dv_path`. The rest of
+    /// the request supplies tool paths, options and the output folder.
+    pub fn start(
+        &self,
+        app: &AppHandle,
+        state: &ProcessingState,
+        request: ProcessingRequest,
+        settle_secs: Option<u64>,
+        max_attempts: Option<u32>,
+    ) -> Result<WatchStatus, String> {
+        let mut runner = self.runner.lock().map_err(|_| "Watch lock failed")?;
+        if runner.is_some() {
+            return Ok(self.status());
+        }
+        // Watched pairs share the run state, so a run and the daemon cannot overlap.
+        if state.is_running() {
+            return Err("A run is in progress; start watch mode once it has finished".to_string());
+        }
+        let hdr_dir = PathBuf::from(&request.hdr_path);
+        let dv_dir = PathBuf::from(&request.dv_path);
+        if !hdr_dir.is_dir() || !dv_dir.is_dir() {
+            return Err("Watch mode needs an HDR folder and a DV folder".to_string());
+        }
+        let dir = watch_dir(app)?;
+        let ledger_path = dir.join("ledger.json");
+        let results_path = dir.join("results.log");
+        let ledger = load_ledger(&ledger_path);
+        let settle = Duration::from_secs(settle_secs.unwrap_or(DEFAULT_SETTLE_SECS).max(1));
+        let max_attempts = max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1);
+
+        let count = |status: &str| {
+            ledger
+                .entries
+                .iter()
+                .filter(|(path, entry)| entry.status == status && Path::new(path).starts_with(&hdr_dir))
+                .count()
+        };
+        let status = WatchStatus {
+            running: true,
+            hdr_dir: Some(hdr_dir.to_string_lossy().to_string()),
+            dv_dir: Some(dv_dir.to_string_lossy().to_string()),
+            settle_secs: settle.as_secs(),
+            current: None,
+            completed: count("completed"),
+            failed: count("failed"),
+            retrying: 0,
+            last_scan_ms: None,
+            results_log: Some(results_path.to_string_lossy().to_string()),
+        };
+        self.update(|current| *current = status.clone());
+        if let Ok(mut flag) = state.cancel_flag.lock() {
+            *flag = false;
+        }
+
+        let options = request.pipeline_options();
+        let output_dir = if request.output_path.is_empty() {
+            options.default_output.clone()
+        } else {
+            request.output_path.clone()
+        };
+        warn_output_in_inputs(app, Path::new(&output_dir), &[&hdr_dir, &dv_dir]);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let daemon = WatchDaemon {
+            app: app.clone(),
+            state: state.clone(),
+            watch: self.clone(),
+            options,
+            request,
+            hdr_dir,
+            dv_dir,
+            ledger,
+            ledger_path,
+            results_path,
+            max_attempts,
+            pending: HashMap::new(),
+        };
+        {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || daemon.run(&stop, settle));
+        }
+        *runner = Some(WatchRunner { stop });
+        emit_log(
+            app,
+            "info",
+            format!(
+                "Watching {} for new pairs",
+                status.hdr_dir.as_deref().unwrap_or_default()
+            ),
+        );
+        Ok(status)
+    }
+
+    /// Stop watching. A pair being processed is cancelled like a run.
+    pub fn stop(&self, state: &ProcessingState) {
+        let Some(runner) = self.runner.lock().ok().and_then(|mut runner| runner.take()) else {
+            return;
+        };
+        runner.stop.store(true, Ordering::Relaxed);
+        if self.status().current.is_some() {
+            state.request_stop(StopReason::UserCancelled);
+        }
+        self.update(|status| {
+            status.running = false;
+            status.current = None;
+        });
+    }
+}
+
+struct WatchDaemon {
+    app: AppHandle,
+    state: ProcessingState,
+    watch: WatchFolder,
+    request: ProcessingRequest,
+    options: PipelineOptions,
+    hdr_dir: PathBuf,
+    dv_dir: PathBuf,
+    ledger: WatchLedger,
+    ledger_path: PathBuf,
+    results_path: PathBuf,
+    max_attempts: u32,
+    pending: HashMap<PathBuf, PendingPair>,
+}
+
+impl WatchDaemon {
+    /// Scan once at start, then again `settle` after the drop folders stop
+    /// changing, and whenever a pending pair is due for its settle check or
+    /// retry. Without a filesystem watcher it rescans every `settle`.
+    fn run(mut self, stop: &AtomicBool, settle: Duration) {
+        let (events, changes) = mpsc::channel();
+        let watcher = match self.watch_folders(events) {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                emit_log(
+                    &self.app,
+                    "warning",
+                    format!("Could not watch the drop folders ({}); rescanning every {}s", err, settle.as_secs()),
+                );
+                None
+            }
+        };
+        let polling = watcher.is_none();
+        let mut sequence = 0usize;
+        let mut next_scan = Some(Instant::now());
+        while !stop.load(Ordering::Relaxed) {
+            match changes.recv_timeout(Duration::from_millis(250)) {
+                Ok(()) => {
+                    // Wait for a burst of changes (a copy in progress) to quiet down.
+                    next_scan = Some(Instant::now() + settle);
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => thread::sleep(Duration::from_millis(250)),
+            }
+            if next_scan.is_none_or(|at| Instant::now() < at) {
+                continue;
+            }
+            next_scan = self.scan(stop, &mut sequence, settle);
+            if polling {
+                next_scan = Some(Instant::now() + settle);
+            }
+        }
+        emit_log(&self.app, "info", "Watch mode stopped");
+    }
+
+    /// Forward every change under the drop folders to `events`.
+    fn watch_folders(&self, events: mpsc::Sender<()>) -> Result<RecommendedWatcher, String> {
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if event.map(|event| !event.kind.is_access()).unwrap_or(true) {
+                let _ = events.send(());
+            }
+        })
+        .map_err(|e| e.to_string())?;
+        let mode = if self.options.output_structure.recursive() {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher.watch(&self.hdr_dir, mode).map_err(|e| e.to_string())?;
+        if self.dv_dir != self.hdr_dir {
+            watcher.watch(&self.dv_dir, mode).map_err(|e| e.to_string())?;
+        }
+        Ok(watcher)
+    }
+
+    /// Process every ready pair, then return when the folders need another
+    /// look: the next settle check or retry of a pending pair, if any.
+    fn scan(&mut self, stop: &AtomicBool, sequence: &mut usize, settle: Duration) -> Option<Instant> {
+        match scan_folder_pairs(&self.hdr_dir, &self.dv_dir, self.options.output_structure.recursive()) {
+            Ok(scan) => {
+                let present: Vec<PathBuf> = scan
+                    .pairs
+                    .iter()
+                    .map(|pair| self.hdr_dir.join(&pair.hdr_file))
+                    .collect();
+                // Pairs removed from the folders no longer need rechecking.
+                self.pending.retain(|hdr_file, _| present.contains(hdr_file));
+                for pair in scan.pairs {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let hdr_file = self.hdr_dir.join(&pair.hdr_file);
+                    let dv_file = self.dv_dir.join(&pair.dv_file);
+                    if self.ready(&hdr_file, &dv_file) {
+                        *sequence += 1;
+                        self.process(&hdr_file, &dv_file, *sequence);
+                    }
+                }
+            }
+            Err(err) => emit_log(&self.app, "warning", format!("Watch scan failed: {}", err)),
+        }
+        let retrying = self.pending.values().filter(|pending| pending.attempts > 0).count();
+        self.watch.update(|status| {
+            status.last_scan_ms = Some(now_ms());
+            status.retrying = retrying;
+        });
+
+        let now = Instant::now();
+        self.pending
+            .values()
+            .map(|pending| match pending.retry_at {
+                Some(at) if at > now => at,
+                _ => now + settle,
+            })
+            .min()
+    }
+
+    /// Whether a pair is new, finished copying (same sizes as the previous
+    /// scan) and not waiting out a retry delay.
+    fn ready(&mut self, hdr_file: &Path, dv_file: &Path) -> bool {
+        if self.ledger.entries.contains_key(&hdr_file.to_string_lossy().to_string()) {
+            return false;
+        }
+        let sizes = (file_size(hdr_file), file_size(dv_file));
+        let pending = self.pending.entry(hdr_file.to_path_buf()).or_default();
+        let settled = pending.sizes == Some(sizes) && sizes.0 > 0 && sizes.1 > 0;
+        pending.sizes = Some(sizes);
+        settled && pending.retry_at.map(|at| Instant::now() >= at).unwrap_or(true)
+    }
+
+    fn process(&mut self, hdr_file: &Path, dv_file: &Path, sequence: usize) {
+        let name = hdr_file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let attempt = self.pending.get(hdr_file).map(|pending| pending.attempts).unwrap_or(0) + 1;
+        emit_log(
+            &self.app,
+            "info",
+            format!("Watch: processing {} (attempt {}/{})", name, attempt, self.max_attempts),
+        );
+        self.watch.update(|status| status.current = Some(name.clone()));
+        begin_pair(&self.app, &self.state, &self.request);
+
+        let item = QueueItem {
+            id: format!("watch-{}", sequence),
+            hdr_path: hdr_file.to_string_lossy().to_string(),
+            dv_path: dv_file.to_string_lossy().to_string(),
+            output_path: self.request.output_path.clone(),
+            hdr10plus_path: None,
+            hdr10plus_delay_ms: None,
+        };
+        let hdr10plus_path = Some(self.request.hdr10plus_path.as_str())
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
+        let result = process_queue_item(
+            self.app.clone(),
+            self.state.clone(),
+            self.request.tool_paths.clone(),
+            self.options.clone(),
+            item,
+            hdr10plus_path,
+            self.request.dv_delay_ms,
+            self.request.hdr10plus_delay_ms,
+            self.request.keep_temp_files,
+        );
+        let metrics = self.state.metrics.summary();
+        self.watch.update(|status| status.current = None);
+
+        let entry = match result {
+            Ok(()) => {
+                emit_log(&self.app, "success", format!("Watch: {} completed", name));
+                self.state.history.finish("completed", None, &metrics);
+                Some(("completed", None))
+            }
+            Err(err) if err == "Processing cancelled" => {
+                // Stopped by `stop_watch`; the pair is picked up again next time.
+                self.state.history.finish("cancelled", None, &metrics);
+                if let Ok(mut flag) = self.state.cancel_flag.lock() {
+                    *flag = false;
+                }
+                None
+            }
+            Err(err) => {
+                self.state.history.finish("error", Some(&err), &metrics);
+                if is_validation_error(&err) || attempt >= self.max_attempts {
+                    emit_log(&self.app, "error", format!("Watch: {} failed: {}", name, err));
+                    Some(("failed", Some(err)))
+                } else {
+                    let delay = Duration::from_secs(self.options.item_retry_delay_secs * u64::from(attempt));
+                    emit_log(
+                        &self.app,
+                        "warning",
+                        format!("Watch: {} failed, retrying in {}s: {}", name, delay.as_secs(), err),
+                    );
+                    let pending = self.pending.entry(hdr_file.to_path_buf()).or_default();
+                    pending.attempts = attempt;
+                    pending.retry_at = Some(Instant::now() + delay);
+                    None
+                }
+            }
+        };
+
+        if let Some((status, error)) = entry {
+            let entry = WatchEntry {
+                status: status.to_string(),
+                attempts: attempt,
+                error,
+                finished_ms: now_ms(),
+            };
+            append_result(&self.results_path, hdr_file, &entry);
+            self.ledger
+                .entries
+                .insert(hdr_file.to_string_lossy().to_string(), entry);
+            save_ledger(&self.ledger_path, &self.ledger);
+            self.pending.remove(hdr_file);
+            self.watch.update(|current| match status {
+                "completed" => current.completed += 1,
+                _ => current.failed += 1,
+            });
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::event_socket::generate_token;
+use crate::models::{OverallPayload, ProcessingState};
+use crate::utils::emit_log;
+
+pub const DEFAULT_WEB_STATUS_PORT: u16 = 8790;
+/// Seconds between reloads of the status page.
+const PAGE_REFRESH_SECS: u32 = 3;
+/// A client that sends nothing for this long is dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn default_bind_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    DEFAULT_WEB_STATUS_PORT
+}
+
+/// Opt-in read-only status page served while a run is active.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebStatusSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Address to listen on; loopback unless another device should see it.
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Required as `?token=` on every request; a blank one is generated.
+    #[serde(default)]
+    pub token: String,
+}
+
+/// What `get_processing_status` returns and the web page shows.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusSnapshot {
+    pub status: String,
+    pub paused: bool,
+    pub overall: Option<OverallPayload>,
+    /// Latest `processing:queue` payload of each queue item.
+    pub queue: Vec<Value>,
+    /// Latest `processing:file` payload of each file.
+    pub files: Vec<Value>,
+}
+
+/// Latest queue and file payloads seen during the run, by id.
+#[derive(Default)]
+struct ProgressBoard {
+    queue: BTreeMap<String, Value>,
+    files: BTreeMap<String, Value>,
+}
+
+struct WebServer {
+    stop: Arc<AtomicBool>,
+    /// The accept loop; it owns the listener, so joining it frees the port.
+    accept: JoinHandle<()>,
+}
+
+/// Tiny HTTP server for checking a run from another device: `/status` is the
+/// status snapshot as JSON, `/` a self-refreshing page rendering it. Nothing
+/// else is served, and every request must carry the settings' token.
+#[derive(Clone, Default)]
+pub struct WebStatus {
+    server: Arc<Mutex<Option<WebServer>>>,
+    board: Arc<Mutex<ProgressBoard>>,
+}
+
+impl WebStatus {
+    /// Start serving, returning the URL to open.
+    pub fn start(&self, app: &AppHandle, settings: &WebStatusSettings) -> Result<String, String> {
+        self.stop();
+        let token = match settings.token.trim() {
+            "" => generate_token()?,
+            token => token.to_string(),
+        };
+        let bind_address = match settings.bind_address.trim() {
+            "" => default_bind_address(),
+            address => address.to_string(),
+        };
+        let listener = TcpListener::bind((bind_address.as_str(), settings.port)).map_err(|e| {
+            format!(
+                "Failed to bind the status page on {}:{}: {}",
+                bind_address, settings.port, e
+            )
+        })?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let address = listener.local_addr().map_err(|e| e.to_string())?;
+        if !address.ip().is_loopback() {
+            emit_log(
+                app,
+                "warning",
+                format!("The status page on {} is reachable from other devices on the network", address),
+            );
+        }
+
+        if let Ok(mut board) = self.board.lock() {
+            *board = ProgressBoard::default();
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let accept = {
+            let app = app.clone();
+            let token = token.clone();
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let app = app.clone();
+                            let token = token.clone();
+                            thread::spawn(move || serve_client(&app, stream, &token));
+                        }
+                        Err(_) => thread::sleep(Duration::from_millis(200)),
+                    }
+                }
+            })
+        };
+        if let Ok(mut server) = self.server.lock() {
+            *server = Some(WebServer { stop, accept });
+        }
+        Ok(format!("http://{}/?token={}", address, urlencoding::encode(&token)))
+    }
+
+    /// Stop serving and wait for the listener to close, so the port can be
+    /// bound again straight away.
+    pub fn stop(&self) {
+        if let Some(server) = self.server.lock().ok().and_then(|mut server| server.take()) {
+            server.stop.store(true, Ordering::Relaxed);
+            let _ = server.accept.join();
+        }
+    }
+
+    /// Keep the latest queue and file payloads while the page is being served.
+    pub fn observe<S: Serialize>(&self, event: &str, payload: &S) {
+        if !matches!(event, "processing:queue" | "processing:file") {
+            return;
+        }
+        if !self.server.lock().map(|server| server.is_some()).unwrap_or(false) {
+            return;
+        }
+        let Ok(payload) = serde_json::to_value(payload) else {
+            return;
+        };
+        let Some(id) = payload.get("id").and_then(Value::as_str).map(str::to_string) else {
+            return;
+        };
+        if let Ok(mut board) = self.board.lock() {
+            match event {
+                "processing:queue" => board.queue.insert(id, payload),
+                _ => board.files.insert(id, payload),
+            };
+        }
+    }
+}
+
+/// Status, pause state, overall progress and, while the page is served, the
+/// latest progress of every queue item and file.
+pub fn status_snapshot(state: &ProcessingState) -> StatusSnapshot {
+    let (queue, files) = state
+        .web
+        .board
+        .lock()
+        .map(|board| (board.queue.values().cloned().collect(), board.files.values().cloned().collect()))
+        .unwrap_or_default();
+    StatusSnapshot {
+        status: state.status.lock().map(|status| status.clone()).unwrap_or_default(),
+        paused: state.pause_flag.lock().map(|paused| *paused).unwrap_or(false),
+        overall: state.job_progress.lock().ok().map(|job| job.payload()),
+        queue,
+        files,
+    }
+}
+
+/// Decoded value of `name` in a request target's query string.
+fn query_param(target: &str, name: &str) -> Option<String> {
+    let (_, query) = target.split_once('?')?;
+    let (_, value) = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)?;
+    urlencoding::decode(&value.replace('+', " "))
+        .ok()
+        .map(|value| value.into_owned())
+}
+
+fn serve_client(app: &AppHandle, stream: TcpStream, token: &str) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(CLIENT_TIMEOUT));
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // The headers say nothing the response depends on.
+    let mut header = String::new();
+    while reader.read_line(&mut header).map(|read| read > 0).unwrap_or(false) && header.trim() != "" {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = target.split('?').next().unwrap_or("");
+    let response = if method != "GET" {
+        (405, "text/plain", "Method not allowed".to_string())
+    } else if !matches!(path, "/" | "/status") {
+        (404, "text/plain", "Not found".to_string())
+    } else if query_param(target, "token").as_deref() != Some(token) {
+        (403, "text/plain", "Forbidden".to_string())
+    } else {
+        let snapshot = status_snapshot(&app.state::<ProcessingState>());
+        if path == "/status" {
+            let body = serde_json::to_string(&snapshot).unwrap_or_else(|_| "{}".to_string());
+            (200, "application/json", body)
+        } else {
+            (200, "text/html; charset=utf-8", render_page(&snapshot))
+        }
+    };
+    let (code, content_type, body) = response;
+    let reason = match code {
+        200 => "OK",
+        403 => "Forbidden",
+        404 => "Not Found",
+        _ => "Method Not Allowed",
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        code,
+        reason,
+        content_type,
+        body.len()
+    );
+    let _ = writer.write_all(head.as_bytes());
+    let _ = writer.write_all(body.as_bytes());
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn progress_row(label: &str, status: &str, progress: u64) -> String {
+    format!(
+        "<tr><td>{}</td><td>{}</td><td><progress max=\"100\" value=\"{}\"></progress> {}%</td></tr>",
+        escape_html(label),
+        escape_html(status),
+        progress,
+        progress
+    )
+}
+
+fn render_page(snapshot: &StatusSnapshot) -> String {
+    let text = |value: &Value, key: &str| value.get(key).and_then(Value::as_str).unwrap_or("").to_string();
+    let percent = |value: &Value| value.get("progress").and_then(Value::as_u64).unwrap_or(0);
+
+    let mut body = format!(
+        "<h1>Hybrid DV HDR</h1><p>Status: <b>{}</b>{}</p>",
+        escape_html(&snapshot.status),
+        if snapshot.paused { " (paused)" } else { "" }
+    );
+    if let Some(overall) = &snapshot.overall {
+        body.push_str(&format!(
+            "<p><progress max=\"100\" value=\"{}\"></progress> {}% &middot; {} of {} file(s) done, {} failed</p>",
+            overall.progress, overall.progress, overall.files_completed, overall.files_total, overall.files_failed
+        ));
+    }
+    if !snapshot.queue.is_empty() {
+        body.push_str("<h2>Queue</h2><table>");
+        for item in &snapshot.queue {
+            let label = match text(item, "currentStep") {
+                step if step.is_empty() => text(item, "id"),
+                step => format!("{} ({})", text(item, "id"), step),
+            };
+            body.push_str(&progress_row(&label, &text(item, "status"), percent(item)));
+        }
+        body.push_str("</table>");
+    }
+    if !snapshot.files.is_empty() {
+        body.push_str("<h2>Files</h2><table>");
+        for file in &snapshot.files {
+            body.push_str(&progress_row(&text(file, "name"), &text(file, "status"), percent(file)));
+        }
+        body.push_str("</table>");
+    }
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+<meta http-equiv=\"refresh\" content=\"{}\"><title>Hybrid DV HDR</title>\
+<style>body{{font-family:sans-serif;margin:1em}}td{{padding:.2em .6em}}progress{{width:8em}}</style></head><body>{}</body></html>",
+        PAGE_REFRESH_SECS, body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_param_finds_and_decodes_values() {
+        let target = "/status?refresh=1&token=a%2Bb%20c&empty=";
+        assert_eq!(query_param(target, "token").as_deref(), Some("a+b c"));
+        assert_eq!(query_param(target, "refresh").as_deref(), Some("1"));
+        assert_eq!(query_param(target, "empty").as_deref(), Some(""));
+        assert_eq!(query_param(target, "missing"), None);
+        assert_eq!(query_param("/status", "token"), None);
+        assert_eq!(query_param("/?token=x+y", "token").as_deref(), Some("x y"));
+    }
+}
@@ -8,6 +8,7 @@ interface ConsoleLogProps {
 }
 
 const typeColors = {
+  debug: 'text-muted-foreground/60',
   info: 'text-muted-foreground',
   success: 'text-primary',
   warning: 'text-amber-400',
@@ -15,6 +16,7 @@ const typeColors = {
 };
 
 const typePrefixes = {
+  debug: '[DBG]',
   info: '[INFO]',
   success: '[OK]',
   warning: '[WARN]',
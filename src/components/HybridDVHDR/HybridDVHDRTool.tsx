@@ -34,12 +34,22 @@ import type {
 import { isPermissionGranted, requestPermission, sendNotification } from '@tauri-apps/api/notification';
 
 const defaultSteps: ProcessingStep[] = [
+  { id: 8, name: 'Stage Inputs', description: 'Copying inputs to the local staging folder', status: 'pending', progress: 0 },
   { id: 1, name: 'Extract Audio & Subtitles', description: 'Extracting audio tracks and subtitles from HDR source', status: 'pending', progress: 0 },
+  { id: 16, name: 'Transcode Audio', description: 'Re-encoding the extracted audio to the chosen codec', status: 'pending', progress: 0 },
   { id: 2, name: 'Extract DV Video', description: 'Extracting H.265 video from Dolby Vision source', status: 'pending', progress: 0 },
   { id: 3, name: 'Extract RPU Data', description: 'Extracting RPU metadata from DV stream', status: 'pending', progress: 0 },
+  { id: 7, name: 'Edit RPU Metadata', description: 'Applying crop and delay edits to the RPU', status: 'pending', progress: 0 },
   { id: 4, name: 'Extract HDR10 Video', description: 'Extracting H.265 video from HDR10 source', status: 'pending', progress: 0 },
+  { id: 15, name: 'Pad HDR10 Video', description: 'Re-encoding the HDR10 base with padding to match the DV height', status: 'pending', progress: 0 },
+  { id: 10, name: 'Demux HDR10+ Donor', description: 'Extracting H.265 video from the HDR10+ source', status: 'pending', progress: 0 },
+  { id: 11, name: 'Extract HDR10+ Metadata', description: 'Extracting HDR10+ dynamic metadata', status: 'pending', progress: 0 },
+  { id: 12, name: 'Edit HDR10+ Metadata', description: 'Applying delay and length edits to the HDR10+ metadata', status: 'pending', progress: 0 },
+  { id: 13, name: 'Inject HDR10+ Metadata', description: 'Injecting HDR10+ metadata into HDR10 video stream', status: 'pending', progress: 0 },
   { id: 5, name: 'Inject RPU Data', description: 'Injecting RPU data into HDR10 video stream', status: 'pending', progress: 0 },
   { id: 6, name: 'Mux Final Output', description: 'Combining video, audio, and subtitles into final MKV', status: 'pending', progress: 0 },
+  { id: 9, name: 'Hash Output', description: 'Recording the SHA-256 of the final output', status: 'pending', progress: 0 },
+  { id: 14, name: 'Copy to Extra Destinations', description: 'Copying the finished output to the additional destinations', status: 'pending', progress: 0 },
 ];
 
 const defaultToolPaths: ToolPaths = {
@@ -50,7 +60,18 @@ const defaultToolPaths: ToolPaths = {
   mediainfo: 'MediaInfo',
   mp4box: 'MP4Box',
   hdr10plusTool: 'hdr10plus_tool',
-  defaultOutput: 'DV.HDR',
+  defaultOutputSingle: 'DV.HDR',
+  defaultOutputBatch: 'DV.HDR',
+};
+
+// Settings saved before the output folder was split only have `defaultOutput`.
+const migrateToolPaths = (saved: Partial<ToolPaths> & { defaultOutput?: string }): ToolPaths => {
+  const { defaultOutput, ...rest } = saved;
+  return {
+    ...defaultToolPaths,
+    ...(defaultOutput ? { defaultOutputSingle: defaultOutput, defaultOutputBatch: defaultOutput } : {}),
+    ...rest,
+  };
 };
 
 export function HybridDVHDRTool() {
@@ -113,6 +134,17 @@ export function HybridDVHDRTool() {
     setLogs(prev => [...prev, entry]);
   }, []);
 
+  const addLogBatch = useCallback((payloads: LogPayload[]) => {
+    const timestamp = new Date();
+    const entries: LogEntry[] = payloads.map(payload => ({
+      id: crypto.randomUUID(),
+      timestamp,
+      type: payload.logType,
+      message: payload.message,
+    }));
+    setLogs(prev => [...prev, ...entries]);
+  }, []);
+
   // Load settings from localStorage
   useEffect(() => {
     const savedConfig = localStorage.getItem('hybrid-dv-hdr-config');
@@ -133,7 +165,7 @@ export function HybridDVHDRTool() {
     if (savedTools) {
        try {
         const parsed = JSON.parse(savedTools);
-        setToolPaths({ ...defaultToolPaths, ...parsed });
+        setToolPaths(migrateToolPaths(parsed));
        } catch (e) { console.error("Failed to load tool paths", e); }
     }
 
@@ -222,6 +254,7 @@ export function HybridDVHDRTool() {
     if (!isTauri()) return;
 
     let unlistenLog: (() => void) | undefined;
+    let unlistenLogBatch: (() => void) | undefined;
     let unlistenStep: (() => void) | undefined;
     let unlistenQueue: (() => void) | undefined;
     let unlistenStatus: (() => void) | undefined;
@@ -232,6 +265,10 @@ export function HybridDVHDRTool() {
         addLog(event.payload.logType, event.payload.message);
       });
 
+      unlistenLogBatch = await listenTauri<LogPayload[]>('processing:log-batch', (event) => {
+        addLogBatch(event.payload);
+      });
+
       unlistenStep = await listenTauri<StepPayload>('processing:step', (event) => {
         const payload = event.payload;
         setSteps(prev => prev.map(step => (
@@ -321,12 +358,13 @@ export function HybridDVHDRTool() {
 
     return () => {
       if (unlistenLog) unlistenLog();
+      if (unlistenLogBatch) unlistenLogBatch();
       if (unlistenStep) unlistenStep();
       if (unlistenQueue) unlistenQueue();
       if (unlistenStatus) unlistenStatus();
       if (unlistenFile) unlistenFile();
     };
-  }, [addLog, computeSmoothedEta, notify]);
+  }, [addLog, addLogBatch, computeSmoothedEta, notify]);
 
   const derivedHdrPath = config.hdrPath || config.hdr10plusPath;
   const derivedHdrKind = config.hdrPath ? pathKinds.hdr : pathKinds.hdr10plus;
@@ -341,7 +379,7 @@ export function HybridDVHDRTool() {
     const preset = presets.find(item => item.id === presetId);
     if (!preset) return;
     setConfig(preset.config);
-    setToolPaths(preset.toolPaths);
+    setToolPaths(migrateToolPaths(preset.toolPaths));
     setDvDelayInput(preset.dvDelayInput || '');
     setHdr10plusDelayInput(preset.hdr10plusDelayInput || '');
     setShowDelays(preset.showDelays ?? false);
@@ -456,7 +494,7 @@ export function HybridDVHDRTool() {
     const dvLabel = config.dvPath.split('\\').filter(Boolean).pop() || config.dvPath;
 
     const outputFile = isBatch
-      ? config.outputPath || toolPaths.defaultOutput
+      ? config.outputPath || toolPaths.defaultOutputBatch
       : config.outputPath || `${hdrLabel.replace('.mkv', '')}.hybrid.mkv`;
 
     const newFile: QueueFile = {
@@ -475,7 +513,7 @@ export function HybridDVHDRTool() {
     setSelectedQueueIds(prev => new Set(prev).add(newFile.id));
     setConfig(prev => ({ ...prev, hdrPath: '', hdr10plusPath: '', dvPath: '', outputPath: '' }));
     addLog('info', `Added to queue: ${newFile.outputFile}`);
-  }, [config, addLog, derivedMode, toolPaths.defaultOutput, derivedHdrPath]);
+  }, [config, addLog, derivedMode, toolPaths.defaultOutputBatch, derivedHdrPath]);
 
   const browseFile = useCallback(
     async (target: 'hdr' | 'dv' | 'output' | 'hdr10plus') => {
@@ -695,7 +733,7 @@ export function HybridDVHDRTool() {
       dvPath: config.dvPath,
       outputPath: config.outputPath,
       hdr10plusPath: config.hdr10plusPath,
-      dvDelayMs: parseDelay(dvDelayInput),
+      dvDelayMs: dvDelayInput.trim() === '' ? undefined : parseDelay(dvDelayInput),
       hdr10plusDelayMs: parseDelay(hdr10plusDelayInput),
       keepTempFiles: config.keepTempFiles,
       parallelTasks: config.parallelTasks,
@@ -866,7 +904,7 @@ export function HybridDVHDRTool() {
               setConfig(prev => ({ ...prev, outputPath: v }));
               setPathKinds(prev => ({ ...prev, output: v.endsWith('\\') || v.endsWith('/') ? 'folder' : 'file' }));
             }}
-            placeholder={derivedMode === 'batch' ? toolPaths.defaultOutput : 'movie.hybrid.mkv (auto)'}
+            placeholder={derivedMode === 'batch' ? toolPaths.defaultOutputBatch : 'movie.hybrid.mkv (auto)'}
             icon="output"
             disabled={isProcessing}
             onBrowseFile={() => browseFile('output')}
@@ -1,6 +1,6 @@
 import { useState } from 'react';
 import { Settings, Folder, Save, RotateCcw, Wrench, Download, ExternalLink } from 'lucide-react';
-import { isTauri, openDialog, openUrl, invokeTauri } from '@/lib/tauri';
+import { isTauri, openDialog, openUrl, invokeTauri, listenTauri } from '@/lib/tauri';
 import { Input } from '@/components/ui/input';
 import { Button } from '@/components/ui/button';
 import { Label } from '@/components/ui/label';
@@ -21,7 +21,7 @@ import {
   PopoverTrigger,
 } from "@/components/ui/popover"
 import { Tabs, TabsContent, TabsList, TabsTrigger } from "@/components/ui/tabs"
-import type { ToolPaths } from './types';
+import type { DownloadProgressPayload, ToolPaths } from './types';
 
 interface ToolSettingsProps {
   toolPaths: ToolPaths;
@@ -40,7 +40,8 @@ const defaultPaths: ToolPaths = {
   mediainfo: 'MediaInfo',
   mp4box: 'MP4Box',
   hdr10plusTool: 'hdr10plus_tool',
-  defaultOutput: 'DV.HDR',
+  defaultOutputSingle: 'DV.HDR',
+  defaultOutputBatch: 'DV.HDR',
 };
 
 const toolLabels = [
@@ -51,7 +52,8 @@ const toolLabels = [
   { key: 'mediainfo' as const, label: 'MediaInfo', icon: '📊' },
   { key: 'mp4box' as const, label: 'MP4Box', icon: '🧰' },
   { key: 'hdr10plusTool' as const, label: 'hdr10plus_tool', icon: '✨' },
-  { key: 'defaultOutput' as const, label: 'Default Output Folder', icon: '📁' },
+  { key: 'defaultOutputSingle' as const, label: 'Default Output Folder (Movies)', icon: '📁' },
+  { key: 'defaultOutputBatch' as const, label: 'Default Output Folder (Batch / TV)', icon: '📁' },
 ];
 
 const downloadLinks = [
@@ -76,6 +78,7 @@ export function ToolSettings({
   const [localParallel, setLocalParallel] = useState(parallelTasks);
   const [localKeepTemp, setLocalKeepTemp] = useState(keepTempFiles);
   const [downloading, setDownloading] = useState(false);
+  const [downloadProgress, setDownloadProgress] = useState<DownloadProgressPayload | null>(null);
 
   // Sync props to local state when dialog opens
   const handleOpenChange = (isOpen: boolean) => {
@@ -111,10 +114,11 @@ export function ToolSettings({
       return;
     }
 
+    const isFolder = key === 'defaultOutputSingle' || key === 'defaultOutputBatch';
     const selected = await openDialog({
-      directory: key === 'defaultOutput',
+      directory: isFolder,
       multiple: false,
-      filters: key === 'defaultOutput' ? undefined : [{ name: 'Executable', extensions: ['exe'] }],
+      filters: isFolder ? undefined : [{ name: 'Executable', extensions: ['exe'] }],
     });
 
     if (typeof selected === 'string') {
@@ -129,10 +133,11 @@ export function ToolSettings({
     }
     
     setDownloading(true);
+    const unlisten = await listenTauri<DownloadProgressPayload>('download:progress', (event) => {
+      setDownloadProgress(event.payload);
+    });
     const bypassBase = "https://bypasszbot.legendindex.workers.dev/direct.aspx?id=";
     const newPaths = { ...paths };
-    let mp4boxZipPath: string | null = null;
-
     try {
       for (const tool of downloadLinks) {
         const url = `${bypassBase}${tool.id}`;
@@ -148,28 +153,29 @@ export function ToolSettings({
         if (tool.name === 'mkvextract') newPaths.mkvextract = savedPath;
         if (tool.name === 'ffmpeg') newPaths.ffmpeg = savedPath;
         if (tool.name === 'hdr10plusTool') newPaths.hdr10plusTool = savedPath;
-        if (tool.name === 'mp4box') {
-          mp4boxZipPath = savedPath;
-        }
+        // The backend unpacks the archive and returns the verified MP4Box executable.
+        if (tool.name === 'mp4box') newPaths.mp4box = savedPath;
       }
       
       setPaths(newPaths);
       // We don't save immediately here to allow user to review/save manually, 
       // OR we could save immediately. The requirement says "everytime... it should set it".
       // Let's keep the manual save model but update local state so 'Save' button commits it.
-      if (mp4boxZipPath) {
-        alert(`MP4Box downloaded as a zip (${mp4boxZipPath}). Please unzip it and set the MP4Box.exe path manually.`);
-      } else {
-        alert("All tools downloaded and configured successfully! Click 'Save Configuration' to apply.");
-      }
+      alert("All tools downloaded and configured successfully! Click 'Save Configuration' to apply.");
     } catch (error) {
       console.error(error);
       alert(`Download failed: ${error}`);
     } finally {
+      unlisten();
+      setDownloadProgress(null);
       setDownloading(false);
     }
   };
 
+  const handleCancelDownload = () => {
+    invokeTauri('cancel_download').catch(console.error);
+  };
+
   return (
     <Dialog open={open} onOpenChange={handleOpenChange}>
       <DialogTrigger asChild>
@@ -199,7 +205,11 @@ export function ToolSettings({
                     <div className="flex items-center justify-between">
                     <div>
                         <h4 className="text-sm font-medium text-foreground">Missing Dependencies?</h4>
-                        <p className="text-xs text-muted-foreground">Download required tools automatically.</p>
+                        <p className="text-xs text-muted-foreground">
+                          {downloadProgress
+                            ? `${downloadProgress.phase === 'extract' ? 'Extracting' : 'Downloading'} ${downloadProgress.filename}${downloadProgress.progress != null ? ` (${downloadProgress.progress}%)` : ''}`
+                            : 'Download required tools automatically.'}
+                        </p>
                     </div>
                     <Button 
                         variant="secondary" 
@@ -211,6 +221,11 @@ export function ToolSettings({
                         <Download className={`h-4 w-4 ${downloading ? 'animate-bounce' : ''}`} />
                         {downloading ? 'Downloading...' : 'Download Needed Packages'}
                     </Button>
+                    {downloading && (
+                      <Button variant="ghost" size="sm" onClick={handleCancelDownload}>
+                        Cancel
+                      </Button>
+                    )}
                     </div>
                 </div>
 
@@ -1,12 +1,13 @@
-export type ProcessingMode = 'single' | 'batch';
-export type ProcessingStatus = 'idle' | 'processing' | 'completed' | 'error';
-export type FileStatus = 'pending' | 'processing' | 'completed' | 'error';
+/** `add-dv` adds Dolby Vision to one finished HDR10 MKV, keeping its other tracks. */
+export type ProcessingMode = 'single' | 'batch' | 'analyze' | 'add-dv';
+export type ProcessingStatus = 'idle' | 'scheduled' | 'processing' | 'paused' | 'completed' | 'error';
+export type FileStatus = 'pending' | 'processing' | 'completed' | 'error' | 'skipped';
 
 export interface ProcessingStep {
   id: number;
   name: string;
   description: string;
-  status: 'pending' | 'active' | 'completed' | 'error';
+  status: 'pending' | 'active' | 'completed' | 'error' | 'skipped';
   progress: number;
 }
 
@@ -18,7 +19,8 @@ export interface ToolPaths {
   mediainfo: string;
   mp4box: string;
   hdr10plusTool: string;
-  defaultOutput: string;
+  defaultOutputSingle: string;
+  defaultOutputBatch: string;
 }
 
 export interface QueueFile {
@@ -29,6 +31,8 @@ export interface QueueFile {
   hdrPath: string;
   dvPath: string;
   outputPath: string;
+  hdr10plusPath?: string | null;
+  hdr10plusDelayMs?: number | null;
   status: FileStatus;
   progress: number;
   currentStep?: string;
@@ -52,22 +56,125 @@ export interface ProcessingConfig {
 export interface LogEntry {
   id: string;
   timestamp: Date;
-  type: 'info' | 'success' | 'warning' | 'error';
+  type: 'debug' | 'info' | 'success' | 'warning' | 'error';
   message: string;
 }
 
 export interface ProcessingRequest {
   mode: ProcessingMode;
+  /** Empty in single mode to remux a profile 8 DV file on its own. */
   hdrPath: string;
   dvPath: string;
   outputPath: string;
   hdr10plusPath: string;
-  dvDelayMs: number;
+  /** Omit to let a title memo supply the delay; 0 forces none. */
+  dvDelayMs?: number | null;
   hdr10plusDelayMs: number;
   keepTempFiles: boolean;
   parallelTasks: number;
+  /** Extract each file's audio and subtitles alongside its DV/RPU steps. */
+  parallelSteps?: boolean;
   toolPaths: ToolPaths;
   queue: QueueFile[];
+  audioTranscode?: AudioTranscode | null;
+  activeAreaMode?: 'auto' | 'override';
+  split?: SplitSpec | null;
+  overwritePolicy?: 'overwrite' | 'rename';
+  resume?: boolean;
+  outputDir?: string;
+  remuxStrategy?: 'rebuild' | 'replace_video';
+  rpuSource?: 'dv-file' | 'hdr-file' | 'best';
+  probeConcurrency?: number;
+  probeRetries?: number;
+  subtitleFilter?: SubtitleFilter;
+  trackDefaults?: TrackDefaults;
+  /** Audio language codes to put first, in order; other tracks follow. */
+  audioOrder?: string[];
+  /** Skip the delay, letterbox, audio order and default tracks remembered for a DV input. */
+  ignoreTitleMemos?: boolean;
+  /** Folders each finished output is also copied to after verification. */
+  additionalOutputs?: string[];
+  /** Frame rate difference allowed beyond exact and NTSC-rounded matches. Default 0.001. */
+  fpsTolerance?: number;
+  /** Read-only status page served while the run is active. */
+  webStatus?: WebStatusSettings;
+  /** Local ISO-8601 time, e.g. `2024-05-01T01:30`, to hold the run until. */
+  startAt?: string;
+  /** Daily hours files may start in; outside them the run pauses until the window reopens. */
+  allowedWindow?: ProcessingWindow;
+  coverArt?: string;
+  /** Keep the source chapters, flatten them to one plain edition, or use a chapters XML. */
+  chapters?: ChapterMode;
+  felPolicy?: 'convert' | 'abort' | 'mel_only';
+  strictPairing?: boolean;
+  strictValidation?: boolean;
+  hdr10plusSource?: 'donor' | 'base' | 'auto';
+  frameCountPolicy?: 'warn' | 'trim' | 'fail';
+  itemRetryCount?: number;
+  itemRetryDelaySecs?: number;
+  stagingDir?: string;
+  stageLink?: boolean;
+  detectLetterbox?: boolean;
+  /** Map a DV frame of another resolution (1080p vs 2160p) onto the HDR frame by width instead of failing. */
+  scaleDvFrame?: boolean;
+  progressIntervalMs?: number;
+  outputStructure?: 'flat' | 'mirror';
+  ordering?: BatchOrdering;
+  hdrVideoTrack?: number;
+  dvVideoTrack?: number;
+  logLevel?: 'debug' | 'info';
+  hashOutput?: boolean;
+  resumeFromStep?: 'mux';
+  startStep?: PipelineStepRef;
+  endStep?: PipelineStepRef;
+  hdr10HevcPath?: string;
+  rpuPath?: string;
+  baseHevcPath?: string;
+  postHook?: string;
+  postHookBlocking?: boolean;
+  mkvUiLanguage?: string;
+  writeColorMetadata?: boolean;
+}
+
+export interface MissingInput {
+  queueId?: string | null;
+  field: 'hdrPath' | 'dvPath' | 'hdr10plusPath';
+  path: string;
+}
+
+export interface ImportedJob {
+  request: ProcessingRequest;
+  missing: MissingInput[];
+}
+
+/** A step by 1-based index, short alias or full name. */
+export type PipelineStepRef = number | 'audio' | 'dv' | 'rpu' | 'hdr10' | 'inject' | 'mux' | string;
+
+export interface SubtitleFilter {
+  dropForced?: boolean;
+  dropSdh?: boolean;
+}
+
+export interface TrackDefaults {
+  audioLanguage?: string;
+  subtitleLanguage?: string;
+  subtitleForced?: boolean;
+}
+
+/** Where the output's chapters come from; `replace` takes a chapters XML path. */
+export type ChapterMode =
+  | { mode: 'keep' }
+  | { mode: 'drop_editions' }
+  | { mode: 'replace'; value: string };
+
+export interface SplitSpec {
+  mode: 'size' | 'duration' | 'chapters';
+  value: string;
+}
+
+export interface AudioTranscode {
+  codec: string;
+  bitrate?: string | null;
 }
 
 export interface LogPayload {
@@ -89,6 +196,119 @@ export interface QueuePayload {
   currentStep?: string | null;
   activeWorkers?: number | null;
   fileTotal?: number | null;
+  filesCompleted?: number;
+  filesFailed?: number;
+  filesActive?: number;
+  filesQueued?: number;
+}
+
+export interface Artifact {
+  path: string;
+  size: number;
+  kind: 'rpu' | 'hevc' | 'mka' | 'json' | 'other';
+}
+
+export interface ArtifactsPayload {
+  output: string;
+  artifacts: Artifact[];
+  totalSize: number;
+}
+
+export type BatchOrdering = 'name' | 'size_ascending' | 'size_descending' | 'interleaved';
+
+export interface ScanReport {
+  queueId?: string | null;
+  hdrDir: string;
+  totalFound: number;
+  processed: number;
+  skipped: Array<{ file: string; reason: 'junk' | 'unpaired' | 'duplicate' | 'already_done' | 'own_output' }>;
+  pairs: Array<{ hdrFile: string; dvFile: string; matchedBy: string; output: string }>;
+  ordering: BatchOrdering;
+  unmatchedDv: string[];
+}
+
+export interface StreamSummary {
+  path: string;
+  width: number;
+  height: number;
+  fps: number;
+  bitDepth?: number | null;
+  durationSecs?: number | null;
+  frameCount?: number | null;
+  format?: string | null;
+  hdrFormat?: string | null;
+  dvProfile?: number | null;
+  hdr10plus: boolean;
+}
+
+export type FrameFit =
+  | { kind: 'same' }
+  | { kind: 'pad'; top: number; bottom: number }
+  | { kind: 'crop'; top: number; bottom: number };
+
+export interface PairAnalysis {
+  hdrPath: string;
+  dvPath: string;
+  hdr10plusPath?: string | null;
+  hdr?: StreamSummary | null;
+  dv?: StreamSummary | null;
+  compatible: boolean;
+  issues: string[];
+  warnings: string[];
+  delay?: { delayMs: number; frames: number; source: 'container' | 'frameCount' } | null;
+  delayPlan?: { frames: number; remove?: string | null; duplicate: number } | null;
+  frameFit?: FrameFit | null;
+  letterbox?: [number, number] | null;
+  hdr10plusPresent?: boolean | null;
+  hdr10plusInject: boolean;
+  error?: string | null;
+}
+
+export interface AnalysisReport {
+  queueId?: string | null;
+  pairs: PairAnalysis[];
+  unpaired: string[];
+  compatible: number;
+}
+
+export interface MetricsSummary {
+  wallSecs: number;
+  steps: Array<{ name: string; secs: number; runs: number }>;
+  peakWorkers: number;
+  peakTempBytes: number;
+  bytesRead: number;
+  bytesWritten: number;
+  copies?: OutputCopy[];
+  hashes?: OutputSidecar[];
+}
+
+/** Contents of the `<output>.json` sidecar next to a finished output. */
+export interface OutputSidecar {
+  file: string;
+  size: number;
+  /** Only when `hashOutput` is on. */
+  sha256?: string;
+}
+
+export interface OutputCopy {
+  source: string;
+  destination: string;
+  status: 'copied' | 'failed';
+  error?: string | null;
+  bytes: number;
+}
+
+/** Settings a DV input was last processed with, from `list_title_memos`. */
+export interface TitleMemoEntry {
+  /** SHA-256 of the DV input's first 16 MiB; pass to `delete_title_memo`. */
+  key: string;
+  title: string;
+  dvFile: string;
+  updated: string;
+  dvDelayMs: number;
+  crop?: { top: number; bottom: number; detected: boolean } | null;
+  audioOrder: string[];
+  trackDefaults: TrackDefaults;
 }
 
 export interface FileProgressEntry {
@@ -103,9 +323,120 @@ export interface FileProgressPayload {
   id: string;
   queueId: string;
   name: string;
+  status: 'queued' | 'processing' | 'completed' | 'completed_with_warnings' | 'cancelled' | 'error' | 'stopped' | 'skipped' | 'partial';
   progress: number;
+  attempt: number;
+  reason?: StopReason;
+  /** Sidecars of the finished output(s), once the file has completed. */
+  hashes?: OutputSidecar[];
+}
+
+export type StopReason =
+  | { kind: 'userCancelled' }
+  | { kind: 'siblingFailed'; file: string }
+  | { kind: 'shuttingDown' }
+  | { kind: 'duplicate'; file: string }
+  | { kind: 'endStep'; file: string };
+
+export interface DownloadProgressPayload {
+  filename: string;
+  phase: 'download' | 'extract';
+  done: number;
+  total?: number | null;
+  progress?: number | null;
 }
 
 export interface StatusPayload {
   status: ProcessingStatus;
+  reason?: StopReason;
+  message?: string;
+  /** Folder workers the run actually uses, sent with the initial status. */
+  parallelTasks?: number;
+}
+
+/** Local `HH:MM` bounds; an end before the start spans midnight. */
+export interface ProcessingWindow {
+  start: string;
+  end: string;
+  /** Also pause running files before their next step once the window closes. */
+  pauseAtBoundary?: boolean;
+}
+
+export interface WebStatusSettings {
+  enabled: boolean;
+  /** Defaults to `127.0.0.1`; use `0.0.0.0` to reach it from a phone. */
+  bindAddress?: string;
+  /** Defaults to 8790. */
+  port?: number;
+  /** Random token required as `?token=`; generated when blank. */
+  token: string;
+}
+
+/** Returned by `get_processing_status` and served as the page's `/status`. */
+export interface StatusSnapshot {
+  status: string;
+  paused: boolean;
+  overall?: OverallPayload | null;
+  queue: QueuePayload[];
+  files: FileProgressPayload[];
+}
+
+/** Countdown sent once a second while a run waits for its `startAt`. */
+export interface ScheduledPayload {
+  startAt: string;
+  remainingSecs: number;
+}
+
+export interface OverallPayload {
+  progress: number;
+  filesCompleted: number;
+  filesFailed: number;
+  filesTotal: number;
+}
+
+export interface EventSocketInfo {
+  port: number;
+  token: string;
+}
+
+export interface SelfTestCheck {
+  tool: string;
+  step: string;
+  status: 'passed' | 'failed' | 'skipped';
+  detail: string;
+}
+
+export interface SelfTestReport {
+  passed: boolean;
+  checks: SelfTestCheck[];
+}
+
+export interface EstimateItem {
+  id: string;
+  hdrPath: string;
+  dvPath: string;
+  inputBytes: number;
+  estimatedSecs: number;
+}
+
+export interface EstimateReport {
+  items: EstimateItem[];
+  unmatched: string[];
+  totalBytes: number;
+  bytesPerSec: number;
+  throughputSource: 'default' | 'measured' | 'benchmark';
+  estimatedSecs: number;
+}
+
+export interface WatchStatus {
+  running: boolean;
+  hdrDir?: string | null;
+  dvDir?: string | null;
+  settleSecs: number;
+  current?: string | null;
+  completed: number;
+  failed: number;
+  retrying: number;
+  lastScanMs?: number | null;
+  resultsLog?: string | null;
 }